@@ -52,6 +52,17 @@ export declare namespace Components {
         } | {
             Deposit: WalletDepositTx;
         };
+        /**
+         * A page of the response from the /wallet endpoint.
+         */
+        export interface WalletTxPage {
+            items: WalletTx[];
+            /**
+             * Cursor to pass to get the next page. Not present if this is the last
+             * page.
+             */
+            next_cursor?: string | null;
+        }
         export interface WalletWithdrawTx {
             amount: string;
             /**
@@ -136,13 +147,31 @@ export declare namespace Paths {
     }
     namespace WalletTxs {
         namespace Parameters {
+            export type Cursor = string | null;
+            export type Direction = "deposit" | "withdraw" | null;
+            export type From = number | null; // int64
+            export type Limit = number | null; // int32
+            export type Order = "asc" | "desc" | null;
+            export type Status = Components.Schemas.WithdrawalStatus | null;
+            export type To = number | null; // int64
+            export type Token = string | null;
             export type Wallet = string;
         }
         export interface PathParameters {
             wallet: Parameters.Wallet;
         }
+        export interface QueryParameters {
+            limit?: Parameters.Limit /* int32 */;
+            cursor?: Parameters.Cursor;
+            token?: Parameters.Token;
+            direction?: Parameters.Direction;
+            status?: Parameters.Status;
+            from?: Parameters.From /* int64 */;
+            to?: Parameters.To /* int64 */;
+            order?: Parameters.Order;
+        }
         namespace Responses {
-            export type $200 = Components.Schemas.WalletTx[];
+            export type $200 = Components.Schemas.WalletTxPage;
             export type $400 = string;
             export type $500 = string;
         }
@@ -220,7 +249,7 @@ export interface OperationMethods {
    * wallet_txs
    */
   'wallet_txs'(
-    parameters?: Parameters<Paths.WalletTxs.PathParameters> | null,
+    parameters?: Parameters<Paths.WalletTxs.QueryParameters & Paths.WalletTxs.PathParameters> | null,
     data?: any,
     config?: AxiosRequestConfig  
   ): OperationResponse<Paths.WalletTxs.Responses.$200>
@@ -291,7 +320,7 @@ export interface PathsDictionary {
      * wallet_txs
      */
     'get'(
-      parameters?: Parameters<Paths.WalletTxs.PathParameters> | null,
+      parameters?: Parameters<Paths.WalletTxs.QueryParameters & Paths.WalletTxs.PathParameters> | null,
       data?: any,
       config?: AxiosRequestConfig  
     ): OperationResponse<Paths.WalletTxs.Responses.$200>
@@ -16,6 +16,10 @@ const QUERY_UPDATE_INTERVAL = 60000;
  * Interval in ms for querying in individual transaction status
  */
 const WATCH_TRANSACTION_INTERVAL = 15000;
+/**
+ * Number of the most recent wallet transactions to query
+ */
+const WALLET_TXS_LIMIT = 100;
 
 type WatchWithdrawParams = Paths.WatchWithdrawTx.PathParameters;
 type WatchWithdrawOptions = UseQueryOptions<
@@ -108,8 +112,8 @@ export const useWalletTransactions = (refetch = false) => {
             if (!wallet) {
                 return undefined;
             }
-            const { data } = await client.wallet_txs({ wallet });
-            return data;
+            const { data } = await client.wallet_txs({ wallet, limit: WALLET_TXS_LIMIT });
+            return data.items;
         },
         { refetchInterval: refetch && QUERY_UPDATE_INTERVAL, enabled: refetch }
     );
@@ -227,3 +227,294 @@ CREATE TABLE IF NOT EXISTS expected_merkle_update (
        tag unit NOT NULL DEFAULT ('') UNIQUE,
        expected_time timestamp with time zone NOT NULL
 );
+
+-- Discrepancies found when checking that the amount locked in the Ethereum
+-- vault for a token is the amount accounted for on Concordium, i.e., the supply
+-- of the mapped token plus withdrawals that have not yet been completed on
+-- Ethereum plus deposits that have not yet been completed on Concordium.
+CREATE TABLE IF NOT EXISTS supply_discrepancies (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- The address of the root token on Ethereum.
+       root_token BYTEA NOT NULL,
+       -- Contract address of the mapped token on Concordium.
+       child_index INT8 NOT NULL,
+       child_subindex INT8 NOT NULL,
+       -- Ethereum block number at which the vault balance was queried.
+       ethereum_height INT8 NOT NULL,
+       -- Concordium block height at which the token supply was queried.
+       concordium_height INT8 NOT NULL,
+       -- Balance of the vault as a decimal string.
+       vault_balance TEXT NOT NULL,
+       -- Supply of the mapped token on Concordium as a decimal string.
+       concordium_supply TEXT NOT NULL,
+       -- Sum of withdrawals that are not yet completed, as a decimal string.
+       pending_withdrawals TEXT NOT NULL,
+       -- Sum of deposits that are not yet completed, as a decimal string.
+       pending_deposits TEXT NOT NULL,
+       -- The vault balance minus the amount accounted for on Concordium, as a
+       -- signed decimal string.
+       delta TEXT NOT NULL,
+       -- Time when the discrepancy was found.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+DO $$ BEGIN
+CREATE TYPE circuit_breaker_trigger AS ENUM (
+    'supply_invariant',
+    'unknown_merkle_root',
+    'unmatched_deposit',
+    'withdrawal_volume'
+   );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Trips of the automatic circuit breaker. While there are unresolved trips the
+-- relayer does not set new Merkle roots. Trips are resolved by the operator
+-- after investigation, by setting the resolved_time, or by starting the relayer
+-- with --breaker-resolve.
+CREATE TABLE IF NOT EXISTS circuit_breaker_trips (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- The trigger that caused the trip.
+       trigger circuit_breaker_trigger NOT NULL,
+       -- Human readable description of the cause.
+       cause TEXT NOT NULL,
+       -- Hash of the transaction that paused the bridge manager on Concordium,
+       -- if one was sent.
+       pause_tx_hash BYTEA,
+       -- Time of the trip.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW(),
+       -- Time when the trip was resolved, NULL if it is not.
+       resolved_time timestamp with time zone
+);
+
+DO $$ BEGIN
+CREATE TYPE withdrawal_hold_reason AS ENUM (
+    'single_limit',
+    'window_limit',
+    'screening'
+   );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Withdrawals that exceeded a configured limit, or matched a screening list,
+-- and are held back from the Merkle tree. A hold is released either when
+-- release_time passes, or when the operator approves it by setting
+-- approved_time. The relayer then sets released_time and includes the
+-- withdrawal in the next Merkle root.
+CREATE TABLE IF NOT EXISTS withdrawal_holds (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- Event index of the held withdraw event.
+       event_index INT8 NOT NULL UNIQUE,
+       -- The limit that was exceeded.
+       reason withdrawal_hold_reason NOT NULL,
+       -- Time when the hold is automatically released. NULL means the hold is
+       -- only released by operator approval.
+       release_time timestamp with time zone,
+       -- Time when the operator approved the withdrawal.
+       approved_time timestamp with time zone,
+       -- Time when the withdrawal was released into the Merkle tree.
+       released_time timestamp with time zone,
+       -- Time when the withdrawal was held.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+DO $$ BEGIN
+CREATE TYPE screened_operation AS ENUM (
+    'deposit',
+    'withdrawal'
+   );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Audit record of deposits and withdrawals that involved an address on one of
+-- the screening lists. Each matching address is a separate row.
+CREATE TABLE IF NOT EXISTS screening_matches (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- Whether the match was for a deposit or a withdrawal.
+       operation screened_operation NOT NULL,
+       -- For deposits the index of the deposit event on Ethereum, for
+       -- withdrawals the index of the withdraw event on Concordium.
+       event_index INT8 NOT NULL,
+       -- The network of the matching address.
+       network network NOT NULL,
+       -- The matching address.
+       address BYTEA NOT NULL,
+       -- Time when the match was found.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+-- Deposits that are held because they matched a screening list. No transaction
+-- is sent to Concordium for these until the operator approves them by setting
+-- approved_time. The relayer then sends the deposit and sets released_time.
+-- Held withdrawals are recorded in withdrawal_holds.
+CREATE TABLE IF NOT EXISTS deposit_holds (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- Hash of the transaction on Ethereum that initiated the deposit.
+       origin_tx_hash BYTEA NOT NULL,
+       -- Index of the deposit event emitted by the StateSender on Ethereum.
+       origin_event_index INT8 NOT NULL UNIQUE,
+       -- The serialized deposit operation to be sent to the bridge manager.
+       deposit BYTEA NOT NULL,
+       -- Time when the operator approved the deposit.
+       approved_time timestamp with time zone,
+       -- Time when the deposit was sent to Concordium.
+       released_time timestamp with time zone,
+       -- Time when the deposit was held.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+-- Time of the block that contains the event, used for measuring latencies. For
+-- concordium_events this is the slot time of the Concordium block, and for
+-- ethereum_deposit_events the timestamp of the Ethereum block. NULL for events
+-- inserted before the column was added.
+ALTER TABLE concordium_events ADD COLUMN IF NOT EXISTS block_time timestamp with time zone;
+ALTER TABLE ethereum_deposit_events ADD COLUMN IF NOT EXISTS block_time timestamp with time zone;
+-- Fee, in microCCD, paid for the transaction, recorded when it is in a
+-- finalized block. It is recorded only once, so the fees of transactions in
+-- blocks that are processed again are not counted twice.
+ALTER TABLE concordium_transactions ADD COLUMN IF NOT EXISTS fee INT8;
+
+-- Indices for the benefit of the API server, so that it can efficiently list
+-- the transactions of a wallet ordered by time.
+CREATE INDEX IF NOT EXISTS concordium_events_receiver_index ON concordium_events (receiver, insert_time);
+CREATE INDEX IF NOT EXISTS ethereum_deposit_events_depositor_index ON ethereum_deposit_events (depositor, insert_time);
+
+-- The Concordium account that receives the deposit. For deposits inserted
+-- before the column was added it is set when the relayer starts, from the
+-- transaction that completes the deposit or from the event on Ethereum.
+ALTER TABLE ethereum_deposit_events ADD COLUMN IF NOT EXISTS deposit_receiver BYTEA;
+-- If withdraw, the Concordium account that initiated the withdrawal. NULL if it
+-- was initiated by a contract, and for other events.
+ALTER TABLE concordium_events ADD COLUMN IF NOT EXISTS ccd_address BYTEA;
+
+-- Backfills of new columns that have been completed, so that they are only
+-- done once.
+CREATE TABLE IF NOT EXISTS backfills (
+       name TEXT PRIMARY KEY,
+       completed_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+-- Indices for the benefit of the API server, so that it can efficiently list
+-- the transactions of a Concordium account ordered by time. All aliases of an
+-- account share the first 29 bytes of the address, so the indices are on those.
+CREATE INDEX IF NOT EXISTS concordium_events_ccd_address_index ON concordium_events ((substring(ccd_address FROM 1 FOR 29)), insert_time);
+CREATE INDEX IF NOT EXISTS ethereum_deposit_events_deposit_receiver_index ON ethereum_deposit_events ((substring(deposit_receiver FROM 1 FOR 29)), insert_time);
+
+-- Time when the Merkle root was recorded. NULL for roots recorded before the
+-- column was added.
+ALTER TABLE merkle_roots ADD COLUMN IF NOT EXISTS insert_time timestamp with time zone;
+ALTER TABLE merkle_roots ALTER COLUMN insert_time SET DEFAULT NOW();
+
+-- Summaries for the statistics endpoint of the API server, so that it does not
+-- have to aggregate all events on each request. They are refreshed periodically
+-- by the relayer. Amounts are in the smallest unit of the token.
+
+-- Totals for each mapped token. Deposits are completed when they are done on
+-- Concordium, and withdrawals when they are claimed on Ethereum. The amount
+-- locked in the vault on Ethereum is all deposits minus completed withdrawals.
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_stats AS
+SELECT tm.root, tm.child_index, tm.child_subindex, tm.eth_name, tm.decimals,
+       COALESCE(d.deposited, 0) AS deposited,
+       COALESCE(d.pending_deposits, 0) AS pending_deposits,
+       COALESCE(d.num_pending_deposits, 0) AS num_pending_deposits,
+       COALESCE(w.withdrawn, 0) AS withdrawn,
+       COALESCE(w.pending_withdrawals, 0) AS pending_withdrawals,
+       COALESCE(w.num_pending_withdrawals, 0) AS num_pending_withdrawals,
+       COALESCE(d.deposited, 0) + COALESCE(d.pending_deposits, 0) - COALESCE(w.withdrawn, 0) AS locked
+FROM token_maps tm
+LEFT JOIN (SELECT root_token,
+                  SUM(amount::NUMERIC) FILTER (WHERE tx_hash IS NOT NULL) AS deposited,
+                  SUM(amount::NUMERIC) FILTER (WHERE tx_hash IS NULL) AS pending_deposits,
+                  COUNT(*) FILTER (WHERE tx_hash IS NULL) AS num_pending_deposits
+           FROM ethereum_deposit_events GROUP BY root_token) d
+       ON d.root_token = tm.root
+LEFT JOIN (SELECT child_index, child_subindex,
+                  SUM(amount::NUMERIC) FILTER (WHERE processed IS NOT NULL) AS withdrawn,
+                  SUM(amount::NUMERIC) FILTER (WHERE processed IS NULL) AS pending_withdrawals,
+                  COUNT(*) FILTER (WHERE processed IS NULL) AS num_pending_withdrawals
+           FROM concordium_events WHERE event_type = 'withdraw'
+           GROUP BY child_index, child_subindex) w
+       ON w.child_index = tm.child_index AND w.child_subindex = tm.child_subindex;
+-- A unique index is needed for refreshing the view concurrently.
+CREATE UNIQUE INDEX IF NOT EXISTS token_stats_root_index ON token_stats (root);
+
+-- Volume of deposits and withdrawals of each mapped token by hour, by the time
+-- of the block that contains the event, or the time it was discovered if that
+-- is not known.
+CREATE MATERIALIZED VIEW IF NOT EXISTS hourly_volumes AS
+SELECT root, hour,
+       SUM(deposited) AS deposited, SUM(num_deposits) AS num_deposits,
+       SUM(withdrawn) AS withdrawn, SUM(num_withdrawals) AS num_withdrawals
+FROM (SELECT root_token AS root,
+             date_trunc('hour', COALESCE(block_time, insert_time) AT TIME ZONE 'UTC') AS hour,
+             amount::NUMERIC AS deposited, 1 AS num_deposits,
+             0 AS withdrawn, 0 AS num_withdrawals
+      FROM ethereum_deposit_events
+      UNION ALL
+      SELECT tm.root,
+             date_trunc('hour', COALESCE(ce.block_time, ce.insert_time) AT TIME ZONE 'UTC'),
+             0, 0, ce.amount::NUMERIC, 1
+      FROM concordium_events ce
+      JOIN token_maps tm
+        ON tm.child_index = ce.child_index AND tm.child_subindex = ce.child_subindex
+      WHERE ce.event_type = 'withdraw') v
+GROUP BY root, hour;
+CREATE UNIQUE INDEX IF NOT EXISTS hourly_volumes_index ON hourly_volumes (root, hour);
+
+DO $$ BEGIN
+CREATE TYPE admin_action AS ENUM (
+    'approve_withdrawal',
+    'approve_deposit',
+    'requeue_deposit',
+    'merkle_update'
+   );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Audit record of the actions taken through the admin API of the API server.
+-- Each row is written in the same database transaction as the action itself.
+CREATE TABLE IF NOT EXISTS admin_actions (
+       id SERIAL8 PRIMARY KEY UNIQUE,
+       -- The action taken.
+       action admin_action NOT NULL,
+       -- For withdrawals the index of the withdraw event on Concordium, for
+       -- deposits the index of the deposit event on Ethereum. NULL for Merkle
+       -- updates.
+       event_index INT8,
+       -- Name of the token the operator authenticated with.
+       operator TEXT NOT NULL,
+       -- Address of the client that made the request.
+       remote_address TEXT NOT NULL,
+       -- Reason given by the operator, if any.
+       reason TEXT,
+       -- Time of the action.
+       insert_time timestamp with time zone NOT NULL DEFAULT NOW()
+);
+
+-- Indices for the benefit of the search endpoint of the API server, so that it
+-- can efficiently find the deposit or withdrawal a transaction belongs to.
+CREATE INDEX IF NOT EXISTS ethereum_deposit_events_origin_tx_hash_index ON ethereum_deposit_events (origin_tx_hash);
+CREATE INDEX IF NOT EXISTS ethereum_deposit_events_tx_hash_index ON ethereum_deposit_events (tx_hash);
+CREATE INDEX IF NOT EXISTS concordium_events_tx_hash_index ON concordium_events (tx_hash);
+CREATE INDEX IF NOT EXISTS concordium_events_processed_index ON concordium_events (processed);
+CREATE INDEX IF NOT EXISTS concordium_events_pending_root_index ON concordium_events (pending_root) WHERE pending_root IS NOT NULL;
+CREATE INDEX IF NOT EXISTS concordium_transactions_origin_tx_hash_index ON concordium_transactions (origin_tx_hash);
+
+-- If withdraw, the first Merkle root that approved the withdrawal, and the time
+-- it was recorded. Later roots also include the withdrawal until it is claimed,
+-- and only replace `root`. NULL until approved, and for withdrawals approved
+-- before the columns were added.
+ALTER TABLE concordium_events ADD COLUMN IF NOT EXISTS first_root BYTEA;
+ALTER TABLE concordium_events ADD COLUMN IF NOT EXISTS first_root_time timestamp with time zone;
+-- The Merkle root set by the Ethereum transaction. For transactions inserted
+-- before the column was added it is set from the calldata when the relayer
+-- starts.
+ALTER TABLE ethereum_transactions ADD COLUMN IF NOT EXISTS root BYTEA;
+CREATE INDEX IF NOT EXISTS ethereum_transactions_root_index ON ethereum_transactions (root);
+CREATE INDEX IF NOT EXISTS merkle_roots_root_index ON merkle_roots (root);
+-- The root reported for a withdrawal by the search endpoint of the API server.
+CREATE INDEX IF NOT EXISTS concordium_events_first_root_index ON concordium_events ((COALESCE(first_root, root)));
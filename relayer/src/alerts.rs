@@ -0,0 +1,358 @@
+//! Alerts posted to webhooks. Alerts are produced from the warnings and errors
+//! that are logged, so every place that logs a warning or an error can raise an
+//! alert. Events may set the following fields to control the alert
+//! - `alert`, a stable name of the condition. Alerts with the same name are
+//!   deduplicated. If not set the location of the log statement is used.
+//! - `critical`, if `true` the alert has [`Severity::Critical`].
+//!
+//! Alerts are sent by a separate task, which deduplicates them and limits the
+//! rate at which they are sent. The alert layer has its own level filter, so
+//! alerts are raised regardless of the log level.
+use std::collections::HashMap;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::Context;
+
+/// Capacity of the queue of alerts waiting to be sent. Alerts are dropped if
+/// the queue is full.
+const ALERT_QUEUE_SIZE: usize = 100;
+
+/// Timeout for posting an alert to a webhook.
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long to wait for the queued alerts to be sent when stopping.
+pub const FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+    Critical,
+}
+
+#[derive(Debug, serde::Serialize)]
+/// The JSON body posted to the webhooks.
+pub struct Alert {
+    /// The service that raised the alert.
+    pub service:    &'static str,
+    pub severity:   Severity,
+    /// Name of the condition, used for deduplication.
+    pub alert:      String,
+    pub message:    String,
+    /// Other fields of the logged event.
+    pub fields:     serde_json::Map<String, serde_json::Value>,
+    /// Unix timestamp in seconds.
+    pub timestamp:  i64,
+    /// Number of alerts with the same name that were suppressed since the last
+    /// one was sent.
+    pub suppressed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// The URLs the alerts are posted to.
+    pub webhooks:       Vec<url::Url>,
+    /// Alerts with lower severity are not sent.
+    pub min_severity:   Severity,
+    /// An alert is not sent again within this window after it was sent.
+    pub dedup_window:   std::time::Duration,
+    /// Maximum number of alerts sent per minute.
+    pub max_per_minute: u32,
+}
+
+#[derive(Default)]
+struct AlertVisitor {
+    message:  String,
+    alert:    Option<String>,
+    critical: bool,
+    fields:   serde_json::Map<String, serde_json::Value>,
+}
+
+impl Visit for AlertVisitor {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "critical" {
+            self.critical = value;
+        } else {
+            self.fields.insert(field.name().into(), value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.into(),
+            "alert" => self.alert = Some(value.into()),
+            name => {
+                self.fields.insert(name.into(), value.into());
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"))
+    }
+}
+
+/// A layer that turns logged warnings and errors into alerts.
+pub struct AlertLayer {
+    service:      &'static str,
+    min_severity: Severity,
+    sender:       tokio::sync::mpsc::Sender<Alert>,
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for AlertLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // Do not alert on failures to send alerts.
+        if metadata.target() == module_path!() {
+            return;
+        }
+        let level_severity = match *metadata.level() {
+            tracing::Level::ERROR => Severity::Error,
+            tracing::Level::WARN => Severity::Warning,
+            _ => return,
+        };
+        let mut visitor = AlertVisitor::default();
+        event.record(&mut visitor);
+        let severity = if visitor.critical {
+            Severity::Critical
+        } else {
+            level_severity
+        };
+        if severity < self.min_severity {
+            return;
+        }
+        let alert = visitor.alert.unwrap_or_else(|| {
+            format!(
+                "{}:{}",
+                metadata.module_path().unwrap_or_else(|| metadata.target()),
+                metadata.line().unwrap_or_default()
+            )
+        });
+        // If the queue is full the alert is dropped. The sender reports this.
+        let _ = self.sender.try_send(Alert {
+            service: self.service,
+            severity,
+            alert,
+            message: visitor.message,
+            fields: visitor.fields,
+            timestamp: chrono::Utc::now().timestamp(),
+            suppressed: 0,
+        });
+    }
+}
+
+/// The task that sends the alerts.
+pub struct AlertTask {
+    handle: tokio::task::JoinHandle<()>,
+    stop:   tokio::sync::oneshot::Sender<()>,
+}
+
+impl AlertTask {
+    /// Send the alerts that are already queued and stop the task. Alerts that
+    /// are not sent within `timeout` are dropped.
+    pub async fn flush(self, timeout: std::time::Duration) {
+        // If the task has already stopped there is nothing to flush.
+        let _ = self.stop.send(());
+        if tokio::time::timeout(timeout, self.handle).await.is_err() {
+            tracing::warn!(
+                "Not all alerts were sent within {}s when stopping.",
+                timeout.as_secs()
+            );
+        }
+    }
+}
+
+/// Construct the layer that raises alerts, and spawn the task that sends them.
+pub fn alerts(service: &'static str, config: AlertConfig) -> (AlertLayer, AlertTask) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(ALERT_QUEUE_SIZE);
+    let (stop, stop_receiver) = tokio::sync::oneshot::channel();
+    let layer = AlertLayer {
+        service,
+        min_severity: config.min_severity,
+        sender: sender.clone(),
+    };
+    let handle = tokio::spawn(send_alerts(config, sender, receiver, stop_receiver));
+    (layer, AlertTask { handle, stop })
+}
+
+async fn send_alerts(
+    config: AlertConfig,
+    // Used to detect alerts dropped because the queue is full.
+    sender: tokio::sync::mpsc::Sender<Alert>,
+    mut receiver: tokio::sync::mpsc::Receiver<Alert>,
+    mut stop: tokio::sync::oneshot::Receiver<()>,
+) {
+    let client = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Unable to construct the client for sending alerts: {e:#}");
+            return;
+        }
+    };
+    let mut dispatcher = Dispatcher {
+        config,
+        client,
+        last_sent: HashMap::new(),
+        recent: std::collections::VecDeque::new(),
+    };
+    loop {
+        let alert = tokio::select! {
+            alert = receiver.recv() => alert,
+            // Stopping, or the task handle was dropped. Send the alerts that
+            // are queued without waiting for new ones.
+            _ = &mut stop => {
+                receiver.close();
+                while let Some(alert) = receiver.recv().await {
+                    dispatcher.dispatch(alert).await;
+                }
+                return;
+            }
+        };
+        let Some(alert) = alert else {
+            return;
+        };
+        if sender.capacity() == 0 {
+            tracing::warn!("The alert queue is full. Alerts are being dropped.");
+        }
+        dispatcher.dispatch(alert).await;
+    }
+}
+
+/// Deduplicates, rate limits, and posts alerts.
+struct Dispatcher {
+    config:    AlertConfig,
+    client:    reqwest::Client,
+    /// For each alert, when it was last sent and how many were suppressed
+    /// since.
+    last_sent: HashMap<String, (tokio::time::Instant, u64)>,
+    /// Times at which alerts were sent within the last minute.
+    recent:    std::collections::VecDeque<tokio::time::Instant>,
+}
+
+impl Dispatcher {
+    async fn dispatch(&mut self, mut alert: Alert) {
+        let now = tokio::time::Instant::now();
+        if let Some((time, suppressed)) = self.last_sent.get_mut(&alert.alert) {
+            if now.duration_since(*time) < self.config.dedup_window {
+                *suppressed += 1;
+                return;
+            }
+            alert.suppressed = *suppressed;
+        }
+        while self.recent.front().map_or(false, |t| {
+            now.duration_since(*t) >= std::time::Duration::from_secs(60)
+        }) {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= self.config.max_per_minute as usize {
+            tracing::warn!(
+                "Alert rate limit reached. Not sending alert {}: {}",
+                alert.alert,
+                alert.message
+            );
+            return;
+        }
+        self.recent.push_back(now);
+        self.last_sent.insert(alert.alert.clone(), (now, 0));
+        for webhook in &self.config.webhooks {
+            let response = self
+                .client
+                .post(webhook.clone())
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if let Err(e) = response {
+                tracing::warn!(
+                    "Unable to post alert {} to {}: {e}",
+                    alert.alert,
+                    webhook.host_str().unwrap_or_default()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::{filter::LevelFilter, prelude::*};
+
+    /// Start a webhook that forwards the alerts posted to it, and return its
+    /// URL.
+    fn webhook_stub(alerts: tokio::sync::mpsc::UnboundedSender<serde_json::Value>) -> url::Url {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Unable to bind.");
+        let address = listener.local_addr().expect("Unable to get the address.");
+        let app = axum::Router::new().route(
+            "/alerts",
+            axum::routing::post(move |axum::Json(alert): axum::Json<serde_json::Value>| {
+                let alerts = alerts.clone();
+                async move {
+                    let _ = alerts.send(alert);
+                }
+            }),
+        );
+        let server = axum::Server::from_tcp(listener)
+            .expect("Unable to start the webhook.")
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        format!("http://{address}/alerts")
+            .parse()
+            .expect("Invalid URL.")
+    }
+
+    #[tokio::test]
+    async fn flush_sends_queued_alerts() {
+        let (alerts_sender, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let config = AlertConfig {
+            webhooks:       vec![webhook_stub(alerts_sender)],
+            min_severity:   Severity::Warning,
+            dedup_window:   std::time::Duration::from_secs(60),
+            max_per_minute: 10,
+        };
+        let (layer, task) = alerts("test", config);
+        // As in the global subscriber, the log filter only applies to the log
+        // output and not to the alerts.
+        let subscriber = tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_test_writer()
+                    .with_filter(LevelFilter::OFF),
+            )
+            .with(layer.with_filter(LevelFilter::WARN));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Not an alert.");
+            tracing::error!(alert = "balance_low", "Balance is low.");
+            tracing::error!(alert = "balance_low", "Balance is still low.");
+            tracing::warn!(
+                alert = "breaker",
+                critical = true,
+                token = "<1,0>",
+                "Tripped."
+            );
+        });
+        task.flush(std::time::Duration::from_secs(5)).await;
+
+        let first = received.try_recv().expect("The first alert is sent.");
+        assert_eq!(first["service"], "test");
+        assert_eq!(first["severity"], "error");
+        assert_eq!(first["alert"], "balance_low");
+        assert_eq!(first["message"], "Balance is low.");
+        let second = received.try_recv().expect("The second alert is sent.");
+        assert_eq!(second["severity"], "critical");
+        assert_eq!(second["alert"], "breaker");
+        assert_eq!(second["fields"]["token"], "<1,0>");
+        assert!(
+            received.try_recv().is_err(),
+            "The repeated alert is suppressed."
+        );
+    }
+}
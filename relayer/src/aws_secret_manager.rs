@@ -9,7 +9,7 @@ use ethers::signers::LocalWallet;
 /// - `AWS_SESSION_TOKEN`
 /// - `AWS_REGION`
 pub async fn get_concordium_keys_aws(secret_name: &str) -> anyhow::Result<WalletAccount> {
-    log::debug!("Loading Concordium keys from AWS secret manager!");
+    tracing::debug!("Loading Concordium keys from AWS secret manager!");
     let shared_config = aws_config::load_from_env().await;
 
     let client = Client::new(&shared_config);
@@ -33,7 +33,7 @@ pub async fn get_concordium_keys_aws(secret_name: &str) -> anyhow::Result<Wallet
 /// - `AWS_SESSION_TOKEN`
 /// - `AWS_REGION`
 pub async fn get_ethereum_keys_aws(secret_name: &str) -> anyhow::Result<LocalWallet> {
-    log::debug!("Loading Ethereum keys from AWS secret manager!");
+    tracing::debug!("Loading Ethereum keys from AWS secret manager!");
     let shared_config = aws_config::load_from_env().await;
 
     let client = Client::new(&shared_config);
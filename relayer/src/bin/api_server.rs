@@ -1,17 +1,33 @@
 use anyhow::Context;
-use axum::{http::StatusCode, Json};
+use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum_prometheus::PrometheusMetricLayerBuilder;
-use ccdeth_relayer::db::TransactionStatus;
+use ccdeth_relayer::{
+    concordium_contracts::{BridgeManagerClient, DepositOperation},
+    db::{
+        EthTransactionStatus, StatusNotification, TransactionStatus, TransferKind, TransferStage,
+        WithdrawalHoldReason, ADMIN_CHANNEL, ADMIN_LISTENER_APPLICATION_NAME,
+        MERKLE_UPDATE_REQUEST, STATUS_CHANNEL,
+    },
+    merkle::{Keccak256Algorithm, MerkleData},
+    telemetry,
+};
 use clap::Parser;
 use concordium::{
     cis2::TokenId,
     types::{hashes::TransactionHash, ContractAddress},
+    v2::{self, BlockIdentifier},
 };
 use concordium_rust_sdk as concordium;
+use ethers::{
+    prelude::{Http, Middleware, Provider, TransactionRequest},
+    types::{transaction::eip2718::TypedTransaction, U256},
+};
+use futures::StreamExt;
 use postgres_types::FromSql;
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use tokio_postgres::NoTls;
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse};
+use tonic::transport::ClientTlsConfig;
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, MakeSpan};
 use utoipa::{openapi::ObjectBuilder, OpenApi};
 
 #[derive(Parser, Debug)]
@@ -23,56 +39,188 @@ struct Api {
         help = "Maximum log level.",
         env = "ETHCCD_API_LOG_LEVEL"
     )]
-    log_level:          tracing_subscriber::filter::LevelFilter,
+    log_level:            tracing_subscriber::filter::LevelFilter,
+    #[clap(
+        long = "log-format",
+        default_value = "text",
+        help = "Format of the log output.",
+        env = "ETHCCD_API_LOG_FORMAT"
+    )]
+    log_format:           telemetry::LogFormat,
+    #[clap(
+        long = "otlp-endpoint",
+        help = "Endpoint of an OpenTelemetry collector to export traces to over OTLP/gRPC, e.g., \
+                http://localhost:4317. If not set traces are not exported.",
+        env = "ETHCCD_API_OTLP_ENDPOINT"
+    )]
+    otlp_endpoint:        Option<String>,
     #[clap(
         long = "db",
         default_value = "host=localhost dbname=relayer user=postgres password=password port=5432",
         help = "Database connection string.",
         env = "ETHCCD_API_DB_STRING"
     )]
-    db_config:          tokio_postgres::Config,
+    db_config:            tokio_postgres::Config,
     #[clap(
         long = "listen-address",
         default_value = "0.0.0.0:8080",
         help = "Listen address for the server.",
         env = "ETHCCD_API_LISTEN_ADDRESS"
     )]
-    listen_address:     std::net::SocketAddr,
+    listen_address:       std::net::SocketAddr,
     #[clap(
         long = "prometheus-address",
         default_value = "0.0.0.0:9090",
         help = "Listen address for the server.",
         env = "ETHCCD_API_PROMETHEUS_ADDRESS"
     )]
-    prometheus_address: Option<std::net::SocketAddr>,
+    prometheus_address:   Option<std::net::SocketAddr>,
     #[clap(
         long = "max-pool-size",
         default_value = "16",
         help = "Maximum size of a database connection pool.",
         env = "ETHCCD_API_MAX_DB_CONNECTION_POOL_SIZE"
     )]
-    max_pool_size:      usize,
+    max_pool_size:        usize,
     #[clap(
         long = "request-timeout",
         default_value = "1000",
         help = "Request timeout in millisecons.",
         env = "ETHCCD_API_REQUEST_TIMEOUT"
     )]
-    request_timeout:    u64,
+    request_timeout:      u64,
     #[clap(
         long = "assets-dir",
         help = "Serve files from the supplied directory under /assets.",
         env = "ETHCCD_API_SERVE_ASSETS"
     )]
-    assets_dir:         Option<PathBuf>,
+    assets_dir:           Option<PathBuf>,
     #[clap(
         long = "log-headers",
         help = "Whether to log headers for requests and responses.",
         env = "ETHCCD_API_LOG_HEADERS"
     )]
-    log_headers:        bool,
+    log_headers:          bool,
+    #[clap(
+        long = "root-chain-manager-address",
+        help = "Address of the RootChainManager proxy instance on Ethereum. If set, it is \
+                returned as the target of the withdraw transactions in Merkle proof responses.",
+        env = "ETHCCD_API_ROOT_CHAIN_MANAGER_PROXY"
+    )]
+    root_chain_manager:   Option<ethers::types::Address>,
+    #[clap(
+        long = "ethereum-api",
+        help = "JSON-RPC interface of an Ethereum node, used to query the fees of the \
+                RootChainManager and estimate the gas of its transactions. Only HTTPS is \
+                supported as transport.",
+        env = "ETHCCD_API_ETHEREUM_API",
+        requires = "root_chain_manager"
+    )]
+    ethereum_api:         Option<url::Url>,
+    #[clap(
+        long = "deposit-gas",
+        help = "Gas used by `depositFor` on the RootChainManager, used for its cost if the gas \
+                cannot be estimated or the estimate is lower.",
+        env = "ETHCCD_API_DEPOSIT_GAS"
+    )]
+    deposit_gas:          Option<u64>,
+    #[clap(
+        long = "deposit-ether-gas",
+        help = "Gas used by `depositEtherFor` on the RootChainManager, used for its cost if the \
+                gas cannot be estimated or the estimate is lower.",
+        env = "ETHCCD_API_DEPOSIT_ETHER_GAS"
+    )]
+    deposit_ether_gas:    Option<u64>,
+    #[clap(
+        long = "withdraw-gas",
+        help = "Gas used by `withdraw` on the RootChainManager, used for its cost if the gas \
+                cannot be estimated or the estimate is lower.",
+        env = "ETHCCD_API_WITHDRAW_GAS"
+    )]
+    withdraw_gas:         Option<u64>,
+    #[clap(
+        long = "concordium-api",
+        help = "GRPC V2 interface of a Concordium node, used to query the fees of the bridge \
+                manager.",
+        env = "ETHCCD_API_CONCORDIUM_API",
+        requires = "bridge_manager"
+    )]
+    concordium_api:       Option<v2::Endpoint>,
+    #[clap(
+        long = "bridge-manager-address",
+        help = "Address of the BridgeManager contract instance on Concordium.",
+        env = "ETHCCD_API_BRIDGE_MANAGER",
+        requires = "concordium_api"
+    )]
+    bridge_manager:       Option<ContractAddress>,
+    #[clap(
+        long = "admin-listen-address",
+        help = "Listen address for the admin API. If not set the admin API is not served.",
+        env = "ETHCCD_API_ADMIN_LISTEN_ADDRESS",
+        requires = "admin_tokens"
+    )]
+    admin_listen_address: Option<std::net::SocketAddr>,
+    #[clap(
+        long = "admin-allow-remote",
+        help = "Allow the admin API to listen on an address that is not a loopback address. The \
+                admin API is served over plain HTTP, so it must then only be reached through a \
+                proxy that terminates TLS.",
+        env = "ETHCCD_API_ADMIN_ALLOW_REMOTE"
+    )]
+    admin_allow_remote:   bool,
+    #[clap(
+        long = "admin-token",
+        help = "Token of an operator of the admin API, in the format `name:token`. The token is \
+                given as a bearer token in the `Authorization` header, and the name is recorded \
+                with the actions taken with it. Can be given multiple times.",
+        env = "ETHCCD_API_ADMIN_TOKENS",
+        value_delimiter = ' '
+    )]
+    admin_tokens:         Vec<AdminToken>,
+    #[clap(
+        long = "api-key",
+        help = "API key of a client of the public API, in the format `name:key:quota`, where the \
+                quota is the number of requests per minute. Requests with a key in the \
+                `X-API-Key` header are limited by the quota of the key instead of the rate \
+                limits, where a proof counts as 10 requests. Can be given multiple times.",
+        env = "ETHCCD_API_API_KEYS",
+        value_delimiter = ' '
+    )]
+    api_keys:             Vec<ApiKey>,
+    #[clap(
+        long = "rate-limit",
+        help = "Rate limit of anonymous requests per client IP to a class of routes, in the \
+                format `class:rate:burst`, where the class is one of `proof`, `query` or `light`, \
+                the rate is in requests per second, and the burst is the number of requests that \
+                can be made at once. Routes of a class without a limit are not limited. Can be \
+                given multiple times.",
+        env = "ETHCCD_API_RATE_LIMITS",
+        value_delimiter = ' '
+    )]
+    rate_limits:          Vec<RouteLimit>,
+    #[clap(
+        long = "trusted-proxy",
+        help = "Address or network, e.g., `10.0.0.0/8`, of a proxy whose `X-Forwarded-For` header \
+                is used to find the IP of the client for rate limiting. Can be given multiple \
+                times.",
+        env = "ETHCCD_API_TRUSTED_PROXIES",
+        value_delimiter = ' '
+    )]
+    trusted_proxies:      Vec<IpNetwork>,
+    #[clap(
+        long = "rate-limit-ipv6-prefix",
+        help = "Length of the prefix of the IPv6 networks that are each rate limited as a single \
+                client IP, since a client is usually given a whole network.",
+        env = "ETHCCD_API_RATE_LIMIT_IPV6_PREFIX",
+        default_value = "64"
+    )]
+    ipv6_client_prefix:   u32,
 }
 
+/// Maximum size of a request body in bytes. This is enough for the maximum
+/// number of withdrawals in a batch proof request.
+const MAX_REQUEST_BODY_SIZE: usize = 64 * 1024;
+
 /// A unit struct used to anchor the generated openapi.json spec.
 #[derive(utoipa::OpenApi)]
 #[openapi(
@@ -81,20 +229,54 @@ struct Api {
         watch_withdraw,
         list_tokens,
         wallet_transactions,
+        concordium_wallet_transactions,
+        watch_status,
         get_merkle_proof,
+        get_merkle_proofs,
         expected_merkle_root_update,
+        stats,
+        fees,
+        search,
     ),
     components(schemas(
         WatchTxResponse,
         WatchWithdrawalResponse,
         TokenMapItem,
         WalletTx,
+        WalletTxPage,
+        WalletTxDirection,
+        SortOrder,
         TransactionStatus,
         EthMerkleProofResponse,
         WithdrawParams,
+        WithdrawTransaction,
         WalletDepositTx,
+        DepositHold,
         WalletWithdrawTx,
-        WithdrawalStatus
+        WithdrawalStatus,
+        WithdrawalHold,
+        WithdrawalHoldReason,
+        TransferUpdate,
+        WithdrawalId,
+        BatchProofRequest,
+        BatchProofItem,
+        MultiProof,
+        BatchProofResponse,
+        StatsInterval,
+        VolumeBucket,
+        TokenStats,
+        MerkleTreeStats,
+        StatsResponse,
+        FeesResponse,
+        ConcordiumFees,
+        EthereumFees,
+        TransactionCost,
+        TimelineStage,
+        TimelineEntry,
+        SearchDeposit,
+        SearchWithdrawal,
+        SearchMerkleRoot,
+        SearchResult
     ))
 )]
 struct ApiDoc;
@@ -103,15 +285,18 @@ struct ApiDoc;
 async fn main() -> anyhow::Result<()> {
     let app: Api = Api::parse();
     {
-        use tracing_subscriber::prelude::*;
         let log_filter = tracing_subscriber::filter::Targets::new()
             .with_target(module_path!(), app.log_level)
             .with_target("tower_http", app.log_level)
             .with_target("tokio_postgres", app.log_level);
-        tracing_subscriber::registry()
-            .with(tracing_subscriber::fmt::layer())
-            .with(log_filter)
-            .init();
+        telemetry::init(
+            "api_server",
+            log_filter,
+            app.log_format,
+            app.otlp_endpoint.as_deref(),
+            None,
+        )
+        .context("Unable to set up logging.")?;
     }
 
     let (prometheus_layer, metric_handle) = PrometheusMetricLayerBuilder::new()
@@ -119,8 +304,139 @@ async fn main() -> anyhow::Result<()> {
         .with_prefix("ccdeth_api_server")
         .build_pair();
 
+    let (status_sender, _) = tokio::sync::broadcast::channel(STATUS_BUFFER_SIZE);
+    tokio::spawn(listen_status(app.db_config.clone(), status_sender.clone()));
+
     let db = Database::new(app.db_config, app.max_pool_size).await?;
 
+    let root_chain_manager = match app.root_chain_manager {
+        Some(address) => {
+            let provider = match app.ethereum_api {
+                Some(url) => {
+                    let network_client = reqwest::ClientBuilder::new()
+                        .timeout(std::time::Duration::from_secs(10))
+                        .https_only(true)
+                        .build()
+                        .context("Unable to construct network client to access Ethereum API.")?;
+                    Some(Provider::new(Http::new_with_client(url, network_client)))
+                }
+                None => None,
+            };
+            Some(Arc::new(RootChainManager {
+                address,
+                provider,
+                ether_address: tokio::sync::OnceCell::new(),
+            }))
+        }
+        None => None,
+    };
+
+    let bridge_manager = match (app.concordium_api, app.bridge_manager) {
+        (Some(endpoint), Some(address)) => {
+            // Use TLS if the URI scheme is HTTPS.
+            let endpoint = if endpoint
+                .uri()
+                .scheme()
+                .map_or(false, |x| x == &http::uri::Scheme::HTTPS)
+            {
+                endpoint
+                    .tls_config(ClientTlsConfig::new())
+                    .context("Unable to construct TLS configuration for the Concordium API.")?
+            } else {
+                endpoint
+            };
+            let endpoint = endpoint
+                .timeout(std::time::Duration::from_secs(10))
+                .connect_timeout(std::time::Duration::from_secs(10));
+            let client = v2::Client::new(endpoint)
+                .await
+                .context("Unable to connect Concordium node.")?;
+            Some(BridgeManagerClient::new(client, None, address))
+        }
+        _ => None,
+    };
+    let (fees_sender, fees_receiver) = tokio::sync::watch::channel(FeesResponse::default());
+    tokio::spawn(refresh_fees(
+        db.clone(),
+        bridge_manager,
+        root_chain_manager.clone(),
+        TransactionGas {
+            deposit_for:       app.deposit_gas,
+            deposit_ether_for: app.deposit_ether_gas,
+            withdraw:          app.withdraw_gas,
+        },
+        fees_sender,
+    ));
+
+    if let Some(admin_address) = app.admin_listen_address {
+        // The admin tokens are sent in plain text, so they must not leave the host
+        // unless a TLS proxy is in front of the admin API.
+        anyhow::ensure!(
+            admin_address.ip().is_loopback() || app.admin_allow_remote,
+            "The admin API may only listen on a loopback address unless `--admin-allow-remote` is \
+             given."
+        );
+        let tokens: Arc<[AdminToken]> = app.admin_tokens.into();
+        let admin_api = axum::Router::new()
+            .route(
+                "/admin/v1/transactions",
+                axum::routing::get(admin_transactions),
+            )
+            .route("/admin/v1/merkle", axum::routing::get(admin_merkle))
+            .route(
+                "/admin/v1/merkle/update",
+                axum::routing::post(admin_merkle_update),
+            )
+            .route("/admin/v1/held", axum::routing::get(admin_held))
+            .route(
+                "/admin/v1/withdrawals/:event_index/approve",
+                axum::routing::post(admin_approve_withdrawal),
+            )
+            .route(
+                "/admin/v1/deposits/:event_index/approve",
+                axum::routing::post(admin_approve_deposit),
+            )
+            .route(
+                "/admin/v1/deposits/:event_index/requeue",
+                axum::routing::post(admin_requeue_deposit),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                tokens,
+                authenticate_admin,
+            ))
+            .with_state(db.clone())
+            .layer(tower_http::trace::TraceLayer::new_for_http())
+            .layer(tower_http::timeout::TimeoutLayer::new(
+                std::time::Duration::from_millis(app.request_timeout),
+            ))
+            .layer(tower_http::limit::RequestBodyLimitLayer::new(
+                MAX_ADMIN_REQUEST_BODY_SIZE,
+            ));
+        // Bind before spawning the server, so that the API server does not start
+        // without the admin API if the address cannot be used.
+        let admin_server = axum::Server::try_bind(&admin_address)
+            .with_context(|| format!("Unable to bind the admin API to {admin_address}."))?
+            .serve(admin_api.into_make_service_with_connect_info::<std::net::SocketAddr>());
+        tracing::info!("Serving the admin API on {admin_address}.");
+        tokio::spawn(async move {
+            if let Err(e) = admin_server.await {
+                tracing::error!("The admin API stopped: {e}");
+            }
+        });
+    }
+
+    anyhow::ensure!(
+        app.ipv6_client_prefix <= 128,
+        "The IPv6 prefix length must be at most 128."
+    );
+    let rate_limiter = Arc::new(RateLimiter::new(
+        &app.rate_limits,
+        &app.api_keys,
+        app.trusted_proxies,
+        app.ipv6_client_prefix,
+    ));
+    tokio::spawn(prune_rate_limits(rate_limiter.clone()));
+
     let openapi = ApiDoc::openapi();
 
     // Serve static files.
@@ -144,27 +460,56 @@ async fn main() -> anyhow::Result<()> {
             "/api/v1/ethereum/proof/:tx_hash/:event_id",
             axum::routing::get(get_merkle_proof),
         )
+        .route(
+            "/api/v1/ethereum/proofs",
+            axum::routing::post(get_merkle_proofs),
+        )
         .route("/api/v1/tokens", axum::routing::get(list_tokens))
         .route("/api/v1/expectedMerkleRootUpdate", axum::routing::get(expected_merkle_root_update))
         .route(
             "/api/v1/wallet/:wallet",
             axum::routing::get(wallet_transactions),
         )
+        .route(
+            "/api/v1/concordium/wallet/:account",
+            axum::routing::get(concordium_wallet_transactions),
+        )
+        .route("/api/v1/events", axum::routing::get(watch_status))
+        .route("/api/v1/stats", axum::routing::get(stats))
+        .route("/api/v1/fees", axum::routing::get(fees))
+        .route("/api/v1/search/:hash", axum::routing::get(search))
         .route(
             "/openapi.json",
             axum::routing::get(|| async move { Json(openapi) }),
         )
-        .with_state(db)
+        // Applied to the matched routes only, so that the route class is known.
+        .route_layer(axum::middleware::from_fn_with_state(rate_limiter, rate_limit))
+        .with_state(AppState {
+            db,
+            status_sender,
+            root_chain_manager,
+            fees: fees_receiver,
+        })
         .layer(tower_http::trace::TraceLayer::new_for_http().
-               make_span_with(DefaultMakeSpan::new().
-                              include_headers(app.log_headers)).
+               make_span_with(move |request: &http::Request<_>| {
+                   // Continue the trace of the caller, if any.
+                   let span = DefaultMakeSpan::new().
+                       include_headers(app.log_headers).
+                       make_span(request);
+                   telemetry::set_remote_parent(&span, request.headers());
+                   span
+               }).
                on_response(DefaultOnResponse::new().
                            include_headers(app.log_headers)))
         .layer(tower_http::timeout::TimeoutLayer::new(
             std::time::Duration::from_millis(app.request_timeout),
         ))
-        .layer(tower_http::limit::RequestBodyLimitLayer::new(0)) // no bodies, we only have GET requests.
-        .layer(tower_http::cors::CorsLayer::permissive().allow_methods([http::Method::GET]))
+        // Only the batch proof endpoint takes a body.
+        .layer(tower_http::limit::RequestBodyLimitLayer::new(MAX_REQUEST_BODY_SIZE))
+        .layer(
+            tower_http::cors::CorsLayer::permissive()
+                .allow_methods([http::Method::GET, http::Method::POST]),
+        )
         .layer(prometheus_layer);
 
     if let Some(prometheus_address) = app.prometheus_address {
@@ -189,9 +534,10 @@ async fn main() -> anyhow::Result<()> {
     // run our app with hyper
     tracing::debug!("listening on {}", app.listen_address);
     axum::Server::bind(&app.listen_address)
-        .serve(api.into_make_service())
+        .serve(api.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .context("Unable to start server.")?;
+    telemetry::shutdown();
     Ok(())
 }
 
@@ -281,7 +627,7 @@ impl<'a, const N: usize> FromSql<'a> for Fixed<N> {
 struct WalletDepositTx {
     #[schema(schema_with = hex_string)]
     root_token:         ethers::prelude::Address,
-    status:             TransactionStatus,
+    status:             WithdrawalStatus,
     #[schema(schema_with = optional_hash)]
     tx_hash:            Option<TransactionHash>,
     #[schema(schema_with = hex_string)]
@@ -289,6 +635,15 @@ struct WalletDepositTx {
     origin_event_index: u64,
     amount:             String,
     timestamp:          i64,
+    /// Present if the deposit is held until the operator approves it.
+    hold:               Option<DepositHold>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// Details of a deposit that is held because it matched a screening list.
+struct DepositHold {
+    /// Time when the deposit was held.
+    timestamp: i64,
 }
 
 #[derive(serde::Serialize, utoipa::ToSchema)]
@@ -304,18 +659,58 @@ struct WalletWithdrawTx {
     amount:             String,
     status:             WithdrawalStatus,
     timestamp:          i64,
+    hold:               Option<WithdrawalHold>,
 }
 
-#[derive(serde::Serialize, utoipa::ToSchema)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 enum WithdrawalStatus {
     #[serde(rename = "pending")]
     #[schema(rename = "pending")]
     Pending,
+    /// The withdrawal exceeded a limit and is held back from the Merkle tree,
+    /// or, in the wallet endpoints, the deposit matched a screening list and
+    /// is held until the operator approves it.
+    #[serde(rename = "held")]
+    #[schema(rename = "held")]
+    Held,
     #[serde(rename = "processed")]
     #[schema(rename = "processed")]
     Processed,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+/// Details of why a withdrawal is held back from the Merkle tree, and when it
+/// will be released.
+struct WithdrawalHold {
+    reason:       WithdrawalHoldReason,
+    /// Time when the withdrawal was held.
+    timestamp:    i64,
+    /// Time when the withdrawal will be released automatically. If not present
+    /// the withdrawal is only released by operator approval.
+    release_time: Option<i64>,
+}
+
+impl WithdrawalHold {
+    /// Parse the hold from a row that is joined with the active holds. Return
+    /// [`None`] if the withdrawal is not held.
+    fn from_row(row: &tokio_postgres::Row) -> Result<Option<Self>, Error> {
+        let Some(reason) = row.try_get::<_, Option<WithdrawalHoldReason>>("hold_reason")? else {
+            return Ok(None);
+        };
+        let timestamp = row
+            .try_get::<_, chrono::DateTime<chrono::Utc>>("hold_time")?
+            .timestamp();
+        let release_time = row
+            .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("hold_release_time")?
+            .map(|t| t.timestamp());
+        Ok(Some(Self {
+            reason,
+            timestamp,
+            release_time,
+        }))
+    }
+}
+
 #[derive(serde::Serialize, utoipa::ToSchema)]
 /// And item in the response from the /wallet endpoint.
 enum WalletTx {
@@ -323,6 +718,116 @@ enum WalletTx {
     Deposit(WalletDepositTx),
 }
 
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A page of the response from the wallet endpoints.
+struct WalletTxPage {
+    items:       Vec<WalletTx>,
+    /// Cursor to pass to get the next page. Not present if this is the last
+    /// page.
+    next_cursor: Option<String>,
+}
+
+/// All aliases of a Concordium account share the first 29 bytes of the
+/// address.
+const ACCOUNT_ALIAS_PREFIX_LEN: usize = 29;
+
+/// Number of transactions returned by the /wallet endpoint if no limit is
+/// given.
+const DEFAULT_WALLET_TXS_LIMIT: u16 = 20;
+
+/// Maximum number of transactions returned by the /wallet endpoint.
+const MAX_WALLET_TXS_LIMIT: u16 = 100;
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, utoipa::ToSchema)]
+enum WalletTxDirection {
+    #[serde(rename = "deposit")]
+    #[schema(rename = "deposit")]
+    Deposit,
+    #[serde(rename = "withdraw")]
+    #[schema(rename = "withdraw")]
+    Withdraw,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, utoipa::ToSchema)]
+enum SortOrder {
+    #[serde(rename = "asc")]
+    #[schema(rename = "asc")]
+    Asc,
+    #[serde(rename = "desc")]
+    #[schema(rename = "desc")]
+    Desc,
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Query parameters of the wallet endpoints.
+struct WalletTxsQuery {
+    /// Maximum number of transactions to return, at most 100. Defaults to 20.
+    limit:     Option<u16>,
+    /// The `next_cursor` of the previous page.
+    cursor:    Option<String>,
+    /// Only return transactions of the token with this address on Ethereum.
+    #[param(value_type = Option<String>)]
+    token:     Option<ethers::types::Address>,
+    /// Only return deposits or withdrawals.
+    direction: Option<WalletTxDirection>,
+    /// Only return transactions with this status.
+    status:    Option<WithdrawalStatus>,
+    /// Only return transactions made at or after this time, in seconds since
+    /// the unix epoch.
+    from:      Option<i64>,
+    /// Only return transactions made before this time, in seconds since the
+    /// unix epoch.
+    to:        Option<i64>,
+    /// Order of the transactions by time. Defaults to `desc`, newest first.
+    order:     Option<SortOrder>,
+}
+
+/// Position of a transaction in the ordering of the /wallet endpoint. It is
+/// given to the client as an opaque string.
+struct WalletCursor {
+    time: chrono::DateTime<chrono::Utc>,
+    /// 0 for withdrawals and 1 for deposits.
+    kind: i16,
+    /// The id of the event in its table.
+    id:   i64,
+}
+
+impl WalletCursor {
+    fn encode(&self) -> String {
+        base64::encode_config(
+            format!("{}.{}.{}", self.time.timestamp_micros(), self.kind, self.id),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let bytes = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+        let string = String::from_utf8(bytes).ok()?;
+        let mut parts = string.split('.');
+        let micros: i64 = parts.next()?.parse().ok()?;
+        let kind = parts.next()?.parse().ok()?;
+        let id = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        let time = chrono::TimeZone::timestamp_opt(
+            &chrono::Utc,
+            micros.div_euclid(1_000_000),
+            (micros.rem_euclid(1_000_000) * 1000) as u32,
+        )
+        .single()?;
+        Some(Self { time, kind, id })
+    }
+}
+
+/// Convert a time in seconds since the unix epoch from a query parameter.
+fn parse_unix_time(name: &str, seconds: i64) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+    chrono::TimeZone::timestamp_opt(&chrono::Utc, seconds, 0)
+        .single()
+        .ok_or_else(|| Error::InvalidRequest(format!("Invalid time in `{name}`.")))
+}
+
 #[utoipa::path(
         get,
         path = "api/v1/wallet/{wallet}",
@@ -330,10 +835,11 @@ enum WalletTx {
         params(
             ("wallet" = String,
             Path,
-            description = "Ethereum Wallet address.")
+            description = "Ethereum Wallet address."),
+            WalletTxsQuery
         ),
         responses(
-            (status = 200, description = "List wallet transactions.", body = [WalletTx]),
+            (status = 200, description = "List wallet transactions, ordered by time.", body = WalletTxPage),
             (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
             (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json"),
         )
@@ -341,71 +847,190 @@ enum WalletTx {
 #[tracing::instrument(level = "debug", skip(db))]
 async fn wallet_transactions(
     axum::extract::Path(wallet): axum::extract::Path<ethers::types::Address>,
+    axum::extract::Query(query): axum::extract::Query<WalletTxsQuery>,
     axum::extract::State(db): axum::extract::State<Database>,
-) -> Result<axum::Json<Vec<WalletTx>>, Error> {
+) -> Result<axum::Json<WalletTxPage>, Error> {
+    let page = query_wallet_txs(
+        &db,
+        &db.prepared_statements.ethereum_wallet_txs,
+        wallet.as_bytes(),
+        query,
+    )
+    .await?;
+    Ok(page.into())
+}
+
+/// List the deposits received by, and the withdrawals initiated by, a
+/// Concordium account.
+#[utoipa::path(
+        get,
+        path = "api/v1/concordium/wallet/{account}",
+        operation_id = "concordium_wallet_txs",
+        params(
+            ("account" = String,
+            Path,
+            description = "Concordium account address. Transactions of all aliases of the account are returned."),
+            WalletTxsQuery
+        ),
+        responses(
+            (status = 200, description = "List transactions of a Concordium account, ordered by time.", body = WalletTxPage),
+            (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
+            (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json"),
+        )
+    )]
+#[tracing::instrument(level = "debug", skip(db))]
+async fn concordium_wallet_transactions(
+    path: Result<
+        axum::extract::Path<concordium::id::types::AccountAddress>,
+        axum::extract::rejection::PathRejection,
+    >,
+    axum::extract::Query(query): axum::extract::Query<WalletTxsQuery>,
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<axum::Json<WalletTxPage>, Error> {
+    let account = match path {
+        Ok(p) => p.0,
+        Err(e) => {
+            return Err(Error::InvalidRequest(e.to_string()));
+        }
+    };
+    let page = query_wallet_txs(
+        &db,
+        &db.prepared_statements.concordium_wallet_txs,
+        &account.0[..ACCOUNT_ALIAS_PREFIX_LEN],
+        query,
+    )
+    .await?;
+    Ok(page.into())
+}
+
+/// Query a page of the transactions of an address, which is the first
+/// parameter of the `statements`.
+async fn query_wallet_txs(
+    db: &Database,
+    statements: &WalletStatements,
+    address: &[u8],
+    query: WalletTxsQuery,
+) -> Result<WalletTxPage, Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_WALLET_TXS_LIMIT);
+    if limit == 0 || limit > MAX_WALLET_TXS_LIMIT {
+        return Err(Error::InvalidRequest(format!(
+            "The limit must be between 1 and {MAX_WALLET_TXS_LIMIT}."
+        )));
+    }
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| {
+            WalletCursor::decode(c).ok_or_else(|| Error::InvalidRequest("Invalid cursor.".into()))
+        })
+        .transpose()?;
+    let from = query.from.map(|t| parse_unix_time("from", t)).transpose()?;
+    let to = query.to.map(|t| parse_unix_time("to", t)).transpose()?;
+    let direction = query.direction.map(|d| match d {
+        WalletTxDirection::Deposit => "deposit",
+        WalletTxDirection::Withdraw => "withdraw",
+    });
+    let status = query.status.map(|s| match s {
+        WithdrawalStatus::Pending => "pending",
+        WithdrawalStatus::Held => "held",
+        WithdrawalStatus::Processed => "processed",
+    });
+    let token = query.token.map(|t| t.as_bytes().to_vec());
+
     let client = db.pool.get().await?;
-    let (statement, param) = &db.prepared_statements.get_withdrawals_for_address;
-    let statement = client
-        .prepare_typed_cached(statement, std::slice::from_ref(param))
-        .await?;
-    let withdraws = client.query(&statement, &[&wallet.as_bytes()]).await?;
-    let (statement, param) = &db.prepared_statements.get_deposits_for_address;
-    let statement = client
-        .prepare_typed_cached(statement, std::slice::from_ref(param))
+    let (statement, params) = match query.order.unwrap_or(SortOrder::Desc) {
+        SortOrder::Asc => &statements.asc,
+        SortOrder::Desc => &statements.desc,
+    };
+    let statement = client.prepare_typed_cached(statement, params).await?;
+    // Query one more than the limit to know whether there is a next page.
+    let rows = client
+        .query(&statement, &[
+            &address,
+            &token,
+            &direction,
+            &status,
+            &from,
+            &to,
+            &cursor.as_ref().map(|c| c.time),
+            &cursor.as_ref().map(|c| c.kind),
+            &cursor.as_ref().map(|c| c.id),
+            &(i64::from(limit) + 1),
+        ])
         .await?;
-    let deposits = client.query(&statement, &[&wallet.as_bytes()]).await?;
-    let mut out = Vec::new();
-    for withdraw in withdraws {
-        let tx_hash = withdraw
-            .try_get::<_, Option<Fixed<32>>>("processed")?
+    let mut items = Vec::with_capacity(rows.len());
+    let mut last = None;
+    for row in rows.iter().take(usize::from(limit)) {
+        let kind = row.try_get::<_, i16>("kind")?;
+        let id = row.try_get::<_, i64>("id")?;
+        let insert_time = row.try_get::<_, chrono::DateTime<chrono::Utc>>("insert_time")?;
+        let tx_hash = row
+            .try_get::<_, Option<Fixed<32>>>("tx_hash")?
             .map(|x| TransactionHash::new(x.0));
-        let origin_tx_hash = TransactionHash::new(withdraw.try_get::<_, Fixed<32>>("tx_hash")?.0);
-        let origin_event_index = withdraw.try_get::<_, i64>("event_index")? as u64;
-        let amount = withdraw.try_get::<_, String>("amount")?;
-        let index = withdraw.try_get::<_, i64>("child_index")? as u64;
-        let subindex = withdraw.try_get::<_, i64>("child_subindex")? as u64;
-        let timestamp = withdraw
-            .try_get::<_, chrono::DateTime<chrono::Utc>>("insert_time")?
-            .timestamp();
-        out.push(WalletTx::Withdraw(WalletWithdrawTx {
-            tx_hash,
-            origin_tx_hash,
-            origin_event_index,
-            amount,
-            timestamp,
-            status: if tx_hash.is_some() {
-                WithdrawalStatus::Processed
-            } else {
-                WithdrawalStatus::Pending
-            },
-            child_token: ContractAddress::new(index, subindex),
-        }))
-    }
-    for deposit in deposits {
-        let tx_hash = deposit.try_get::<_, Option<Fixed<32>>>("tx_hash")?;
-        let origin_tx_hash =
-            TransactionHash::new(deposit.try_get::<_, Fixed<32>>("origin_tx_hash")?.0);
-        let origin_event_index = deposit.try_get::<_, i64>("origin_event_index")? as u64;
-        let amount = deposit.try_get::<_, String>("amount")?;
-        let root_token = deposit.try_get::<_, Fixed<20>>("root_token")?;
-        let timestamp = deposit
-            .try_get::<_, chrono::DateTime<chrono::Utc>>("insert_time")?
-            .timestamp();
-        out.push(WalletTx::Deposit(WalletDepositTx {
-            status: if tx_hash.is_some() {
-                TransactionStatus::Finalized
-            } else {
-                TransactionStatus::Pending
-            },
-            tx_hash: tx_hash.map(|x| TransactionHash::new(x.0)),
-            origin_tx_hash,
-            origin_event_index,
-            amount,
-            timestamp,
-            root_token: root_token.0.into(),
-        }))
+        let origin_tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("origin_tx_hash")?.0);
+        let origin_event_index = row.try_get::<_, i64>("event_index")? as u64;
+        let amount = row.try_get::<_, String>("amount")?;
+        let timestamp = insert_time.timestamp();
+        match kind {
+            0 => {
+                let index = row.try_get::<_, i64>("child_index")? as u64;
+                let subindex = row.try_get::<_, i64>("child_subindex")? as u64;
+                let hold = WithdrawalHold::from_row(row)?;
+                items.push(WalletTx::Withdraw(WalletWithdrawTx {
+                    tx_hash,
+                    origin_tx_hash,
+                    origin_event_index,
+                    amount,
+                    timestamp,
+                    status: if tx_hash.is_some() {
+                        WithdrawalStatus::Processed
+                    } else if hold.is_some() {
+                        WithdrawalStatus::Held
+                    } else {
+                        WithdrawalStatus::Pending
+                    },
+                    child_token: ContractAddress::new(index, subindex),
+                    hold,
+                }))
+            }
+            1 => {
+                let root_token = row.try_get::<_, Fixed<20>>("root_token")?;
+                let hold = row
+                    .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("hold_time")?
+                    .map(|t| DepositHold {
+                        timestamp: t.timestamp(),
+                    });
+                items.push(WalletTx::Deposit(WalletDepositTx {
+                    status: if tx_hash.is_some() {
+                        WithdrawalStatus::Processed
+                    } else if hold.is_some() {
+                        WithdrawalStatus::Held
+                    } else {
+                        WithdrawalStatus::Pending
+                    },
+                    tx_hash,
+                    origin_tx_hash,
+                    origin_event_index,
+                    amount,
+                    timestamp,
+                    root_token: root_token.0.into(),
+                    hold,
+                }))
+            }
+            _ => return Err(Error::Internal),
+        }
+        last = Some(WalletCursor {
+            time: insert_time,
+            kind,
+            id,
+        });
     }
-    Ok(out.into())
+    let next_cursor = if rows.len() > usize::from(limit) {
+        last.map(|c| c.encode())
+    } else {
+        None
+    };
+    Ok(WalletTxPage { items, next_cursor })
 }
 
 #[derive(serde::Serialize, utoipa::ToSchema)]
@@ -423,12 +1048,452 @@ pub struct WithdrawParams {
     token_id:        TokenId,
 }
 
+impl WithdrawParams {
+    fn new(
+        ccd_tx_hash: TransactionHash,
+        we: ccdeth_relayer::concordium_contracts::WithdrawEvent,
+    ) -> Self {
+        Self {
+            ccd_index: we.contract.index,
+            ccd_sub_index: we.contract.subindex,
+            amount: we.amount.to_string(),
+            user_wallet: we.eth_address.into(),
+            ccd_tx_hash,
+            ccd_event_index: we.event_index,
+            token_id: we.token_id,
+        }
+    }
+}
+
 #[derive(serde::Serialize, utoipa::ToSchema)]
 /// Response to the Merkle proof request.
 struct EthMerkleProofResponse {
-    params: WithdrawParams,
-    // hex string
-    proof:  String,
+    params:       WithdrawParams,
+    /// The hashes of the proof concatenated, in hex.
+    proof:        String,
+    /// The hashes of the proof in hex, in the order expected by the `withdraw`
+    /// function of the RootChainManager contract.
+    proof_hashes: Vec<String>,
+    /// The transaction that completes the withdrawal.
+    transaction:  WithdrawTransaction,
+}
+
+impl EthMerkleProofResponse {
+    fn new(
+        tx_hash: TransactionHash,
+        we: ccdeth_relayer::concordium_contracts::WithdrawEvent,
+        proof: Vec<[u8; 32]>,
+        root_chain_manager: Option<&RootChainManager>,
+    ) -> Result<Self, Error> {
+        let data = MerkleData::new(tx_hash, &we).map_err(|_| Error::Internal)?;
+        let calldata = data.withdraw_calldata(&proof);
+        Ok(Self {
+            params:       WithdrawParams::new(tx_hash, we),
+            proof:        hex::encode(proof.concat()),
+            proof_hashes: proof.iter().map(hex::encode).collect(),
+            transaction:  WithdrawTransaction {
+                to:           root_chain_manager.map(|manager| manager.address),
+                data:         format!("0x{}", hex::encode(calldata)),
+                value:        None,
+                gas_estimate: None,
+            },
+        })
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A call of `withdraw` on the RootChainManager contract, ready to be signed.
+struct WithdrawTransaction {
+    /// Address of the RootChainManager contract. Only present if the API
+    /// server is configured with it.
+    #[schema(value_type = Option<String>)]
+    to:           Option<ethers::types::Address>,
+    /// The calldata, in hex with a `0x` prefix.
+    data:         String,
+    /// The amount of wei to send with the transaction, which is the withdraw
+    /// fee of the contract. Only present with the gas estimate.
+    value:        Option<String>,
+    /// Estimate of the gas used by the transaction. Only present if the API
+    /// server is configured with an Ethereum node and the estimate succeeded.
+    gas_estimate: Option<u64>,
+}
+
+/// Maximum time for querying the Ethereum node for the withdraw fee and the
+/// gas estimate. If it takes longer the proof is returned without them.
+const GAS_ESTIMATE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The estimates of the gas used by the transactions in the fees endpoint are
+/// increased by this percentage. They are made against other state than that
+/// of the user, e.g., deposits of tokens are estimated with an amount of 0,
+/// so the transaction of the user might use more gas.
+const GAS_ESTIMATE_MARGIN_PERCENT: u64 = 25;
+
+/// The RootChainManager contract on Ethereum, which completes withdrawals.
+struct RootChainManager {
+    address:       ethers::types::Address,
+    /// Client of an Ethereum node, for estimating the gas of withdrawals.
+    provider:      Option<Provider<Http>>,
+    /// The `ETHER_ADDRESS` of the contract, which is constant, once queried.
+    ether_address: tokio::sync::OnceCell<ethers::types::Address>,
+}
+
+impl RootChainManager {
+    /// Query the withdraw fee of the contract and the gas used by the
+    /// transaction when sent from the given wallet, and add them to the
+    /// transaction. Nothing is added if no Ethereum node is configured.
+    async fn estimate(
+        &self,
+        transaction: &mut WithdrawTransaction,
+        from: ethers::types::Address,
+    ) -> anyhow::Result<()> {
+        let Some(provider) = &self.provider else {
+            return Ok(());
+        };
+        let fee = self.query_fee(provider, "withdrawFee").await?;
+        let calldata = hex::decode(&transaction.data[2..])?;
+        let gas = self.estimate_gas(provider, from, fee, calldata).await?;
+        transaction.value = Some(fee.to_string());
+        transaction.gas_estimate = Some(gas);
+        Ok(())
+    }
+
+    /// Estimate the gas used by a call of the contract from the given wallet,
+    /// sending the given fee.
+    async fn estimate_gas(
+        &self,
+        provider: &Provider<Http>,
+        from: ethers::types::Address,
+        fee: U256,
+        calldata: Vec<u8>,
+    ) -> anyhow::Result<u64> {
+        let call: TypedTransaction = TransactionRequest::new()
+            .from(from)
+            .to(self.address)
+            .value(fee)
+            .data(calldata)
+            .into();
+        let gas = provider.estimate_gas(&call, None).await?;
+        Ok(gas.as_u64())
+    }
+
+    /// Query a function of the contract without arguments that returns a
+    /// single word, such as `depositFee`, `withdrawFee` or `ETHER_ADDRESS`.
+    /// The generated bindings do not include the fees, so they are queried
+    /// directly.
+    async fn query_word(
+        &self,
+        provider: &Provider<Http>,
+        function: &str,
+    ) -> anyhow::Result<[u8; 32]> {
+        let call: TypedTransaction = TransactionRequest::new()
+            .to(self.address)
+            .data(ethabi::short_signature(function, &[]).to_vec())
+            .into();
+        let word = provider.call(&call, None).await?;
+        <[u8; 32]>::try_from(&word[..])
+            .ok()
+            .with_context(|| format!("Unexpected response when querying {function}."))
+    }
+
+    /// Query one of the fees of the contract, `depositFee` or `withdrawFee`.
+    async fn query_fee(&self, provider: &Provider<Http>, function: &str) -> anyhow::Result<U256> {
+        let fee = self.query_word(provider, function).await?;
+        Ok(U256::from_big_endian(&fee))
+    }
+
+    /// Estimate the gas used by a deposit, `depositEtherFor` if `ether` is set
+    /// and `depositFor` otherwise, as if it was made by the depositor of the
+    /// latest deposit of that kind. Tokens are estimated with a deposit of 0,
+    /// which needs no allowance, and Ether with only the fee sent, so the
+    /// estimate is lower than the gas of an actual deposit. Returns [`None`] if
+    /// there are no such deposits.
+    async fn estimate_deposit(
+        &self,
+        provider: &Provider<Http>,
+        db: &Database,
+        fee: U256,
+        ether: bool,
+    ) -> anyhow::Result<Option<u64>> {
+        let ether_address = *self
+            .ether_address
+            .get_or_try_init(|| async {
+                let word = self.query_word(provider, "ETHER_ADDRESS").await?;
+                anyhow::Ok(ethers::types::Address::from_slice(&word[12..]))
+            })
+            .await?;
+        let client = db.pool.get().await?;
+        let (statement, params) = &db.prepared_statements.latest_deposit;
+        let statement = client.prepare_typed_cached(statement, &params[..]).await?;
+        let Some(row) = client
+            .query_opt(&statement, &[&ether_address.as_bytes(), &ether])
+            .await?
+        else {
+            return Ok(None);
+        };
+        let depositor = ethers::types::Address::from(row.try_get::<_, Fixed<20>>("depositor")?.0);
+        let root_token = ethers::types::Address::from(row.try_get::<_, Fixed<20>>("root_token")?.0);
+        // The receiver on Concordium does not affect the gas used.
+        let receiver = ethabi::Token::FixedBytes(vec![0; 32]);
+        let calldata = if ether {
+            let mut calldata = ethabi::short_signature("depositEtherFor", &[
+                ethabi::ParamType::Address,
+                ethabi::ParamType::FixedBytes(32),
+            ])
+            .to_vec();
+            calldata.extend(ethabi::encode(&[
+                ethabi::Token::Address(depositor),
+                receiver,
+            ]));
+            calldata
+        } else {
+            let mut calldata = ethabi::short_signature("depositFor", &[
+                ethabi::ParamType::Address,
+                ethabi::ParamType::FixedBytes(32),
+                ethabi::ParamType::Address,
+                ethabi::ParamType::Bytes,
+            ])
+            .to_vec();
+            let amount = ethabi::encode(&[ethabi::Token::Uint(U256::zero())]);
+            calldata.extend(ethabi::encode(&[
+                ethabi::Token::Address(depositor),
+                receiver,
+                ethabi::Token::Address(root_token),
+                ethabi::Token::Bytes(amount),
+            ]));
+            calldata
+        };
+        let gas = self
+            .estimate_gas(provider, depositor, fee, calldata)
+            .await?;
+        Ok(Some(gas))
+    }
+
+    /// Estimate the gas used by `withdraw` for a withdrawal in the latest
+    /// Merkle root that is not yet completed. Returns [`None`] if there are no
+    /// such withdrawals.
+    async fn estimate_withdraw(
+        &self,
+        provider: &Provider<Http>,
+        db: &Database,
+        fee: U256,
+    ) -> anyhow::Result<Option<u64>> {
+        let client = db.pool.get().await?;
+        let Some(tree) = db.merkle_tree(&client).await? else {
+            return Ok(None);
+        };
+        let statement = &db.prepared_statements.latest_pending_withdrawal;
+        let statement = client.prepare_typed_cached(statement, &[]).await?;
+        let Some(row) = client.query_opt(&statement, &[]).await? else {
+            return Ok(None);
+        };
+        let tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0);
+        let event_index = row.try_get::<_, i64>("event_index")? as u64;
+        // The root may have changed since the tree was read.
+        let Some(position) = tree.position(tx_hash, event_index) else {
+            return Ok(None);
+        };
+        let data = row.try_get::<_, Vec<u8>>("event_data")?;
+        let we: ccdeth_relayer::concordium_contracts::WithdrawEvent =
+            concordium::smart_contracts::common::from_bytes(&data).map_err(|_| Error::Internal)?;
+        let from = we.eth_address.into();
+        let calldata =
+            MerkleData::new(tx_hash, &we)?.withdraw_calldata(&tree.proof_hashes(&[position]));
+        let gas = self.estimate_gas(provider, from, fee, calldata).await?;
+        Ok(Some(gas))
+    }
+
+    /// Query the fees of the contract and the gas price, and estimate the cost
+    /// of its transactions, or [`None`] if no Ethereum node is configured. The
+    /// estimates are increased by [`GAS_ESTIMATE_MARGIN_PERCENT`], and the
+    /// configured gas of a transaction is used if its gas cannot be estimated
+    /// or the estimate is lower.
+    async fn fees(
+        &self,
+        db: &Database,
+        gas: TransactionGas,
+    ) -> anyhow::Result<Option<EthereumFees>> {
+        let Some(provider) = &self.provider else {
+            return Ok(None);
+        };
+        let deposit_fee = self.query_fee(provider, "depositFee").await?;
+        let withdraw_fee = self.query_fee(provider, "withdrawFee").await?;
+        let gas_price = provider.get_gas_price().await?;
+        let estimate = |result: anyhow::Result<Option<u64>>, function: &str| match result {
+            Ok(gas) => gas,
+            Err(e) => {
+                tracing::debug!("Unable to estimate the gas of {function}: {e:#}");
+                None
+            }
+        };
+        let estimated = TransactionGas {
+            deposit_for:       estimate(
+                self.estimate_deposit(provider, db, deposit_fee, false)
+                    .await,
+                "depositFor",
+            ),
+            deposit_ether_for: estimate(
+                self.estimate_deposit(provider, db, deposit_fee, true).await,
+                "depositEtherFor",
+            ),
+            withdraw:          estimate(
+                self.estimate_withdraw(provider, db, withdraw_fee).await,
+                "withdraw",
+            ),
+        };
+        let cost = |estimated_gas: Option<u64>, configured_gas: Option<u64>, fee: U256| {
+            let estimated_gas = estimated_gas.map(|gas| {
+                gas.saturating_add(gas.saturating_mul(GAS_ESTIMATE_MARGIN_PERCENT) / 100)
+            });
+            let (gas, estimated) = match (estimated_gas, configured_gas) {
+                (Some(gas), Some(configured)) if configured > gas => (configured, false),
+                (Some(gas), _) => (gas, true),
+                (None, Some(gas)) => (gas, false),
+                (None, None) => return None,
+            };
+            let gas_cost = gas_price.saturating_mul(gas.into());
+            Some(TransactionCost {
+                gas,
+                estimated,
+                gas_cost: gas_cost.to_string(),
+                total: gas_cost.saturating_add(fee).to_string(),
+            })
+        };
+        Ok(Some(EthereumFees {
+            deposit_fee:       deposit_fee.to_string(),
+            withdraw_fee:      withdraw_fee.to_string(),
+            gas_price:         gas_price.to_string(),
+            deposit_for:       cost(estimated.deposit_for, gas.deposit_for, deposit_fee),
+            deposit_ether_for: cost(
+                estimated.deposit_ether_for,
+                gas.deposit_ether_for,
+                deposit_fee,
+            ),
+            withdraw:          cost(estimated.withdraw, gas.withdraw, withdraw_fee),
+            updated:           chrono::Utc::now().timestamp(),
+        }))
+    }
+}
+
+/// How often the fees are queried from the chains.
+const FEES_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Clone, Default, serde::Serialize, utoipa::ToSchema)]
+/// Response of the fees endpoint.
+struct FeesResponse {
+    /// Not present if the API server is not configured with a Concordium node,
+    /// or the fees have not yet been queried.
+    concordium: Option<ConcordiumFees>,
+    /// Not present if the API server is not configured with an Ethereum node,
+    /// or the fees have not yet been queried.
+    ethereum:   Option<EthereumFees>,
+}
+
+#[derive(Clone, serde::Serialize, utoipa::ToSchema)]
+/// Fees of the bridge manager contract on Concordium.
+struct ConcordiumFees {
+    /// Fee paid when initiating a withdrawal, in microCCD.
+    withdraw_fee: String,
+    /// When the fees were queried, in seconds since the unix epoch.
+    updated:      i64,
+}
+
+#[derive(Clone, serde::Serialize, utoipa::ToSchema)]
+/// Fees of the RootChainManager contract on Ethereum, and the estimated cost
+/// of its transactions. Amounts are in wei.
+struct EthereumFees {
+    /// Fee sent with `depositFor` and `depositEtherFor`.
+    deposit_fee:       String,
+    /// Fee sent with `withdraw`.
+    withdraw_fee:      String,
+    gas_price:         String,
+    /// Not present if the gas used by `depositFor` can neither be estimated
+    /// nor is configured.
+    deposit_for:       Option<TransactionCost>,
+    /// Not present if the gas used by `depositEtherFor` can neither be
+    /// estimated nor is configured.
+    deposit_ether_for: Option<TransactionCost>,
+    /// Not present if the gas used by `withdraw` can neither be estimated nor
+    /// is configured.
+    withdraw:          Option<TransactionCost>,
+    /// When the fees were queried, in seconds since the unix epoch.
+    updated:           i64,
+}
+
+#[derive(Clone, serde::Serialize, utoipa::ToSchema)]
+/// Estimated cost of a transaction at the current gas price, in wei.
+struct TransactionCost {
+    /// Gas used by the transaction.
+    gas:       u64,
+    /// Whether the gas was estimated by the Ethereum node, including a safety
+    /// margin. Otherwise it is the configured gas.
+    estimated: bool,
+    /// Cost of the gas at the current gas price.
+    gas_cost:  String,
+    /// Cost of the gas plus the fee sent with the transaction.
+    total:     String,
+}
+
+/// Gas used by the transactions of the RootChainManager contract.
+#[derive(Clone, Copy)]
+struct TransactionGas {
+    deposit_for:       Option<u64>,
+    deposit_ether_for: Option<u64>,
+    withdraw:          Option<u64>,
+}
+
+/// Periodically query the fees from both chains and publish them. If a query
+/// fails the previous fees of that chain are kept. The configured gas is used
+/// for the transactions whose gas cannot be estimated.
+async fn refresh_fees(
+    db: Database,
+    mut bridge_manager: Option<BridgeManagerClient>,
+    root_chain_manager: Option<Arc<RootChainManager>>,
+    gas: TransactionGas,
+    sender: tokio::sync::watch::Sender<FeesResponse>,
+) {
+    let mut interval = tokio::time::interval(FEES_REFRESH_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Some(client) = bridge_manager.as_mut() {
+            match client.view_configuration(BlockIdentifier::LastFinal).await {
+                Ok(configuration) => {
+                    let fees = ConcordiumFees {
+                        withdraw_fee: configuration.withdraw_fee.micro_ccd.to_string(),
+                        updated:      chrono::Utc::now().timestamp(),
+                    };
+                    sender.send_modify(|response| response.concordium = Some(fees));
+                }
+                Err(e) => tracing::warn!("Unable to query the fees on Concordium: {e:#}"),
+            }
+        }
+        if let Some(manager) = &root_chain_manager {
+            match manager.fees(&db, gas).await {
+                Ok(Some(fees)) => sender.send_modify(|response| response.ethereum = Some(fees)),
+                Ok(None) => (),
+                Err(e) => tracing::warn!("Unable to query the fees on Ethereum: {e:#}"),
+            }
+        }
+    }
+}
+
+/// The current fees of the bridge contracts on both chains, and the estimated
+/// cost of the transactions on Ethereum at the current gas price. The fees are
+/// queried every 30 seconds.
+#[utoipa::path(
+        get,
+        path = "api/v1/fees",
+        operation_id = "fees",
+        responses(
+            (status = 200, description = "Fees of the bridge.", body = FeesResponse),
+        )
+    )]
+#[tracing::instrument(level = "debug", skip(fees))]
+async fn fees(
+    axum::extract::State(fees): axum::extract::State<tokio::sync::watch::Receiver<FeesResponse>>,
+) -> axum::Json<FeesResponse> {
+    let fees = fees.borrow().clone();
+    fees.into()
 }
 
 #[utoipa::path(
@@ -454,6 +1519,7 @@ struct EthMerkleProofResponse {
 async fn get_merkle_proof(
     axum::extract::Path((tx_hash, event_id)): axum::extract::Path<(TransactionHash, u64)>,
     axum::extract::State(db): axum::extract::State<Database>,
+    axum::extract::State(root_chain_manager): axum::extract::State<Option<Arc<RootChainManager>>>,
 ) -> Result<axum::Json<EthMerkleProofResponse>, Error> {
     let client = db.pool.get().await?;
     let (statement, params) = &db.prepared_statements.get_event;
@@ -469,30 +1535,29 @@ async fn get_merkle_proof(
         let data = row.try_get::<_, Vec<u8>>("event_data")?;
         let we: ccdeth_relayer::concordium_contracts::WithdrawEvent =
             concordium::smart_contracts::common::from_bytes(&data).map_err(|_| Error::Internal)?;
-        let statement = &db.prepared_statements.get_merkle_leafs;
-        let statement = client.prepare_typed_cached(statement, &[]).await?;
-        let rows = client.query(&statement, &[]).await?;
-        let rows = rows.into_iter().map(|row| {
-            let tx_hash = row.try_get::<_, Fixed<32>>("tx_hash")?;
-            let event_merkle_hash = row.try_get::<_, Fixed<32>>("event_merkle_hash")?.0;
-            let tx_hash = TransactionHash::new(tx_hash.0);
-            Ok::<_, Error>((tx_hash, event_merkle_hash))
-        });
-        let proof = ccdeth_relayer::merkle::make_proof(rows, tx_hash)?;
-        if let Some(proof) = proof {
-            Ok(EthMerkleProofResponse {
-                params: WithdrawParams {
-                    ccd_index:       we.contract.index,
-                    ccd_sub_index:   we.contract.subindex,
-                    amount:          we.amount.to_string(),
-                    user_wallet:     we.eth_address.into(),
-                    ccd_tx_hash:     tx_hash,
-                    ccd_event_index: we.event_index,
-                    token_id:        we.token_id,
-                },
-                proof:  hex::encode(proof.to_bytes()),
+        let tree = db.merkle_tree(&client).await?;
+        let position = tree
+            .as_ref()
+            .and_then(|tree| tree.position(tx_hash, event_id));
+        if let (Some(tree), Some(position)) = (tree, position) {
+            let from = we.eth_address.into();
+            let mut response = EthMerkleProofResponse::new(
+                tx_hash,
+                we,
+                tree.proof_hashes(&[position]),
+                root_chain_manager.as_deref(),
+            )?;
+            if let Some(manager) = root_chain_manager {
+                let estimate = manager.estimate(&mut response.transaction, from);
+                match tokio::time::timeout(GAS_ESTIMATE_TIMEOUT, estimate).await {
+                    Ok(Ok(())) => (),
+                    Ok(Err(e)) => {
+                        tracing::debug!("Unable to estimate the gas of a withdrawal: {e:#}")
+                    }
+                    Err(_) => tracing::debug!("Timed out estimating the gas of a withdrawal."),
+                }
             }
-            .into())
+            Ok(response.into())
         } else {
             Err(Error::InvalidRequest(
                 "Event not in Merkle root at present.".into(),
@@ -503,10 +1568,171 @@ async fn get_merkle_proof(
     }
 }
 
-#[utoipa::path(
-        get,
-        path = "api/v1/expectedMerkleRootUpdate",
-        operation_id = "expected_merkle_root_update",
+/// Maximum number of withdrawals in a request to the batch proof endpoint.
+const MAX_BATCH_PROOFS: usize = 100;
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+/// A withdrawal to make a Merkle proof for.
+struct WithdrawalId {
+    /// Hash of the transaction on Concordium that made the withdrawal.
+    #[schema(schema_with = hex_string)]
+    tx_hash:     TransactionHash,
+    /// Event index of the withdrawal.
+    event_index: u64,
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+/// Request to the batch proof endpoint.
+struct BatchProofRequest {
+    /// The withdrawals to make proofs for, at most 100.
+    withdrawals: Vec<WithdrawalId>,
+    /// Whether to also make a single proof for all the withdrawals that are
+    /// in the tree.
+    #[serde(default)]
+    multi_proof: bool,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// The proof for one withdrawal in the response of the batch proof endpoint.
+struct BatchProofItem {
+    #[schema(schema_with = hex_string)]
+    tx_hash:     TransactionHash,
+    event_index: u64,
+    /// The proof, if the withdrawal is in the latest Merkle root.
+    proof:       Option<EthMerkleProofResponse>,
+    /// Why there is no proof for the withdrawal.
+    error:       Option<String>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A proof for several leaves of the Merkle tree at once. This is not accepted
+/// by the bridge contract on Ethereum, which verifies one withdrawal at a
+/// time, but can be used to verify the withdrawals off chain.
+struct MultiProof {
+    /// Positions of the leaves in the tree, in increasing order.
+    leaf_indices: Vec<u64>,
+    /// The leaves, in the same order as the positions, in hex.
+    leaves:       Vec<String>,
+    /// Number of leaves in the tree.
+    total_leaves: u64,
+    /// Hashes of the proof, concatenated, in hex.
+    proof:        String,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// Response of the batch proof endpoint.
+struct BatchProofResponse {
+    /// The proofs, in the order of the request.
+    proofs:      Vec<BatchProofItem>,
+    /// If requested, a proof for all the withdrawals that have a proof. Not
+    /// present if none do.
+    multi_proof: Option<MultiProof>,
+}
+
+/// Make Merkle proofs for several withdrawals at once.
+#[utoipa::path(
+    post,
+    path = "/api/v1/ethereum/proofs",
+    operation_id = "eth_merkle_proofs",
+    request_body = BatchProofRequest,
+    responses(
+        (status = 200, description = "Proofs.", body = BatchProofResponse),
+        (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
+        (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json"),
+    )
+)]
+#[tracing::instrument(level = "debug", skip(db, rate_limit))]
+async fn get_merkle_proofs(
+    axum::extract::State(db): axum::extract::State<Database>,
+    axum::extract::State(root_chain_manager): axum::extract::State<Option<Arc<RootChainManager>>>,
+    axum::Extension(rate_limit): axum::Extension<RateLimitCharge>,
+    axum::Json(request): axum::Json<BatchProofRequest>,
+) -> Result<axum::Json<BatchProofResponse>, Error> {
+    if request.withdrawals.len() > MAX_BATCH_PROOFS {
+        return Err(Error::InvalidRequest(format!(
+            "At most {MAX_BATCH_PROOFS} withdrawals can be requested."
+        )));
+    }
+    // Each withdrawal counts as a request to the rate limits.
+    rate_limit.charge(request.withdrawals.len().saturating_sub(1));
+    let client = db.pool.get().await?;
+    let (statement, param) = &db.prepared_statements.get_events;
+    let statement = client
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    let event_indices = request
+        .withdrawals
+        .iter()
+        .map(|w| w.event_index as i64)
+        .collect::<Vec<_>>();
+    let mut events = HashMap::new();
+    for row in client.query(&statement, &[&event_indices]).await? {
+        let tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0);
+        let event_index = row.try_get::<_, i64>("event_index")? as u64;
+        let processed = row.try_get::<_, Option<Fixed<32>>>("processed")?.is_some();
+        let data = row.try_get::<_, Vec<u8>>("event_data")?;
+        events.insert((tx_hash, event_index), (processed, data));
+    }
+    let tree = db.merkle_tree(&client).await?;
+    let mut proofs = Vec::with_capacity(request.withdrawals.len());
+    let mut positions = Vec::new();
+    for WithdrawalId {
+        tx_hash,
+        event_index,
+    } in request.withdrawals
+    {
+        let position = tree
+            .as_ref()
+            .and_then(|tree| tree.position(tx_hash, event_index));
+        let (proof, error) = match (events.get(&(tx_hash, event_index)), position) {
+            (None, _) => (None, Some("Withdrawal not found.")),
+            (Some((true, _)), _) => (None, Some("Event already processed")),
+            (Some((false, _)), None) => (None, Some("Event not in Merkle root at present.")),
+            (Some((false, data)), Some(position)) => {
+                let we: ccdeth_relayer::concordium_contracts::WithdrawEvent =
+                    concordium::smart_contracts::common::from_bytes(data)
+                        .map_err(|_| Error::Internal)?;
+                positions.push(position);
+                let proof = tree
+                    .as_ref()
+                    .map_or_else(Vec::new, |tree| tree.proof_hashes(&[position]));
+                let proof =
+                    EthMerkleProofResponse::new(tx_hash, we, proof, root_chain_manager.as_deref())?;
+                (Some(proof), None)
+            }
+        };
+        proofs.push(BatchProofItem {
+            tx_hash,
+            event_index,
+            proof,
+            error: error.map(String::from),
+        });
+    }
+    let multi_proof = match tree {
+        Some(tree) if request.multi_proof && !positions.is_empty() => {
+            positions.sort_unstable();
+            positions.dedup();
+            let leaves = tree.tree.leaves().unwrap_or_default();
+            Some(MultiProof {
+                leaf_indices: positions.iter().map(|&p| p as u64).collect(),
+                leaves:       positions.iter().map(|&p| hex::encode(leaves[p])).collect(),
+                total_leaves: leaves.len() as u64,
+                proof:        hex::encode(tree.proof_hashes(&positions).concat()),
+            })
+        }
+        _ => None,
+    };
+    Ok(BatchProofResponse {
+        proofs,
+        multi_proof,
+    }
+    .into())
+}
+
+#[utoipa::path(
+        get,
+        path = "api/v1/expectedMerkleRootUpdate",
+        operation_id = "expected_merkle_root_update",
         responses(
             (status = 200, description = "Unix timestamp (in seconds) of the next scheduled update..", body = Option<i64>, content_type = "application/json"),
             (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json")
@@ -594,6 +1820,8 @@ pub async fn watch_deposit(
 struct WatchWithdrawalResponse {
     status:              TransactionStatus,
     concordium_event_id: Option<u64>,
+    /// Present if the withdrawal is held back from the Merkle tree.
+    hold:                Option<WithdrawalHold>,
 }
 
 /// Queried by Concordium transaction hash, respond with the status of
@@ -643,6 +1871,7 @@ async fn watch_withdraw(
                     TransactionStatus::Pending
                 },
                 concordium_event_id: event_index.map(|x| x as u64),
+                hold:                WithdrawalHold::from_row(first)?,
             }))
         } else {
             tracing::warn!("Multiple deposit events for the same transaction.");
@@ -652,6 +1881,7 @@ async fn watch_withdraw(
         Ok(axum::Json(WatchWithdrawalResponse {
             status:              TransactionStatus::Missing,
             concordium_event_id: None,
+            hold:                None,
         }))
     }
 }
@@ -680,6 +1910,253 @@ fn contract_address() -> utoipa::openapi::Object {
         .build()
 }
 
+/// Number of status notifications buffered for each subscriber. A subscriber
+/// that falls further behind misses notifications, and is sent a `lagged`
+/// event.
+const STATUS_BUFFER_SIZE: usize = 1024;
+
+/// Time to wait before reconnecting to the database after the connection used
+/// for listening to status notifications fails.
+const STATUS_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+/// A message forwarded to the subscribers of the events endpoint.
+enum StatusMessage {
+    /// A transfer reached a stage.
+    Update(Arc<StatusNotification>),
+    /// The connection listening to status notifications was established, and
+    /// notifications sent before it was might have been missed.
+    Reset,
+}
+
+/// Listen to the status notifications sent by the relayer and forward them to
+/// the subscribers of the events endpoint. The connection is reestablished if
+/// it fails. Notifications sent while it is down are missed, so a
+/// [`StatusMessage::Reset`] is sent each time it is established.
+async fn listen_status(
+    config: tokio_postgres::Config,
+    sender: tokio::sync::broadcast::Sender<StatusMessage>,
+) {
+    loop {
+        if let Err(e) = listen_status_connection(&config, &sender).await {
+            tracing::warn!("Error listening to status notifications: {e:#}.");
+        }
+        tokio::time::sleep(STATUS_RECONNECT_DELAY).await;
+    }
+}
+
+async fn listen_status_connection(
+    config: &tokio_postgres::Config,
+    sender: &tokio::sync::broadcast::Sender<StatusMessage>,
+) -> anyhow::Result<()> {
+    let (client, mut connection) = config.connect(NoTls).await?;
+    let messages_sender = sender.clone();
+    // The connection must be polled for the LISTEN command to complete, so the
+    // messages are handled in a separate task.
+    let messages = tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if let tokio_postgres::AsyncMessage::Notification(notification) = message? {
+                match serde_json::from_str::<StatusNotification>(notification.payload()) {
+                    // There being no subscribers is not an error.
+                    Ok(status) => {
+                        let _ = messages_sender.send(StatusMessage::Update(Arc::new(status)));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Unable to parse status notification: {e}.");
+                    }
+                }
+            }
+        }
+        Ok::<(), tokio_postgres::Error>(())
+    });
+    client
+        .batch_execute(&format!("LISTEN {STATUS_CHANNEL}"))
+        .await?;
+    tracing::info!("Listening to status notifications.");
+    let _ = sender.send(StatusMessage::Reset);
+    messages.await??;
+    anyhow::bail!("The database connection was closed.")
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Query parameters of the events endpoint. Updates matching any of the
+/// parameters are sent.
+struct WatchStatusQuery {
+    /// Hash of a transaction, in hex. Either the transaction that initiated
+    /// the transfer, or one that made it reach a stage.
+    tx_hash: Option<String>,
+    /// Ethereum address of the depositor or of the receiver of a withdrawal.
+    #[param(value_type = Option<String>)]
+    wallet:  Option<ethers::types::Address>,
+    /// Concordium account that receives a deposit or initiates a withdrawal.
+    /// All aliases of the account match.
+    #[param(value_type = Option<String>)]
+    account: Option<concordium::id::types::AccountAddress>,
+}
+
+/// The transfers a subscriber of the events endpoint is interested in. The
+/// fields are in the hex encoding used in the notifications.
+struct StatusFilter {
+    tx_hash:        Option<String>,
+    wallet:         Option<String>,
+    account_prefix: Option<String>,
+}
+
+impl StatusFilter {
+    fn matches(&self, status: &StatusNotification) -> bool {
+        let tx_hash = self.tx_hash.as_ref().map_or(false, |h| {
+            *h == status.origin_tx_hash || Some(h) == status.tx_hash.as_ref()
+        });
+        let wallet = self.wallet.as_ref() == Some(&status.wallet);
+        let account = self
+            .account_prefix
+            .as_ref()
+            .zip(status.account.as_ref())
+            .map_or(false, |(prefix, account)| {
+                account.starts_with(prefix.as_str())
+            });
+        tx_hash || wallet || account
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// An update sent by the events endpoint when a transfer reaches a stage.
+struct TransferUpdate {
+    #[schema(value_type = String)]
+    kind:           TransferKind,
+    /// One of `ethereum_seen`, `concordium_submitted`, `concordium_finalized`,
+    /// `merkle_root`, or `claimed`. This is also the name of the event.
+    #[schema(value_type = String)]
+    stage:          TransferStage,
+    /// Hash of the transaction that initiated the transfer, on Ethereum for
+    /// deposits and on Concordium for withdrawals.
+    origin_tx_hash: String,
+    /// The id of the deposit, or the event index of the withdrawal.
+    event_index:    u64,
+    /// The depositor, or the receiver of the withdrawal.
+    #[schema(schema_with = hex_string)]
+    wallet:         ethers::types::Address,
+    /// The receiver of the deposit, or the account that initiated the
+    /// withdrawal.
+    #[schema(value_type = Option<String>)]
+    account:        Option<concordium::id::types::AccountAddress>,
+    /// Hash of the transaction that made the transfer reach the stage, if any.
+    tx_hash:        Option<String>,
+    /// The Merkle root that includes the withdrawal, if any.
+    merkle_root:    Option<String>,
+}
+
+impl TransferUpdate {
+    fn from_notification(status: &StatusNotification) -> Option<Self> {
+        let wallet = hex::decode(&status.wallet).ok()?;
+        let account = match &status.account {
+            Some(account) => Some(concordium::id::types::AccountAddress(
+                hex::decode(account).ok()?.try_into().ok()?,
+            )),
+            None => None,
+        };
+        Some(Self {
+            kind: status.kind,
+            stage: status.stage,
+            origin_tx_hash: status.origin_tx_hash.clone(),
+            event_index: status.event_index,
+            wallet: ethers::types::Address::from_slice(&wallet),
+            account,
+            tx_hash: status.tx_hash.clone(),
+            merkle_root: status.merkle_root.clone(),
+        })
+    }
+}
+
+/// Subscribe to updates of deposits and withdrawals, as server-sent events.
+///
+/// An event is sent each time a matching transfer reaches a stage. If the
+/// subscriber falls behind and misses updates, a `lagged` event is sent with
+/// the number of missed updates, after which the state of the transfers should
+/// be queried again. The same holds for a `reset` event, which is sent when
+/// the server reconnects to the database and might have missed updates.
+#[utoipa::path(
+        get,
+        path = "api/v1/events",
+        operation_id = "watch_status",
+        params(WatchStatusQuery),
+        responses(
+            (status = 200, description = "Stream of updates.", body = TransferUpdate, content_type = "text/event-stream"),
+            (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
+        )
+    )]
+#[tracing::instrument(level = "debug", skip(status_sender))]
+async fn watch_status(
+    axum::extract::Query(query): axum::extract::Query<WatchStatusQuery>,
+    axum::extract::State(status_sender): axum::extract::State<
+        tokio::sync::broadcast::Sender<StatusMessage>,
+    >,
+) -> Result<
+    axum::response::sse::Sse<
+        impl futures::Stream<Item = Result<axum::response::sse::Event, axum::Error>>,
+    >,
+    Error,
+> {
+    let tx_hash = query
+        .tx_hash
+        .map(|h| {
+            let bytes = hex::decode(h.strip_prefix("0x").unwrap_or(&h))
+                .ok()
+                .filter(|b| b.len() == 32)
+                .ok_or_else(|| Error::InvalidRequest("Invalid transaction hash.".into()))?;
+            Ok::<_, Error>(hex::encode(bytes))
+        })
+        .transpose()?;
+    let filter = StatusFilter {
+        tx_hash,
+        wallet: query.wallet.map(|w| hex::encode(w.as_bytes())),
+        account_prefix: query
+            .account
+            .map(|a| hex::encode(&a.0[..ACCOUNT_ALIAS_PREFIX_LEN])),
+    };
+    if filter.tx_hash.is_none() && filter.wallet.is_none() && filter.account_prefix.is_none() {
+        return Err(Error::InvalidRequest(
+            "One of `tx_hash`, `wallet`, or `account` is required.".into(),
+        ));
+    }
+    let receiver = status_sender.subscribe();
+    let events = futures::stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(StatusMessage::Reset) => {
+                    let event = axum::response::sse::Event::default()
+                        .event("reset")
+                        .data("reconnected");
+                    return Some((Ok(event), (receiver, filter)));
+                }
+                Ok(StatusMessage::Update(status)) => {
+                    if !filter.matches(&status) {
+                        continue;
+                    }
+                    let Some(update) = TransferUpdate::from_notification(&status) else {
+                        tracing::warn!("Invalid address in status notification.");
+                        continue;
+                    };
+                    let event = axum::response::sse::Event::default()
+                        .event(status.stage.as_str())
+                        .json_data(update);
+                    return Some((event, (receiver, filter)));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    let event = axum::response::sse::Event::default()
+                        .event("lagged")
+                        .data(skipped.to_string());
+                    return Some((Ok(event), (receiver, filter)));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(axum::response::sse::Sse::new(events).keep_alive(axum::response::sse::KeepAlive::default()))
+}
+
 /// List all tokens that are mapped.
 #[utoipa::path(
         get,
@@ -717,92 +2194,2453 @@ async fn list_tokens(
     Ok(out.into())
 }
 
-#[derive(Clone)]
-pub struct Database {
-    pool:                deadpool_postgres::Pool,
-    prepared_statements: Arc<QueryStatements>,
+/// Default number of volume buckets returned by the statistics endpoint.
+const DEFAULT_STATS_BUCKETS: u16 = 30;
+/// Maximum number of volume buckets returned by the statistics endpoint.
+const MAX_STATS_BUCKETS: u16 = 366;
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, utoipa::ToSchema)]
+/// Length of the volume buckets of the statistics endpoint. Buckets start at
+/// the beginning of the hour, day, or week (on Monday) in UTC.
+enum StatsInterval {
+    #[serde(rename = "hour")]
+    #[schema(rename = "hour")]
+    Hour,
+    #[serde(rename = "day")]
+    #[schema(rename = "day")]
+    Day,
+    #[serde(rename = "week")]
+    #[schema(rename = "week")]
+    Week,
 }
 
-impl Database {
-    pub async fn new(config: tokio_postgres::Config, pool_size: usize) -> anyhow::Result<Self> {
-        let manager_config = deadpool_postgres::ManagerConfig {
-            recycling_method: deadpool_postgres::RecyclingMethod::Verified,
+impl StatsInterval {
+    fn as_str(self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Query parameters of the statistics endpoint.
+struct StatsQuery {
+    /// Length of the volume buckets. Defaults to `day`.
+    interval: Option<StatsInterval>,
+    /// Number of volume buckets, counting back from and including the current
+    /// one, at most 366. Defaults to 30.
+    buckets:  Option<u16>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// Volume of a token in a time bucket. Amounts are in whole tokens.
+struct VolumeBucket {
+    /// Start of the bucket, in seconds since the unix epoch.
+    start:           i64,
+    deposited:       String,
+    num_deposits:    u64,
+    withdrawn:       String,
+    num_withdrawals: u64,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// Totals of a mapped token. Amounts are decimal strings in whole tokens.
+struct TokenStats {
+    #[schema(schema_with = hex_string)]
+    eth_address:             ethers::types::Address,
+    eth_name:                String,
+    decimals:                u8,
+    #[schema(schema_with = contract_address)]
+    ccd_contract:            ContractAddress,
+    /// Deposits completed on Concordium.
+    deposited:               String,
+    /// Deposits not yet completed on Concordium.
+    pending_deposits:        String,
+    num_pending_deposits:    u64,
+    /// Withdrawals completed on Ethereum.
+    withdrawn:               String,
+    /// Withdrawals not yet completed on Ethereum.
+    pending_withdrawals:     String,
+    num_pending_withdrawals: u64,
+    /// Amount locked in the vault on Ethereum, all deposits minus the
+    /// completed withdrawals.
+    locked:                  String,
+    /// Volumes in the requested buckets, oldest first. Buckets without
+    /// deposits or withdrawals are omitted.
+    volumes:                 Vec<VolumeBucket>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// The latest Merkle root set on Ethereum.
+struct MerkleTreeStats {
+    /// The root, in hex.
+    root:     String,
+    /// Number of withdrawals in the tree.
+    size:     u64,
+    /// When the root was recorded, in seconds since the unix epoch. Not known
+    /// for roots recorded by earlier versions of the relayer.
+    set_time: Option<i64>,
+    /// Seconds since the root was recorded.
+    age:      Option<i64>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// Response of the statistics endpoint.
+struct StatsResponse {
+    tokens:      Vec<TokenStats>,
+    /// Not present if no Merkle root has been set.
+    merkle_tree: Option<MerkleTreeStats>,
+}
+
+/// Format an integer amount in the smallest unit of a token as a decimal
+/// number of whole tokens, without trailing zeros.
+fn decimal_amount(amount: &str, decimals: u8) -> String {
+    let (sign, digits) = match amount.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", amount),
+    };
+    let decimals = usize::from(decimals);
+    let digits = format!("{digits:0>width$}", width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{sign}{whole}")
+    } else {
+        format!("{sign}{whole}.{fraction}")
+    }
+}
+
+/// Totals and volumes of the mapped tokens, and the size and age of the
+/// Merkle tree. The figures are refreshed by the relayer every minute.
+#[utoipa::path(
+        get,
+        path = "api/v1/stats",
+        operation_id = "stats",
+        params(StatsQuery),
+        responses(
+            (status = 200, description = "Statistics of the bridge.", body = StatsResponse),
+            (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
+            (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json")
+        )
+    )]
+#[tracing::instrument(level = "debug", skip(db))]
+async fn stats(
+    axum::extract::Query(query): axum::extract::Query<StatsQuery>,
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<axum::Json<StatsResponse>, Error> {
+    let interval = query.interval.unwrap_or(StatsInterval::Day);
+    let buckets = query.buckets.unwrap_or(DEFAULT_STATS_BUCKETS);
+    if buckets == 0 || buckets > MAX_STATS_BUCKETS {
+        return Err(Error::InvalidRequest(format!(
+            "The number of buckets must be between 1 and {MAX_STATS_BUCKETS}."
+        )));
+    }
+    let client = db.pool.get().await?;
+    let statement = &db.prepared_statements.token_stats;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let rows = client.query(&statement, &[]).await?;
+    let mut tokens = Vec::with_capacity(rows.len());
+    for row in rows {
+        let decimals = row.try_get::<_, i16>("decimals")? as u8;
+        let amount = |column: &str| -> Result<String, Error> {
+            Ok(decimal_amount(&row.try_get::<_, String>(column)?, decimals))
         };
-        let manager = deadpool_postgres::Manager::from_config(config, NoTls, manager_config);
-        let pool = deadpool_postgres::Pool::builder(manager)
-            .create_timeout(Some(std::time::Duration::from_secs(5)))
-            .recycle_timeout(Some(std::time::Duration::from_secs(5)))
-            .wait_timeout(Some(std::time::Duration::from_secs(5)))
-            .max_size(pool_size)
-            .runtime(deadpool_postgres::Runtime::Tokio1)
-            .build()?;
-        Ok(Self {
-            pool,
-            prepared_statements: Arc::new(QueryStatements::new()),
-        })
+        tokens.push(TokenStats {
+            eth_address: ethers::types::Address::from_slice(&row.try_get::<_, Vec<u8>>("root")?),
+            eth_name: row.try_get("eth_name")?,
+            decimals,
+            ccd_contract: ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            ),
+            deposited: amount("deposited")?,
+            pending_deposits: amount("pending_deposits")?,
+            num_pending_deposits: row.try_get::<_, i64>("num_pending_deposits")? as u64,
+            withdrawn: amount("withdrawn")?,
+            pending_withdrawals: amount("pending_withdrawals")?,
+            num_pending_withdrawals: row.try_get::<_, i64>("num_pending_withdrawals")? as u64,
+            locked: amount("locked")?,
+            volumes: Vec::new(),
+        });
+    }
+    let (statement, params) = &db.prepared_statements.volumes;
+    let statement = client.prepare_typed_cached(statement, &params[..]).await?;
+    let rows = client
+        .query(&statement, &[&interval.as_str(), &i32::from(buckets)])
+        .await?;
+    for row in rows {
+        let root = ethers::types::Address::from_slice(&row.try_get::<_, Vec<u8>>("root")?);
+        // Tokens mapped after the last refresh are not yet in the totals.
+        let Some(token) = tokens.iter_mut().find(|token| token.eth_address == root) else {
+            continue;
+        };
+        let deposited = row.try_get::<_, String>("deposited")?;
+        let withdrawn = row.try_get::<_, String>("withdrawn")?;
+        token.volumes.push(VolumeBucket {
+            start:           row
+                .try_get::<_, chrono::DateTime<chrono::Utc>>("bucket")?
+                .timestamp(),
+            deposited:       decimal_amount(&deposited, token.decimals),
+            num_deposits:    row.try_get::<_, i64>("num_deposits")? as u64,
+            withdrawn:       decimal_amount(&withdrawn, token.decimals),
+            num_withdrawals: row.try_get::<_, i64>("num_withdrawals")? as u64,
+        });
+    }
+    let statement = &db.prepared_statements.merkle_tree_stats;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let merkle_tree = match client.query_opt(&statement, &[]).await? {
+        Some(row) => {
+            let set_time =
+                row.try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("insert_time")?;
+            Some(MerkleTreeStats {
+                root:     hex::encode(row.try_get::<_, Fixed<32>>("root")?.0),
+                size:     row.try_get::<_, i64>("size")? as u64,
+                set_time: set_time.map(|t| t.timestamp()),
+                age:      set_time.map(|t| (chrono::Utc::now() - t).num_seconds()),
+            })
+        }
+        None => None,
+    };
+    Ok(StatsResponse {
+        tokens,
+        merkle_tree,
     }
+    .into())
 }
 
-struct QueryStatements {
-    concordium_tx_status:        (String, tokio_postgres::types::Type),
-    withdrawal_status:           (String, tokio_postgres::types::Type),
-    get_event:                   (String, [tokio_postgres::types::Type; 2]),
-    get_merkle_leafs:            String,
-    get_withdrawals_for_address: (String, tokio_postgres::types::Type),
-    get_deposits_for_address:    (String, tokio_postgres::types::Type),
-    list_tokens:                 String,
-    get_next_merkle_root:        String,
+#[derive(Debug, Clone, Copy, serde::Serialize, utoipa::ToSchema)]
+/// A stage in the timeline of a deposit or a withdrawal. The names are the
+/// same as the stages of the events endpoint, where they overlap.
+enum TimelineStage {
+    /// The deposit was made on Ethereum.
+    #[serde(rename = "ethereum_seen")]
+    #[schema(rename = "ethereum_seen")]
+    EthereumSeen,
+    /// The deposit or withdrawal was held, either because it matched a
+    /// screening list, or because the withdrawal exceeded a limit.
+    #[serde(rename = "held")]
+    #[schema(rename = "held")]
+    Held,
+    /// The operator approved the held deposit or withdrawal.
+    #[serde(rename = "approved")]
+    #[schema(rename = "approved")]
+    Approved,
+    /// The hold was released, and the deposit sent to Concordium or the
+    /// withdrawal added to the Merkle tree.
+    #[serde(rename = "released")]
+    #[schema(rename = "released")]
+    Released,
+    /// The relayer submitted a transaction for the deposit to Concordium.
+    #[serde(rename = "concordium_submitted")]
+    #[schema(rename = "concordium_submitted")]
+    ConcordiumSubmitted,
+    /// The deposit was completed, or the withdrawal was made, on Concordium.
+    #[serde(rename = "concordium_finalized")]
+    #[schema(rename = "concordium_finalized")]
+    ConcordiumFinalized,
+    /// The withdrawal was approved in a Merkle root set on Ethereum.
+    #[serde(rename = "merkle_root")]
+    #[schema(rename = "merkle_root")]
+    MerkleRoot,
+    /// The withdrawal was claimed on Ethereum.
+    #[serde(rename = "claimed")]
+    #[schema(rename = "claimed")]
+    Claimed,
 }
 
-impl QueryStatements {
-    pub fn new() -> Self {
-        let concordium_tx_status = (
-            "SELECT tx_hash FROM ethereum_deposit_events WHERE origin_tx_hash = $1".into(),
-            tokio_postgres::types::Type::BYTEA,
-        );
-        let withdrawal_status = (
-            "SELECT processed, root, event_index FROM concordium_events WHERE tx_hash = $1".into(),
-            tokio_postgres::types::Type::BYTEA,
-        );
-        let get_event = (
-            "SELECT event_data, processed FROM concordium_events WHERE tx_hash = $1 AND \
-             event_index = $2"
-                .into(),
-            [
-                tokio_postgres::types::Type::BYTEA,
-                tokio_postgres::types::Type::INT8,
-            ],
-        );
-        let get_merkle_leafs = "SELECT tx_hash, event_merkle_hash FROM concordium_events WHERE \
-                                root IN (SELECT root FROM merkle_roots ORDER BY id DESC LIMIT 1) \
-                                ORDER BY event_index ASC"
-            .into();
-        let get_withdrawals_for_address = (
-            "SELECT insert_time, processed, tx_hash, child_index, child_subindex, amount, \
-             event_index FROM concordium_events WHERE event_type = 'withdraw' AND receiver = $1"
-                .into(),
-            tokio_postgres::types::Type::BYTEA,
-        );
-        let get_deposits_for_address = (
-            "SELECT insert_time, tx_hash, root_token, tx_hash, amount, origin_tx_hash, \
-             origin_event_index FROM ethereum_deposit_events WHERE depositor = $1"
-                .into(),
-            tokio_postgres::types::Type::BYTEA,
-        );
-        let list_tokens = "SELECT root, child_index, child_subindex, eth_name, decimals FROM \
-                           token_maps ORDER BY id ASC"
-            .into();
-        let get_next_merkle_root =
-            "SELECT expected_time FROM expected_merkle_update WHERE tag = ''".into();
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// An entry in the timeline of a deposit or a withdrawal.
+struct TimelineEntry {
+    stage:       TimelineStage,
+    /// Unix timestamp (in seconds) when the stage was reached, if known.
+    timestamp:   Option<i64>,
+    /// Hash of the transaction of the stage, on Ethereum or on Concordium, if
+    /// any.
+    #[schema(schema_with = optional_hash)]
+    tx_hash:     Option<TransactionHash>,
+    /// Status of the transaction, for `concordium_submitted` and
+    /// `merkle_root`. A Merkle root is pending until the transaction setting
+    /// it is confirmed.
+    status:      Option<TransactionStatus>,
+    /// The Merkle root, for `merkle_root`.
+    merkle_root: Option<String>,
+    /// Why the withdrawal was held, for `held`.
+    reason:      Option<WithdrawalHoldReason>,
+}
+
+impl TimelineEntry {
+    fn new(stage: TimelineStage, timestamp: Option<i64>) -> Self {
         Self {
-            concordium_tx_status,
-            withdrawal_status,
-            get_event,
-            get_merkle_leafs,
-            get_withdrawals_for_address,
-            get_deposits_for_address,
-            list_tokens,
-            get_next_merkle_root,
+            stage,
+            timestamp,
+            tx_hash: None,
+            status: None,
+            merkle_root: None,
+            reason: None,
+        }
+    }
+}
+
+/// Read an optional time column as a unix timestamp in seconds.
+fn unix_time(row: &tokio_postgres::Row, column: &str) -> Result<Option<i64>, Error> {
+    Ok(row
+        .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(column)?
+        .map(|t| t.timestamp()))
+}
+
+/// Add the stages of the hold of a deposit or a withdrawal to the timeline,
+/// from the `hold_*` columns of the row. Nothing is added if it was not held.
+fn push_hold_stages(
+    timeline: &mut Vec<TimelineEntry>,
+    row: &tokio_postgres::Row,
+    reason: Option<WithdrawalHoldReason>,
+) -> Result<(), Error> {
+    let Some(held) = unix_time(row, "hold_time")? else {
+        return Ok(());
+    };
+    timeline.push(TimelineEntry {
+        reason,
+        ..TimelineEntry::new(TimelineStage::Held, Some(held))
+    });
+    if let Some(approved) = unix_time(row, "hold_approved_time")? {
+        timeline.push(TimelineEntry::new(TimelineStage::Approved, Some(approved)));
+    }
+    if let Some(released) = unix_time(row, "hold_released_time")? {
+        timeline.push(TimelineEntry::new(TimelineStage::Released, Some(released)));
+    }
+    Ok(())
+}
+
+/// Order the timeline by time. Stages without a known time keep their order
+/// and are placed last.
+fn sort_timeline(timeline: &mut [TimelineEntry]) {
+    timeline.sort_by_key(|entry| entry.timestamp.unwrap_or(i64::MAX));
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A deposit found by the search endpoint.
+struct SearchDeposit {
+    #[schema(schema_with = hex_string)]
+    root_token:         ethers::types::Address,
+    amount:             String,
+    #[schema(schema_with = hex_string)]
+    depositor:          ethers::types::Address,
+    /// The Concordium account receiving the deposit, if known.
+    #[schema(value_type = Option<String>)]
+    receiver:           Option<concordium::id::types::AccountAddress>,
+    #[schema(schema_with = hex_string)]
+    origin_tx_hash:     TransactionHash,
+    origin_event_index: u64,
+    /// The deposit is failed if all transactions sent for it failed, and it
+    /// has not been requeued.
+    status:             TransactionStatus,
+    timeline:           Vec<TimelineEntry>,
+}
+
+impl SearchDeposit {
+    /// Construct the deposit from a row of the deposit search, and the
+    /// transactions the relayer submitted to Concordium for it.
+    fn from_rows(
+        row: &tokio_postgres::Row,
+        submitted: &[tokio_postgres::Row],
+    ) -> Result<Self, Error> {
+        let origin_tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("origin_tx_hash")?.0);
+        let tx_hash = row
+            .try_get::<_, Option<Fixed<32>>>("tx_hash")?
+            .map(|x| TransactionHash::new(x.0));
+        let mut timeline = vec![TimelineEntry {
+            tx_hash: Some(origin_tx_hash),
+            ..TimelineEntry::new(TimelineStage::EthereumSeen, unix_time(row, "time")?)
+        }];
+        push_hold_stages(&mut timeline, row, None)?;
+        for tx in submitted {
+            timeline.push(TimelineEntry {
+                tx_hash: Some(TransactionHash::new(
+                    tx.try_get::<_, Fixed<32>>("tx_hash")?.0,
+                )),
+                status: Some(tx.try_get("status")?),
+                ..TimelineEntry::new(
+                    TimelineStage::ConcordiumSubmitted,
+                    Some(tx.try_get::<_, i64>("timestamp")?),
+                )
+            });
+        }
+        if tx_hash.is_some() {
+            timeline.push(TimelineEntry {
+                tx_hash,
+                ..TimelineEntry::new(
+                    TimelineStage::ConcordiumFinalized,
+                    unix_time(row, "completed_time")?,
+                )
+            });
         }
+        sort_timeline(&mut timeline);
+        let status = if tx_hash.is_some() {
+            TransactionStatus::Finalized
+        } else if row.try_get::<_, bool>("failed")? {
+            TransactionStatus::Failed
+        } else {
+            TransactionStatus::Pending
+        };
+        Ok(Self {
+            root_token: row.try_get::<_, Fixed<20>>("root_token")?.0.into(),
+            amount: row.try_get("amount")?,
+            depositor: row.try_get::<_, Fixed<20>>("depositor")?.0.into(),
+            receiver: row
+                .try_get::<_, Option<Fixed<32>>>("deposit_receiver")?
+                .map(|x| concordium::id::types::AccountAddress(x.0)),
+            origin_tx_hash,
+            origin_event_index: row.try_get::<_, i64>("origin_event_index")? as u64,
+            status,
+            timeline,
+        })
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A withdrawal found by the search endpoint.
+struct SearchWithdrawal {
+    #[schema(schema_with = contract_address)]
+    child_token:        ContractAddress,
+    amount:             String,
+    #[schema(schema_with = hex_string)]
+    receiver:           ethers::types::Address,
+    /// The Concordium account that made the withdrawal, if it was not made by
+    /// a contract.
+    #[schema(value_type = Option<String>)]
+    sender:             Option<concordium::id::types::AccountAddress>,
+    #[schema(schema_with = hex_string)]
+    origin_tx_hash:     TransactionHash,
+    origin_event_index: u64,
+    status:             WithdrawalStatus,
+    /// The first Merkle root set on Ethereum that approved the withdrawal, if
+    /// any. For withdrawals approved before the first root was recorded this
+    /// is the latest root that approves it.
+    merkle_root:        Option<String>,
+    timeline:           Vec<TimelineEntry>,
+}
+
+impl SearchWithdrawal {
+    /// Construct the withdrawal from a row of the withdrawal search.
+    fn from_row(row: &tokio_postgres::Row) -> Result<Self, Error> {
+        let origin_tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0);
+        let processed = row
+            .try_get::<_, Option<Fixed<32>>>("processed")?
+            .map(|x| TransactionHash::new(x.0));
+        let reason = row.try_get::<_, Option<WithdrawalHoldReason>>("hold_reason")?;
+        let held = reason.is_some() && unix_time(row, "hold_released_time")?.is_none();
+        let mut timeline = vec![TimelineEntry {
+            tx_hash: Some(origin_tx_hash),
+            ..TimelineEntry::new(TimelineStage::ConcordiumFinalized, unix_time(row, "time")?)
+        }];
+        push_hold_stages(&mut timeline, row, reason)?;
+        let root = row
+            .try_get::<_, Option<Fixed<32>>>("root")?
+            .map(|x| hex::encode(x.0));
+        let root_set = row.try_get::<_, bool>("root_set")?;
+        if let Some(root) = &root {
+            let status = if root_set {
+                TransactionStatus::Finalized
+            } else {
+                TransactionStatus::Pending
+            };
+            timeline.push(TimelineEntry {
+                tx_hash: row
+                    .try_get::<_, Option<Fixed<32>>>("root_tx_hash")?
+                    .map(|x| TransactionHash::new(x.0)),
+                status: Some(status),
+                merkle_root: Some(root.clone()),
+                ..TimelineEntry::new(TimelineStage::MerkleRoot, unix_time(row, "root_time")?)
+            });
+        }
+        if processed.is_some() {
+            timeline.push(TimelineEntry {
+                tx_hash: processed,
+                ..TimelineEntry::new(TimelineStage::Claimed, None)
+            });
+        }
+        sort_timeline(&mut timeline);
+        let status = if processed.is_some() {
+            WithdrawalStatus::Processed
+        } else if held {
+            WithdrawalStatus::Held
+        } else {
+            WithdrawalStatus::Pending
+        };
+        Ok(Self {
+            child_token: ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            ),
+            amount: row.try_get("amount")?,
+            receiver: row.try_get::<_, Fixed<20>>("receiver")?.0.into(),
+            sender: row
+                .try_get::<_, Option<Fixed<32>>>("ccd_address")?
+                .map(|x| concordium::id::types::AccountAddress(x.0)),
+            origin_tx_hash,
+            origin_event_index: row.try_get::<_, i64>("event_index")? as u64,
+            status,
+            merkle_root: root.filter(|_| root_set),
+            timeline,
+        })
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// A transaction setting a Merkle root found by the search endpoint.
+struct SearchMerkleRoot {
+    merkle_root: String,
+    #[schema(schema_with = hex_string)]
+    tx_hash:     TransactionHash,
+    /// Status of the transaction. A transaction is missing if it was replaced
+    /// by another one with a higher gas price.
+    status:      TransactionStatus,
+    /// Unix timestamp (in seconds) when the transaction was sent.
+    timestamp:   i64,
+    /// Unix timestamp (in seconds) when the root was recorded as set, if it
+    /// was.
+    set_time:    Option<i64>,
+    /// Whether this is the latest Merkle root.
+    latest:      bool,
+    /// Event indices of the withdrawals that report this root as their
+    /// `merkle_root`, i.e., that it approved first, or that it will approve
+    /// first if the transaction is pending.
+    withdrawals: Vec<u64>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+/// An item in the response from the /search endpoint.
+enum SearchResult {
+    Deposit(SearchDeposit),
+    Withdraw(SearchWithdrawal),
+    MerkleRoot(SearchMerkleRoot),
+}
+
+/// Parse a transaction hash of either chain, in hex with an optional `0x`
+/// prefix.
+fn parse_search_hash(hash: &str) -> Result<[u8; 32], Error> {
+    let hash = hash.strip_prefix("0x").unwrap_or(hash);
+    hex::decode(hash)
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .ok_or_else(|| Error::InvalidRequest("The hash must be 32 bytes in hex.".into()))
+}
+
+/// Look up a transaction hash of either chain, and respond with the timelines
+/// of the deposits or withdrawals it belongs to.
+///
+/// The hash can be of the Ethereum transaction that made a deposit, of a
+/// Concordium transaction submitted for it, of the Concordium transaction that
+/// made a withdrawal, or of the Ethereum transaction that claimed it. It can
+/// also be of an Ethereum transaction setting a Merkle root, in which case the
+/// root and the withdrawals it approves are returned. There are several items
+/// if the transaction contains several deposits or withdrawals.
+#[utoipa::path(
+        get,
+        path = "api/v1/search/{hash}",
+        operation_id = "search",
+        params(
+            ("hash" = String,
+            Path,
+            description = "Hash of a transaction on Ethereum or Concordium, in hex.")
+        ),
+        responses(
+            (status = 200, description = "The deposits, withdrawals or Merkle root the transaction belongs to.", body = [SearchResult]),
+            (status = 400, description = "Invalid request.", body = inline(String), content_type = "application/json"),
+            (status = 404, description = "No transaction with the hash is known.", body = inline(String), content_type = "application/json"),
+            (status = 500, description = "Internal server error.", body = inline(String), content_type = "application/json")
+        )
+    )]
+#[tracing::instrument(level = "debug", skip(db))]
+async fn search(
+    axum::extract::Path(hash): axum::extract::Path<String>,
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<axum::Json<Vec<SearchResult>>, Error> {
+    let hash = parse_search_hash(&hash)?;
+    let client = db.pool.get().await?;
+    let mut results = Vec::new();
+
+    let (statement, param) = &db.prepared_statements.search_deposits;
+    let statement = client
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    let deposits = client.query(&statement, &[&&hash[..]]).await?;
+    let (statement, param) = &db.prepared_statements.search_concordium_txs;
+    let statement = client
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    for row in deposits {
+        let origin_tx_hash = row.try_get::<_, Vec<u8>>("origin_tx_hash")?;
+        let submitted = client.query(&statement, &[&origin_tx_hash]).await?;
+        results.push(SearchResult::Deposit(SearchDeposit::from_rows(
+            &row, &submitted,
+        )?));
+    }
+
+    let (statement, param) = &db.prepared_statements.search_withdrawals;
+    let statement = client
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    for row in client.query(&statement, &[&&hash[..]]).await? {
+        results.push(SearchResult::Withdraw(SearchWithdrawal::from_row(&row)?));
+    }
+
+    let (statement, param) = &db.prepared_statements.search_merkle_root_tx;
+    let statement = client
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    if let Some(row) = client.query_opt(&statement, &[&&hash[..]]).await? {
+        let root = row.try_get::<_, Fixed<32>>("root")?.0;
+        let (statement, param) = &db.prepared_statements.search_merkle_root;
+        let statement = client
+            .prepare_typed_cached(statement, std::slice::from_ref(param))
+            .await?;
+        let info = client.query_one(&statement, &[&&root[..]]).await?;
+        results.push(SearchResult::MerkleRoot(SearchMerkleRoot {
+            merkle_root: hex::encode(root),
+            tx_hash:     TransactionHash::new(hash),
+            status:      match row.try_get::<_, EthTransactionStatus>("status")? {
+                EthTransactionStatus::Pending => TransactionStatus::Pending,
+                EthTransactionStatus::Confirmed => TransactionStatus::Finalized,
+                EthTransactionStatus::Missing => TransactionStatus::Missing,
+            },
+            timestamp:   row.try_get::<_, i64>("timestamp")?,
+            set_time:    unix_time(&info, "set_time")?,
+            latest:      info.try_get::<_, bool>("latest")?,
+            withdrawals: info
+                .try_get::<_, Vec<i64>>("withdrawals")?
+                .into_iter()
+                .map(|index| index as u64)
+                .collect(),
+        }));
+    }
+
+    if results.is_empty() {
+        return Err(Error::NotFound);
+    }
+    Ok(results.into())
+}
+
+/// How often the rate limits of clients that have not made requests for a
+/// while are forgotten.
+const RATE_LIMIT_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Maximum number of buckets of anonymous clients that are tracked. Clients
+/// that make their first request while this many are tracked share a bucket
+/// for each class until the buckets are pruned.
+const MAX_RATE_LIMIT_CLIENTS: usize = 100_000;
+
+/// The header in which clients give their API key.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Minimum length of API keys.
+const MIN_API_KEY_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Classes of the routes of the public API, which are rate limited separately.
+enum RouteClass {
+    /// Merkle proofs, which are expensive to make.
+    Proof,
+    /// Lookups of transfers, wallets, statistics, and the stream of updates.
+    Query,
+    /// Token list, fees and other responses that are cheap to make.
+    Light,
+}
+
+impl RouteClass {
+    const ALL: [RouteClass; 3] = [RouteClass::Proof, RouteClass::Query, RouteClass::Light];
+
+    /// The class of the route with the given path, as it was registered in the
+    /// router.
+    fn of(path: &str) -> Self {
+        if path.starts_with("/api/v1/ethereum/proof") {
+            RouteClass::Proof
+        } else if path == "/api/v1/tokens"
+            || path == "/api/v1/fees"
+            || path == "/api/v1/expectedMerkleRootUpdate"
+            || path == "/openapi.json"
+            || path.starts_with("/assets")
+        {
+            RouteClass::Light
+        } else {
+            RouteClass::Query
+        }
+    }
+
+    /// Number of requests of the quota of an API key that a request of the
+    /// class counts as.
+    fn weight(self) -> f64 {
+        match self {
+            RouteClass::Proof => 10.0,
+            RouteClass::Query | RouteClass::Light => 1.0,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RouteClass::Proof => "proof",
+            RouteClass::Query => "query",
+            RouteClass::Light => "light",
+        }
+    }
+}
+
+impl std::str::FromStr for RouteClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RouteClass::ALL
+            .into_iter()
+            .find(|class| class.as_str() == s)
+            .with_context(|| format!("Unknown route class {s}, expected proof, query or light."))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The parameters of a token bucket.
+struct Limit {
+    /// Number of requests per second that are added to the bucket.
+    rate:  f64,
+    /// Size of the bucket, i.e., the number of requests that can be made at
+    /// once.
+    burst: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A limit on the requests of anonymous clients to a class of routes.
+struct RouteLimit {
+    class: RouteClass,
+    limit: Limit,
+}
+
+impl std::str::FromStr for RouteLimit {
+    type Err = anyhow::Error;
+
+    /// Parse a limit in the format `class:rate:burst`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [class, rate, burst] = s.split(':').collect::<Vec<_>>()[..] else {
+            anyhow::bail!("Expected format class:rate:burst");
+        };
+        let rate: f64 = rate.parse()?;
+        let burst: u32 = burst.parse()?;
+        anyhow::ensure!(
+            rate > 0.0 && burst > 0,
+            "The rate and the burst must be positive."
+        );
+        Ok(Self {
+            class: class.parse()?,
+            limit: Limit {
+                rate,
+                burst: burst.into(),
+            },
+        })
+    }
+}
+
+#[derive(Clone)]
+/// An API key and the number of requests per minute it may make.
+struct ApiKey {
+    name:   String,
+    /// SHA-256 digest of the key.
+    digest: [u8; 32],
+    quota:  u32,
+}
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Do not log anything derived from the key.
+        f.debug_struct("ApiKey")
+            .field("name", &self.name)
+            .field("quota", &self.quota)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::str::FromStr for ApiKey {
+    type Err = anyhow::Error;
+
+    /// Parse a key in the format `name:key:quota`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [name, key, quota] = s.split(':').collect::<Vec<_>>()[..] else {
+            anyhow::bail!("Expected format name:key:quota");
+        };
+        anyhow::ensure!(!name.is_empty(), "The name of the key must not be empty.");
+        anyhow::ensure!(
+            key.len() >= MIN_API_KEY_LEN,
+            "The key must be at least {MIN_API_KEY_LEN} characters long."
+        );
+        let quota: u32 = quota.parse()?;
+        anyhow::ensure!(quota > 0, "The quota must be positive.");
+        Ok(Self {
+            name: name.into(),
+            digest: <sha2::Sha256 as sha2::Digest>::digest(key.as_bytes()).into(),
+            quota,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// An IP address with a prefix length, e.g., `10.0.0.0/8`. An address without
+/// a prefix length is a network of that address only.
+struct IpNetwork {
+    address: std::net::IpAddr,
+    prefix:  u32,
+}
+
+impl std::str::FromStr for IpNetwork {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address.parse()?, Some(prefix.parse()?)),
+            None => (s.parse()?, None),
+        };
+        let bits = match address {
+            std::net::IpAddr::V4(_) => 32,
+            std::net::IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(bits);
+        anyhow::ensure!(prefix <= bits, "The prefix length must be at most {bits}.");
+        Ok(Self { address, prefix })
+    }
+}
+
+impl IpNetwork {
+    /// The address with the bits after the prefix cleared.
+    fn network_address(&self) -> std::net::IpAddr {
+        match self.address {
+            std::net::IpAddr::V4(address) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                std::net::Ipv4Addr::from(u32::from(address) & mask).into()
+            }
+            std::net::IpAddr::V6(address) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                std::net::Ipv6Addr::from(u128::from(address) & mask).into()
+            }
+        }
+    }
+
+    fn contains(&self, ip: std::net::IpAddr) -> bool {
+        match (self.address, ip) {
+            (std::net::IpAddr::V4(network), std::net::IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (std::net::IpAddr::V6(network), std::net::IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A token bucket. Each request takes tokens for its cost, and tokens are
+/// added at the rate of the limit up to its burst. A request is admitted if
+/// there is at least one token, so a request that costs more than that leaves
+/// the bucket in debt, which delays the following requests.
+struct TokenBucket {
+    tokens:  f64,
+    updated: std::time::Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: Limit, now: std::time::Instant) -> Self {
+        Self {
+            tokens:  limit.burst,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: Limit, now: std::time::Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst);
+        self.updated = now;
+    }
+
+    /// Take the cost of a request if there is a token. Otherwise return how
+    /// long it takes until there is.
+    fn take(
+        &mut self,
+        limit: Limit,
+        cost: f64,
+        now: std::time::Instant,
+    ) -> Result<(), std::time::Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            Err(std::time::Duration::from_secs_f64(
+                (1.0 - self.tokens) / limit.rate,
+            ))
+        }
+    }
+
+    /// Take the cost of an admitted request, even if there are not enough
+    /// tokens.
+    fn charge(&mut self, limit: Limit, cost: f64, now: std::time::Instant) {
+        self.refill(limit, now);
+        self.tokens -= cost;
+    }
+}
+
+/// The state of an API key.
+struct ApiKeyState {
+    name:   String,
+    limit:  Limit,
+    bucket: std::sync::Mutex<TokenBucket>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A client of the public API, as identified for rate limiting.
+enum RateLimitClient {
+    /// A client with the API key with the given digest.
+    Key([u8; 32]),
+    /// An anonymous client with the given IP, or the address of the IPv6
+    /// network of the client.
+    Anonymous(std::net::IpAddr),
+}
+
+#[derive(Default)]
+/// The token buckets of anonymous clients.
+struct AnonymousBuckets {
+    /// The buckets by class and client. At most [`MAX_RATE_LIMIT_CLIENTS`].
+    clients:  HashMap<(RouteClass, std::net::IpAddr), TokenBucket>,
+    /// The buckets by class shared by the clients that are not in `clients`
+    /// because it was full.
+    overflow: HashMap<RouteClass, TokenBucket>,
+}
+
+/// Rate limits of the public API. Requests with an API key are limited by the
+/// quota of the key, where each request counts as the weight of its route
+/// class, and anonymous requests by the limit of the route class per client
+/// IP.
+struct RateLimiter {
+    limits:          HashMap<RouteClass, Limit>,
+    /// The API keys by the digest of the key.
+    keys:            HashMap<[u8; 32], ApiKeyState>,
+    /// Proxies whose `X-Forwarded-For` headers are used to find the IP of the
+    /// client.
+    trusted_proxies: Vec<IpNetwork>,
+    /// Length of the prefix of the IPv6 networks that are limited as one
+    /// client.
+    ipv6_prefix:     u32,
+    anonymous:       std::sync::Mutex<AnonymousBuckets>,
+}
+
+impl RateLimiter {
+    fn new(
+        limits: &[RouteLimit],
+        keys: &[ApiKey],
+        trusted_proxies: Vec<IpNetwork>,
+        ipv6_prefix: u32,
+    ) -> Self {
+        let now = std::time::Instant::now();
+        let limits: HashMap<_, _> = limits.iter().map(|l| (l.class, l.limit)).collect();
+        for (class, limit) in &limits {
+            metrics::gauge!(
+                "ccdeth_api_server_rate_limit_rate",
+                limit.rate,
+                "class" => class.as_str()
+            );
+            metrics::gauge!(
+                "ccdeth_api_server_rate_limit_burst",
+                limit.burst,
+                "class" => class.as_str()
+            );
+        }
+        let keys = keys
+            .iter()
+            .map(|key| {
+                metrics::gauge!(
+                    "ccdeth_api_server_api_key_quota",
+                    f64::from(key.quota),
+                    "key" => key.name.clone()
+                );
+                // The quota is per minute, and can be used all at once.
+                let limit = Limit {
+                    rate:  f64::from(key.quota) / 60.0,
+                    burst: key.quota.into(),
+                };
+                (key.digest, ApiKeyState {
+                    name: key.name.clone(),
+                    limit,
+                    bucket: std::sync::Mutex::new(TokenBucket::new(limit, now)),
+                })
+            })
+            .collect();
+        Self {
+            limits,
+            keys,
+            trusted_proxies,
+            ipv6_prefix,
+            anonymous: Default::default(),
+        }
+    }
+
+    /// Apply `f` to the bucket of the client for the class of routes, with the
+    /// limit of the bucket and the weight of a request. Returns `None` if
+    /// requests of the client to the class are not limited.
+    fn with_bucket<R>(
+        &self,
+        client: RateLimitClient,
+        class: RouteClass,
+        f: impl FnOnce(&mut TokenBucket, Limit, f64) -> R,
+    ) -> Option<R> {
+        match client {
+            RateLimitClient::Key(digest) => {
+                let key = self.keys.get(&digest)?;
+                let mut bucket = key.bucket.lock().ok()?;
+                Some(f(&mut bucket, key.limit, class.weight()))
+            }
+            RateLimitClient::Anonymous(ip) => {
+                let limit = *self.limits.get(&class)?;
+                let mut anonymous = self.anonymous.lock().ok()?;
+                let AnonymousBuckets { clients, overflow } = &mut *anonymous;
+                let new_bucket = || TokenBucket::new(limit, std::time::Instant::now());
+                let bucket = if clients.len() < MAX_RATE_LIMIT_CLIENTS
+                    || clients.contains_key(&(class, ip))
+                {
+                    clients.entry((class, ip)).or_insert_with(new_bucket)
+                } else {
+                    overflow.entry(class).or_insert_with(new_bucket)
+                };
+                Some(f(bucket, limit, 1.0))
+            }
+        }
+    }
+
+    /// The name of the client in metrics.
+    fn client_name(&self, client: RateLimitClient) -> String {
+        match client {
+            RateLimitClient::Key(digest) => self
+                .keys
+                .get(&digest)
+                .map_or_else(|| "unknown".into(), |key| key.name.clone()),
+            RateLimitClient::Anonymous(_) => "anonymous".into(),
+        }
+    }
+
+    fn is_trusted(&self, ip: std::net::IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|network| network.contains(ip))
+    }
+
+    /// The IP of the client that made the request. If the peer is a trusted
+    /// proxy this is the last address in `X-Forwarded-For` that is not a
+    /// trusted proxy.
+    fn client_ip(&self, peer: std::net::IpAddr, headers: &http::HeaderMap) -> std::net::IpAddr {
+        let mut client = peer;
+        if !self.is_trusted(client) {
+            return client;
+        }
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for address in forwarded.into_iter().rev() {
+            let Ok(address) = address.trim().parse() else {
+                break;
+            };
+            client = address;
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+
+    /// The client an anonymous request from the given IP is limited as. IPv6
+    /// addresses are truncated to the network of the configured prefix length.
+    fn anonymous_client(&self, ip: std::net::IpAddr) -> RateLimitClient {
+        let address = match ip {
+            std::net::IpAddr::V4(_) => ip,
+            std::net::IpAddr::V6(_) => IpNetwork {
+                address: ip,
+                prefix:  self.ipv6_prefix,
+            }
+            .network_address(),
+        };
+        RateLimitClient::Anonymous(address)
+    }
+
+    /// Forget the clients whose buckets are full, and record the number of
+    /// clients that are still tracked.
+    fn prune(&self) {
+        let now = std::time::Instant::now();
+        let Ok(mut anonymous) = self.anonymous.lock() else {
+            return;
+        };
+        let is_used = |class: &RouteClass, bucket: &mut TokenBucket| {
+            let Some(limit) = self.limits.get(class) else {
+                return false;
+            };
+            bucket.refill(*limit, now);
+            bucket.tokens < limit.burst
+        };
+        anonymous
+            .clients
+            .retain(|(class, _), bucket| is_used(class, bucket));
+        anonymous
+            .overflow
+            .retain(|class, bucket| is_used(class, bucket));
+        metrics::gauge!(
+            "ccdeth_api_server_rate_limit_clients",
+            anonymous.clients.len() as f64
+        );
+    }
+}
+
+#[derive(Clone)]
+/// The client and route class a request was admitted for by the rate limits.
+/// It is added to the extensions of the request, so that handlers of requests
+/// that do the work of several requests can charge for them.
+struct RateLimitCharge {
+    limiter: Arc<RateLimiter>,
+    client:  RateLimitClient,
+    class:   RouteClass,
+}
+
+impl RateLimitCharge {
+    /// Charge the client for the given number of requests in addition to the
+    /// one it was admitted for.
+    fn charge(&self, requests: usize) {
+        let now = std::time::Instant::now();
+        self.limiter
+            .with_bucket(self.client, self.class, |bucket, limit, weight| {
+                bucket.charge(limit, weight * requests as f64, now)
+            });
+    }
+}
+
+/// Periodically forget the clients that have not made requests for a while.
+async fn prune_rate_limits(limiter: Arc<RateLimiter>) {
+    let mut interval = tokio::time::interval(RATE_LIMIT_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        limiter.prune();
+    }
+}
+
+/// Apply the rate limits to a request to the public API.
+async fn rate_limit<B>(
+    axum::extract::State(limiter): axum::extract::State<Arc<RateLimiter>>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    path: Option<axum::extract::MatchedPath>,
+    mut request: http::Request<B>,
+    next: axum::middleware::Next<B>,
+) -> axum::response::Response {
+    let class = path.map_or(RouteClass::Query, |path| RouteClass::of(path.as_str()));
+    let client = if let Some(key) = request.headers().get(API_KEY_HEADER) {
+        let digest: [u8; 32] = <sha2::Sha256 as sha2::Digest>::digest(key.as_bytes()).into();
+        let Some(key) = limiter.keys.get(&digest) else {
+            metrics::increment_counter!("ccdeth_api_server_invalid_api_key_total");
+            return (StatusCode::UNAUTHORIZED, Json("Invalid API key.")).into_response();
+        };
+        metrics::increment_counter!(
+            "ccdeth_api_server_api_key_requests_total",
+            "key" => key.name.clone(),
+            "class" => class.as_str()
+        );
+        RateLimitClient::Key(digest)
+    } else {
+        limiter.anonymous_client(limiter.client_ip(peer.ip(), request.headers()))
+    };
+    let now = std::time::Instant::now();
+    let result = limiter.with_bucket(client, class, |bucket, limit, weight| {
+        bucket.take(limit, weight, now)
+    });
+    if let Some(Err(wait)) = result {
+        metrics::increment_counter!(
+            "ccdeth_api_server_rate_limited_requests_total",
+            "client" => limiter.client_name(client),
+            "class" => class.as_str()
+        );
+        let retry_after = wait.as_secs_f64().ceil() as u64;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(http::header::RETRY_AFTER, retry_after.to_string())],
+            Json("Too many requests."),
+        )
+            .into_response();
+    }
+    request.extensions_mut().insert(RateLimitCharge {
+        limiter,
+        client,
+        class,
+    });
+    next.run(request).await
+}
+
+/// Minimum length of the tokens of the admin API.
+const MIN_ADMIN_TOKEN_LEN: usize = 16;
+
+/// Maximum size of a request body of the admin API in bytes.
+const MAX_ADMIN_REQUEST_BODY_SIZE: usize = 4 * 1024;
+
+/// A token of an operator of the admin API.
+#[derive(Clone)]
+struct AdminToken {
+    /// Name of the operator, recorded with the actions taken with the token.
+    name:   String,
+    /// SHA-256 digest of the token.
+    digest: [u8; 32],
+}
+
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Do not log anything derived from the token.
+        f.debug_struct("AdminToken")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::str::FromStr for AdminToken {
+    type Err = anyhow::Error;
+
+    /// Parse a token in the format `name:token`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, token)) = s.split_once(':') else {
+            anyhow::bail!("Expected format name:token");
+        };
+        anyhow::ensure!(
+            !name.is_empty(),
+            "The name of the operator must not be empty."
+        );
+        anyhow::ensure!(
+            token.len() >= MIN_ADMIN_TOKEN_LEN,
+            "The token must be at least {MIN_ADMIN_TOKEN_LEN} characters long."
+        );
+        Ok(Self {
+            name:   name.into(),
+            digest: <sha2::Sha256 as sha2::Digest>::digest(token.as_bytes()).into(),
+        })
+    }
+}
+
+impl AdminToken {
+    /// Check whether the digest of a presented token matches this token. The
+    /// digests are compared in constant time.
+    fn matches(&self, digest: &[u8; 32]) -> bool {
+        self.digest
+            .iter()
+            .zip(digest)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+}
+
+/// The name of the operator that made a request to the admin API.
+#[derive(Debug, Clone)]
+struct Operator(String);
+
+/// Authenticate a request to the admin API by the bearer token in the
+/// `Authorization` header, and add the [`Operator`] to the request.
+async fn authenticate_admin<B>(
+    axum::extract::State(tokens): axum::extract::State<Arc<[AdminToken]>>,
+    mut request: http::Request<B>,
+    next: axum::middleware::Next<B>,
+) -> Result<axum::response::Response, StatusCode> {
+    let digest: Option<[u8; 32]> = request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| <sha2::Sha256 as sha2::Digest>::digest(token.as_bytes()).into());
+    let Some(digest) = digest else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    // Check all the tokens, so that the time taken does not depend on which one
+    // matches.
+    let mut operator = None;
+    for token in tokens.iter() {
+        if token.matches(&digest) {
+            operator = Some(token.name.clone());
+        }
+    }
+    let Some(operator) = operator else {
+        tracing::warn!("Rejected a request to the admin API with an unknown token.");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    request.extensions_mut().insert(Operator(operator));
+    Ok(next.run(request).await)
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The actions recorded in the `admin_actions` table.
+enum AdminAction {
+    ApproveWithdrawal,
+    ApproveDeposit,
+    RequeueDeposit,
+    MerkleUpdate,
+}
+
+impl AdminAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            AdminAction::ApproveWithdrawal => "approve_withdrawal",
+            AdminAction::ApproveDeposit => "approve_deposit",
+            AdminAction::RequeueDeposit => "requeue_deposit",
+            AdminAction::MerkleUpdate => "merkle_update",
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+/// Optional body of the action endpoints of the admin API.
+struct AdminActionRequest {
+    /// Reason for the action, recorded in the audit table.
+    reason: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+/// Response of the action endpoints of the admin API.
+struct AdminActionResponse {
+    /// Id of the record of the action in the `admin_actions` table.
+    id: u64,
+}
+
+#[derive(serde::Serialize)]
+/// Response of the Merkle update endpoint of the admin API.
+struct AdminMerkleUpdateResponse {
+    /// Id of the record of the action in the `admin_actions` table.
+    id:                u64,
+    /// Whether a relayer was listening for the request when it was made. If
+    /// not, the request has no effect.
+    relayer_listening: bool,
+}
+
+/// Who requested an admin action, and why.
+struct AdminCaller {
+    operator:       Operator,
+    remote_address: std::net::SocketAddr,
+    reason:         Option<String>,
+}
+
+impl AdminCaller {
+    /// Construct the caller from the body of the request, which is either
+    /// empty or an [`AdminActionRequest`].
+    fn new(
+        operator: Operator,
+        remote_address: std::net::SocketAddr,
+        body: &[u8],
+    ) -> Result<Self, Error> {
+        let reason = if body.iter().all(u8::is_ascii_whitespace) {
+            None
+        } else {
+            serde_json::from_slice::<AdminActionRequest>(body)
+                .map_err(|e| Error::InvalidRequest(format!("Invalid request body: {e}.")))?
+                .reason
+        };
+        Ok(Self {
+            operator,
+            remote_address,
+            reason,
+        })
+    }
+
+    /// Record the action in the audit table as part of the transaction that
+    /// takes it, and return the response to the caller.
+    async fn audit(
+        &self,
+        db: &Database,
+        db_tx: &deadpool_postgres::Transaction<'_>,
+        action: AdminAction,
+        event_index: Option<u64>,
+    ) -> Result<AdminActionResponse, Error> {
+        let (statement, params) = &db.prepared_statements.insert_admin_action;
+        let statement = db_tx.prepare_typed_cached(statement, &params[..]).await?;
+        let row = db_tx
+            .query_one(&statement, &[
+                &action.as_str(),
+                &event_index.map(|i| i as i64),
+                &self.operator.0,
+                &self.remote_address.to_string(),
+                &self.reason,
+            ])
+            .await?;
+        let id = row.try_get::<_, i64>("id")? as u64;
+        tracing::info!(
+            operator = %self.operator.0,
+            remote_address = %self.remote_address,
+            reason = ?self.reason,
+            "Recorded admin action {} with id {id}.",
+            action.as_str()
+        );
+        Ok(AdminActionResponse { id })
+    }
+}
+
+#[derive(serde::Serialize)]
+/// A Concordium transaction sent by the relayer that is not yet finalized.
+struct AdminConcordiumTx {
+    tx_hash:        TransactionHash,
+    /// Hash of the Ethereum transaction the transaction was sent for.
+    origin_tx_hash: TransactionHash,
+    /// Time when the transaction was made, in seconds since the unix epoch.
+    timestamp:      i64,
+}
+
+#[derive(serde::Serialize)]
+/// An Ethereum transaction sent by the relayer that is not yet confirmed.
+struct AdminEthereumTx {
+    tx_hash:   TransactionHash,
+    /// Time when the transaction was made, in seconds since the unix epoch.
+    timestamp: i64,
+}
+
+#[derive(serde::Serialize)]
+/// Response of the pending transactions endpoint of the admin API.
+struct AdminTransactions {
+    concordium: Vec<AdminConcordiumTx>,
+    ethereum:   Vec<AdminEthereumTx>,
+}
+
+/// List the transactions sent by the relayer that are not yet final.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_transactions(
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<Json<AdminTransactions>, Error> {
+    let client = db.pool.get().await?;
+    let statement = &db.prepared_statements.admin_concordium_txs;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let mut concordium = Vec::new();
+    for row in client.query(&statement, &[]).await? {
+        concordium.push(AdminConcordiumTx {
+            tx_hash:        TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0),
+            origin_tx_hash: TransactionHash::new(row.try_get::<_, Fixed<32>>("origin_tx_hash")?.0),
+            timestamp:      row.try_get::<_, i64>("timestamp")?,
+        });
+    }
+    let statement = &db.prepared_statements.admin_ethereum_txs;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let mut ethereum = Vec::new();
+    for row in client.query(&statement, &[]).await? {
+        ethereum.push(AdminEthereumTx {
+            tx_hash:   TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0),
+            timestamp: row.try_get::<_, i64>("timestamp")?,
+        });
+    }
+    Ok(AdminTransactions {
+        concordium,
+        ethereum,
+    }
+    .into())
+}
+
+#[derive(serde::Serialize)]
+/// A withdrawal in the leaf set of the Merkle tree.
+struct AdminLeaf {
+    tx_hash:      TransactionHash,
+    event_index:  u64,
+    /// Hash of the leaf, in hex.
+    merkle_hash:  String,
+    /// The latest root that contains the withdrawal, in hex.
+    root:         Option<String>,
+    /// The root that is being set and contains the withdrawal, in hex.
+    pending_root: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+/// Response of the Merkle tree endpoint of the admin API.
+struct AdminMerkleState {
+    /// The latest root that was set, in hex.
+    root:         Option<String>,
+    /// The root of the transaction that is being sent, in hex.
+    pending_root: Option<String>,
+    /// The withdrawals that are not completed or held, which are the leaves of
+    /// the next root.
+    leaves:       Vec<AdminLeaf>,
+}
+
+/// Get the current leaf set of the Merkle tree and the root that is being set.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_merkle(
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<Json<AdminMerkleState>, Error> {
+    let client = db.pool.get().await?;
+    let statement = &db.prepared_statements.get_latest_merkle_root;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let root = match client.query_opt(&statement, &[]).await? {
+        Some(row) => Some(hex::encode(row.try_get::<_, Fixed<32>>("root")?.0)),
+        None => None,
+    };
+    let statement = &db.prepared_statements.admin_leaves;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let rows = client.query(&statement, &[]).await?;
+    let mut leaves = Vec::with_capacity(rows.len());
+    for row in rows {
+        let optional_hex = |column: &str| -> Result<Option<String>, Error> {
+            Ok(row
+                .try_get::<_, Option<Fixed<32>>>(column)?
+                .map(|h| hex::encode(h.0)))
+        };
+        leaves.push(AdminLeaf {
+            tx_hash:      TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0),
+            event_index:  row.try_get::<_, i64>("event_index")? as u64,
+            merkle_hash:  hex::encode(row.try_get::<_, Fixed<32>>("event_merkle_hash")?.0),
+            root:         optional_hex("root")?,
+            pending_root: optional_hex("pending_root")?,
+        });
+    }
+    let pending_root = leaves.iter().find_map(|leaf| leaf.pending_root.clone());
+    Ok(AdminMerkleState {
+        root,
+        pending_root,
+        leaves,
+    }
+    .into())
+}
+
+#[derive(serde::Serialize)]
+/// A withdrawal that is held back from the Merkle tree.
+struct AdminHeldWithdrawal {
+    tx_hash:       TransactionHash,
+    event_index:   u64,
+    child_token:   ContractAddress,
+    amount:        String,
+    receiver:      ethers::types::Address,
+    reason:        WithdrawalHoldReason,
+    /// Time when the withdrawal was held.
+    timestamp:     i64,
+    /// Time when the withdrawal is released automatically, if it is.
+    release_time:  Option<i64>,
+    /// Time when the withdrawal was approved, if it was. It is released by the
+    /// relayer shortly after.
+    approved_time: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+/// A deposit that is held because it matched a screening list.
+struct AdminHeldDeposit {
+    origin_tx_hash:     TransactionHash,
+    origin_event_index: u64,
+    /// Time when the deposit was held.
+    timestamp:          i64,
+    /// Time when the deposit was approved or requeued, if it was. It is sent by
+    /// the relayer shortly after.
+    approved_time:      Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+/// A deposit for which all the transactions sent to Concordium failed.
+struct AdminFailedDeposit {
+    origin_tx_hash:     TransactionHash,
+    origin_event_index: u64,
+    root_token:         ethers::types::Address,
+    amount:             String,
+    /// Time when the deposit was discovered.
+    timestamp:          i64,
+    /// The failed transactions sent for the Ethereum transaction of the
+    /// deposit.
+    failed_txs:         Vec<TransactionHash>,
+}
+
+#[derive(serde::Serialize)]
+/// Response of the held items endpoint of the admin API.
+struct AdminHeldItems {
+    withdrawals:     Vec<AdminHeldWithdrawal>,
+    deposits:        Vec<AdminHeldDeposit>,
+    failed_deposits: Vec<AdminFailedDeposit>,
+}
+
+/// List the held withdrawals and deposits, and the deposits that failed.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_held(
+    axum::extract::State(db): axum::extract::State<Database>,
+) -> Result<Json<AdminHeldItems>, Error> {
+    let client = db.pool.get().await?;
+    let time = |row: &tokio_postgres::Row, column: &str| -> Result<Option<i64>, Error> {
+        Ok(row
+            .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(column)?
+            .map(|t| t.timestamp()))
+    };
+    let statement = &db.prepared_statements.admin_held_withdrawals;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let mut withdrawals = Vec::new();
+    for row in client.query(&statement, &[]).await? {
+        withdrawals.push(AdminHeldWithdrawal {
+            tx_hash:       TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0),
+            event_index:   row.try_get::<_, i64>("event_index")? as u64,
+            child_token:   ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            ),
+            amount:        row.try_get("amount")?,
+            receiver:      row.try_get::<_, Fixed<20>>("receiver")?.0.into(),
+            reason:        row.try_get("reason")?,
+            timestamp:     time(&row, "insert_time")?.ok_or(Error::Internal)?,
+            release_time:  time(&row, "release_time")?,
+            approved_time: time(&row, "approved_time")?,
+        });
+    }
+    let statement = &db.prepared_statements.admin_held_deposits;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let mut deposits = Vec::new();
+    for row in client.query(&statement, &[]).await? {
+        deposits.push(AdminHeldDeposit {
+            origin_tx_hash:     TransactionHash::new(
+                row.try_get::<_, Fixed<32>>("origin_tx_hash")?.0,
+            ),
+            origin_event_index: row.try_get::<_, i64>("origin_event_index")? as u64,
+            timestamp:          time(&row, "insert_time")?.ok_or(Error::Internal)?,
+            approved_time:      time(&row, "approved_time")?,
+        });
+    }
+    let statement = &db.prepared_statements.admin_failed_deposits;
+    let statement = client.prepare_typed_cached(statement, &[]).await?;
+    let mut failed_deposits = Vec::new();
+    for row in client.query(&statement, &[]).await? {
+        let failed_txs = row
+            .try_get::<_, Vec<Fixed<32>>>("failed_txs")?
+            .into_iter()
+            .map(|h| TransactionHash::new(h.0))
+            .collect();
+        failed_deposits.push(AdminFailedDeposit {
+            origin_tx_hash: TransactionHash::new(row.try_get::<_, Fixed<32>>("origin_tx_hash")?.0),
+            origin_event_index: row.try_get::<_, i64>("origin_event_index")? as u64,
+            root_token: row.try_get::<_, Fixed<20>>("root_token")?.0.into(),
+            amount: row.try_get("amount")?,
+            timestamp: time(&row, "insert_time")?.ok_or(Error::Internal)?,
+            failed_txs,
+        });
+    }
+    Ok(AdminHeldItems {
+        withdrawals,
+        deposits,
+        failed_deposits,
+    }
+    .into())
+}
+
+/// Approve a held withdrawal. The relayer releases it into the Merkle tree the
+/// next time it checks the holds.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_approve_withdrawal(
+    axum::extract::Path(event_index): axum::extract::Path<u64>,
+    axum::extract::State(db): axum::extract::State<Database>,
+    axum::Extension(operator): axum::Extension<Operator>,
+    axum::extract::ConnectInfo(remote_address): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    body: axum::body::Bytes,
+) -> Result<Json<AdminActionResponse>, Error> {
+    let caller = AdminCaller::new(operator, remote_address, &body)?;
+    let mut client = db.pool.get().await?;
+    let db_tx = client.transaction().await?;
+    let (statement, param) = &db.prepared_statements.admin_approve_withdrawal;
+    let statement = db_tx
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    if db_tx
+        .query_opt(&statement, &[&(event_index as i64)])
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let response = caller
+        .audit(
+            &db,
+            &db_tx,
+            AdminAction::ApproveWithdrawal,
+            Some(event_index),
+        )
+        .await?;
+    db_tx.commit().await?;
+    Ok(response.into())
+}
+
+/// Approve a held deposit. The relayer sends it to Concordium the next time it
+/// checks the holds.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_approve_deposit(
+    axum::extract::Path(event_index): axum::extract::Path<u64>,
+    axum::extract::State(db): axum::extract::State<Database>,
+    axum::Extension(operator): axum::Extension<Operator>,
+    axum::extract::ConnectInfo(remote_address): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    body: axum::body::Bytes,
+) -> Result<Json<AdminActionResponse>, Error> {
+    let caller = AdminCaller::new(operator, remote_address, &body)?;
+    let mut client = db.pool.get().await?;
+    let db_tx = client.transaction().await?;
+    let (statement, param) = &db.prepared_statements.admin_approve_deposit;
+    let statement = db_tx
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    if db_tx
+        .query_opt(&statement, &[&(event_index as i64)])
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let response = caller
+        .audit(&db, &db_tx, AdminAction::ApproveDeposit, Some(event_index))
+        .await?;
+    db_tx.commit().await?;
+    Ok(response.into())
+}
+
+/// Requeue a deposit for which all the transactions sent to Concordium failed.
+/// The deposit is recorded as an approved hold, so that the relayer makes a new
+/// transaction for it the next time it checks the holds.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_requeue_deposit(
+    axum::extract::Path(event_index): axum::extract::Path<u64>,
+    axum::extract::State(db): axum::extract::State<Database>,
+    axum::Extension(operator): axum::Extension<Operator>,
+    axum::extract::ConnectInfo(remote_address): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    body: axum::body::Bytes,
+) -> Result<Json<AdminActionResponse>, Error> {
+    let caller = AdminCaller::new(operator, remote_address, &body)?;
+    let mut client = db.pool.get().await?;
+    let db_tx = client.transaction().await?;
+    let (statement, param) = &db.prepared_statements.admin_get_failed_deposit;
+    let statement = db_tx
+        .prepare_typed_cached(statement, std::slice::from_ref(param))
+        .await?;
+    let Some(row) = db_tx
+        .query_opt(&statement, &[&(event_index as i64)])
+        .await?
+    else {
+        return Err(Error::NotFound);
+    };
+    let Some(receiver) = row.try_get::<_, Option<Fixed<32>>>("deposit_receiver")? else {
+        return Err(Error::InvalidRequest(
+            "The receiver of the deposit is not recorded.".into(),
+        ));
+    };
+    let amount = row
+        .try_get::<_, String>("amount")?
+        .parse::<num_bigint::BigUint>()
+        .map_err(|_| Error::Internal)?;
+    let deposit = DepositOperation::new(
+        event_index,
+        concordium::id::types::AccountAddress(receiver.0),
+        row.try_get::<_, Fixed<20>>("root_token")?.0,
+        concordium::cis2::TokenAmount(amount),
+    );
+    let origin_tx_hash = row.try_get::<_, Vec<u8>>("origin_tx_hash")?;
+    let (statement, params) = &db.prepared_statements.admin_requeue_deposit;
+    let statement = db_tx.prepare_typed_cached(statement, &params[..]).await?;
+    db_tx
+        .execute(&statement, &[
+            &origin_tx_hash,
+            &(event_index as i64),
+            &concordium::smart_contracts::common::to_bytes(&deposit),
+        ])
+        .await?;
+    let response = caller
+        .audit(&db, &db_tx, AdminAction::RequeueDeposit, Some(event_index))
+        .await?;
+    db_tx.commit().await?;
+    Ok(response.into())
+}
+
+/// Ask the relayer to set a new Merkle root without waiting for the update
+/// interval. A root is only set if there are withdrawals that are not yet in
+/// the latest one. The request is lost if the relayer is not listening, which
+/// is reported in the response.
+#[tracing::instrument(level = "debug", skip(db))]
+async fn admin_merkle_update(
+    axum::extract::State(db): axum::extract::State<Database>,
+    axum::Extension(operator): axum::Extension<Operator>,
+    axum::extract::ConnectInfo(remote_address): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    body: axum::body::Bytes,
+) -> Result<Json<AdminMerkleUpdateResponse>, Error> {
+    let caller = AdminCaller::new(operator, remote_address, &body)?;
+    let mut client = db.pool.get().await?;
+    let db_tx = client.transaction().await?;
+    // The notification is only delivered when the transaction is committed.
+    let statement = &db.prepared_statements.admin_request_merkle_update;
+    let statement = db_tx.prepare_typed_cached(statement, &[]).await?;
+    let relayer_listening = db_tx
+        .query_one(&statement, &[])
+        .await?
+        .try_get::<_, bool>("listening")?;
+    let AdminActionResponse { id } = caller
+        .audit(&db, &db_tx, AdminAction::MerkleUpdate, None)
+        .await?;
+    db_tx.commit().await?;
+    Ok(AdminMerkleUpdateResponse {
+        id,
+        relayer_listening,
+    }
+    .into())
+}
+
+/// The state shared by the handlers.
+#[derive(Clone)]
+struct AppState {
+    db:                 Database,
+    /// Sender of the status notifications, for subscribing to them.
+    status_sender:      tokio::sync::broadcast::Sender<StatusMessage>,
+    /// The RootChainManager contract, if configured.
+    root_chain_manager: Option<Arc<RootChainManager>>,
+    /// The latest fees.
+    fees:               tokio::sync::watch::Receiver<FeesResponse>,
+}
+
+impl axum::extract::FromRef<AppState> for Database {
+    fn from_ref(state: &AppState) -> Self { state.db.clone() }
+}
+
+impl axum::extract::FromRef<AppState> for tokio::sync::broadcast::Sender<StatusMessage> {
+    fn from_ref(state: &AppState) -> Self { state.status_sender.clone() }
+}
+
+impl axum::extract::FromRef<AppState> for Option<Arc<RootChainManager>> {
+    fn from_ref(state: &AppState) -> Self { state.root_chain_manager.clone() }
+}
+
+impl axum::extract::FromRef<AppState> for tokio::sync::watch::Receiver<FeesResponse> {
+    fn from_ref(state: &AppState) -> Self { state.fees.clone() }
+}
+
+#[derive(Clone)]
+pub struct Database {
+    pool:                deadpool_postgres::Pool,
+    prepared_statements: Arc<QueryStatements>,
+    /// The Merkle tree of the latest root, if it has been built.
+    merkle_tree:         Arc<tokio::sync::Mutex<Option<Arc<CachedMerkleTree>>>>,
+}
+
+/// The Merkle tree of a root, kept in memory so that proofs can be made
+/// without rebuilding the tree.
+struct CachedMerkleTree {
+    /// Id of the root in the `merkle_roots` table.
+    root_id:   i64,
+    tree:      rs_merkle::MerkleTree<Keccak256Algorithm>,
+    /// Position of the leaf of each withdrawal, by the hash of the transaction
+    /// and the event index.
+    positions: HashMap<(TransactionHash, u64), usize>,
+}
+
+impl CachedMerkleTree {
+    /// The position of the leaf of the withdrawal, if it is in the tree.
+    fn position(&self, tx_hash: TransactionHash, event_index: u64) -> Option<usize> {
+        self.positions.get(&(tx_hash, event_index)).copied()
+    }
+
+    /// Make a proof for the leaves at the given positions. If there is one leaf
+    /// the hashes are in the order expected by the bridge contract on
+    /// Ethereum.
+    fn proof_hashes(&self, positions: &[usize]) -> Vec<[u8; 32]> {
+        self.tree.proof(positions).proof_hashes().to_vec()
+    }
+}
+
+impl Database {
+    pub async fn new(config: tokio_postgres::Config, pool_size: usize) -> anyhow::Result<Self> {
+        let manager_config = deadpool_postgres::ManagerConfig {
+            recycling_method: deadpool_postgres::RecyclingMethod::Verified,
+        };
+        let manager = deadpool_postgres::Manager::from_config(config, NoTls, manager_config);
+        let pool = deadpool_postgres::Pool::builder(manager)
+            .create_timeout(Some(std::time::Duration::from_secs(5)))
+            .recycle_timeout(Some(std::time::Duration::from_secs(5)))
+            .wait_timeout(Some(std::time::Duration::from_secs(5)))
+            .max_size(pool_size)
+            .runtime(deadpool_postgres::Runtime::Tokio1)
+            .build()?;
+        Ok(Self {
+            pool,
+            prepared_statements: Arc::new(QueryStatements::new()),
+            merkle_tree: Arc::default(),
+        })
+    }
+
+    /// Get the Merkle tree of the latest root, or [`None`] if no root has been
+    /// set. The tree is only built again when a new root is recorded.
+    async fn merkle_tree(
+        &self,
+        client: &deadpool_postgres::Client,
+    ) -> Result<Option<Arc<CachedMerkleTree>>, Error> {
+        let statement = &self.prepared_statements.get_latest_merkle_root;
+        let statement = client.prepare_typed_cached(statement, &[]).await?;
+        let Some(row) = client.query_opt(&statement, &[]).await? else {
+            return Ok(None);
+        };
+        let root_id = row.try_get::<_, i64>("id")?;
+        let root = row.try_get::<_, Fixed<32>>("root")?.0;
+        // Hold the lock while building, so that concurrent requests wait for the
+        // tree instead of building it as well.
+        let mut cached = self.merkle_tree.lock().await;
+        if let Some(tree) = cached.as_ref().filter(|tree| tree.root_id == root_id) {
+            return Ok(Some(tree.clone()));
+        }
+        let (statement, param) = &self.prepared_statements.get_merkle_leafs;
+        let statement = client
+            .prepare_typed_cached(statement, std::slice::from_ref(param))
+            .await?;
+        let rows = client.query(&statement, &[&&root[..]]).await?;
+        let mut leaves = Vec::with_capacity(rows.len());
+        let mut positions = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let tx_hash = TransactionHash::new(row.try_get::<_, Fixed<32>>("tx_hash")?.0);
+            let event_index = row.try_get::<_, i64>("event_index")? as u64;
+            positions.insert((tx_hash, event_index), leaves.len());
+            leaves.push(row.try_get::<_, Fixed<32>>("event_merkle_hash")?.0);
+        }
+        let tree = rs_merkle::MerkleTree::<Keccak256Algorithm>::from_leaves(&leaves);
+        if tree.root() != Some(root) {
+            tracing::warn!(
+                "The Merkle root {} does not match the withdrawals recorded for it.",
+                hex::encode(root)
+            );
+        }
+        tracing::debug!(
+            "Built the Merkle tree of root {} with {} leaves.",
+            hex::encode(root),
+            leaves.len()
+        );
+        let tree = Arc::new(CachedMerkleTree {
+            root_id,
+            tree,
+            positions,
+        });
+        *cached = Some(tree.clone());
+        Ok(Some(tree))
+    }
+}
+
+struct QueryStatements {
+    concordium_tx_status:        (String, tokio_postgres::types::Type),
+    withdrawal_status:           (String, tokio_postgres::types::Type),
+    get_event:                   (String, [tokio_postgres::types::Type; 2]),
+    get_events:                  (String, tokio_postgres::types::Type),
+    get_latest_merkle_root:      String,
+    get_merkle_leafs:            (String, tokio_postgres::types::Type),
+    ethereum_wallet_txs:         WalletStatements,
+    concordium_wallet_txs:       WalletStatements,
+    list_tokens:                 String,
+    get_next_merkle_root:        String,
+    token_stats:                 String,
+    volumes:                     (String, [tokio_postgres::types::Type; 2]),
+    merkle_tree_stats:           String,
+    admin_concordium_txs:        String,
+    admin_ethereum_txs:          String,
+    admin_leaves:                String,
+    admin_held_withdrawals:      String,
+    admin_held_deposits:         String,
+    admin_failed_deposits:       String,
+    admin_get_failed_deposit:    (String, tokio_postgres::types::Type),
+    admin_approve_withdrawal:    (String, tokio_postgres::types::Type),
+    admin_approve_deposit:       (String, tokio_postgres::types::Type),
+    admin_requeue_deposit:       (String, [tokio_postgres::types::Type; 3]),
+    admin_request_merkle_update: String,
+    insert_admin_action:         (String, [tokio_postgres::types::Type; 5]),
+    search_deposits:             (String, tokio_postgres::types::Type),
+    search_concordium_txs:       (String, tokio_postgres::types::Type),
+    search_withdrawals:          (String, tokio_postgres::types::Type),
+    search_merkle_root_tx:       (String, tokio_postgres::types::Type),
+    search_merkle_root:          (String, tokio_postgres::types::Type),
+    latest_deposit:              (String, [tokio_postgres::types::Type; 2]),
+    latest_pending_withdrawal:   String,
+}
+
+/// The condition on a deposit in `ethereum_deposit_events de` that the
+/// transactions sent to Concordium for it failed, and that it is neither
+/// pending nor held.
+const FAILED_DEPOSIT_CONDITION: &str =
+    "de.tx_hash IS NULL AND EXISTS (SELECT 1 FROM concordium_transactions ct WHERE \
+     ct.origin_tx_hash = de.origin_tx_hash AND ct.status = 'failed') AND NOT EXISTS (SELECT 1 \
+     FROM concordium_transactions ct WHERE ct.origin_tx_hash = de.origin_tx_hash AND ct.status = \
+     'pending') AND NOT EXISTS (SELECT 1 FROM deposit_holds dh WHERE dh.origin_event_index = \
+     de.origin_event_index AND dh.released_time IS NULL)";
+
+/// The queries for listing the transactions of an address, in ascending and
+/// descending order of time.
+struct WalletStatements {
+    asc:  (String, [tokio_postgres::types::Type; 10]),
+    desc: (String, [tokio_postgres::types::Type; 10]),
+}
+
+impl WalletStatements {
+    /// Construct the queries from the conditions that select the withdrawals
+    /// in `concordium_events ce` and the deposits in `ethereum_deposit_events
+    /// de` of the address given as the first parameter.
+    fn new(withdraw_address: &str, deposit_address: &str) -> Self {
+        Self {
+            asc:  (
+                wallet_txs_statement(withdraw_address, deposit_address, "ASC", ">"),
+                WALLET_TXS_PARAMS,
+            ),
+            desc: (
+                wallet_txs_statement(withdraw_address, deposit_address, "DESC", "<"),
+                WALLET_TXS_PARAMS,
+            ),
+        }
+    }
+}
+
+/// Types of the parameters of the queries for the wallet endpoints. These are
+/// the address, the root token, the direction, the status, the start
+/// and end of the time range, the cursor (time, kind and id), and the limit.
+const WALLET_TXS_PARAMS: [tokio_postgres::types::Type; 10] = [
+    tokio_postgres::types::Type::BYTEA,
+    tokio_postgres::types::Type::BYTEA,
+    tokio_postgres::types::Type::TEXT,
+    tokio_postgres::types::Type::TEXT,
+    tokio_postgres::types::Type::TIMESTAMPTZ,
+    tokio_postgres::types::Type::TIMESTAMPTZ,
+    tokio_postgres::types::Type::TIMESTAMPTZ,
+    tokio_postgres::types::Type::INT2,
+    tokio_postgres::types::Type::INT8,
+    tokio_postgres::types::Type::INT8,
+];
+
+/// The query for the wallet endpoints, with the transactions in the given
+/// order, and `cmp` the comparison that selects the transactions after the
+/// cursor in that order. Withdrawals and deposits are each limited separately
+/// first so that the indices on the address and time are used.
+fn wallet_txs_statement(
+    withdraw_address: &str,
+    deposit_address: &str,
+    order: &str,
+    cmp: &str,
+) -> String {
+    format!(
+        "SELECT * FROM ((SELECT 0::INT2 AS kind, ce.id, ce.insert_time, ce.tx_hash AS \
+         origin_tx_hash, ce.event_index, ce.processed AS tx_hash, ce.amount, ce.child_index, \
+         ce.child_subindex, NULL::BYTEA AS root_token, wh.reason AS hold_reason, wh.insert_time \
+         AS hold_time, wh.release_time AS hold_release_time FROM concordium_events ce LEFT JOIN \
+         withdrawal_holds wh ON wh.event_index = ce.event_index AND wh.released_time IS NULL \
+         WHERE ce.event_type = 'withdraw' AND {withdraw_address} AND $3 IS DISTINCT FROM \
+         'deposit' AND ($2 IS NULL OR EXISTS (SELECT 1 FROM token_maps tm WHERE tm.root = $2 AND \
+         tm.child_index = ce.child_index AND tm.child_subindex = ce.child_subindex)) AND ($4 IS \
+         NULL OR ($4 = 'processed' AND ce.processed IS NOT NULL) OR ($4 = 'held' AND ce.processed \
+         IS NULL AND wh.id IS NOT NULL) OR ($4 = 'pending' AND ce.processed IS NULL AND wh.id IS \
+         NULL)) AND ($5 IS NULL OR ce.insert_time >= $5) AND ($6 IS NULL OR ce.insert_time < $6) \
+         AND ($7 IS NULL OR (ce.insert_time {cmp}= $7 AND (ce.insert_time, 0::INT2, ce.id) {cmp} \
+         ($7, $8, $9))) ORDER BY ce.insert_time {order}, ce.id {order} LIMIT $10) UNION ALL \
+         (SELECT 1::INT2 AS kind, de.id, de.insert_time, de.origin_tx_hash, de.origin_event_index \
+         AS event_index, de.tx_hash, de.amount, NULL::INT8 AS child_index, NULL::INT8 AS \
+         child_subindex, de.root_token, NULL::withdrawal_hold_reason AS hold_reason, \
+         dh.insert_time AS hold_time, NULL::TIMESTAMPTZ AS hold_release_time FROM \
+         ethereum_deposit_events de LEFT JOIN deposit_holds dh ON dh.origin_event_index = \
+         de.origin_event_index AND dh.released_time IS NULL WHERE {deposit_address} AND $3 IS \
+         DISTINCT FROM 'withdraw' AND ($2 IS NULL OR de.root_token = $2) AND ($4 IS NULL OR ($4 = \
+         'processed' AND de.tx_hash IS NOT NULL) OR ($4 = 'held' AND de.tx_hash IS NULL AND dh.id \
+         IS NOT NULL) OR ($4 = 'pending' AND de.tx_hash IS NULL AND dh.id IS NULL)) AND ($5 IS \
+         NULL OR de.insert_time >= $5) AND ($6 IS NULL OR de.insert_time < $6) AND ($7 IS NULL OR \
+         (de.insert_time {cmp}= $7 AND (de.insert_time, 1::INT2, de.id) {cmp} ($7, $8, $9))) \
+         ORDER BY de.insert_time {order}, de.id {order} LIMIT $10)) AS txs ORDER BY insert_time \
+         {order}, kind {order}, id {order} LIMIT $10"
+    )
+}
+
+impl QueryStatements {
+    pub fn new() -> Self {
+        let concordium_tx_status = (
+            "SELECT tx_hash FROM ethereum_deposit_events WHERE origin_tx_hash = $1".into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        let withdrawal_status = (
+            "SELECT ce.processed, ce.root, ce.event_index, wh.reason AS hold_reason, \
+             wh.insert_time AS hold_time, wh.release_time AS hold_release_time FROM \
+             concordium_events ce LEFT JOIN withdrawal_holds wh ON wh.event_index = \
+             ce.event_index AND wh.released_time IS NULL WHERE ce.tx_hash = $1"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        let get_event = (
+            "SELECT event_data, processed FROM concordium_events WHERE tx_hash = $1 AND \
+             event_index = $2"
+                .into(),
+            [
+                tokio_postgres::types::Type::BYTEA,
+                tokio_postgres::types::Type::INT8,
+            ],
+        );
+        let get_events = (
+            "SELECT tx_hash, event_index, event_data, processed FROM concordium_events WHERE \
+             event_index = ANY($1)"
+                .into(),
+            tokio_postgres::types::Type::INT8_ARRAY,
+        );
+        let get_latest_merkle_root =
+            "SELECT id, root FROM merkle_roots ORDER BY id DESC LIMIT 1".into();
+        let get_merkle_leafs = (
+            "SELECT tx_hash, event_index, event_merkle_hash FROM concordium_events WHERE root = \
+             $1 ORDER BY event_index ASC"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        let ethereum_wallet_txs = WalletStatements::new("ce.receiver = $1", "de.depositor = $1");
+        // The indices are on the first 29 bytes of the addresses, which identify the
+        // account regardless of the alias.
+        let concordium_wallet_txs = WalletStatements::new(
+            "substring(ce.ccd_address FROM 1 FOR 29) = $1",
+            "substring(de.deposit_receiver FROM 1 FOR 29) = $1",
+        );
+        let list_tokens = "SELECT root, child_index, child_subindex, eth_name, decimals FROM \
+                           token_maps ORDER BY id ASC"
+            .into();
+        let get_next_merkle_root =
+            "SELECT expected_time FROM expected_merkle_update WHERE tag = ''".into();
+        let token_stats = "SELECT root, child_index, child_subindex, eth_name, decimals, \
+                           deposited::TEXT, pending_deposits::TEXT, num_pending_deposits, \
+                           withdrawn::TEXT, pending_withdrawals::TEXT, num_pending_withdrawals, \
+                           locked::TEXT FROM token_stats ORDER BY eth_name ASC"
+            .into();
+        // The buckets are aggregated from the hourly volumes, starting from the bucket
+        // `$2 - 1` intervals before the current one.
+        let volumes = (
+            "SELECT root, date_trunc($1, hour) AT TIME ZONE 'UTC' AS bucket, SUM(deposited)::TEXT \
+             AS deposited, SUM(num_deposits)::INT8 AS num_deposits, SUM(withdrawn)::TEXT AS \
+             withdrawn, SUM(num_withdrawals)::INT8 AS num_withdrawals FROM hourly_volumes WHERE \
+             hour >= date_trunc($1, NOW() AT TIME ZONE 'UTC') - ($2 - 1) * ('1 ' || $1)::INTERVAL \
+             GROUP BY root, bucket ORDER BY bucket ASC"
+                .into(),
+            [
+                tokio_postgres::types::Type::TEXT,
+                tokio_postgres::types::Type::INT4,
+            ],
+        );
+        let merkle_tree_stats = "SELECT mr.root, mr.insert_time, (SELECT COUNT(*) FROM \
+                                 concordium_events ce WHERE ce.root = mr.root) AS size FROM \
+                                 merkle_roots mr ORDER BY mr.id DESC LIMIT 1"
+            .into();
+        let admin_concordium_txs = "SELECT tx_hash, origin_tx_hash, timestamp FROM \
+                                    concordium_transactions WHERE status = 'pending' ORDER BY id \
+                                    ASC"
+        .into();
+        let admin_ethereum_txs = "SELECT tx_hash, timestamp FROM ethereum_transactions WHERE \
+                                  status = 'pending' ORDER BY id ASC"
+            .into();
+        // The leaves are the withdrawals that are neither completed nor held, like the
+        // leaves the relayer keeps in memory.
+        let admin_leaves = "SELECT ce.tx_hash, ce.event_index, ce.event_merkle_hash, ce.root, \
+                            ce.pending_root FROM concordium_events ce WHERE ce.event_type = \
+                            'withdraw' AND ce.processed IS NULL AND NOT EXISTS (SELECT 1 FROM \
+                            withdrawal_holds wh WHERE wh.event_index = ce.event_index AND \
+                            wh.released_time IS NULL) ORDER BY ce.event_index ASC"
+            .into();
+        let admin_held_withdrawals =
+            "SELECT ce.tx_hash, wh.event_index, ce.child_index, ce.child_subindex, ce.amount, \
+             ce.receiver, wh.reason, wh.insert_time, wh.release_time, wh.approved_time FROM \
+             withdrawal_holds wh JOIN concordium_events ce ON ce.event_index = wh.event_index \
+             WHERE wh.released_time IS NULL ORDER BY wh.id ASC"
+                .into();
+        let admin_held_deposits = "SELECT origin_tx_hash, origin_event_index, insert_time, \
+                                   approved_time FROM deposit_holds WHERE released_time IS NULL \
+                                   ORDER BY id ASC"
+            .into();
+        let admin_failed_deposits = format!(
+            "SELECT de.origin_tx_hash, de.origin_event_index, de.root_token, de.amount, \
+             de.insert_time, ARRAY(SELECT ct.tx_hash FROM concordium_transactions ct WHERE \
+             ct.origin_tx_hash = de.origin_tx_hash AND ct.status = 'failed' ORDER BY ct.id ASC) \
+             AS failed_txs FROM ethereum_deposit_events de WHERE {FAILED_DEPOSIT_CONDITION} ORDER \
+             BY de.id ASC"
+        );
+        let admin_get_failed_deposit = (
+            format!(
+                "SELECT de.origin_tx_hash, de.root_token, de.amount, de.deposit_receiver FROM \
+                 ethereum_deposit_events de WHERE de.origin_event_index = $1 AND \
+                 {FAILED_DEPOSIT_CONDITION} FOR UPDATE"
+            ),
+            tokio_postgres::types::Type::INT8,
+        );
+        let admin_approve_withdrawal = (
+            "UPDATE withdrawal_holds SET approved_time = COALESCE(approved_time, NOW()) WHERE \
+             event_index = $1 AND released_time IS NULL RETURNING id"
+                .into(),
+            tokio_postgres::types::Type::INT8,
+        );
+        let admin_approve_deposit = (
+            "UPDATE deposit_holds SET approved_time = COALESCE(approved_time, NOW()) WHERE \
+             origin_event_index = $1 AND released_time IS NULL RETURNING id"
+                .into(),
+            tokio_postgres::types::Type::INT8,
+        );
+        // A hold that was released before the deposit failed is used again.
+        let admin_requeue_deposit = (
+            "INSERT INTO deposit_holds (origin_tx_hash, origin_event_index, deposit, \
+             approved_time) VALUES ($1, $2, $3, NOW()) ON CONFLICT (origin_event_index) DO UPDATE \
+             SET deposit = EXCLUDED.deposit, approved_time = NOW(), released_time = NULL"
+                .into(),
+            [
+                tokio_postgres::types::Type::BYTEA,
+                tokio_postgres::types::Type::INT8,
+                tokio_postgres::types::Type::BYTEA,
+            ],
+        );
+        // Whether the relayer is listening is only known for connections that are
+        // visible to the user of the API server, which includes its own user.
+        let admin_request_merkle_update = format!(
+            "SELECT pg_notify('{ADMIN_CHANNEL}', '{MERKLE_UPDATE_REQUEST}'), EXISTS (SELECT 1 \
+             FROM pg_stat_activity WHERE application_name = '{ADMIN_LISTENER_APPLICATION_NAME}') \
+             AS listening"
+        );
+        let insert_admin_action = (
+            "INSERT INTO admin_actions (action, event_index, operator, remote_address, reason) \
+             VALUES ($1::admin_action, $2, $3, $4, $5) RETURNING id"
+                .into(),
+            [
+                tokio_postgres::types::Type::TEXT,
+                tokio_postgres::types::Type::INT8,
+                tokio_postgres::types::Type::TEXT,
+                tokio_postgres::types::Type::TEXT,
+                tokio_postgres::types::Type::TEXT,
+            ],
+        );
+        // The deposit is found by the hash of the Ethereum transaction that made it, of
+        // the Concordium transaction that completed it, or of any Concordium
+        // transaction sent for it.
+        let search_deposits = (
+            format!(
+                "SELECT de.origin_tx_hash, de.origin_event_index, de.amount, de.depositor, \
+                 de.root_token, de.tx_hash, de.deposit_receiver, COALESCE(de.block_time, \
+                 de.insert_time) AS time, COALESCE(ce.block_time, ce.insert_time) AS \
+                 completed_time, dh.insert_time AS hold_time, dh.approved_time AS \
+                 hold_approved_time, dh.released_time AS hold_released_time, \
+                 ({FAILED_DEPOSIT_CONDITION}) AS failed FROM ethereum_deposit_events de LEFT JOIN \
+                 concordium_events ce ON ce.origin_event_index = de.origin_event_index AND \
+                 ce.event_type = 'deposit' LEFT JOIN deposit_holds dh ON dh.origin_event_index = \
+                 de.origin_event_index WHERE de.origin_tx_hash = ANY(ARRAY(SELECT \
+                 ct.origin_tx_hash FROM concordium_transactions ct WHERE ct.tx_hash = $1) || $1) \
+                 OR de.tx_hash = $1 ORDER BY de.origin_event_index ASC"
+            ),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        let search_concordium_txs = (
+            "SELECT tx_hash, timestamp, status FROM concordium_transactions WHERE origin_tx_hash \
+             = $1 ORDER BY id ASC"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        // The root is the first root that approved the withdrawal. Withdrawals
+        // approved before it was recorded fall back to the latest root. The root
+        // is pending while only the pending root of the withdrawal is set.
+        let search_withdrawals = (
+            "SELECT ce.tx_hash, ce.event_index, ce.child_index, ce.child_subindex, ce.amount, \
+             ce.receiver, ce.ccd_address, ce.processed, COALESCE(ce.block_time, ce.insert_time) \
+             AS time, r.root, ce.root IS NOT NULL AS root_set, COALESCE(ce.first_root_time, \
+             (SELECT MIN(mr.insert_time) FROM merkle_roots mr WHERE mr.root = ce.root)) AS \
+             root_time, (SELECT et.tx_hash FROM ethereum_transactions et WHERE et.status <> \
+             'missing' AND et.root = r.root ORDER BY et.id DESC LIMIT 1) AS root_tx_hash, \
+             wh.reason AS hold_reason, wh.insert_time AS hold_time, wh.approved_time AS \
+             hold_approved_time, wh.released_time AS hold_released_time FROM concordium_events ce \
+             CROSS JOIN LATERAL (SELECT COALESCE(ce.first_root, ce.root, ce.pending_root) AS \
+             root) r LEFT JOIN withdrawal_holds wh ON wh.event_index = ce.event_index WHERE \
+             ce.event_type = 'withdraw' AND (ce.tx_hash = $1 OR ce.processed = $1) ORDER BY \
+             ce.event_index ASC"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        // Transactions without a root do not set a Merkle root.
+        let search_merkle_root_tx = (
+            "SELECT root, timestamp, status FROM ethereum_transactions WHERE tx_hash = $1 AND \
+             root IS NOT NULL"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        // The withdrawals are those whose root in `search_withdrawals` is this root.
+        let search_merkle_root = (
+            "SELECT (SELECT MIN(insert_time) FROM merkle_roots WHERE root = $1) AS set_time, \
+             COALESCE((SELECT root FROM merkle_roots ORDER BY id DESC LIMIT 1) = $1, FALSE) AS \
+             latest, ARRAY(SELECT event_index FROM concordium_events WHERE COALESCE(first_root, \
+             root) = $1 UNION SELECT event_index FROM concordium_events WHERE pending_root = $1 \
+             AND root IS NULL ORDER BY 1) AS withdrawals"
+                .into(),
+            tokio_postgres::types::Type::BYTEA,
+        );
+        let latest_deposit = (
+            "SELECT depositor, root_token FROM ethereum_deposit_events WHERE (root_token = $1) = \
+             $2 ORDER BY id DESC LIMIT 1"
+                .into(),
+            [
+                tokio_postgres::types::Type::BYTEA,
+                tokio_postgres::types::Type::BOOL,
+            ],
+        );
+        let latest_pending_withdrawal = "SELECT tx_hash, event_index, event_data FROM \
+                                         concordium_events WHERE root = (SELECT root FROM \
+                                         merkle_roots ORDER BY id DESC LIMIT 1) AND processed IS \
+                                         NULL ORDER BY event_index DESC LIMIT 1"
+            .into();
+        Self {
+            concordium_tx_status,
+            withdrawal_status,
+            get_event,
+            get_events,
+            get_latest_merkle_root,
+            get_merkle_leafs,
+            ethereum_wallet_txs,
+            concordium_wallet_txs,
+            list_tokens,
+            get_next_merkle_root,
+            token_stats,
+            volumes,
+            merkle_tree_stats,
+            admin_concordium_txs,
+            admin_ethereum_txs,
+            admin_leaves,
+            admin_held_withdrawals,
+            admin_held_deposits,
+            admin_failed_deposits,
+            admin_get_failed_deposit,
+            admin_approve_withdrawal,
+            admin_approve_deposit,
+            admin_requeue_deposit,
+            admin_request_merkle_update,
+            insert_admin_action,
+            search_deposits,
+            search_concordium_txs,
+            search_withdrawals,
+            search_merkle_root_tx,
+            search_merkle_root,
+            latest_deposit,
+            latest_pending_withdrawal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::IpAddr, time::Duration};
+
+    fn ip(s: &str) -> IpAddr { s.parse().expect("Invalid IP.") }
+
+    fn network(s: &str) -> IpNetwork { s.parse().expect("Invalid network.") }
+
+    fn limiter(trusted_proxies: &[&str]) -> RateLimiter {
+        let limits = ["query:1:2".parse().expect("Invalid limit.")];
+        let trusted_proxies = trusted_proxies.iter().map(|s| network(s)).collect();
+        RateLimiter::new(&limits, &[], trusted_proxies, 64)
+    }
+
+    fn forwarded_for(values: &[&'static str]) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", http::HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn ip_network_contains() {
+        let all_v4 = network("0.0.0.0/0");
+        assert!(all_v4.contains(ip("0.0.0.0")));
+        assert!(all_v4.contains(ip("255.255.255.255")));
+        assert!(!all_v4.contains(ip("::")));
+        let all_v6 = network("::/0");
+        assert!(all_v6.contains(ip("ffff::1")));
+        assert!(!all_v6.contains(ip("10.0.0.1")));
+
+        let host = network("10.1.2.3/32");
+        assert!(host.contains(ip("10.1.2.3")));
+        assert!(!host.contains(ip("10.1.2.4")));
+        let v6_network = network("2001:db8::/32");
+        assert!(v6_network.contains(ip("2001:db8:ffff::1")));
+        assert!(!v6_network.contains(ip("2001:db9::1")));
+
+        let v6_host = network("2001:db8::1/128");
+        assert!(v6_host.contains(ip("2001:db8::1")));
+        assert!(!v6_host.contains(ip("2001:db8::2")));
+        let bare = network("2001:db8::1");
+        assert_eq!(bare.prefix, 128);
+        assert!(!bare.contains(ip("2001:db8::2")));
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("::/129".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn client_ip_is_rightmost_untrusted_hop() {
+        let limiter = limiter(&["10.0.0.0/8"]);
+        // Spoofed hops on the left are ignored.
+        let headers = forwarded_for(&["1.1.1.1, 2.2.2.2, 10.0.0.5"]);
+        assert_eq!(limiter.client_ip(ip("10.0.0.1"), &headers), ip("2.2.2.2"));
+        // Several headers are read as one list.
+        let headers = forwarded_for(&["1.1.1.1", "2.2.2.2", "10.0.0.5, 10.0.0.6"]);
+        assert_eq!(limiter.client_ip(ip("10.0.0.1"), &headers), ip("2.2.2.2"));
+        // The header is only used if the peer is a trusted proxy.
+        let headers = forwarded_for(&["1.1.1.1"]);
+        assert_eq!(limiter.client_ip(ip("3.3.3.3"), &headers), ip("3.3.3.3"));
+        // A trusted peer without the header is the client.
+        let headers = http::HeaderMap::new();
+        assert_eq!(limiter.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn anonymous_clients_are_ipv6_networks() {
+        let limiter = limiter(&[]);
+        let client = |s| match limiter.anonymous_client(ip(s)) {
+            RateLimitClient::Anonymous(address) => address,
+            RateLimitClient::Key(_) => panic!("Expected an anonymous client."),
+        };
+        assert_eq!(client("2001:db8:1:2:3:4:5:6"), ip("2001:db8:1:2::"));
+        assert_eq!(client("2001:db8:1:2::ffff"), client("2001:db8:1:2::1"));
+        assert_ne!(client("2001:db8:1:3::1"), client("2001:db8:1:2::1"));
+        assert_eq!(client("192.0.2.1"), ip("192.0.2.1"));
+    }
+
+    #[test]
+    fn token_bucket_take_and_charge() {
+        let limit = Limit {
+            rate:  1.0,
+            burst: 2.0,
+        };
+        let start = std::time::Instant::now();
+        let mut bucket = TokenBucket::new(limit, start);
+        assert!(bucket.take(limit, 1.0, start).is_ok());
+        assert!(bucket.take(limit, 1.0, start).is_ok());
+        assert_eq!(bucket.take(limit, 1.0, start), Err(Duration::from_secs(1)));
+        let later = start + Duration::from_secs(1);
+        assert!(bucket.take(limit, 1.0, later).is_ok());
+        // The bucket does not fill up beyond the burst.
+        let much_later = later + Duration::from_secs(100);
+        bucket.refill(limit, much_later);
+        assert_eq!(bucket.tokens, 2.0);
+        // A request is admitted with one token, and leaves the bucket in debt.
+        assert!(bucket.take(limit, 4.0, much_later).is_ok());
+        assert_eq!(
+            bucket.take(limit, 1.0, much_later),
+            Err(Duration::from_secs(3))
+        );
+        bucket.charge(limit, 1.0, much_later);
+        assert_eq!(bucket.tokens, -3.0);
+    }
+
+    #[test]
+    fn untracked_clients_share_a_bucket() {
+        let limiter = limiter(&[]);
+        {
+            let mut anonymous = limiter.anonymous.lock().expect("Poisoned lock.");
+            let now = std::time::Instant::now();
+            let limit = limiter.limits[&RouteClass::Query];
+            for i in 0..MAX_RATE_LIMIT_CLIENTS {
+                let address = std::net::Ipv4Addr::from(i as u32).into();
+                anonymous
+                    .clients
+                    .insert((RouteClass::Query, address), TokenBucket::new(limit, now));
+            }
+        }
+        let take = |s| {
+            let now = std::time::Instant::now();
+            limiter.with_bucket(
+                limiter.anonymous_client(ip(s)),
+                RouteClass::Query,
+                |bucket, limit, weight| bucket.take(limit, weight, now),
+            )
+        };
+        // A tracked client keeps its own bucket.
+        assert!(matches!(take("0.0.0.1"), Some(Ok(()))));
+        // New clients share the burst of 2.
+        assert!(matches!(take("192.0.2.1"), Some(Ok(()))));
+        assert!(matches!(take("192.0.2.2"), Some(Ok(()))));
+        assert!(matches!(take("192.0.2.3"), Some(Err(_))));
+        let anonymous = limiter.anonymous.lock().expect("Poisoned lock.");
+        assert_eq!(anonymous.clients.len(), MAX_RATE_LIMIT_CLIENTS);
     }
 }
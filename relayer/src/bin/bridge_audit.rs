@@ -0,0 +1,1056 @@
+use anyhow::Context;
+use ccdeth_relayer::{
+    concordium_contracts::{BridgeEvent, BridgeManagerClient},
+    db,
+    ethereum::{self, EthEvent},
+    merkle,
+    state_sender::{MerkleRootFilter, StateSender},
+    telemetry,
+};
+use clap::Parser;
+use concordium_rust_sdk::{
+    smart_contracts::common as contracts_common,
+    types::{hashes::TransactionHash, BlockItemSummary, ContractAddress},
+    v2,
+};
+use ethers::{
+    core::types::{Address, H256},
+    prelude::{EthLogDecode, Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClient},
+};
+use futures::{StreamExt, TryStreamExt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use tonic::transport::ClientTlsConfig;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct BridgeAudit {
+    #[clap(
+        long = "log-level",
+        default_value = "info",
+        help = "Maximum log level.",
+        env = "ETHCCD_AUDIT_LOG_LEVEL"
+    )]
+    log_level: tracing_subscriber::filter::LevelFilter,
+    #[clap(
+        long = "db",
+        default_value = "host=localhost dbname=relayer user=postgres password=password port=5432",
+        help = "Database connection string.",
+        env = "ETHCCD_RELAYER_DB_STRING"
+    )]
+    db_config: tokio_postgres::Config,
+    #[clap(
+        long = "ethereum-api",
+        help = "JSON-RPC interface of an Ethereum node.",
+        env = "ETHCCD_RELAYER_ETHEREUM_API"
+    )]
+    ethereum_api: url::Url,
+    #[clap(
+        long,
+        help = "Timeout for requests to the Ethereum node.",
+        env = "ETHCCD_RELAYER_ETHEREUM_REQUEST_TIMEOUT",
+        default_value = "10"
+    )]
+    ethereum_request_timeout: u64,
+    #[clap(
+        long = "state-sender-address",
+        help = "Address of the StateSender proxy instance on Ethereum.",
+        env = "ETHCCD_RELAYER_STATE_SENDER_PROXY"
+    )]
+    state_sender: Address,
+    #[clap(
+        long = "state-sender-creation-height",
+        help = "Block number when the state sender instance was created.",
+        env = "ETHCCD_RELAYER_STATE_SENDER_CREATION_BLOCK_NUMBER"
+    )]
+    state_sender_creation_block_number: u64,
+    #[clap(
+        long,
+        help = "Number of confirmations required on Ethereum before considering the transaction \
+                as final.",
+        env = "ETHCCD_RELAYER_NUM_CONFIRMATIONS",
+        default_value = "10"
+    )]
+    num_confirmations: u64,
+    #[clap(
+        long = "ethereum-block-range",
+        help = "Maximum number of blocks to query logs for in a single request.",
+        env = "ETHCCD_AUDIT_ETHEREUM_BLOCK_RANGE",
+        default_value = "10000"
+    )]
+    block_range: u64,
+    #[clap(
+        long = "concordium-api",
+        help = "GRPC V2 interface of the Concordium node.",
+        env = "ETHCCD_RELAYER_CONCORDIUM_API",
+        default_value = "http://localhost:20000"
+    )]
+    concordium_api: v2::Endpoint,
+    #[clap(
+        long,
+        help = "Timeout for requests to the Concordium node.",
+        env = "ETHCCD_RELAYER_CONCORDIUM_REQUEST_TIMEOUT",
+        default_value = "10"
+    )]
+    concordium_request_timeout: u64,
+    #[clap(
+        long = "concordium-max-parallel",
+        help = "Maximum number of parallel queries of the Concordium node.",
+        env = "ETHCCD_RELAYER_MAX_PARALLEL_QUERIES_CONCORDIUM",
+        default_value = "1"
+    )]
+    max_parallel: usize,
+    #[clap(
+        long = "bridge-manager-address",
+        help = "Address of the BridgeManger contract instance on Concordium.",
+        env = "ETHCCD_RELAYER_BRIDGE_MANAGER"
+    )]
+    bridge_manager: ContractAddress,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FindingKind {
+    /// The record exists on the chain, but not in the database.
+    Missing,
+    /// The record exists in the database, but not on the chain.
+    Extra,
+    /// The record exists in both, but they do not agree.
+    Mismatch,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Finding {
+    /// The table the record belongs to.
+    table:   &'static str,
+    kind:    FindingKind,
+    /// Identifies the record, e.g., the event index or the transaction hash.
+    key:     String,
+    message: String,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+/// The JSON report written to stdout.
+struct Report {
+    /// The Ethereum checkpoint of the database. Ethereum events are checked up
+    /// to this block.
+    ethereum_height:   Option<u64>,
+    /// The Concordium checkpoint of the database.
+    concordium_height: Option<u64>,
+    /// Number of rows checked in each table.
+    checked:           BTreeMap<&'static str, usize>,
+    findings:          Vec<Finding>,
+}
+
+impl Report {
+    fn add(
+        &mut self,
+        table: &'static str,
+        kind: FindingKind,
+        key: impl std::fmt::Display,
+        message: impl Into<String>,
+    ) {
+        self.findings.push(Finding {
+            table,
+            kind,
+            key: key.to_string(),
+            message: message.into(),
+        })
+    }
+}
+
+struct DepositRow {
+    origin_tx_hash:     H256,
+    origin_event_index: u64,
+    amount:             String,
+    depositor:          Address,
+    /// Not recorded for deposits inserted before the column was added.
+    deposit_receiver:   Option<Vec<u8>>,
+    root_token:         Address,
+    tx_hash:            Option<TransactionHash>,
+}
+
+struct ConcordiumEventRow {
+    id:                 i64,
+    tx_hash:            TransactionHash,
+    event_index:        Option<u64>,
+    origin_event_index: Option<u64>,
+    event_type:         String,
+    receiver:           Option<Vec<u8>>,
+    ccd_address:        Option<Vec<u8>>,
+    event_data:         Vec<u8>,
+    child:              Option<ContractAddress>,
+    amount:             Option<String>,
+    processed:          Option<H256>,
+    event_merkle_hash:  Option<Vec<u8>>,
+    root:               Option<Vec<u8>>,
+}
+
+struct TokenMapRow {
+    root:     Address,
+    child:    ContractAddress,
+    eth_name: String,
+    decimals: u8,
+}
+
+struct EthereumTransactionRow {
+    tx_hash: H256,
+    tx:      Vec<u8>,
+    status:  String,
+}
+
+/// A consistent snapshot of the tables that are audited.
+struct Snapshot {
+    ethereum_height:       Option<u64>,
+    concordium_height:     Option<u64>,
+    deposits:              Vec<DepositRow>,
+    concordium_events:     Vec<ConcordiumEventRow>,
+    token_maps:            Vec<TokenMapRow>,
+    merkle_roots:          Vec<[u8; 32]>,
+    ethereum_transactions: Vec<EthereumTransactionRow>,
+}
+
+fn hash<T: TryFrom<Vec<u8>, Error = Vec<u8>>>(bytes: Vec<u8>) -> anyhow::Result<T> {
+    T::try_from(bytes).map_err(|b| anyhow::anyhow!("Unexpected length {} of a hash.", b.len()))
+}
+
+/// Read the tables in a single read-only transaction so that the audit sees a
+/// consistent state even if the relayer is running.
+async fn read_snapshot(client: &mut tokio_postgres::Client) -> anyhow::Result<Snapshot> {
+    let db_tx = client
+        .build_transaction()
+        .isolation_level(tokio_postgres::IsolationLevel::RepeatableRead)
+        .read_only(true)
+        .start()
+        .await?;
+    let checkpoint = |network: &'static str| {
+        let db_tx = &db_tx;
+        async move {
+            let row = db_tx
+                .query_opt(
+                    "SELECT last_processed_height FROM checkpoints WHERE network::TEXT = $1",
+                    &[&network],
+                )
+                .await?;
+            anyhow::Ok(row.map(|row| row.get::<_, i64>(0) as u64))
+        }
+    };
+    let ethereum_height = checkpoint("ethereum").await?;
+    let concordium_height = checkpoint("concordium").await?;
+
+    let mut deposits = Vec::new();
+    for row in db_tx
+        .query(
+            "SELECT origin_tx_hash, origin_event_index, amount, depositor, deposit_receiver, \
+             root_token, tx_hash FROM ethereum_deposit_events ORDER BY origin_event_index ASC",
+            &[],
+        )
+        .await?
+    {
+        let origin_tx_hash: Vec<u8> = row.try_get("origin_tx_hash")?;
+        let depositor: Vec<u8> = row.try_get("depositor")?;
+        let root_token: Vec<u8> = row.try_get("root_token")?;
+        let tx_hash: Option<Vec<u8>> = row.try_get("tx_hash")?;
+        deposits.push(DepositRow {
+            origin_tx_hash:     H256::from_slice(&origin_tx_hash),
+            origin_event_index: row.try_get::<_, i64>("origin_event_index")? as u64,
+            amount:             row.try_get("amount")?,
+            depositor:          Address::from_slice(&depositor),
+            deposit_receiver:   row.try_get("deposit_receiver")?,
+            root_token:         Address::from_slice(&root_token),
+            tx_hash:            tx_hash.map(|h| h[..].try_into()).transpose()?,
+        });
+    }
+
+    let mut concordium_events = Vec::new();
+    for row in db_tx
+        .query(
+            "SELECT id, tx_hash, event_index, origin_event_index, event_type::TEXT AS event_type, \
+             receiver, ccd_address, event_data, child_index, child_subindex, amount, processed, \
+             event_merkle_hash, root FROM concordium_events ORDER BY id ASC",
+            &[],
+        )
+        .await?
+    {
+        let tx_hash: Vec<u8> = row.try_get("tx_hash")?;
+        let child_index: Option<i64> = row.try_get("child_index")?;
+        let child_subindex: Option<i64> = row.try_get("child_subindex")?;
+        let processed: Option<Vec<u8>> = row.try_get("processed")?;
+        concordium_events.push(ConcordiumEventRow {
+            id:                 row.try_get("id")?,
+            tx_hash:            tx_hash[..].try_into()?,
+            event_index:        row
+                .try_get::<_, Option<i64>>("event_index")?
+                .map(|x| x as u64),
+            origin_event_index: row
+                .try_get::<_, Option<i64>>("origin_event_index")?
+                .map(|x| x as u64),
+            event_type:         row.try_get("event_type")?,
+            receiver:           row.try_get("receiver")?,
+            ccd_address:        row.try_get("ccd_address")?,
+            event_data:         row.try_get("event_data")?,
+            child:              child_index
+                .zip(child_subindex)
+                .map(|(i, s)| ContractAddress::new(i as u64, s as u64)),
+            amount:             row.try_get("amount")?,
+            processed:          processed.map(|h| H256::from_slice(&h)),
+            event_merkle_hash:  row.try_get("event_merkle_hash")?,
+            root:               row.try_get("root")?,
+        });
+    }
+
+    let mut token_maps = Vec::new();
+    for row in db_tx
+        .query(
+            "SELECT root, child_index, child_subindex, eth_name, decimals FROM token_maps ORDER \
+             BY id ASC",
+            &[],
+        )
+        .await?
+    {
+        let root: Vec<u8> = row.try_get("root")?;
+        token_maps.push(TokenMapRow {
+            root:     Address::from_slice(&root),
+            child:    ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            ),
+            eth_name: row.try_get("eth_name")?,
+            decimals: row.try_get::<_, i16>("decimals")? as u8,
+        });
+    }
+
+    let mut merkle_roots = Vec::new();
+    for row in db_tx
+        .query("SELECT root FROM merkle_roots ORDER BY id ASC", &[])
+        .await?
+    {
+        merkle_roots.push(hash(row.try_get("root")?)?);
+    }
+
+    let mut ethereum_transactions = Vec::new();
+    for row in db_tx
+        .query(
+            "SELECT tx_hash, tx, status::TEXT AS status FROM ethereum_transactions ORDER BY id ASC",
+            &[],
+        )
+        .await?
+    {
+        let tx_hash: Vec<u8> = row.try_get("tx_hash")?;
+        ethereum_transactions.push(EthereumTransactionRow {
+            tx_hash: H256::from_slice(&tx_hash),
+            tx:      row.try_get("tx")?,
+            status:  row.try_get("status")?,
+        });
+    }
+    db_tx.commit().await?;
+    Ok(Snapshot {
+        ethereum_height,
+        concordium_height,
+        deposits,
+        concordium_events,
+        token_maps,
+        merkle_roots,
+        ethereum_transactions,
+    })
+}
+
+/// The state of the bridge on Ethereum, reconstructed from the logs of the
+/// state sender.
+#[derive(Default)]
+struct EthereumState {
+    /// Deposits by their id.
+    deposits:    BTreeMap<u64, (H256, EthEvent)>,
+    /// Current token maps, by the root token.
+    token_maps:  BTreeMap<Address, (ContractAddress, String, u8)>,
+    /// Completed withdrawals, by the index of the withdraw event on
+    /// Concordium, with the hash of the transaction that completed them.
+    withdrawals: BTreeMap<u64, H256>,
+    /// The Merkle roots that were set, with the block they were set in.
+    roots:       BTreeMap<[u8; 32], u64>,
+}
+
+/// Split `from..=to` into ranges of at most `size` blocks.
+fn block_ranges(from: u64, to: u64, size: u64) -> impl Iterator<Item = (u64, u64)> {
+    (from..=to)
+        .step_by(size.max(1) as usize)
+        .map(move |start| (start, to.min(start.saturating_add(size.max(1) - 1))))
+}
+
+async fn read_ethereum_state<M: Middleware + 'static>(
+    state_sender: &StateSender<M>,
+    app: &BridgeAudit,
+    checkpoint: Option<u64>,
+) -> anyhow::Result<EthereumState>
+where
+    M::Error: 'static, {
+    let mut state = EthereumState::default();
+    if let Some(checkpoint) = checkpoint {
+        for (start, end) in block_ranges(
+            app.state_sender_creation_block_number,
+            checkpoint,
+            app.block_range,
+        ) {
+            tracing::info!("Reading state sender events in blocks {start}..={end}.");
+            let events = ethereum::get_eth_block_events(state_sender, start, end).await?;
+            for event in events.events {
+                match event.event {
+                    EthEvent::TokenLocked { id, .. } => {
+                        state
+                            .deposits
+                            .insert(id.low_u64(), (event.tx_hash, event.event));
+                    }
+                    EthEvent::TokenMapped {
+                        root_token,
+                        child_token,
+                        name,
+                        decimals,
+                        ..
+                    } => {
+                        state
+                            .token_maps
+                            .insert(root_token, (child_token, name, decimals));
+                    }
+                    EthEvent::TokenUnmapped {
+                        root_token,
+                        child_token,
+                        ..
+                    } => {
+                        if state
+                            .token_maps
+                            .get(&root_token)
+                            .map_or(false, |(child, ..)| *child == child_token)
+                        {
+                            state.token_maps.remove(&root_token);
+                        }
+                    }
+                    EthEvent::Withdraw {
+                        origin_event_index, ..
+                    } => {
+                        state.withdrawals.insert(origin_event_index, event.tx_hash);
+                    }
+                }
+            }
+        }
+    }
+    // Merkle roots are set independently of the checkpoint, so they are read
+    // up to the latest block.
+    let latest = state_sender.client().get_block_number().await?.as_u64();
+    for (start, end) in block_ranges(
+        app.state_sender_creation_block_number,
+        latest,
+        app.block_range,
+    ) {
+        let filter = state_sender
+            .merkle_root_filter()
+            .filter
+            .from_block(start)
+            .to_block(end);
+        let logs = state_sender
+            .client()
+            .get_logs(&filter)
+            .await
+            .context("Unable to get MerkleRoot logs.")?;
+        for log in logs {
+            let block = log
+                .block_number
+                .context("Log in a confirmed block must have a block number.")?
+                .as_u64();
+            let decoded = MerkleRootFilter::decode_log(&ethabi::RawLog {
+                topics: log.topics,
+                data:   log.data.0.into(),
+            })?;
+            state.roots.insert(decoded.root, block);
+        }
+    }
+    Ok(state)
+}
+
+fn check_deposits(
+    report: &mut Report,
+    snapshot: &Snapshot,
+    ethereum: &EthereumState,
+    concordium_deposits: &BTreeMap<u64, TransactionHash>,
+) {
+    const TABLE: &str = "ethereum_deposit_events";
+    report.checked.insert(TABLE, snapshot.deposits.len());
+    let mut seen = BTreeSet::new();
+    for row in &snapshot.deposits {
+        let id = row.origin_event_index;
+        seen.insert(id);
+        let Some((
+            tx_hash,
+            EthEvent::TokenLocked {
+                depositor,
+                deposit_receiver,
+                root_token,
+                amount,
+                ..
+            },
+        )) = ethereum.deposits.get(&id)
+        else {
+            report.add(
+                TABLE,
+                FindingKind::Extra,
+                id,
+                "No LockedToken event with this id up to the Ethereum checkpoint.",
+            );
+            continue;
+        };
+        let mut differences = Vec::new();
+        if *tx_hash != row.origin_tx_hash {
+            differences.push(format!(
+                "origin_tx_hash {:#x} differs from {tx_hash:#x}",
+                row.origin_tx_hash
+            ));
+        }
+        if amount.to_string() != row.amount {
+            differences.push(format!("amount {} differs from {amount}", row.amount));
+        }
+        if *depositor != row.depositor {
+            differences.push(format!(
+                "depositor {:#x} differs from {depositor:#x}",
+                row.depositor
+            ));
+        }
+        if let Some(receiver) = &row.deposit_receiver {
+            if receiver[..] != deposit_receiver.0[..] {
+                differences.push(format!(
+                    "deposit_receiver 0x{} differs from {deposit_receiver}",
+                    hex::encode(receiver)
+                ));
+            }
+        }
+        if *root_token != row.root_token {
+            differences.push(format!(
+                "root_token {:#x} differs from {root_token:#x}",
+                row.root_token
+            ));
+        }
+        if row.tx_hash.as_ref() != concordium_deposits.get(&id) {
+            differences.push(format!(
+                "tx_hash {:?} does not match the deposit event on Concordium {:?}",
+                row.tx_hash.map(|h| h.to_string()),
+                concordium_deposits.get(&id).map(|h| h.to_string())
+            ));
+        }
+        if !differences.is_empty() {
+            report.add(TABLE, FindingKind::Mismatch, id, differences.join(", "));
+        }
+    }
+    for (id, (tx_hash, _)) in &ethereum.deposits {
+        if !seen.contains(id) {
+            report.add(
+                TABLE,
+                FindingKind::Missing,
+                id,
+                format!("Deposit in transaction {tx_hash:#x} is not in the database."),
+            );
+        }
+    }
+}
+
+fn check_token_maps(report: &mut Report, snapshot: &Snapshot, ethereum: &EthereumState) {
+    const TABLE: &str = "token_maps";
+    report.checked.insert(TABLE, snapshot.token_maps.len());
+    let mut seen = BTreeSet::new();
+    for row in &snapshot.token_maps {
+        let key = format!("{:#x}", row.root);
+        seen.insert(row.root);
+        let Some((child, name, decimals)) = ethereum.token_maps.get(&row.root) else {
+            report.add(
+                TABLE,
+                FindingKind::Extra,
+                key,
+                "The token is not mapped on Ethereum.",
+            );
+            continue;
+        };
+        if (child, name, decimals) != (&row.child, &row.eth_name, &row.decimals) {
+            report.add(
+                TABLE,
+                FindingKind::Mismatch,
+                key,
+                format!(
+                    "Mapped to {} ({}, {} decimals) in the database, but to {child} ({name}, \
+                     {decimals} decimals) on Ethereum.",
+                    row.child, row.eth_name, row.decimals
+                ),
+            );
+        }
+    }
+    for (root, (child, ..)) in &ethereum.token_maps {
+        if !seen.contains(root) {
+            report.add(
+                TABLE,
+                FindingKind::Missing,
+                format!("{root:#x}"),
+                format!("The mapping to {child} is not in the database."),
+            );
+        }
+    }
+}
+
+/// The name of the event type in the database.
+fn event_type(event: &BridgeEvent) -> &'static str {
+    match event {
+        BridgeEvent::TokenMap(_) => "token_map",
+        BridgeEvent::Deposit(_) => "deposit",
+        BridgeEvent::Withdraw(_) => "withdraw",
+        BridgeEvent::GrantRole(_) => "grant_role",
+        BridgeEvent::RevokeRole(_) => "revoke_role",
+    }
+}
+
+/// Check the columns derived from the event, and the processing state of
+/// withdrawals. Return the differences found.
+fn check_concordium_event(
+    row: &ConcordiumEventRow,
+    event: &BridgeEvent,
+    ethereum: &EthereumState,
+    roots: &BTreeSet<[u8; 32]>,
+) -> anyhow::Result<Vec<String>> {
+    let mut differences = Vec::new();
+    if row.event_type != event_type(event) {
+        differences.push(format!(
+            "event_type {} differs from {}",
+            row.event_type,
+            event_type(event)
+        ));
+    }
+    if row.event_index != event.event_index() {
+        differences.push(format!(
+            "event_index {:?} differs from {:?}",
+            row.event_index,
+            event.event_index()
+        ));
+    }
+    let origin_event_index = match event {
+        BridgeEvent::TokenMap(tm) => Some(tm.id),
+        BridgeEvent::Deposit(de) => Some(de.id),
+        _ => None,
+    };
+    if row.origin_event_index != origin_event_index {
+        differences.push(format!(
+            "origin_event_index {:?} differs from {origin_event_index:?}",
+            row.origin_event_index
+        ));
+    }
+    if let BridgeEvent::Withdraw(we) = event {
+        if row.child != Some(we.contract) {
+            differences.push(format!("child contract differs from {}", we.contract));
+        }
+        if row.receiver.as_deref() != Some(&we.eth_address[..]) {
+            differences.push(format!(
+                "receiver differs from 0x{}",
+                hex::encode(we.eth_address)
+            ));
+        }
+        match &we.ccd_address {
+            contracts_common::Address::Account(account) => {
+                if row.ccd_address.as_deref() != Some(&account.0[..]) {
+                    differences.push(format!("ccd_address differs from {account}"));
+                }
+            }
+            contracts_common::Address::Contract(contract) => {
+                if row.ccd_address.is_some() {
+                    differences.push(format!(
+                        "ccd_address is set, but the withdrawal was initiated by {contract}"
+                    ));
+                }
+            }
+        }
+        if row.amount.as_deref() != Some(we.amount.to_string().as_str()) {
+            differences.push(format!(
+                "amount {:?} differs from {}",
+                row.amount, we.amount
+            ));
+        }
+        let leaf = merkle::make_event_leaf_hash(row.tx_hash, we)?;
+        if row.event_merkle_hash.as_deref() != Some(&leaf[..]) {
+            differences.push("event_merkle_hash does not match the event".into());
+        }
+        match (row.processed, ethereum.withdrawals.get(&we.event_index)) {
+            (Some(processed), Some(tx_hash)) if processed != *tx_hash => differences.push(format!(
+                "processed {processed:#x} differs from the withdrawal {tx_hash:#x} on Ethereum"
+            )),
+            (Some(processed), None) => differences.push(format!(
+                "processed by {processed:#x}, but not withdrawn on Ethereum up to the checkpoint"
+            )),
+            (None, Some(tx_hash)) => differences.push(format!(
+                "withdrawn on Ethereum in {tx_hash:#x}, but not marked as processed"
+            )),
+            _ => (),
+        }
+        if let Some(root) = &row.root {
+            if root.len() != 32 || !roots.contains(&root[..]) {
+                differences.push(format!(
+                    "root {} is not a Merkle root in the database",
+                    hex::encode(root)
+                ));
+            }
+        }
+    }
+    Ok(differences)
+}
+
+async fn check_concordium_events(
+    report: &mut Report,
+    snapshot: &Snapshot,
+    ethereum: &EthereumState,
+    bridge_manager: &BridgeManagerClient,
+    max_parallel: usize,
+) -> anyhow::Result<()> {
+    const TABLE: &str = "concordium_events";
+    report
+        .checked
+        .insert(TABLE, snapshot.concordium_events.len());
+    let roots = snapshot
+        .merkle_roots
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>();
+    let mut by_tx = BTreeMap::<TransactionHash, Vec<&ConcordiumEventRow>>::new();
+    for row in &snapshot.concordium_events {
+        by_tx.entry(row.tx_hash).or_default().push(row);
+    }
+    let summaries: Vec<(TransactionHash, Option<BlockItemSummary>)> =
+        futures::stream::iter(by_tx.keys().copied())
+            .map(|tx_hash| {
+                let mut client = bridge_manager.client.clone();
+                async move {
+                    match client.get_block_item_status(&tx_hash).await {
+                        Ok(status) => Ok((
+                            tx_hash,
+                            status.is_finalized().map(|(_, summary)| summary.clone()),
+                        )),
+                        Err(e) if e.is_not_found() => Ok((tx_hash, None)),
+                        Err(e) => Err(anyhow::Error::from(e).context(format!(
+                            "Unable to get the status of transaction {tx_hash}."
+                        ))),
+                    }
+                }
+            })
+            .buffered(max_parallel.max(1))
+            .try_collect()
+            .await?;
+    let mut bridge_manager = bridge_manager.clone();
+    for (tx_hash, summary) in summaries {
+        let rows = &by_tx[&tx_hash];
+        let Some(summary) = summary else {
+            for row in rows {
+                report.add(
+                    TABLE,
+                    FindingKind::Extra,
+                    row.id,
+                    format!("Transaction {tx_hash} is not finalized on Concordium."),
+                );
+            }
+            continue;
+        };
+        let mut chain_events = bridge_manager
+            .extract_events(&summary)?
+            .into_iter()
+            .map(|event| (contracts_common::to_bytes(&event), event))
+            .collect::<Vec<_>>();
+        for row in rows {
+            let Some(position) = chain_events
+                .iter()
+                .position(|(data, _)| *data == row.event_data)
+            else {
+                report.add(
+                    TABLE,
+                    FindingKind::Mismatch,
+                    row.id,
+                    format!("The event data is not emitted by transaction {tx_hash}."),
+                );
+                continue;
+            };
+            let (_, event) = chain_events.swap_remove(position);
+            let differences = check_concordium_event(row, &event, ethereum, &roots)?;
+            if !differences.is_empty() {
+                report.add(TABLE, FindingKind::Mismatch, row.id, differences.join(", "));
+            }
+        }
+        for (_, event) in chain_events {
+            report.add(
+                TABLE,
+                FindingKind::Missing,
+                tx_hash,
+                format!(
+                    "A {} event emitted by the transaction is not in the database.",
+                    event_type(&event)
+                ),
+            );
+        }
+    }
+    // Every withdrawal completed on Ethereum must refer to a withdraw event.
+    let withdraws = snapshot
+        .concordium_events
+        .iter()
+        .filter_map(|row| row.event_index)
+        .collect::<BTreeSet<_>>();
+    for (event_index, tx_hash) in &ethereum.withdrawals {
+        if !withdraws.contains(event_index) {
+            report.add(
+                TABLE,
+                FindingKind::Missing,
+                event_index,
+                format!(
+                    "The withdraw event completed on Ethereum in {tx_hash:#x} is not in the \
+                     database."
+                ),
+            );
+        }
+    }
+    // Every deposit on Concordium must correspond to a deposit on Ethereum.
+    for row in &snapshot.concordium_events {
+        if let (Some(id), "deposit") = (row.origin_event_index, row.event_type.as_str()) {
+            if !ethereum.deposits.contains_key(&id) {
+                report.add(
+                    TABLE,
+                    FindingKind::Mismatch,
+                    row.id,
+                    format!("Deposit {id} was not made on Ethereum up to the checkpoint."),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_merkle_roots(
+    report: &mut Report,
+    snapshot: &Snapshot,
+    ethereum: &EthereumState,
+    final_height: u64,
+) {
+    const TABLE: &str = "merkle_roots";
+    report.checked.insert(TABLE, snapshot.merkle_roots.len());
+    let known = snapshot
+        .merkle_roots
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for root in &snapshot.merkle_roots {
+        if !ethereum.roots.contains_key(root) {
+            report.add(
+                TABLE,
+                FindingKind::Extra,
+                hex::encode(root),
+                "The root was never set on Ethereum.",
+            );
+        }
+    }
+    for (root, block) in &ethereum.roots {
+        // Roots in recent blocks might not be confirmed by the relayer yet.
+        if *block <= final_height && !known.contains(root) {
+            report.add(
+                TABLE,
+                FindingKind::Missing,
+                hex::encode(root),
+                format!("The root set in block {block} is not in the database."),
+            );
+        }
+    }
+}
+
+async fn check_ethereum_transactions<M: Middleware + 'static>(
+    report: &mut Report,
+    snapshot: &Snapshot,
+    state_sender: &StateSender<M>,
+) -> anyhow::Result<()>
+where
+    M::Error: 'static, {
+    const TABLE: &str = "ethereum_transactions";
+    report
+        .checked
+        .insert(TABLE, snapshot.ethereum_transactions.len());
+    let known = snapshot
+        .merkle_roots
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for row in &snapshot.ethereum_transactions {
+        let key = format!("{:#x}", row.tx_hash);
+        if H256::from(ethers::utils::keccak256(&row.tx)) != row.tx_hash {
+            report.add(
+                TABLE,
+                FindingKind::Mismatch,
+                &key,
+                "The hash of the stored transaction does not match tx_hash.",
+            );
+        }
+        // Pending transactions are still being tracked by the relayer.
+        if row.status == "pending" {
+            continue;
+        }
+        let receipt = state_sender
+            .client()
+            .get_transaction_receipt(row.tx_hash)
+            .await
+            .with_context(|| format!("Unable to get the receipt of transaction {key}."))?;
+        match (row.status.as_str(), receipt) {
+            ("confirmed", None) => report.add(
+                TABLE,
+                FindingKind::Extra,
+                &key,
+                "Marked as confirmed, but the transaction is not on Ethereum.",
+            ),
+            ("confirmed", Some(receipt)) => {
+                if receipt.status != Some(1.into()) {
+                    report.add(
+                        TABLE,
+                        FindingKind::Mismatch,
+                        &key,
+                        "Marked as confirmed, but the transaction failed.",
+                    );
+                }
+                for log in receipt
+                    .logs
+                    .into_iter()
+                    .filter(|log| log.address == state_sender.address())
+                {
+                    let raw = ethabi::RawLog {
+                        topics: log.topics,
+                        data:   log.data.0.into(),
+                    };
+                    if let Ok(decoded) = MerkleRootFilter::decode_log(&raw) {
+                        if !known.contains(&decoded.root) {
+                            report.add(
+                                TABLE,
+                                FindingKind::Mismatch,
+                                &key,
+                                format!(
+                                    "The transaction set root {} which is not in the database.",
+                                    hex::encode(decoded.root)
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+            ("missing", Some(receipt)) => report.add(
+                TABLE,
+                FindingKind::Mismatch,
+                &key,
+                format!(
+                    "Marked as missing, but the transaction is in block {:?}.",
+                    receipt.block_number
+                ),
+            ),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let app = BridgeAudit::parse();
+    telemetry::init(
+        "bridge_audit",
+        tracing_subscriber::filter::Targets::new()
+            .with_target(module_path!(), app.log_level)
+            .with_target("ccdeth_relayer", app.log_level),
+        telemetry::LogFormat::Text,
+        None,
+        None,
+    )?;
+
+    let (mut db_client, connection) = db::connect(&app.db_config)
+        .await
+        .context("Unable to connect to the database.")?;
+    let snapshot = read_snapshot(&mut db_client)
+        .await
+        .context("Unable to read the database.")?;
+    drop(db_client);
+    connection.abort();
+
+    let ethereum_client = {
+        let network_client = reqwest::ClientBuilder::new()
+            .timeout(std::time::Duration::from_secs(app.ethereum_request_timeout))
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()?;
+        let http = Http::new_with_client(app.ethereum_api.clone(), network_client);
+        Arc::new(Provider::new(RetryClient::new(
+            http,
+            Box::<HttpRateLimitRetryPolicy>::default(),
+            5,
+            3000,
+        )))
+    };
+    let state_sender = StateSender::new(app.state_sender, ethereum_client.clone());
+
+    let concordium_client = {
+        // Use TLS if the URI scheme is HTTPS.
+        let endpoint = if app
+            .concordium_api
+            .uri()
+            .scheme()
+            .map_or(false, |x| x == &http::uri::Scheme::HTTPS)
+        {
+            app.concordium_api
+                .clone()
+                .tls_config(ClientTlsConfig::new())
+                .context("Unable to construct TLS configuration for the Concordium API.")?
+        } else {
+            app.concordium_api.clone()
+        };
+        let ep = endpoint
+            .timeout(std::time::Duration::from_secs(
+                app.concordium_request_timeout,
+            ))
+            .connect_timeout(std::time::Duration::from_secs(10));
+        v2::Client::new(ep)
+            .await
+            .context("Unable to connect Concordium node.")?
+    };
+    let bridge_manager = BridgeManagerClient::new(concordium_client, None, app.bridge_manager);
+
+    let ethereum = read_ethereum_state(&state_sender, &app, snapshot.ethereum_height)
+        .await
+        .context("Unable to read the events on Ethereum.")?;
+    let final_height = ethereum_client
+        .get_block_number()
+        .await?
+        .as_u64()
+        .saturating_sub(app.num_confirmations);
+
+    let mut report = Report {
+        ethereum_height: snapshot.ethereum_height,
+        concordium_height: snapshot.concordium_height,
+        ..Report::default()
+    };
+    let concordium_deposits = snapshot
+        .concordium_events
+        .iter()
+        .filter(|row| row.event_type == "deposit")
+        .filter_map(|row| Some((row.origin_event_index?, row.tx_hash)))
+        .collect::<BTreeMap<_, _>>();
+    check_deposits(&mut report, &snapshot, &ethereum, &concordium_deposits);
+    check_token_maps(&mut report, &snapshot, &ethereum);
+    check_concordium_events(
+        &mut report,
+        &snapshot,
+        &ethereum,
+        &bridge_manager,
+        app.max_parallel,
+    )
+    .await
+    .context("Unable to check the Concordium events.")?;
+    check_merkle_roots(&mut report, &snapshot, &ethereum, final_height);
+    check_ethereum_transactions(&mut report, &snapshot, &state_sender)
+        .await
+        .context("Unable to check the Ethereum transactions.")?;
+
+    tracing::info!("Audit found {} discrepancies.", report.findings.len());
+    serde_json::to_writer_pretty(std::io::stdout(), &report)?;
+    println!();
+    if !report.findings.is_empty() {
+        std::process::exit(2);
+    }
+    Ok(())
+}
@@ -1,12 +1,17 @@
 use anyhow::Context;
 use ccdeth_relayer::{
+    alerts, chain_lag, circuit_breaker,
     concordium_contracts::{self, BridgeManagerClient},
+    config,
     db::{self, Database},
     ethereum,
+    health::{Health, Task},
     merkle::{self, MerkleSetterClient},
+    rebuild, reconciliation, screening,
     state_sender::StateSender,
+    telemetry, withdrawal_holds,
 };
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use concordium::{
     id::types::AccountAddress,
     smart_contracts::common::Amount,
@@ -19,7 +24,11 @@ use ethers::prelude::{
     Http, HttpRateLimitRetryPolicy, LocalWallet, Middleware, Provider, RetryClient, Signer,
 };
 use futures::StreamExt;
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::{btree_map, BTreeMap},
+    path::PathBuf,
+    sync::Arc,
+};
 use tonic::transport::ClientTlsConfig;
 
 #[derive(Parser, Debug)]
@@ -120,6 +129,14 @@ struct EthereumConfig {
         env = "ETHCCD_RELAYER_MIN_ETHEREUM_BALANCE"
     )]
     min_balance: u128,
+    #[clap(
+        long = "ethereum-max-behind",
+        help = "Maximum number of seconds the latest block of the Ethereum API can be behind \
+                before sending of deposits and Merkle roots is paused.",
+        env = "ETHCCD_RELAYER_ETHEREUM_MAX_BEHIND",
+        default_value = "240"
+    )]
+    max_behind: u64,
 }
 
 impl EthereumConfig {
@@ -139,21 +156,25 @@ impl EthereumConfig {
             escalation_interval,
             warn_duration,
             min_balance,
+            max_behind,
         } = self;
-        log::info!("Using {state_sender:#x} as the state sender address.");
-        log::info!("Using {root_chain_manager:#x} as the root chain manager address.");
-        log::info!(
+        tracing::info!("Using {state_sender:#x} as the state sender address.");
+        tracing::info!("Using {root_chain_manager:#x} as the root chain manager address.");
+        tracing::info!(
             "Using {state_sender_creation_block_number} as the starting height on Ethereum."
         );
-        log::info!("Using {max_gas_price} as the maximum gas price.");
-        log::info!("Using {max_gas} as the maximum allowed gas for transactions.");
-        log::info!("Using {merkle_update_interval}s as the update interval for Merkle roots.");
-        log::info!("Using {chain_id} as the chain id.");
-        log::info!("Requiring {num_confirmations} confirmations for transactions on Ethereum.");
-        log::info!("Using {ethereum_request_timeout}s as the request timeout for Ethereum API.");
-        log::info!("Will escalate price every {escalation_interval}s.");
-        log::info!("Will warn after transaction is not confirmed after {warn_duration}s.");
-        log::info!("Requiring {min_balance} microETH on the sender account balance.");
+        tracing::info!("Using {max_gas_price} as the maximum gas price.");
+        tracing::info!("Using {max_gas} as the maximum allowed gas for transactions.");
+        tracing::info!("Using {merkle_update_interval}s as the update interval for Merkle roots.");
+        tracing::info!("Using {chain_id} as the chain id.");
+        tracing::info!("Requiring {num_confirmations} confirmations for transactions on Ethereum.");
+        tracing::info!(
+            "Using {ethereum_request_timeout}s as the request timeout for Ethereum API."
+        );
+        tracing::info!("Will escalate price every {escalation_interval}s.");
+        tracing::info!("Will warn after transaction is not confirmed after {warn_duration}s.");
+        tracing::info!("Requiring {min_balance} microETH on the sender account balance.");
+        tracing::info!("Allowing the Ethereum API to be at most {max_behind}s behind present.");
     }
 }
 
@@ -180,7 +201,7 @@ struct ConcordiumConfig {
     #[clap(
         long = "concordium-max-behind",
         help = "Maximum number of seconds the Concordium node's last finalized block can be \
-                behind before we log warnings.",
+                behind before sending of deposits and Merkle roots is paused.",
         env = "ETHCCD_RELAYER_CONCORDIUM_MAX_BEHIND",
         default_value = "240"
     )]
@@ -226,30 +247,328 @@ impl ConcordiumConfig {
             max_energy,
             min_balance,
         } = self;
-        log::info!("Using Concordium node at {}", api.uri());
-        log::info!("Allowing up to {max_parallel} parallel queries of the Concordium node.");
-        log::info!("Allowing the Concordium node to be at most {max_behind}s behind present.");
-        log::info!("Using {request_timeout}s as the request timeout for Concordium.");
-        log::info!("Using {bridge_manager} as bridge manager.");
-        log::info!("Allowing up to {max_energy}NRG for Concordium tranasactions.");
-        log::info!("Requiring  {min_balance} microCCD on the Concordium sender account.");
+        tracing::info!("Using Concordium node at {}", api.uri());
+        tracing::info!("Allowing up to {max_parallel} parallel queries of the Concordium node.");
+        tracing::info!("Allowing the Concordium node to be at most {max_behind}s behind present.");
+        tracing::info!("Using {request_timeout}s as the request timeout for Concordium.");
+        tracing::info!("Using {bridge_manager} as bridge manager.");
+        tracing::info!("Allowing up to {max_energy}NRG for Concordium tranasactions.");
+        tracing::info!("Requiring  {min_balance} microCCD on the Concordium sender account.");
+    }
+}
+
+#[derive(Debug, Parser)]
+struct CircuitBreakerConfig {
+    #[clap(
+        long = "breaker-on-supply-breach",
+        help = "Trip the circuit breaker if an Ethereum vault holds less than what is accounted \
+                for on Concordium.",
+        env = "ETHCCD_RELAYER_BREAKER_ON_SUPPLY_BREACH"
+    )]
+    on_supply_breach:       bool,
+    #[clap(
+        long = "breaker-on-unknown-merkle-root",
+        help = "Trip the circuit breaker if the Merkle root on Ethereum was not set by the \
+                relayer.",
+        env = "ETHCCD_RELAYER_BREAKER_ON_UNKNOWN_MERKLE_ROOT"
+    )]
+    on_unknown_merkle_root: bool,
+    #[clap(
+        long = "breaker-on-unmatched-deposit",
+        help = "Trip the circuit breaker if a deposit is completed on Concordium without a \
+                matching deposit on Ethereum.",
+        env = "ETHCCD_RELAYER_BREAKER_ON_UNMATCHED_DEPOSIT"
+    )]
+    on_unmatched_deposit:   bool,
+    #[clap(
+        long = "breaker-withdrawal-limit",
+        help = "Trip the circuit breaker if withdrawals of a token within the window exceed the \
+                limit. The format is `<index,subindex>:amount` where the amount is in the \
+                smallest unit of the token. Can be given multiple times.",
+        env = "ETHCCD_RELAYER_BREAKER_WITHDRAWAL_LIMITS",
+        value_delimiter = ' '
+    )]
+    withdrawal_limits:      Vec<circuit_breaker::WithdrawalLimit>,
+    #[clap(
+        long = "breaker-withdrawal-window",
+        help = "The window, in seconds, over which withdrawal volume is computed.",
+        env = "ETHCCD_RELAYER_BREAKER_WITHDRAWAL_WINDOW",
+        default_value = "86400"
+    )]
+    withdrawal_window:      u32,
+    #[clap(
+        long = "breaker-concordium-wallet-file",
+        help = "File with a Concordium wallet, in the browser extension wallet export format, of \
+                an account with the admin role on the bridge manager. If set, the bridge manager \
+                is paused when the circuit breaker trips. This must not be the account used for \
+                sending deposits.",
+        env = "ETHCCD_RELAYER_BREAKER_CONCORDIUM_WALLET_FILE"
+    )]
+    wallet:                 Option<PathBuf>,
+    #[clap(
+        long = "breaker-resolve",
+        help = "Mark all previous trips of the circuit breaker as resolved on startup.",
+        env = "ETHCCD_RELAYER_BREAKER_RESOLVE"
+    )]
+    resolve:                bool,
+}
+
+impl CircuitBreakerConfig {
+    fn log(&self) {
+        let CircuitBreakerConfig {
+            on_supply_breach,
+            on_unknown_merkle_root,
+            on_unmatched_deposit,
+            withdrawal_limits,
+            withdrawal_window,
+            wallet,
+            resolve: _,
+        } = self;
+        tracing::info!("Circuit breaker trips on supply invariant breach: {on_supply_breach}.");
+        tracing::info!("Circuit breaker trips on unknown Merkle root: {on_unknown_merkle_root}.");
+        tracing::info!("Circuit breaker trips on unmatched deposit: {on_unmatched_deposit}.");
+        for limit in withdrawal_limits {
+            tracing::info!(
+                "Circuit breaker trips if withdrawals of {} exceed {} within {withdrawal_window}s.",
+                limit.token,
+                limit.limit
+            );
+        }
+        if let Some(wallet) = wallet {
+            tracing::info!(
+                "Circuit breaker pauses the bridge manager using the wallet in {}.",
+                wallet.display()
+            );
+        } else {
+            tracing::info!("Circuit breaker does not pause the bridge manager.");
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+struct WithdrawalHoldConfig {
+    #[clap(
+        long = "hold-limit",
+        help = "Hold withdrawals of a token that exceed the limits instead of adding them to the \
+                Merkle tree. The format is `<index,subindex>:window_amount:single_amount` where \
+                the amounts are in the smallest unit of the token. Either amount may be left \
+                empty for no limit. Can be given multiple times.",
+        env = "ETHCCD_RELAYER_HOLD_LIMITS",
+        value_delimiter = ' '
+    )]
+    limits:        Vec<withdrawal_holds::HoldLimit>,
+    #[clap(
+        long = "hold-window",
+        help = "The window, in seconds, over which the withdrawn amount is computed.",
+        env = "ETHCCD_RELAYER_HOLD_WINDOW",
+        default_value = "86400"
+    )]
+    window:        u32,
+    #[clap(
+        long = "hold-release-delay",
+        help = "The delay, in seconds, after which held withdrawals are released. If 0 held \
+                withdrawals are only released by operator approval.",
+        env = "ETHCCD_RELAYER_HOLD_RELEASE_DELAY",
+        default_value = "86400"
+    )]
+    release_delay: u32,
+}
+
+impl WithdrawalHoldConfig {
+    fn log(&self) {
+        let WithdrawalHoldConfig {
+            limits,
+            window,
+            release_delay,
+        } = self;
+        for limit in limits {
+            if let Some(single_limit) = limit.single_limit {
+                tracing::info!(
+                    "Holding withdrawals of {} of more than {single_limit}.",
+                    limit.token
+                );
+            }
+            if let Some(window_limit) = limit.window_limit {
+                tracing::info!(
+                    "Holding withdrawals of {} above {window_limit} within {window}s.",
+                    limit.token
+                );
+            }
+        }
+        if *release_delay == 0 {
+            tracing::info!("Held withdrawals are only released by operator approval.");
+        } else {
+            tracing::info!("Held withdrawals are released after {release_delay}s.");
+        }
+    }
+
+    fn policy(self) -> withdrawal_holds::HoldPolicy {
+        withdrawal_holds::HoldPolicy {
+            limits:        self.limits,
+            window:        chrono::Duration::seconds(self.window.into()),
+            release_delay: if self.release_delay == 0 {
+                None
+            } else {
+                Some(chrono::Duration::seconds(self.release_delay.into()))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+struct AlertConfig {
+    #[clap(
+        long = "alert-webhook",
+        help = "URL to post alerts to as JSON. Can be given multiple times. If not given no \
+                alerts are sent.",
+        env = "ETHCCD_RELAYER_ALERT_WEBHOOKS",
+        value_delimiter = ' '
+    )]
+    webhooks:       Vec<url::Url>,
+    #[clap(
+        long = "alert-min-severity",
+        help = "Minimum severity of the alerts that are sent.",
+        env = "ETHCCD_RELAYER_ALERT_MIN_SEVERITY",
+        default_value = "error"
+    )]
+    min_severity:   alerts::Severity,
+    #[clap(
+        long = "alert-dedup-window",
+        help = "Number of seconds after an alert is sent during which alerts of the same kind are \
+                suppressed.",
+        env = "ETHCCD_RELAYER_ALERT_DEDUP_WINDOW",
+        default_value = "3600"
+    )]
+    dedup_window:   u64,
+    #[clap(
+        long = "alert-rate-limit",
+        help = "Maximum number of alerts sent per minute.",
+        env = "ETHCCD_RELAYER_ALERT_RATE_LIMIT",
+        default_value = "10"
+    )]
+    max_per_minute: u32,
+}
+
+impl AlertConfig {
+    fn log(&self) {
+        if self.webhooks.is_empty() {
+            tracing::info!("Alerts are not sent.");
+        } else {
+            tracing::info!(
+                "Sending alerts of severity {:?} and above to {} webhooks, at most {} per minute, \
+                 suppressing repeated alerts for {}s.",
+                self.min_severity,
+                self.webhooks.len(),
+                self.max_per_minute,
+                self.dedup_window
+            );
+        }
+    }
+
+    fn config(&self) -> Option<alerts::AlertConfig> {
+        if self.webhooks.is_empty() {
+            return None;
+        }
+        Some(alerts::AlertConfig {
+            webhooks:       self.webhooks.clone(),
+            min_severity:   self.min_severity,
+            dedup_window:   std::time::Duration::from_secs(self.dedup_window),
+            max_per_minute: self.max_per_minute,
+        })
+    }
+}
+
+#[derive(Debug, Parser)]
+struct ScreeningConfig {
+    #[clap(
+        long = "screening-ethereum-list",
+        help = "File with Ethereum addresses to screen deposits and withdrawals against, one per \
+                line. The file is reloaded when it changes.",
+        env = "ETHCCD_RELAYER_SCREENING_ETHEREUM_LIST"
+    )]
+    ethereum_list:   Option<PathBuf>,
+    #[clap(
+        long = "screening-concordium-list",
+        help = "File with Concordium account addresses to screen deposits and withdrawals \
+                against, one per line. All aliases of a listed account are screened. The file is \
+                reloaded when it changes.",
+        env = "ETHCCD_RELAYER_SCREENING_CONCORDIUM_LIST"
+    )]
+    concordium_list: Option<PathBuf>,
+}
+
+impl ScreeningConfig {
+    fn log(&self) {
+        let ScreeningConfig {
+            ethereum_list,
+            concordium_list,
+        } = self;
+        if let Some(path) = ethereum_list {
+            tracing::info!("Screening Ethereum addresses listed in {}.", path.display());
+        }
+        if let Some(path) = concordium_list {
+            tracing::info!(
+                "Screening Concordium accounts listed in {}.",
+                path.display()
+            );
+        }
     }
 }
 
+#[derive(Debug, clap::Subcommand)]
+enum RelayerCommand {
+    /// Check the configuration, including that the wallet and screening files
+    /// can be read, and exit.
+    ValidateConfig,
+    /// Rebuild an empty database from the history of both chains, and exit.
+    RebuildDatabase,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Relayer {
+    #[clap(subcommand)]
+    command: Option<RelayerCommand>,
+    #[clap(
+        long = "config",
+        help = "TOML file with options, keyed by the long option names. Options given on the \
+                command line or in the environment take precedence over the file.",
+        env = "ETHCCD_RELAYER_CONFIG"
+    )]
+    config: Option<PathBuf>,
     #[clap(
         long = "log-level",
         default_value = "info",
         help = "Maximum log level.",
         env = "ETHCCD_RELAYER_LOG_LEVEL"
     )]
-    log_level:                     log::LevelFilter,
+    log_level: tracing_subscriber::filter::LevelFilter,
+    #[clap(
+        long = "log-format",
+        default_value = "text",
+        help = "Format of the log output.",
+        env = "ETHCCD_RELAYER_LOG_FORMAT"
+    )]
+    log_format: telemetry::LogFormat,
+    #[clap(
+        long = "otlp-endpoint",
+        help = "Endpoint of an OpenTelemetry collector to export traces to over OTLP/gRPC, e.g., \
+                http://localhost:4317. If not set traces are not exported.",
+        env = "ETHCCD_RELAYER_OTLP_ENDPOINT"
+    )]
+    otlp_endpoint: Option<String>,
     #[clap(flatten)]
-    ethereum_config:               EthereumConfig,
+    ethereum_config: EthereumConfig,
     #[clap(flatten)]
-    concordium_config:             ConcordiumConfig,
+    concordium_config: ConcordiumConfig,
+    #[clap(flatten)]
+    breaker_config: CircuitBreakerConfig,
+    #[clap(flatten)]
+    hold_config: WithdrawalHoldConfig,
+    #[clap(flatten)]
+    screening_config: ScreeningConfig,
+    #[clap(flatten)]
+    alert_config: AlertConfig,
     #[clap(
         long = "concordium-wallet-file",
         name = "concordium-wallet-file",
@@ -257,7 +576,7 @@ struct Relayer {
         env = "ETHCCD_RELAYER_CONCORDIUM_WALLET_FILE",
         conflicts_with = "concordium-wallet-secret-name"
     )]
-    concordium_wallet:             Option<PathBuf>,
+    concordium_wallet: Option<PathBuf>,
     #[clap(
         long = "concordium-wallet-secret-name",
         name = "concordium-wallet-secret-name",
@@ -273,7 +592,7 @@ struct Relayer {
                 derived from this key must have the MERKLE_UPDATER role.",
         env = "ETHCCD_RELAYER_ETH_PRIVATE_KEY"
     )]
-    eth_private_key:               Option<LocalWallet>,
+    eth_private_key: Option<LocalWallet>,
     #[clap(
         long = "eth-key-secret-name",
         name = "eth-key-secret-name",
@@ -281,20 +600,43 @@ struct Relayer {
         env = "ETHCCD_RELAYER_ETH_PRIVATE_KEY_SECRET_NAME",
         conflicts_with = "eth-private-key"
     )]
-    eth_private_key_secret_name:   Option<String>,
+    eth_private_key_secret_name: Option<String>,
     #[clap(
         long = "db",
         default_value = "host=localhost dbname=relayer user=postgres password=password port=5432",
         help = "Database connection string.",
         env = "ETHCCD_RELAYER_DB_STRING"
     )]
-    db_config:                     tokio_postgres::Config,
+    db_config: tokio_postgres::Config,
     #[clap(
         long = "prometheus-server",
         help = "Listen address:port for the Prometheus server.",
         env = "ETHCCD_RELAYER_PROMETHEUS_SERVER"
     )]
-    prometheus_server:             Option<std::net::SocketAddr>,
+    prometheus_server: Option<std::net::SocketAddr>,
+    #[clap(
+        long = "health-max-stall",
+        default_value = "300",
+        help = "Number of seconds a task may go without making progress before the relayer is \
+                reported as not ready on the /ready endpoint of the Prometheus server.",
+        env = "ETHCCD_RELAYER_HEALTH_MAX_STALL"
+    )]
+    health_max_stall: u64,
+    #[clap(
+        long = "supply-check-interval",
+        default_value = "600",
+        help = "Interval, in seconds, at which to check that the balance of the Ethereum vaults \
+                matches the amount of tokens accounted for on Concordium.",
+        env = "ETHCCD_RELAYER_SUPPLY_CHECK_INTERVAL"
+    )]
+    supply_check_interval: u64,
+    #[clap(
+        long = "read-only",
+        help = "Only index the chains into the database, without sending any transactions. No \
+                keys, hold limits, or screening lists may be given in this mode.",
+        env = "ETHCCD_RELAYER_READ_ONLY"
+    )]
+    read_only: bool,
 }
 
 async fn find_start_ethereum_config<M: Middleware>(
@@ -336,24 +678,131 @@ async fn find_concordium_start_height(
     }
 }
 
+impl Relayer {
+    /// Parse the command line arguments and the environment, together with
+    /// the configuration file if one is given.
+    fn try_parse_with_config_file() -> anyhow::Result<Self> {
+        let args = config::args_with_config_file(Self::command(), "config")?;
+        Ok(Self::try_parse_from(args)?)
+    }
+
+    /// Check the consistency of the configuration, and that the files it
+    /// refers to can be read.
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            anyhow::ensure!(
+                self.concordium_wallet.is_none()
+                    && self.concordium_wallet_secret_name.is_none()
+                    && self.eth_private_key.is_none()
+                    && self.eth_private_key_secret_name.is_none()
+                    && self.breaker_config.wallet.is_none(),
+                "No keys may be given in read-only mode."
+            );
+            // Only the relayer sending transactions decides what is held.
+            anyhow::ensure!(
+                self.hold_config.limits.is_empty()
+                    && self.screening_config.ethereum_list.is_none()
+                    && self.screening_config.concordium_list.is_none(),
+                "No hold limits or screening lists may be given in read-only mode."
+            );
+        } else {
+            match (&self.concordium_wallet, &self.concordium_wallet_secret_name) {
+                (Some(w), None) => {
+                    WalletAccount::from_json_file(w)
+                        .context("Unable to read Concordium wallet from the provided file.")?;
+                }
+                (None, Some(_)) => (),
+                _ => anyhow::bail!(
+                    "Exactly one of --concordium-wallet-file and --concordium-wallet-secret-name \
+                     must be given."
+                ),
+            }
+            anyhow::ensure!(
+                self.eth_private_key.is_some() != self.eth_private_key_secret_name.is_some(),
+                "Exactly one of --eth-private-key and --eth-key-secret-name must be given."
+            );
+        }
+        if let Some(wallet) = &self.breaker_config.wallet {
+            WalletAccount::from_json_file(wallet)
+                .context("Unable to read the circuit breaker wallet from the provided file.")?;
+        }
+        screening::check_lists(
+            self.screening_config.ethereum_list.as_deref(),
+            self.screening_config.concordium_list.as_deref(),
+        )
+        .context("Unable to read the screening lists.")?;
+        anyhow::ensure!(
+            self.ethereum_config.merkle_update_interval > 0,
+            "The Merkle update interval must be positive."
+        );
+        anyhow::ensure!(
+            self.ethereum_config.escalation_interval > 0,
+            "The escalation interval must be positive."
+        );
+        anyhow::ensure!(
+            self.supply_check_interval > 0,
+            "The supply check interval must be positive."
+        );
+        anyhow::ensure!(
+            self.alert_config.webhooks.is_empty() || self.alert_config.max_per_minute > 0,
+            "The alert rate limit must be positive if alerts are sent."
+        );
+        Ok(())
+    }
+
+    /// The part of the configuration that is reloaded on SIGHUP.
+    fn settings(&self) -> config::Settings {
+        config::Settings {
+            max_gas_price:          self.ethereum_config.max_gas_price,
+            max_gas:                self.ethereum_config.max_gas,
+            merkle_update_interval: std::time::Duration::from_secs(
+                self.ethereum_config.merkle_update_interval,
+            ),
+            supply_check_interval:  std::time::Duration::from_secs(self.supply_check_interval),
+            min_concordium_balance: Amount::from_micro_ccd(self.concordium_config.min_balance),
+            min_ethereum_balance:   U256::from(self.ethereum_config.min_balance)
+                * 1_000_000_000_000u64,
+        }
+    }
+}
+
+/// Only log the relayer itself, i.e., the binary and the library.
+fn log_filter(
+    level: tracing_subscriber::filter::LevelFilter,
+) -> tracing_subscriber::filter::Targets {
+    tracing_subscriber::filter::Targets::new().with_target(module_path!(), level)
+}
+
+/// How often the balances of the sender accounts are queried.
+const BALANCE_QUERY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
 async fn query_concordium_balance(
     metrics: ccdeth_relayer::metrics::Metrics,
     mut client: v2::Client,
     address: AccountAddress,
-    min_balance: Amount,
+    settings: config::SettingsReceiver,
+    health: Health,
 ) -> anyhow::Result<()> {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+    let mut interval = tokio::time::interval(BALANCE_QUERY_INTERVAL);
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     loop {
         interval.tick().await;
+        let min_balance = settings.borrow().min_concordium_balance;
         match client
             .get_account_info(&address.into(), BlockIdentifier::LastFinal)
             .await
         {
             Ok(ai) => {
+                health.progress(Task::ConcordiumBalance);
                 let balance = ai.response.account_amount;
                 metrics.concordium_balance.set(balance.micro_ccd);
                 if ai.response.account_amount < min_balance {
+                    tracing::error!(
+                        alert = "concordium_balance_low",
+                        critical = true,
+                        "Concordium account balance {balance} is below minimum required \
+                         {min_balance}."
+                    );
                     anyhow::bail!(
                         "Concordium account balance {balance} is below minimum required \
                          {min_balance}."
@@ -362,7 +811,8 @@ async fn query_concordium_balance(
             }
             Err(e) => {
                 metrics.warnings_total.inc();
-                log::warn!("Unable to query Concordium account balance: {e:#}")
+                health.retrying(Task::ConcordiumBalance);
+                tracing::warn!("Unable to query Concordium account balance: {e:#}")
             }
         }
     }
@@ -372,18 +822,27 @@ async fn query_ethereum_balance<M: Middleware>(
     metrics: ccdeth_relayer::metrics::Metrics,
     client: M,
     address: ethers::prelude::Address,
-    min_balance: U256,
+    settings: config::SettingsReceiver,
+    health: Health,
 ) -> anyhow::Result<()> {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+    let mut interval = tokio::time::interval(BALANCE_QUERY_INTERVAL);
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     loop {
         interval.tick().await;
+        let min_balance = settings.borrow().min_ethereum_balance;
         match client.get_balance(address, None).await {
             Ok(balance) => {
+                health.progress(Task::EthereumBalance);
                 metrics
                     .ethereum_balance
                     .set((balance / 1_000_000_000_000u64).low_u64());
                 if balance < min_balance {
+                    tracing::error!(
+                        alert = "ethereum_balance_low",
+                        critical = true,
+                        "Ethereum account balance {balance} is below minimum required \
+                         {min_balance}."
+                    );
                     anyhow::bail!(
                         "Ethereum account balance {balance} is below minimum required \
                          {min_balance}."
@@ -392,9 +851,84 @@ async fn query_ethereum_balance<M: Middleware>(
             }
             Err(e) => {
                 metrics.warnings_total.inc();
-                log::warn!("Unable to query Ethereum account balance: {e:#}")
+                health.retrying(Task::EthereumBalance);
+                tracing::warn!("Unable to query Ethereum account balance: {e:#}")
+            }
+        }
+    }
+}
+
+/// Record the receivers of the deposits inserted before they were stored, and
+/// that the relayer has not sent to Concordium, from the `LockedToken` events
+/// on Ethereum. This runs in the background on a connection of its own, and
+/// is best effort. Deposits whose receiver cannot be looked up are logged and
+/// skipped, and the backfill is only recorded as completed if none were
+/// skipped, so that they are looked up again when the relayer restarts.
+async fn backfill_deposit_receivers<M: Middleware + 'static>(
+    metrics: ccdeth_relayer::metrics::Metrics,
+    config: tokio_postgres::Config,
+    contract: StateSender<M>,
+) where
+    M::Error: 'static, {
+    let result = async {
+        let (mut client, _connection) = db::connect(&config).await?;
+        let Some(missing) = db::missing_deposit_receivers(&client).await? else {
+            return Ok(());
+        };
+        if !missing.is_empty() {
+            tracing::info!(
+                "Looking up the receivers of {} existing deposits on Ethereum.",
+                missing.len()
+            );
+        }
+        let mut receivers = Vec::with_capacity(missing.len());
+        let mut skipped = 0;
+        let mut by_tx = BTreeMap::new();
+        for (id, tx_hash, deposit_id) in missing {
+            let tx_receivers = match by_tx.entry(tx_hash) {
+                btree_map::Entry::Occupied(e) => e.into_mut(),
+                btree_map::Entry::Vacant(e) => {
+                    match ethereum::get_deposit_receivers(&contract, tx_hash).await {
+                        Ok(tx_receivers) => e.insert(Some(tx_receivers)),
+                        Err(err) => {
+                            metrics.warnings_total.inc();
+                            tracing::warn!(
+                                "Unable to look up the deposits in transaction {tx_hash:#x}: \
+                                 {err:#}"
+                            );
+                            e.insert(None)
+                        }
+                    }
+                }
+            };
+            match tx_receivers
+                .as_ref()
+                .and_then(|tx_receivers| tx_receivers.get(&deposit_id))
+            {
+                Some(receiver) => receivers.push((id, *receiver)),
+                None => {
+                    if tx_receivers.is_some() {
+                        metrics.warnings_total.inc();
+                        tracing::warn!(
+                            "No deposit with id {deposit_id} in transaction {tx_hash:#x}."
+                        );
+                    }
+                    skipped += 1;
+                }
             }
         }
+        if skipped > 0 {
+            tracing::warn!(
+                "Skipped recording the receiver of {skipped} existing deposits. They are looked \
+                 up again when the relayer restarts."
+            );
+        }
+        db::set_deposit_receivers(&mut client, &receivers, skipped == 0).await
+    }
+    .await;
+    if let Err(e) = result {
+        metrics.warnings_total.inc();
+        tracing::warn!("Unable to record the receivers of existing deposits: {e:#}");
     }
 }
 
@@ -421,41 +955,92 @@ where
 
 #[tokio::main(worker_threads = 4)]
 async fn main() -> anyhow::Result<()> {
-    let app: Relayer = Relayer::parse();
+    let app = match Relayer::try_parse_with_config_file() {
+        Ok(app) => app,
+        Err(e) => match e.downcast::<clap::Error>() {
+            Ok(e) => e.exit(),
+            Err(e) => return Err(e),
+        },
+    };
+    app.validate().context("Invalid configuration.")?;
+    if let Some(RelayerCommand::ValidateConfig) = app.command {
+        println!("The configuration is valid.");
+        return Ok(());
+    }
 
-    let mut log_builder = env_logger::Builder::from_env("ETHCCD_RELAYER_LOG");
-    // only log the current module (main).
-    log_builder.filter_module(module_path!(), app.log_level);
-    log_builder.init();
+    let (alert_layer, alert_task) = match app.alert_config.config() {
+        Some(config) => {
+            let (layer, task) = alerts::alerts("ccdeth_relayer", config);
+            (Some(layer), Some(task))
+        }
+        None => (None, None),
+    };
+    let filter_handle = telemetry::init(
+        "ccdeth_relayer",
+        log_filter(app.log_level),
+        app.log_format,
+        app.otlp_endpoint.as_deref(),
+        alert_layer,
+    )
+    .context("Unable to set up logging.")?;
+
+    let result = run(app, filter_handle).await;
+    if let Err(e) = &result {
+        tracing::error!("The relayer stopped due to an error: {e:#}");
+    }
+    // The alert sender runs until the end so that alerts raised during
+    // shutdown, or by the error that stopped the relayer, are also sent.
+    if let Some(alert_task) = alert_task {
+        alert_task.flush(alerts::FLUSH_TIMEOUT).await;
+    }
+    telemetry::shutdown();
+    result
+}
+
+/// Run the relayer, or the command given, until it is stopped.
+async fn run(app: Relayer, filter_handle: telemetry::FilterHandle) -> anyhow::Result<()> {
+    let initial_settings = app.settings();
 
-    log::info!("Using {} as the maximum log level.", app.log_level);
+    tracing::info!("Using {} as the maximum log level.", app.log_level);
     app.ethereum_config.log();
     app.concordium_config.log();
+    app.breaker_config.log();
+    app.hold_config.log();
+    app.screening_config.log();
+    app.alert_config.log();
+
+    if app.read_only {
+        tracing::info!("Running in read-only mode. No transactions will be sent.");
+    }
 
     let concordium_wallet = match (
         app.concordium_wallet.as_ref(),
         app.concordium_wallet_secret_name.as_ref(),
     ) {
+        _ if app.read_only => None,
         (Some(_), Some(_)) => {
             anyhow::bail!(
                 "Both file and secret name provided as the key location for Concordium. Choose \
                  one."
             )
         }
-        (Some(w), None) => WalletAccount::from_json_file(w)
-            .context("Unable to read Concordium wallet from the provided file.")?,
-        (None, Some(sn)) => ccdeth_relayer::aws_secret_manager::get_concordium_keys_aws(sn)
-            .await
-            .context("Unable to get Concordium wallet from Amazon secret manager.")?,
+        (Some(w), None) => Some(
+            WalletAccount::from_json_file(w)
+                .context("Unable to read Concordium wallet from the provided file.")?,
+        ),
+        (None, Some(sn)) => Some(
+            ccdeth_relayer::aws_secret_manager::get_concordium_keys_aws(sn)
+                .await
+                .context("Unable to get Concordium wallet from Amazon secret manager.")?,
+        ),
         (None, None) => {
             anyhow::bail!("Concordium keys were not provided.")
         }
     };
-    let concordium_sender_address = concordium_wallet.address;
-    log::info!(
-        "Using {} as the sender of Concordium transactions.",
-        concordium_sender_address
-    );
+    let concordium_sender_address = concordium_wallet.as_ref().map(|w| w.address);
+    if let Some(address) = concordium_sender_address {
+        tracing::info!("Using {address} as the sender of Concordium transactions.");
+    }
 
     let inner_ethereum_client = {
         let network_client = reqwest::ClientBuilder::new()
@@ -480,46 +1065,56 @@ async fn main() -> anyhow::Result<()> {
 
     // Transactions will be signed with the private key below and will be broadcast
     // via the eth_sendRawTransaction API)
-    let wallet: LocalWallet = match (
+    let wallet: Option<LocalWallet> = match (
         app.eth_private_key,
         app.eth_private_key_secret_name.as_ref(),
     ) {
+        _ if app.read_only => None,
         (Some(_), Some(_)) => {
             anyhow::bail!(
                 "Both file and secret name provided as the key location for Ethereum. Choose one."
             )
         }
-        (Some(w), None) => w.with_chain_id(app.ethereum_config.chain_id),
-        (None, Some(sn)) => ccdeth_relayer::aws_secret_manager::get_ethereum_keys_aws(sn)
-            .await
-            .context("Unable to get Ethereum wallet from Amazon secret manager.")?
-            .with_chain_id(app.ethereum_config.chain_id),
+        (Some(w), None) => Some(w.with_chain_id(app.ethereum_config.chain_id)),
+        (None, Some(sn)) => Some(
+            ccdeth_relayer::aws_secret_manager::get_ethereum_keys_aws(sn)
+                .await
+                .context("Unable to get Ethereum wallet from Amazon secret manager.")?
+                .with_chain_id(app.ethereum_config.chain_id),
+        ),
         (None, None) => {
             anyhow::bail!("Ethereum keys were not provided.")
         }
     };
 
-    let ethereum_sender = wallet.address();
-    log::info!("Using {ethereum_sender:#x} as the Ethereum wallet.");
+    // The Ethereum wallet together with its next nonce.
+    let ethereum_signer = if let Some(wallet) = wallet {
+        let ethereum_sender = wallet.address();
+        tracing::info!("Using {ethereum_sender:#x} as the Ethereum wallet.");
 
-    let balance = ethereum_client
-        .get_balance(ethereum_sender, None)
-        .await
-        .context("Unable to get initial balance of the Ethereum sender")?;
-    log::info!(
-        "Balance of the Ethereum sender account is {} microETH.",
-        balance / 1_000_000_000_000u64
-    );
-    let ethereum_nonce = ethereum_client
-        .get_transaction_count(ethereum_sender, None)
-        .await
-        .context("Unable to get nonce for the Ethereum account")?;
-    log::info!("Nonce of the Ethereum sender account is {ethereum_nonce}.");
+        let balance = ethereum_client
+            .get_balance(ethereum_sender, None)
+            .await
+            .context("Unable to get initial balance of the Ethereum sender")?;
+        tracing::info!(
+            "Balance of the Ethereum sender account is {} microETH.",
+            balance / 1_000_000_000_000u64
+        );
+        let ethereum_nonce = ethereum_client
+            .get_transaction_count(ethereum_sender, None)
+            .await
+            .context("Unable to get nonce for the Ethereum account")?;
+        tracing::info!("Nonce of the Ethereum sender account is {ethereum_nonce}.");
+        Some((wallet, ethereum_nonce))
+    } else {
+        None
+    };
+    let ethereum_sender = ethereum_signer.as_ref().map(|(wallet, _)| wallet.address());
 
     // Set up signal handlers before doing anything non-trivial so we have some sort
     // of graceful shut down during initial database lookups and pending
     // transaction sends.
-    log::info!("Setting up signal handlers.");
+    tracing::info!("Setting up signal handlers.");
     let (stop_sender, mut stop_receiver) = tokio::sync::watch::channel(());
     let (died_sender, died_receiver) = tokio::sync::broadcast::channel(10);
     let shutdown_handler_handle = spawn_cancel(
@@ -529,14 +1124,31 @@ async fn main() -> anyhow::Result<()> {
 
     let (registry, metrics) = ccdeth_relayer::metrics::Metrics::new()
         .context("Unable to construct new metrics object.")?;
+    let health = Health::new();
+    let (settings_sender, settings) = tokio::sync::watch::channel(initial_settings);
+    let reload_handle = spawn_cancel(
+        died_sender.clone(),
+        reload_on_hangup(filter_handle, settings_sender),
+    );
     if let Some(prometheus_server) = app.prometheus_server {
-        log::info!("Starting prometheus server at {prometheus_server}.");
+        tracing::info!("Starting prometheus server at {prometheus_server}.");
         spawn_cancel(
             died_sender.clone(),
-            ccdeth_relayer::metrics::start_prometheus_server(prometheus_server, registry),
+            ccdeth_relayer::metrics::start_prometheus_server(
+                prometheus_server,
+                registry,
+                health.clone(),
+            ),
         );
     }
 
+    let (screener, screening_reload_task) = screening::screener(
+        metrics.clone(),
+        app.screening_config.ethereum_list,
+        app.screening_config.concordium_list,
+    )
+    .context("Unable to load screening lists.")?;
+
     let state_sender_contract =
         StateSender::new(app.ethereum_config.state_sender, ethereum_client.clone());
 
@@ -545,7 +1157,11 @@ async fn main() -> anyhow::Result<()> {
         Arc::new(ethereum_client.clone()),
     );
 
-    let mut concordium_client = {
+    // The supply and Merkle root monitors only query the root chain manager.
+    let supply_root_chain_manager = root_chain_manager_contract.clone();
+    let merkle_root_chain_manager = root_chain_manager_contract.clone();
+
+    let concordium_client = {
         // Use TLS if the URI scheme is HTTPS.
         // This uses whatever system certificates have been installed as trusted roots.
         let endpoint = if app
@@ -571,50 +1187,57 @@ async fn main() -> anyhow::Result<()> {
             .await
             .context("Unable to connect Concordium node.")?
     };
-    {
-        let lfb = concordium_client
-            .get_consensus_info()
-            .await
-            .context("Unable to get Consensus info.")?
-            .last_finalized_block;
-        let bi = concordium_client
-            .get_block_info(lfb)
-            .await
-            .context("Unable to get block information about last finalized block")?
-            .response;
-        if chrono::Utc::now().signed_duration_since(bi.block_slot_time)
-            > chrono::Duration::seconds(app.concordium_config.max_behind.into())
-        {
-            anyhow::bail!(
-                "Unable to start. The last finalized time of the Concordium node is more than {}s \
-                 in the past.",
-                app.concordium_config.max_behind,
-            );
-        }
-    }
 
-    let (last_ethereum, last_concordium, db) = Database::new(&app.db_config)
+    let (last_ethereum, last_concordium, mut db) = Database::new(&app.db_config)
         .await
         .context("Unable to connect to the database.")?;
-    let start_nonce = db
-        .submit_missing_txs(concordium_client.clone())
-        .await
-        .context("Unable to submit missing transactions.")?;
-
     let bridge_manager_client = BridgeManagerClient::new(
         concordium_client.clone(),
-        concordium_wallet.address,
+        concordium_sender_address,
         app.concordium_config.bridge_manager,
     );
 
-    let bridge_manager = concordium_contracts::BridgeManager::new(
-        bridge_manager_client.clone(),
-        concordium_wallet,
-        start_nonce,
-        app.concordium_config.max_energy,
-    )
-    .await
-    .context("Unable to connect to Concordium API.")?;
+    let bridge_manager = if let Some(concordium_wallet) = concordium_wallet {
+        let start_nonce = db
+            .submit_missing_txs(concordium_client.clone())
+            .await
+            .context("Unable to submit missing transactions.")?;
+
+        let bridge_manager = concordium_contracts::BridgeManager::new(
+            bridge_manager_client.clone(),
+            concordium_wallet,
+            start_nonce,
+            app.concordium_config.max_energy,
+        )
+        .await
+        .context("Unable to connect to Concordium API.")?;
+        Some(bridge_manager)
+    } else {
+        None
+    };
+
+    if let Some(RelayerCommand::RebuildDatabase) = app.command {
+        rebuild::rebuild_database(
+            &metrics,
+            &mut db,
+            bridge_manager,
+            bridge_manager_client,
+            app.concordium_config.bridge_manager,
+            &state_sender_contract,
+            app.ethereum_config.state_sender_creation_block_number,
+            app.ethereum_config.num_confirmations,
+            app.concordium_config.max_parallel,
+        )
+        .await
+        .context("Unable to rebuild the database.")?;
+        return Ok(());
+    }
+
+    let backfill_handle = tokio::spawn(backfill_deposit_receivers(
+        metrics.clone(),
+        app.db_config.clone(),
+        state_sender_contract.clone(),
+    ));
 
     let (start_number, upper_number) = find_start_ethereum_config(
         ethereum_client.clone(),
@@ -624,7 +1247,7 @@ async fn main() -> anyhow::Result<()> {
     )
     .await
     .context("Unable to find starting point for Ethereum monitoring")?;
-    log::info!(
+    tracing::info!(
         "Found starting point on Ethereum chain at start = {start_number}, end = {upper_number})"
     );
     let concordium_start_height = find_concordium_start_height(
@@ -635,7 +1258,11 @@ async fn main() -> anyhow::Result<()> {
     .await
     .context("Unable to find starting point for Concordium monitoring.")?;
 
-    log::info!("Starting at {concordium_start_height} on the Concordium chain.");
+    tracing::info!("Starting at {concordium_start_height} on the Concordium chain.");
+    let mapped_tokens = db
+        .mapped_tokens()
+        .await
+        .context("Unable to get the mapped tokens.")?;
 
     let (max_marked_event_index, leaves) = db
         .pending_withdrawals(bridge_manager_client.clone())
@@ -648,11 +1275,99 @@ async fn main() -> anyhow::Result<()> {
     let (ccd_transaction_sender, ccd_transaction_receiver) = tokio::sync::mpsc::channel(50);
     let (merkle_setter_sender, merkle_setter_receiver) = tokio::sync::mpsc::channel(50);
 
+    {
+        let max_stall = std::time::Duration::from_secs(app.health_max_stall);
+        for task in [
+            Task::EthereumWatcher,
+            Task::ConcordiumListener,
+            Task::DatabaseWorker,
+        ] {
+            health.register(task, max_stall);
+        }
+        health.register_queue(Task::DatabaseWorker, &db_sender);
+        if app.read_only {
+            health.register(
+                Task::MerkleRootTracker,
+                max_stall + merkle::ROOT_TRACKING_INTERVAL,
+            );
+            health.register_queue(Task::MerkleRootTracker, &merkle_setter_sender);
+        } else {
+            health.register(Task::ConcordiumSender, max_stall);
+            // The Merkle sender only makes progress once per update interval, and
+            // may wait for a pending transaction until it is escalated.
+            health.register(
+                Task::MerkleSender,
+                max_stall
+                    + std::time::Duration::from_secs(
+                        app.ethereum_config.merkle_update_interval
+                            + app.ethereum_config.escalation_interval,
+                    ),
+            );
+            for task in [Task::ConcordiumBalance, Task::EthereumBalance] {
+                health.register(task, max_stall + BALANCE_QUERY_INTERVAL);
+            }
+            health.register_queue(Task::ConcordiumSender, &ccd_transaction_sender);
+            health.register_queue(Task::MerkleSender, &merkle_setter_sender);
+        }
+    }
+
     let pending_merkle_set = db
         .pending_ethereum_tx()
         .await
         .context("Unable to get pending Merkle tree.")?;
 
+    // Sending is paused until the monitors have established that neither of the
+    // nodes is behind.
+    let (lag_reporter, lag_guard) = chain_lag::lag_guard();
+
+    if app.breaker_config.resolve {
+        let resolved = db
+            .resolve_circuit_breaker_trips()
+            .await
+            .context("Unable to resolve circuit breaker trips.")?;
+        tracing::info!("Marked {resolved} trips of the circuit breaker as resolved.");
+    }
+    let unresolved_trips = db
+        .unresolved_circuit_breaker_trips()
+        .await
+        .context("Unable to get circuit breaker trips.")?;
+    if unresolved_trips > 0 {
+        tracing::error!(
+            "There are {unresolved_trips} unresolved trips of the circuit breaker. No new Merkle \
+             roots will be set until they are resolved."
+        );
+    }
+    let pauser = if let Some(wallet) = app.breaker_config.wallet.as_ref() {
+        let admin = WalletAccount::from_json_file(wallet)
+            .context("Unable to read the circuit breaker wallet from the provided file.")?;
+        anyhow::ensure!(
+            Some(admin.address) != concordium_sender_address,
+            "The circuit breaker account must be different from the account sending deposits."
+        );
+        Some(circuit_breaker::Pauser {
+            client: bridge_manager_client.clone(),
+            admin,
+            max_energy: app.concordium_config.max_energy,
+        })
+    } else {
+        None
+    };
+    let (breaker, breaker_state, breaker_task) = circuit_breaker::circuit_breaker(
+        metrics.clone(),
+        circuit_breaker::Triggers {
+            supply_invariant:    app.breaker_config.on_supply_breach,
+            unknown_merkle_root: app.breaker_config.on_unknown_merkle_root,
+            unmatched_deposit:   app.breaker_config.on_unmatched_deposit,
+            withdrawal_limits:   app.breaker_config.withdrawal_limits,
+            withdrawal_window:   chrono::Duration::seconds(
+                app.breaker_config.withdrawal_window.into(),
+            ),
+        },
+        pauser,
+        db_sender.clone(),
+        unresolved_trips > 0,
+    );
+
     // Now we set up the main service, after we have established a baseline.
     // The different tasks communicate using channels established above.
     // The shutdown plan is as follows.
@@ -661,15 +1376,22 @@ async fn main() -> anyhow::Result<()> {
     //   opportunity to shut down gracefully by sending a signal on the
     //   stop_sender/stop_receiver channel. The same broadcast channel is shared by
     //   all tasks, and the only sender is the signal handler.
-    let tx_sender_handle = spawn_cancel(
-        died_sender.clone(),
-        concordium_contracts::concordium_tx_sender(
-            metrics.clone(),
-            concordium_client.clone(),
-            ccd_transaction_receiver,
-            stop_receiver.clone(),
-        ),
-    );
+    // In read-only mode no transactions are made, so there is nothing to send.
+    let tx_sender_handle = (!app.read_only).then(|| {
+        spawn_cancel(
+            died_sender.clone(),
+            concordium_contracts::concordium_tx_sender(
+                metrics.clone(),
+                concordium_client.clone(),
+                ccd_transaction_receiver,
+                lag_guard.clone(),
+                health.clone(),
+                stop_receiver.clone(),
+            ),
+        )
+    });
+    let admin_db_config = app.db_config.clone();
+    let stats_db_config = app.db_config.clone();
     let db_task_handle = spawn_cancel(
         died_sender.clone(),
         db::handle_database(
@@ -680,24 +1402,35 @@ async fn main() -> anyhow::Result<()> {
             bridge_manager,
             ccd_transaction_sender,
             merkle_setter_sender,
+            breaker.clone(),
+            app.hold_config.policy(),
+            screener,
+            health.clone(),
             stop_receiver.clone(),
         ),
     );
-    let merkle_updater_handle = {
+    // The circuit breaker stops when all the handles to it are dropped.
+    let breaker_handle = spawn_cancel(died_sender.clone(), breaker_task);
+    let mut admin_listen_handle = None;
+    let merkle_updater_handle = if let Some((wallet, ethereum_nonce)) = ethereum_signer {
         let merkle_client = MerkleSetterClient::new(
             root_chain_manager_contract,
             wallet,
-            app.ethereum_config.max_gas_price,
-            app.ethereum_config.max_gas,
+            settings.clone(),
             ethereum_nonce,
             &pending_merkle_set,
-            std::time::Duration::from_secs(app.ethereum_config.merkle_update_interval),
             leaves,
             max_marked_event_index,
             std::time::Duration::from_secs(app.ethereum_config.escalation_interval),
             std::time::Duration::from_secs(app.ethereum_config.warn_duration),
         )
         .context("Unable to construct the client for setting Merkle roots.")?;
+        // Requests for immediate Merkle updates made through the admin API are
+        // only relevant to the relayer that sets the roots.
+        admin_listen_handle = Some(spawn_cancel(
+            died_sender.clone(),
+            db::listen_admin_requests(admin_db_config, merkle_client.update_requests.clone()),
+        ));
 
         spawn_cancel(
             died_sender.clone(),
@@ -708,6 +1441,25 @@ async fn main() -> anyhow::Result<()> {
                 merkle_setter_receiver,
                 db_sender.clone(),
                 app.ethereum_config.num_confirmations,
+                lag_guard,
+                breaker_state,
+                health.clone(),
+                stop_receiver.clone(),
+            ),
+        )
+    } else {
+        // Record the roots set by the relayer that is sending transactions, so
+        // that proofs of withdrawals can be served from this database.
+        spawn_cancel(
+            died_sender.clone(),
+            merkle::track_merkle_roots(
+                metrics.clone(),
+                root_chain_manager_contract,
+                leaves,
+                merkle_setter_receiver,
+                db_sender.clone(),
+                app.ethereum_config.num_confirmations,
+                health.clone(),
                 stop_receiver.clone(),
             ),
         )
@@ -723,6 +1475,8 @@ async fn main() -> anyhow::Result<()> {
             concordium_start_height,
             app.concordium_config.max_parallel,
             app.concordium_config.max_behind,
+            mapped_tokens.into_iter().collect(),
+            health.clone(),
         ),
     );
     let watch_ethereum_handle = spawn_cancel(
@@ -734,52 +1488,137 @@ async fn main() -> anyhow::Result<()> {
             start_number,
             upper_number,
             app.ethereum_config.num_confirmations,
+            health.clone(),
         ),
     );
 
-    let balance_query_handle = spawn_cancel(
+    let concordium_lag_handle = spawn_cancel(
         died_sender.clone(),
-        query_concordium_balance(
+        chain_lag::monitor_concordium_lag(
             metrics.clone(),
-            concordium_client,
-            concordium_sender_address,
-            Amount::from_micro_ccd(app.concordium_config.min_balance),
+            concordium_client.clone(),
+            std::time::Duration::from_secs(app.concordium_config.max_behind.into()),
+            lag_reporter.clone(),
+        ),
+    );
+
+    let ethereum_lag_handle = spawn_cancel(
+        died_sender.clone(),
+        chain_lag::monitor_ethereum_lag(
+            metrics.clone(),
+            ethereum_client.clone(),
+            std::time::Duration::from_secs(app.ethereum_config.max_behind),
+            lag_reporter,
         ),
     );
 
-    let ethereum_balance_query_handle = spawn_cancel(
+    let supply_monitor_handle = spawn_cancel(
         died_sender.clone(),
-        query_ethereum_balance(
+        reconciliation::monitor_supply(
             metrics.clone(),
-            ethereum_client,
-            ethereum_sender,
-            U256::from(app.ethereum_config.min_balance) * 1_000_000_000_000u64,
+            concordium_client.clone(),
+            supply_root_chain_manager,
+            db_sender.clone(),
+            breaker.clone(),
+            settings.clone(),
         ),
     );
 
+    // In read-only mode roots are only known once they are tracked, so they
+    // cannot be checked against the database.
+    let merkle_root_monitor_handle = (!app.read_only).then(|| {
+        spawn_cancel(
+            died_sender.clone(),
+            circuit_breaker::monitor_merkle_root(
+                metrics.clone(),
+                merkle_root_chain_manager,
+                db_sender.clone(),
+                breaker,
+                app.ethereum_config.num_confirmations,
+            ),
+        )
+    });
+
+    let screening_reload_handle = spawn_cancel(died_sender.clone(), screening_reload_task);
+
+    let hold_release_handle = spawn_cancel(
+        died_sender.clone(),
+        withdrawal_holds::release_holds(db_sender.clone()),
+    );
+
+    let pending_ages_handle = spawn_cancel(
+        died_sender.clone(),
+        ccdeth_relayer::metrics::report_pending_ages(db_sender.clone()),
+    );
+
+    let stats_refresh_handle = spawn_cancel(
+        died_sender.clone(),
+        ccdeth_relayer::db::refresh_stats(metrics.clone(), stats_db_config),
+    );
+
+    let balance_query_handle = concordium_sender_address.map(|address| {
+        spawn_cancel(
+            died_sender.clone(),
+            query_concordium_balance(
+                metrics.clone(),
+                concordium_client,
+                address,
+                settings.clone(),
+                health.clone(),
+            ),
+        )
+    });
+
+    let ethereum_balance_query_handle = ethereum_sender.map(|address| {
+        spawn_cancel(
+            died_sender.clone(),
+            query_ethereum_balance(metrics.clone(), ethereum_client, address, settings, health),
+        )
+    });
+
     // Wait for signal to be received.
     if let Err(e) = stop_receiver.changed().await {
-        log::error!("The signal handler unexpectedly died with {e}. Shutting off the service.");
+        tracing::error!("The signal handler unexpectedly died with {e}. Shutting off the service.");
     }
 
     // Stop watcher tasks.
     watch_concordium_handle.abort();
     watch_ethereum_handle.abort();
-    balance_query_handle.abort();
-    ethereum_balance_query_handle.abort();
+    for handle in [balance_query_handle, ethereum_balance_query_handle]
+        .into_iter()
+        .flatten()
+    {
+        handle.abort();
+    }
+    concordium_lag_handle.abort();
+    ethereum_lag_handle.abort();
+    supply_monitor_handle.abort();
+    if let Some(handle) = merkle_root_monitor_handle {
+        handle.abort();
+    }
+    if let Some(handle) = admin_listen_handle {
+        handle.abort();
+    }
+    backfill_handle.abort();
+    hold_release_handle.abort();
+    pending_ages_handle.abort();
+    stats_refresh_handle.abort();
+    screening_reload_handle.abort();
+    reload_handle.abort();
     // And wait for all of them to terminate.
     let shutdown = [
         await_and_report("merkle updater", merkle_updater_handle),
         await_and_report("watch Ethereum", watch_ethereum_handle),
         await_and_report("watch Concordium", watch_concordium_handle),
         await_and_report("database handler", db_task_handle),
-        await_and_report("concordium transaction sender", tx_sender_handle),
-    ];
-    shutdown
-        .into_iter()
-        .collect::<futures::stream::FuturesUnordered<_>>()
-        .collect::<()>()
-        .await;
+        await_and_report("circuit breaker", breaker_handle),
+    ]
+    .into_iter()
+    .collect::<futures::stream::FuturesUnordered<_>>();
+    if let Some(handle) = tx_sender_handle {
+        shutdown.push(await_and_report("concordium transaction sender", handle));
+    }
+    shutdown.collect::<()>().await;
     await_and_report("shutdown handler", shutdown_handler_handle).await;
     drop(died_sender); // keep the sender alive until here explicitly so that we don't have spurious
                        // errors when the last task is dying.
@@ -794,21 +1633,74 @@ async fn await_and_report<E: std::fmt::Display>(
 ) {
     match handle.await {
         Ok(Ok(())) => {
-            log::info!("Task {descr} terminated.");
+            tracing::info!("Task {descr} terminated.");
         }
         Ok(Err(e)) => {
-            log::error!("Task {descr} unexpectedly stopped due to {e:#}.");
+            tracing::error!("Task {descr} unexpectedly stopped due to {e:#}.");
         }
         Err(e) => {
             if e.is_panic() {
-                log::error!("Task panicked.");
+                tracing::error!("Task panicked.");
             } else if e.is_cancelled() {
-                log::info!("Task {descr} was cancelled.");
+                tracing::info!("Task {descr} was cancelled.");
+            } else {
+                tracing::error!("Task {descr} unexpectedly closed.");
+            }
+        }
+    }
+}
+
+/// Reload the configuration when SIGHUP is received. Only the log level and the
+/// [`config::Settings`] take effect, changes to other options require a
+/// restart.
+async fn reload_on_hangup(
+    filter_handle: telemetry::FilterHandle,
+    settings_sender: tokio::sync::watch::Sender<config::Settings>,
+) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix as unix_signal;
+        let mut hangup_stream = unix_signal::signal(unix_signal::SignalKind::hangup())?;
+        while hangup_stream.recv().await.is_some() {
+            tracing::info!("Reloading the configuration.");
+            let app = match Relayer::try_parse_with_config_file()
+                .and_then(|app| app.validate().map(|_| app))
+            {
+                Ok(app) => app,
+                Err(e) => {
+                    tracing::error!(
+                        "Unable to reload the configuration, keeping the current one: {e:#}"
+                    );
+                    continue;
+                }
+            };
+            if let Err(e) = filter_handle.modify(|filter| *filter = log_filter(app.log_level)) {
+                tracing::warn!("Unable to change the log level: {e}");
             } else {
-                log::error!("Task {descr} unexpectedly closed.");
+                tracing::info!("Using {} as the maximum log level.", app.log_level);
             }
+            let settings = app.settings();
+            tracing::info!(
+                "Using {} as the maximum gas price and {} as the maximum gas, updating Merkle \
+                 roots every {}s, checking the supply every {}s, and requiring {} microCCD on the \
+                 Concordium and {} microETH on the Ethereum sender account.",
+                settings.max_gas_price,
+                settings.max_gas,
+                settings.merkle_update_interval.as_secs(),
+                settings.supply_check_interval.as_secs(),
+                settings.min_concordium_balance.micro_ccd,
+                app.ethereum_config.min_balance
+            );
+            settings_sender.send_replace(settings);
         }
     }
+    #[cfg(windows)]
+    {
+        // There is no SIGHUP on Windows, so the configuration is never reloaded.
+        let _ = (filter_handle, settings_sender);
+        futures::future::pending::<()>().await;
+    }
+    Ok(())
 }
 
 /// Construct a future for shutdown signals (for unix: SIGINT and SIGTERM) (for
@@ -828,7 +1720,7 @@ async fn set_shutdown(
         let task_died = Box::pin(task_died.recv());
         futures::future::select(task_died, futures::future::select(terminate, interrupt)).await;
         if stop_sender.send(()).is_err() {
-            log::error!("Unable to send stop signal.");
+            tracing::error!("Unable to send stop signal.");
         }
     }
     #[cfg(windows)]
@@ -841,7 +1733,7 @@ async fn set_shutdown(
         let task_died = Box::pin(task_died.recv());
         futures::future::select(task_died, futures::future::select(ctrl_break, ctrl_c)).await;
         if stop_sender.send(()).is_err() {
-            log::error!("Unable to send stop signal.");
+            tracing::error!("Unable to send stop signal.");
         }
     }
     Ok(())
@@ -0,0 +1,199 @@
+//! Monitors of how far behind the present the Concordium node and the Ethereum
+//! API are. While either of them is too far behind the sender tasks stop
+//! sending deposits and Merkle roots, and they resume automatically once the
+//! nodes catch up.
+use concordium_rust_sdk::v2::{self, BlockIdentifier};
+use ethers::prelude::{BlockNumber, Middleware};
+use std::sync::Arc;
+
+/// How often the monitors query the nodes.
+const LAG_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The last known state of the two chains.
+pub struct LagStatus {
+    /// Whether the last finalized block of the Concordium node is too old.
+    pub concordium_behind: bool,
+    /// Whether the latest block of the Ethereum API is too old.
+    pub ethereum_behind:   bool,
+}
+
+impl LagStatus {
+    /// Whether it is safe to send transactions to either chain.
+    pub fn is_caught_up(&self) -> bool { !self.concordium_behind && !self.ethereum_behind }
+}
+
+#[derive(Debug, Clone)]
+/// A handle used by the sender tasks to check whether sending is allowed.
+pub struct LagGuard {
+    receiver:   tokio::sync::watch::Receiver<LagStatus>,
+    /// Whether a chain was behind at the last [`LagGuard::check`].
+    was_behind: bool,
+}
+
+#[derive(Debug, Clone)]
+/// A handle used by the monitor tasks to update the shared [`LagStatus`].
+pub struct LagReporter {
+    sender: Arc<tokio::sync::watch::Sender<LagStatus>>,
+}
+
+/// Construct a new pair of reporter and guard. Both chains are initially
+/// considered behind until the monitors have checked them, so nothing is sent
+/// before the nodes are known to be up to date.
+pub fn lag_guard() -> (LagReporter, LagGuard) {
+    let (sender, receiver) = tokio::sync::watch::channel(LagStatus {
+        concordium_behind: true,
+        ethereum_behind:   true,
+    });
+    (
+        LagReporter {
+            sender: Arc::new(sender),
+        },
+        LagGuard {
+            receiver,
+            was_behind: false,
+        },
+    )
+}
+
+impl LagGuard {
+    /// Whether either of the chains is currently behind.
+    pub fn is_behind(&self) -> bool { !self.receiver.borrow().is_caught_up() }
+
+    /// Whether either of the chains is currently behind, for tasks that check
+    /// the guard periodically instead of waiting. A warning that `action` is
+    /// paused is logged when a chain falls behind, and a message that it is
+    /// resumed when the chains have caught up, instead of on every check.
+    pub fn check(&mut self, metrics: &crate::metrics::Metrics, action: &str) -> bool {
+        let behind = self.is_behind();
+        if behind && !self.was_behind {
+            metrics.warnings_total.inc();
+            tracing::warn!("Not {action} since the nodes are behind.");
+        } else if !behind && self.was_behind {
+            tracing::info!("The nodes caught up. Resuming {action}.");
+        }
+        self.was_behind = behind;
+        behind
+    }
+
+    /// Wait until both chains are caught up. Returns `false` if the service
+    /// was asked to stop while waiting, and `true` otherwise.
+    pub async fn wait_caught_up(&mut self, stop: &mut tokio::sync::watch::Receiver<()>) -> bool {
+        loop {
+            if self.receiver.borrow_and_update().is_caught_up() {
+                return true;
+            }
+            let stop = tokio::select! {
+                biased;
+                _ = stop.changed() => true,
+                r = self.receiver.changed() => r.is_err(),
+            };
+            if stop {
+                return false;
+            }
+        }
+    }
+}
+
+impl LagReporter {
+    fn set_concordium_behind(&self, behind: bool) {
+        self.sender.send_if_modified(|s| {
+            let changed = s.concordium_behind != behind;
+            s.concordium_behind = behind;
+            changed
+        });
+    }
+
+    fn set_ethereum_behind(&self, behind: bool) {
+        self.sender.send_if_modified(|s| {
+            let changed = s.ethereum_behind != behind;
+            s.ethereum_behind = behind;
+            changed
+        });
+    }
+}
+
+/// Periodically check the age of the last finalized block of the Concordium
+/// node, export it as a metric, and pause sending while it exceeds
+/// `max_behind`.
+pub async fn monitor_concordium_lag(
+    metrics: crate::metrics::Metrics,
+    mut client: v2::Client,
+    max_behind: std::time::Duration,
+    reporter: LagReporter,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(LAG_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let max_behind = chrono::Duration::from_std(max_behind)?;
+    let mut was_behind = None;
+    loop {
+        interval.tick().await;
+        match client.get_block_info(BlockIdentifier::LastFinal).await {
+            Ok(bi) => {
+                let lag = chrono::Utc::now().signed_duration_since(bi.response.block_slot_time);
+                metrics.concordium_lag_seconds.set(lag.num_seconds());
+                let behind = lag > max_behind;
+                if behind && was_behind != Some(true) {
+                    metrics.warnings_total.inc();
+                    tracing::warn!(
+                        "The last finalized block of the Concordium node is {}s old. Pausing \
+                         sending of deposits and Merkle roots.",
+                        lag.num_seconds()
+                    );
+                } else if !behind && was_behind == Some(true) {
+                    tracing::info!("The Concordium node caught up. Resuming sending.");
+                }
+                was_behind = Some(behind);
+                reporter.set_concordium_behind(behind);
+            }
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Unable to query the last finalized block of Concordium: {e:#}");
+            }
+        }
+    }
+}
+
+/// Periodically check the age of the latest block of the Ethereum API,
+/// export it as a metric, and pause sending while it exceeds `max_behind`.
+pub async fn monitor_ethereum_lag<M: Middleware>(
+    metrics: crate::metrics::Metrics,
+    client: M,
+    max_behind: std::time::Duration,
+    reporter: LagReporter,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(LAG_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut was_behind = None;
+    loop {
+        interval.tick().await;
+        match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => {
+                let block_time = block.timestamp.low_u64();
+                let now = chrono::Utc::now().timestamp().max(0) as u64;
+                let lag = now.saturating_sub(block_time);
+                metrics.ethereum_lag_seconds.set(lag as i64);
+                let behind = lag > max_behind.as_secs();
+                if behind && was_behind != Some(true) {
+                    metrics.warnings_total.inc();
+                    tracing::warn!(
+                        "The latest block of the Ethereum API is {lag}s old. Pausing sending of \
+                         deposits and Merkle roots."
+                    );
+                } else if !behind && was_behind == Some(true) {
+                    tracing::info!("The Ethereum API caught up. Resuming sending.");
+                }
+                was_behind = Some(behind);
+                reporter.set_ethereum_behind(behind);
+            }
+            Ok(None) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("The Ethereum API did not return the latest block.");
+            }
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Unable to query the latest block of Ethereum: {e:#}");
+            }
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! An automatic circuit breaker. Other parts of the relayer report anomalies
+//! to the breaker, and if the corresponding trigger is enabled the breaker
+//! trips. When tripped the relayer stops setting new Merkle roots, and if a key
+//! with the admin role on the bridge manager is configured the bridge manager
+//! is paused on Concordium. Every trip is recorded in the database together
+//! with its cause. The breaker stays tripped, also across restarts, until the
+//! trips are resolved by the operator.
+use crate::{
+    concordium_contracts::BridgeManagerClient,
+    db::{CircuitBreakerTrigger, DatabaseOperation},
+    root_chain_manager::BridgeManager,
+};
+use concordium_rust_sdk::types::{hashes::TransactionHash, ContractAddress, Energy, WalletAccount};
+use ethabi::ethereum_types::H160;
+use ethers::prelude::Middleware;
+use num_bigint::{BigInt, BigUint};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+/// How often the Merkle root on Ethereum is checked.
+const MERKLE_ROOT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+/// The cause of a trip of the circuit breaker.
+pub enum TripCause {
+    /// The vault holds less than what is accounted for on Concordium.
+    SupplyInvariant {
+        root_token: H160,
+        delta:      BigInt,
+    },
+    /// The Merkle root on Ethereum was not set by the relayer.
+    UnknownMerkleRoot { root: [u8; 32] },
+    /// A deposit was completed on Concordium with no matching deposit on
+    /// Ethereum.
+    UnmatchedDeposit {
+        tx_hash:            TransactionHash,
+        origin_event_index: u64,
+    },
+    /// The volume of withdrawals of a token within the configured window
+    /// exceeds the limit.
+    WithdrawalVolume {
+        token:  ContractAddress,
+        volume: BigUint,
+        limit:  BigUint,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What an anomaly is about, e.g., the token or the Merkle root, regardless of
+/// amounts that change as the anomaly persists.
+enum TripSubject {
+    SupplyInvariant(H160),
+    UnknownMerkleRoot([u8; 32]),
+    UnmatchedDeposit(u64),
+    WithdrawalVolume(ContractAddress),
+}
+
+impl TripCause {
+    fn subject(&self) -> TripSubject {
+        match self {
+            TripCause::SupplyInvariant { root_token, .. } => {
+                TripSubject::SupplyInvariant(*root_token)
+            }
+            TripCause::UnknownMerkleRoot { root } => TripSubject::UnknownMerkleRoot(*root),
+            TripCause::UnmatchedDeposit {
+                origin_event_index, ..
+            } => TripSubject::UnmatchedDeposit(*origin_event_index),
+            TripCause::WithdrawalVolume { token, .. } => TripSubject::WithdrawalVolume(*token),
+        }
+    }
+
+    pub fn trigger(&self) -> CircuitBreakerTrigger {
+        match self {
+            TripCause::SupplyInvariant { .. } => CircuitBreakerTrigger::SupplyInvariant,
+            TripCause::UnknownMerkleRoot { .. } => CircuitBreakerTrigger::UnknownMerkleRoot,
+            TripCause::UnmatchedDeposit { .. } => CircuitBreakerTrigger::UnmatchedDeposit,
+            TripCause::WithdrawalVolume { .. } => CircuitBreakerTrigger::WithdrawalVolume,
+        }
+    }
+}
+
+impl std::fmt::Display for TripCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TripCause::SupplyInvariant { root_token, delta } => write!(
+                f,
+                "The vault for token {root_token:#x} holds {} less than is accounted for on \
+                 Concordium.",
+                -delta
+            ),
+            TripCause::UnknownMerkleRoot { root } => write!(
+                f,
+                "The Merkle root {} on Ethereum was not set by the relayer.",
+                TransactionHash::from(*root)
+            ),
+            TripCause::UnmatchedDeposit {
+                tx_hash,
+                origin_event_index,
+            } => write!(
+                f,
+                "Deposit with id {origin_event_index} was completed in transaction {tx_hash}, but \
+                 there is no matching deposit on Ethereum."
+            ),
+            TripCause::WithdrawalVolume {
+                token,
+                volume,
+                limit,
+            } => write!(
+                f,
+                "Withdrawals of token {token} amount to {volume}, which exceeds the limit {limit}."
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A limit on the volume of withdrawals of a single token within the window.
+pub struct WithdrawalLimit {
+    pub token: ContractAddress,
+    pub limit: u128,
+}
+
+impl std::str::FromStr for WithdrawalLimit {
+    type Err = anyhow::Error;
+
+    /// Parse a limit in the format `<index,subindex>:amount`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, limit) = s
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Expected format <index,subindex>:amount"))?;
+        Ok(Self {
+            token: token.parse()?,
+            limit: limit.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Which triggers are enabled.
+pub struct Triggers {
+    pub supply_invariant:    bool,
+    pub unknown_merkle_root: bool,
+    pub unmatched_deposit:   bool,
+    /// Limits on the withdrawal volume, per token.
+    pub withdrawal_limits:   Vec<WithdrawalLimit>,
+    /// The window over which the withdrawal volume is computed.
+    pub withdrawal_window:   chrono::Duration,
+}
+
+impl Triggers {
+    fn is_enabled(&self, trigger: CircuitBreakerTrigger) -> bool {
+        match trigger {
+            CircuitBreakerTrigger::SupplyInvariant => self.supply_invariant,
+            CircuitBreakerTrigger::UnknownMerkleRoot => self.unknown_merkle_root,
+            CircuitBreakerTrigger::UnmatchedDeposit => self.unmatched_deposit,
+            CircuitBreakerTrigger::WithdrawalVolume => !self.withdrawal_limits.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum BreakerEvent {
+    Anomaly(TripCause),
+    Withdrawals {
+        block_time:  chrono::DateTime<chrono::Utc>,
+        withdrawals: Vec<(ContractAddress, BigUint)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// A handle used to report anomalies to the circuit breaker.
+pub struct CircuitBreaker {
+    triggers: Arc<Triggers>,
+    sender:   tokio::sync::mpsc::UnboundedSender<BreakerEvent>,
+}
+
+impl CircuitBreaker {
+    /// Report an anomaly. The breaker trips if the trigger for the anomaly is
+    /// enabled.
+    pub fn report(&self, cause: TripCause) {
+        if self.triggers.is_enabled(cause.trigger()) {
+            // If the breaker task has stopped the relayer is shutting down.
+            let _ = self.sender.send(BreakerEvent::Anomaly(cause));
+        }
+    }
+
+    /// Report new withdrawals from a block with the given slot time.
+    pub fn report_withdrawals(
+        &self,
+        block_time: chrono::DateTime<chrono::Utc>,
+        withdrawals: Vec<(ContractAddress, BigUint)>,
+    ) {
+        if !self.triggers.withdrawal_limits.is_empty() && !withdrawals.is_empty() {
+            let _ = self.sender.send(BreakerEvent::Withdrawals {
+                block_time,
+                withdrawals,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A handle used by the Merkle root sender to check whether the breaker has
+/// tripped.
+pub struct BreakerState {
+    receiver:    tokio::sync::watch::Receiver<bool>,
+    /// Whether the breaker was tripped at the last [`BreakerState::check`].
+    was_tripped: bool,
+}
+
+impl BreakerState {
+    pub fn is_tripped(&self) -> bool { *self.receiver.borrow() }
+
+    /// Whether the breaker is tripped, for tasks that check it periodically.
+    /// A warning that `action` is stopped is only logged the first time the
+    /// breaker is seen tripped, instead of on every check.
+    pub fn check(&mut self, metrics: &crate::metrics::Metrics, action: &str) -> bool {
+        let tripped = self.is_tripped();
+        if tripped && !self.was_tripped {
+            metrics.warnings_total.inc();
+            tracing::warn!("Not {action} since the circuit breaker is tripped.");
+        }
+        self.was_tripped = tripped;
+        tripped
+    }
+}
+
+/// The key used for pausing the bridge manager when the breaker trips.
+pub struct Pauser {
+    pub client:     BridgeManagerClient,
+    /// An account with the admin role on the bridge manager. This must be
+    /// different from the account used to send deposits.
+    pub admin:      WalletAccount,
+    pub max_energy: Energy,
+}
+
+/// Construct the circuit breaker. The returned future must be spawned as a
+/// task. `tripped` is the initial state, which should be `true` if there are
+/// unresolved trips in the database.
+pub fn circuit_breaker(
+    metrics: crate::metrics::Metrics,
+    triggers: Triggers,
+    pauser: Option<Pauser>,
+    db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
+    tripped: bool,
+) -> (
+    CircuitBreaker,
+    BreakerState,
+    impl std::future::Future<Output = anyhow::Result<()>>,
+) {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let (state_sender, state_receiver) = tokio::sync::watch::channel(tripped);
+    metrics.circuit_breaker_tripped.set(tripped.into());
+    let triggers = Arc::new(triggers);
+    let breaker = CircuitBreaker {
+        triggers: triggers.clone(),
+        sender,
+    };
+    let task = run_circuit_breaker(metrics, triggers, pauser, db_sender, receiver, state_sender);
+    (
+        breaker,
+        BreakerState {
+            receiver:    state_receiver,
+            was_tripped: false,
+        },
+        task,
+    )
+}
+
+/// The volume of withdrawals of a token within the window.
+struct WithdrawalVolume {
+    limit:       BigUint,
+    withdrawals: VecDeque<(chrono::DateTime<chrono::Utc>, BigUint)>,
+    volume:      BigUint,
+}
+
+async fn run_circuit_breaker(
+    metrics: crate::metrics::Metrics,
+    triggers: Arc<Triggers>,
+    mut pauser: Option<Pauser>,
+    db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
+    mut receiver: tokio::sync::mpsc::UnboundedReceiver<BreakerEvent>,
+    state: tokio::sync::watch::Sender<bool>,
+) -> anyhow::Result<()> {
+    let mut volumes = triggers
+        .withdrawal_limits
+        .iter()
+        .map(|l| {
+            (l.token, WithdrawalVolume {
+                limit:       l.limit.into(),
+                withdrawals: VecDeque::new(),
+                volume:      BigUint::from(0u8),
+            })
+        })
+        .collect::<BTreeMap<_, _>>();
+    // The anomalies recorded since the relayer started. An anomaly keeps being
+    // reported while it persists, e.g., on every supply check, and is only
+    // logged and recorded the first time.
+    let mut recorded = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        let causes = match event {
+            BreakerEvent::Anomaly(cause) => vec![cause],
+            BreakerEvent::Withdrawals {
+                block_time,
+                withdrawals,
+            } => {
+                let mut causes = Vec::new();
+                for (token, amount) in withdrawals {
+                    let Some(v) = volumes.get_mut(&token) else {
+                        continue;
+                    };
+                    v.volume += &amount;
+                    v.withdrawals.push_back((block_time, amount));
+                    while let Some((time, amount)) = v.withdrawals.front() {
+                        if *time + triggers.withdrawal_window > block_time {
+                            break;
+                        }
+                        v.volume -= amount;
+                        v.withdrawals.pop_front();
+                    }
+                    if v.volume > v.limit {
+                        causes.push(TripCause::WithdrawalVolume {
+                            token,
+                            volume: v.volume.clone(),
+                            limit: v.limit.clone(),
+                        });
+                    }
+                }
+                causes
+            }
+        };
+        for cause in causes {
+            let subject = cause.subject();
+            if recorded.contains(&subject) {
+                tracing::debug!("The circuit breaker is already tripped by: {cause}");
+                continue;
+            }
+            recorded.push(subject);
+            metrics.errors_total.inc();
+            metrics.circuit_breaker_trips.inc();
+            tracing::error!(
+                alert = "circuit_breaker_tripped",
+                critical = true,
+                "Circuit breaker tripped: {cause}"
+            );
+            let was_tripped = state.send_replace(true);
+            metrics.circuit_breaker_tripped.set(1);
+            let mut pause_tx_hash = None;
+            if !was_tripped {
+                tracing::error!("No new Merkle roots will be set until the trip is resolved.");
+                if let Some(pauser) = pauser.as_mut() {
+                    match pauser
+                        .client
+                        .set_paused(&pauser.admin, true, pauser.max_energy)
+                        .await
+                    {
+                        Ok(tx_hash) => {
+                            tracing::error!(
+                                "Sent transaction {tx_hash} to pause the bridge manager on \
+                                 Concordium."
+                            );
+                            pause_tx_hash = Some(tx_hash);
+                        }
+                        Err(e) => {
+                            metrics.errors_total.inc();
+                            tracing::error!(
+                                "Unable to pause the bridge manager on Concordium: {e:#}"
+                            );
+                        }
+                    }
+                }
+            }
+            if db_sender
+                .send(DatabaseOperation::InsertCircuitBreakerTrip {
+                    trigger: cause.trigger(),
+                    cause: cause.to_string(),
+                    pause_tx_hash,
+                })
+                .await
+                .is_err()
+            {
+                metrics.errors_total.inc();
+                tracing::error!("Unable to record the trip since the database channel is closed.");
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Periodically check that the Merkle root on Ethereum is one that was set by
+/// the relayer, and report an anomaly otherwise. The root is checked in the
+/// last block with the required number of confirmations.
+pub async fn monitor_merkle_root<M: Middleware + 'static>(
+    metrics: crate::metrics::Metrics,
+    root_manager: BridgeManager<M>,
+    db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
+    breaker: CircuitBreaker,
+    num_confirmations: u64,
+) -> anyhow::Result<()>
+where
+    M::Error: 'static, {
+    let mut interval = tokio::time::interval(MERKLE_ROOT_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_reported = None;
+    loop {
+        interval.tick().await;
+        let root = async {
+            let height = root_manager
+                .client()
+                .get_block_number()
+                .await?
+                .as_u64()
+                .saturating_sub(num_confirmations);
+            anyhow::Ok(root_manager.get_merkle_root().block(height).call().await?)
+        }
+        .await;
+        let root = match root {
+            Ok(root) => root,
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Unable to query the Merkle root on Ethereum: {e:#}");
+                continue;
+            }
+        };
+        if root == [0u8; 32] {
+            // No root was set yet.
+            continue;
+        }
+        let (response, receiver) = tokio::sync::oneshot::channel();
+        if db_sender
+            .send(DatabaseOperation::IsKnownMerkleRoot { root, response })
+            .await
+            .is_err()
+        {
+            tracing::info!("The database channel is closed. Stopping the Merkle root monitor.");
+            return Ok(());
+        }
+        let Ok(known) = receiver.await else {
+            tracing::info!("The database worker stopped. Stopping the Merkle root monitor.");
+            return Ok(());
+        };
+        if !known && last_reported != Some(root) {
+            metrics.errors_total.inc();
+            tracing::error!(
+                alert = "unknown_merkle_root",
+                critical = true,
+                "The Merkle root {} on Ethereum was not set by the relayer.",
+                TransactionHash::from(root)
+            );
+            breaker.report(TripCause::UnknownMerkleRoot { root });
+            last_reported = Some(root);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 //! This module deal with interaction with the bridge manager contract
 //! on Concordium. It deals with parsing events emitted by the contract,
 //! and sending updates to it.
-use crate::db;
+use crate::{
+    db,
+    health::{Health, Task},
+};
 use anyhow::Context;
 use concordium_rust_sdk::{
     cis2::{self, TokenId},
@@ -16,13 +19,14 @@ use concordium_rust_sdk::{
             ContractContext, InvokeContractResult, OwnedParameter, OwnedReceiveName,
         },
         transactions::{self, BlockItem, EncodedPayload, UpdateContractPayload},
-        AbsoluteBlockHeight, Address, BlockItemSummary, ContractAddress, Energy, Nonce,
-        RejectReason, WalletAccount,
+        AbsoluteBlockHeight, Address, BlockItemSummary, BlockItemSummaryDetails, ContractAddress,
+        Energy, Nonce, RejectReason, WalletAccount,
     },
     v2::{self, BlockIdentifier},
 };
 use futures::{StreamExt, TryStreamExt};
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
+use tracing::Instrument;
 
 /// Type of Ethereum addresses.
 type EthAddress = [u8; 20];
@@ -42,6 +46,23 @@ pub struct DepositOperation {
     pub token_id: TokenId,
 }
 
+impl DepositOperation {
+    /// Construct the operation that completes the deposit with the given id
+    /// on Ethereum.
+    pub fn new(id: u64, user: AccountAddress, root: EthAddress, amount: cis2::TokenAmount) -> Self {
+        Self {
+            id,
+            user: user.into(),
+            root,
+            amount,
+            // TODO: Hardcoded token ID. Works with contracts as they are
+            // now, but is not ideal. But until those contracts are changed not
+            // much to do here.
+            token_id: TokenId::new_unchecked(vec![0u8; 8]),
+        }
+    }
+}
+
 #[derive(contracts_common::Serialize, Debug)]
 pub struct TokenMapOperation {
     /// Id of the operation emitted by Ethereum StateSender.
@@ -237,7 +258,7 @@ impl BridgeManager {
                         return Ok(None);
                     }
                     DryRunReturn::OtherError { reason } => {
-                        log::error!(
+                        tracing::error!(
                             "Unexpected response from dry running state update. This is a \
                              configuration error: {reason:#?}"
                         );
@@ -248,7 +269,7 @@ impl BridgeManager {
                     }
                 },
                 Err(e) => {
-                    log::warn!("Unable to dry run state update due to: {e:#}");
+                    tracing::warn!("Unable to dry run state update due to: {e:#}");
                 }
             }
             tokio::time::sleep(std::time::Duration::from_millis(1000 << iter_num)).await;
@@ -376,18 +397,33 @@ impl BridgeEvent {
     }
 }
 
+#[derive(contracts_common::Serialize, Debug)]
+/// Return value of the `viewConfiguration` function of the bridge manager.
+pub struct BridgeManagerConfiguration {
+    /// Whether the contract is paused.
+    pub paused:            bool,
+    /// The event index of the last withdraw event.
+    pub emit_event_index:  u64,
+    /// The fee paid when initiating a withdrawal.
+    pub withdraw_fee:      contracts_common::Amount,
+    /// The account receiving the fees.
+    pub treasurer_address: contracts_common::AccountAddress,
+}
+
 #[derive(Clone, Debug)]
 /// A client for querying and looking at events of the bridge manager contract.
 pub struct BridgeManagerClient {
     pub client:         v2::Client,
-    pub sender_account: AccountAddress,
+    /// The account sending transactions to the bridge manager, if any. This is
+    /// [`None`] in read-only mode.
+    pub sender_account: Option<AccountAddress>,
     contract:           ContractAddress,
 }
 
 impl BridgeManagerClient {
     pub fn new(
         client: v2::Client,
-        sender_account: AccountAddress,
+        sender_account: Option<AccountAddress>,
         contract: ContractAddress,
     ) -> Self {
         Self {
@@ -397,6 +433,54 @@ impl BridgeManagerClient {
         }
     }
 
+    /// Query the configuration of the bridge manager in the given block.
+    pub async fn view_configuration(
+        &mut self,
+        bi: impl v2::IntoBlockIdentifier,
+    ) -> anyhow::Result<BridgeManagerConfiguration> {
+        let ctx = ContractContext {
+            invoker:   None,
+            contract:  self.contract,
+            amount:    Amount::from_micro_ccd(0),
+            method:    OwnedReceiveName::new_unchecked("bridge-manager.viewConfiguration".into()),
+            parameter: OwnedParameter::new_unchecked(Vec::new()),
+            energy:    10_000.into(),
+        };
+        let result = self.client.invoke_instance(bi, &ctx).await?;
+        match result.response {
+            InvokeContractResult::Success { return_value, .. } => {
+                let rv = return_value.context("Unexpected response.")?.value;
+                contracts_common::from_bytes(&rv)
+                    .context("Unable to parse the configuration of the bridge manager.")
+            }
+            InvokeContractResult::Failure { reason, .. } => {
+                anyhow::bail!("Invocation of viewConfiguration failed: {reason:?}")
+            }
+        }
+    }
+
+    /// Whether the transaction touches the bridge manager or one of the mapped
+    /// tokens, or was sent by the relayer.
+    fn is_bridge_transaction(
+        &self,
+        mapped_tokens: &BTreeSet<ContractAddress>,
+        summary: &BlockItemSummary,
+    ) -> bool {
+        summary
+            .affected_contracts()
+            .iter()
+            .any(|ca| *ca == self.contract || mapped_tokens.contains(ca))
+            || summary
+                .sender_account()
+                .map_or(false, |account| self.is_sender(&account))
+    }
+
+    /// Whether the account is (an alias of) the sender account.
+    fn is_sender(&self, account: &AccountAddress) -> bool {
+        self.sender_account
+            .map_or(false, |sender| account.is_alias(&sender))
+    }
+
     /// Get all the bridge manager event logs.
     pub fn extract_events(
         &mut self,
@@ -420,6 +504,59 @@ impl BridgeManagerClient {
             Ok(Vec::new())
         }
     }
+
+    /// Send a transaction that pauses or unpauses the bridge manager. The
+    /// `admin` account must have the admin role, and must not be the account
+    /// used for sending deposits since the nonce is queried from the node.
+    ///
+    /// The transaction is dry run first, and sent only if that succeeds.
+    pub async fn set_paused(
+        &mut self,
+        admin: &WalletAccount,
+        paused: bool,
+        max_energy: Energy,
+    ) -> anyhow::Result<TransactionHash> {
+        let payload = UpdateContractPayload {
+            amount:       Amount::from_micro_ccd(0),
+            address:      self.contract,
+            receive_name: OwnedReceiveName::new_unchecked("bridge-manager.setPaused".into()),
+            message:      OwnedParameter::new_unchecked(contracts_common::to_bytes(&paused)),
+        };
+        let ctx =
+            ContractContext::new_from_payload(admin.address, ALLOWED_DRY_RUN_NRG, payload.clone());
+        let result = self
+            .client
+            .invoke_instance(BlockIdentifier::LastFinal, &ctx)
+            .await?;
+        let used_energy = match result.response {
+            InvokeContractResult::Success { used_energy, .. } => used_energy,
+            InvokeContractResult::Failure { reason, .. } => {
+                anyhow::bail!("Dry run of setPaused failed: {reason:?}")
+            }
+        };
+        let energy: Energy = used_energy.energy.saturating_add(1000).into();
+        anyhow::ensure!(
+            energy <= max_energy,
+            "Estimated energy exceeds maximum allowed"
+        );
+        let nonce = self
+            .client
+            .get_next_account_sequence_number(&admin.address)
+            .await?
+            .nonce;
+        let expiry: TransactionTime =
+            TransactionTime::from_seconds((chrono::Utc::now().timestamp() + 60 * 60) as u64);
+        let tx = transactions::send::update_contract(
+            admin,
+            admin.address,
+            nonce,
+            expiry,
+            payload,
+            energy,
+        );
+        let bi: BlockItem<EncodedPayload> = tx.into();
+        Ok(self.client.send_block_item(&bi).await?)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -447,6 +584,10 @@ pub async fn listen_concordium(
     max_parallel: u32,
     // Maximum number of seconds to wait for a new finalized block.
     max_behind: u32,
+    // The mapped tokens on Concordium. Tokens mapped later are added as their
+    // events are processed.
+    mut mapped_tokens: BTreeSet<ContractAddress>,
+    health: Health,
 ) -> anyhow::Result<()> {
     let mut retry_attempt = 0;
     let mut last_height = height;
@@ -458,6 +599,8 @@ pub async fn listen_concordium(
             &mut height,
             max_parallel,
             max_behind,
+            &mut mapped_tokens,
+            &health,
         )
         .await;
 
@@ -468,18 +611,19 @@ pub async fn listen_concordium(
         }
         match res {
             Ok(()) => {
-                log::info!("Terminated listening for new Concordium events.");
+                tracing::info!("Terminated listening for new Concordium events.");
                 return Ok(());
             }
             Err(e) => match e {
                 NodeError::Timeout => {
                     retry_attempt += 1;
                     if retry_attempt > 6 {
-                        log::error!("Too many failures attempting to reconnect. Aborting.");
+                        tracing::error!("Too many failures attempting to reconnect. Aborting.");
                         anyhow::bail!("Too many failures attempting to reconnect. Aborting.");
                     }
                     let delay = std::time::Duration::from_secs(5 << retry_attempt);
-                    log::warn!(
+                    health.retrying(Task::ConcordiumListener);
+                    tracing::warn!(
                         "Querying the node timed out. Will attempt again in {} seconds..",
                         delay.as_secs()
                     );
@@ -488,11 +632,12 @@ pub async fn listen_concordium(
                 NodeError::QueryError(e) => {
                     retry_attempt += 1;
                     if retry_attempt > 6 {
-                        log::error!("Too many failures attempting to reconnect. Aborting.");
+                        tracing::error!("Too many failures attempting to reconnect. Aborting.");
                         anyhow::bail!("Too many failures attempting to reconnect. Aborting.");
                     }
                     let delay = std::time::Duration::from_secs(5 << retry_attempt);
-                    log::warn!(
+                    health.retrying(Task::ConcordiumListener);
+                    tracing::warn!(
                         "Querying the node failed due to {:#}. Will attempt again in {} seconds.",
                         e,
                         delay.as_secs()
@@ -500,7 +645,9 @@ pub async fn listen_concordium(
                     tokio::time::sleep(delay).await;
                 }
                 NodeError::Internal(e) => {
-                    log::error!("Internal configuration error: {e}. Terminating the query task.");
+                    tracing::error!(
+                        "Internal configuration error: {e}. Terminating the query task."
+                    );
                     return Err(e);
                 }
             },
@@ -508,6 +655,243 @@ pub async fn listen_concordium(
     }
 }
 
+/// While catching up, the blocks are scanned for bridge transactions in
+/// windows of this many blocks. The matching blocks of a window are processed
+/// and the checkpoint is recorded at its end before the next window is scanned.
+const CATCH_UP_WINDOW: u64 = 10_000;
+
+/// Query the info and the transaction summaries of a block.
+pub(crate) async fn query_block(
+    mut node: v2::Client,
+    block: impl v2::IntoBlockIdentifier,
+) -> Result<(BlockInfo, Vec<BlockItemSummary>), QueryError> {
+    let binfo = node.get_block_info(block).await?;
+    let events = if binfo.response.transaction_count == 0 {
+        Vec::new()
+    } else {
+        node.get_block_transaction_events(binfo.response.block_hash)
+            .await?
+            .response
+            .try_collect()
+            .await?
+    };
+    Ok((binfo.response, events))
+}
+
+/// Process the transactions of a finalized block and send the bridge manager
+/// events to the database. Tokens mapped in the block are added to
+/// `mapped_tokens`.
+/// Return false if the channel to the database was closed.
+async fn process_block(
+    metrics: &crate::metrics::Metrics,
+    bridge_manager: &mut BridgeManagerClient,
+    sender: &tokio::sync::mpsc::Sender<db::DatabaseOperation>,
+    block: BlockInfo,
+    summaries: Vec<BlockItemSummary>,
+    mapped_tokens: &mut BTreeSet<ContractAddress>,
+) -> Result<bool, NodeError> {
+    tracing::debug!(
+        "Processing Concordium block {} at height {}",
+        block.block_hash,
+        block.block_height
+    );
+    let mut transaction_events = Vec::new();
+    let mut fees = Vec::new();
+    for summary in summaries {
+        let events = bridge_manager
+            .extract_events(&summary)
+            .map_err(NodeError::Internal)?;
+        for event in &events {
+            if let BridgeEvent::TokenMap(tm) = event {
+                mapped_tokens.insert(tm.child);
+            }
+        }
+        if !events.is_empty() {
+            transaction_events.push((summary.hash, events));
+        }
+        if let BlockItemSummaryDetails::AccountTransaction(at) = &summary.details {
+            if bridge_manager.is_sender(&at.sender) {
+                fees.push((summary.hash, at.cost));
+            }
+        }
+        // Also check for any other transactions from the sender account.
+        // So we can mark transactions we have sent as failed.
+        if summary.is_rejected_account_transaction().is_some() {
+            if let Some(acc) = summary.sender_account() {
+                if bridge_manager.is_sender(&acc) {
+                    tracing::warn!(
+                        alert = "concordium_transaction_failed",
+                        "Discovered a failed transaction {} sent by Concordium relayer account.",
+                        summary.hash
+                    );
+                    if sender
+                        .send(db::DatabaseOperation::MarkConcordiumTransaction {
+                            tx_hash: summary.hash,
+                            state:   db::TransactionStatus::Failed,
+                        })
+                        .await
+                        .is_err()
+                    {
+                        tracing::info!("The channel to the database writer has been closed.");
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+    metrics
+        .concordium_height
+        .set(block.block_height.height as i64);
+    // The fees are counted by the database worker, since only those of the
+    // transactions that the relayer submitted, and only once, are counted.
+    if !fees.is_empty()
+        && sender
+            .send(db::DatabaseOperation::RecordConcordiumFees { fees })
+            .await
+            .is_err()
+    {
+        tracing::info!("The channel to the database writer has been closed.");
+        return Ok(false);
+    }
+    if sender
+        .send(db::DatabaseOperation::ConcordiumEvents {
+            blocks: vec![(block, transaction_events)],
+        })
+        .await
+        .is_err()
+    {
+        tracing::info!("The channel to the database writer has been closed.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Find the blocks from `start` to `end` with transactions that touch the
+/// bridge manager or a mapped token, or that were sent by the relayer. Up to
+/// `max_parallel` blocks are queried at a time, in any order, and only the
+/// matching blocks are kept, together with their transaction summaries, so
+/// that they can be processed without querying them again. They are returned
+/// in order of height.
+async fn find_bridge_blocks(
+    bridge_manager: &BridgeManagerClient,
+    mapped_tokens: &BTreeSet<ContractAddress>,
+    start: u64,
+    end: u64,
+    max_parallel: u32,
+    health: &Health,
+) -> Result<Vec<(BlockInfo, Vec<BlockItemSummary>)>, NodeError> {
+    let client = bridge_manager.client.clone();
+    let mut blocks = futures::stream::iter(start..=end)
+        .map(|height| {
+            query_block(
+                client.clone(),
+                BlockIdentifier::AbsoluteHeight(AbsoluteBlockHeight { height }),
+            )
+        })
+        .buffer_unordered(max_parallel.max(1) as usize);
+    let mut found = Vec::new();
+    while let Some(result) = blocks.next().await {
+        let (block, summaries) = result?;
+        if summaries
+            .iter()
+            .any(|summary| bridge_manager.is_bridge_transaction(mapped_tokens, summary))
+        {
+            found.push((block, summaries));
+        }
+        health.progress(Task::ConcordiumListener);
+    }
+    found.sort_unstable_by_key(|(block, _)| block.block_height.height);
+    Ok(found)
+}
+
+/// Process the blocks from `height` up to the last finalized block in windows
+/// of [`CATCH_UP_WINDOW`] blocks. Each window is first scanned in parallel for
+/// the blocks with bridge transactions, and then only those blocks are
+/// processed in order, followed by a checkpoint at the end of the window. This
+/// is much faster than processing every block when the relayer is far behind.
+/// Return false if the channel to the database was closed.
+async fn catch_up_concordium(
+    metrics: &crate::metrics::Metrics,
+    bridge_manager: &mut BridgeManagerClient,
+    sender: &tokio::sync::mpsc::Sender<db::DatabaseOperation>,
+    height: &mut AbsoluteBlockHeight,
+    max_parallel: u32,
+    mapped_tokens: &mut BTreeSet<ContractAddress>,
+    health: &Health,
+) -> Result<bool, NodeError> {
+    let last_final = bridge_manager
+        .client
+        .get_block_info(BlockIdentifier::LastFinal)
+        .await?
+        .response
+        .block_height;
+    if last_final < *height {
+        return Ok(true);
+    }
+    tracing::info!("Catching up on Concordium from height {height} to {last_final}.");
+    while *height <= last_final {
+        let end = last_final
+            .height
+            .min(height.height.saturating_add(CATCH_UP_WINDOW - 1));
+        let blocks = find_bridge_blocks(
+            bridge_manager,
+            mapped_tokens,
+            height.height,
+            end,
+            max_parallel,
+            health,
+        )
+        .await?;
+        tracing::debug!(
+            "Found {} blocks with bridge transactions between heights {height} and {end}.",
+            blocks.len()
+        );
+        for (block, summaries) in blocks {
+            let block_height = block.block_height;
+            if !process_block(
+                metrics,
+                bridge_manager,
+                sender,
+                block,
+                summaries,
+                mapped_tokens,
+            )
+            .await?
+            {
+                return Ok(false);
+            }
+            health.progress(Task::ConcordiumListener);
+            *height = block_height.next();
+        }
+        if height.height <= end {
+            // Record the checkpoint at the end of the window. The block has no
+            // bridge transactions, so its summaries are not needed.
+            let block = bridge_manager
+                .client
+                .get_block_info(BlockIdentifier::AbsoluteHeight(AbsoluteBlockHeight {
+                    height: end,
+                }))
+                .await?
+                .response;
+            if !process_block(
+                metrics,
+                bridge_manager,
+                sender,
+                block,
+                Vec::new(),
+                mapped_tokens,
+            )
+            .await?
+            {
+                return Ok(false);
+            }
+            *height = AbsoluteBlockHeight { height: end }.next();
+        }
+    }
+    tracing::info!("Caught up on Concordium. Following finalized blocks from height {height}.");
+    Ok(true)
+}
+
 /// Return Err if querying the node failed.
 /// Return Ok(()) if the channel to the database was closed.
 async fn listen_concordium_worker(
@@ -522,7 +906,22 @@ async fn listen_concordium_worker(
     max_parallel: u32,
     // Maximum number of seconds to wait for a new finalized block.
     max_behind: u32,
+    mapped_tokens: &mut BTreeSet<ContractAddress>,
+    health: &Health,
 ) -> Result<(), NodeError> {
+    if !catch_up_concordium(
+        metrics,
+        bridge_manager,
+        sender,
+        height,
+        max_parallel,
+        mapped_tokens,
+        health,
+    )
+    .await?
+    {
+        return Ok(());
+    }
     let mut finalized_blocks = bridge_manager
         .client
         .get_finalized_blocks_from(*height)
@@ -535,78 +934,25 @@ async fn listen_concordium_worker(
             .map_err(|_| NodeError::Timeout)?;
         let mut futures = futures::stream::FuturesOrdered::new();
         for fb in chunk {
-            let mut node = bridge_manager.client.clone();
             // A future to query the block at the given hash.
-            let poller = async move {
-                let binfo = node.get_block_info(fb.block_hash).await?;
-                let events = if binfo.response.transaction_count == 0 {
-                    Vec::new()
-                } else {
-                    node.get_block_transaction_events(fb.block_hash)
-                        .await?
-                        .response
-                        .try_collect()
-                        .await?
-                };
-                Ok::<(BlockInfo, Vec<BlockItemSummary>), QueryError>((binfo.response, events))
-            };
-            futures.push_back(poller);
+            futures.push_back(query_block(bridge_manager.client.clone(), fb.block_hash));
         }
 
         while let Some(result) = futures.next().await {
             let (block, summaries) = result?;
-            log::debug!(
-                "Processing Concordium block {} at height {}",
-                block.block_hash,
-                block.block_height
-            );
-            let mut transaction_events = Vec::new();
-            for summary in summaries {
-                let events = bridge_manager
-                    .extract_events(&summary)
-                    .map_err(NodeError::Internal)?;
-                if !events.is_empty() {
-                    transaction_events.push((summary.hash, events));
-                }
-                // Also check for any other transactions from the sender account.
-                // So we can mark transactions we have sent as failed.
-                if summary.is_rejected_account_transaction().is_some() {
-                    if let Some(acc) = summary.sender_account() {
-                        if acc.is_alias(&bridge_manager.sender_account) {
-                            log::warn!(
-                                "Discovered a failed transaction {} sent by Concordium relayer \
-                                 account.",
-                                summary.hash
-                            );
-                            if sender
-                                .send(db::DatabaseOperation::MarkConcordiumTransaction {
-                                    tx_hash: summary.hash,
-                                    state:   db::TransactionStatus::Failed,
-                                })
-                                .await
-                                .is_err()
-                            {
-                                log::info!("The channel to the database writer has been closed.");
-                                return Ok(());
-                            }
-                        }
-                    }
-                }
-            }
-            metrics
-                .concordium_height
-                .set(block.block_height.height as i64);
-            if sender
-                .send(db::DatabaseOperation::ConcordiumEvents {
-                    block,
-                    transaction_events,
-                })
-                .await
-                .is_err()
+            if !process_block(
+                metrics,
+                bridge_manager,
+                sender,
+                block,
+                summaries,
+                mapped_tokens,
+            )
+            .await?
             {
-                log::info!("The channel to the database writer has been closed.");
                 return Ok(());
             }
+            health.progress(Task::ConcordiumListener);
             *height = height.next();
         }
         if error {
@@ -619,37 +965,31 @@ async fn listen_concordium_worker(
     }
 }
 
-/// A worker that sends transactions to the Concordium node.
+/// Process the response of submitting a transaction.
+/// Return an error if submitting this transaction failed and this cannot be
+/// recovered.
 ///
-/// The transactions in the channel should be in increasing order of nonces,
-/// otherwise sending will fail.
-pub async fn concordium_tx_sender(
-    metrics: crate::metrics::Metrics,
-    mut client: v2::Client,
-    mut receiver: tokio::sync::mpsc::Receiver<BlockItem<EncodedPayload>>,
-    // Flag to signal stopping the task gracefully.
-    mut stop: tokio::sync::watch::Receiver<()>,
-) -> anyhow::Result<()> {
-    // Process the response.
-    // Return an error if submitting this transaction failed and this cannot be
-    // recovered.
-    //
-    // Otherwise either return Ok(true) if retry should be attempted, or Ok(false).
-    // if submission succeeded.
-    let process_response = |hash, response: v2::RPCResult<TransactionHash>| match response {
+/// Otherwise either return Ok(true) if retry should be attempted, or Ok(false).
+/// if submission succeeded.
+fn process_response(
+    metrics: &crate::metrics::Metrics,
+    hash: TransactionHash,
+    response: v2::RPCResult<TransactionHash>,
+) -> anyhow::Result<bool> {
+    match response {
         Ok(hash) => {
-            log::info!("Transaction {hash} sent to the Concordium node.");
+            tracing::info!("Transaction {hash} sent to the Concordium node.");
             metrics.sent_concordium_transactions.inc();
             Ok(false)
         }
         Err(e) => {
             if e.is_duplicate() {
                 metrics.warnings_total.inc();
-                log::warn!("Transaction {hash} already exists at the node.");
+                tracing::warn!("Transaction {hash} already exists at the node.");
                 Ok(false)
             } else if e.is_invalid_argument() {
                 metrics.errors_total.inc();
-                log::error!(
+                tracing::error!(
                     "Transaction {hash} is not valid for the current state of the node: {e:#}. \
                      Aborting."
                 );
@@ -659,13 +999,75 @@ pub async fn concordium_tx_sender(
                 )
             } else {
                 metrics.warnings_total.inc();
-                log::warn!("Sending transaction to Concordium failed due to {e:#}. Will retry.");
+                tracing::warn!(
+                    "Sending transaction to Concordium failed due to {e:#}. Will retry."
+                );
                 Ok(true)
             }
         }
-    };
+    }
+}
+
+/// Send a single transaction, retrying if the node is unavailable.
+/// Return whether the sender should stop.
+async fn send_transaction(
+    metrics: &crate::metrics::Metrics,
+    client: &mut v2::Client,
+    bi: &BlockItem<EncodedPayload>,
+    lag_guard: &mut crate::chain_lag::LagGuard,
+    health: &Health,
+    stop: &mut tokio::sync::watch::Receiver<()>,
+) -> anyhow::Result<bool> {
+    let hash = bi.hash();
+    if lag_guard.is_behind() {
+        tracing::info!("Holding transaction {hash} until the nodes have caught up.");
+        if !lag_guard.wait_caught_up(stop).await {
+            return Ok(true);
+        }
+    }
+    let retry = process_response(metrics, hash, client.send_block_item(bi).await)?;
+    if retry {
+        health.retrying(Task::ConcordiumSender);
+        // Retry at most 5 times, waiting at most 32 * 5 = 160s
+        for i in 0..6 {
+            // if the stop sender has been dropped we should terminate since that
+            // should be the last thing that happens in the program.
+            if stop.has_changed().unwrap_or(true) {
+                return Ok(true);
+            }
+            let delay = std::time::Duration::from_secs(5 << i);
+            metrics.warnings_total.inc();
+            tracing::warn!(
+                "Waiting for {} seconds before resubmitting {hash}.",
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+            let retry = process_response(metrics, hash, client.send_block_item(bi).await)?;
+            if !retry {
+                return Ok(false);
+            }
+        }
+        anyhow::bail!("Unable to reconnect in 6 attempts.");
+    }
+    Ok(false)
+}
 
-    'outer: while let Some(bi) = tokio::select! {
+/// A worker that sends transactions to the Concordium node.
+///
+/// The transactions in the channel should be in increasing order of nonces,
+/// otherwise sending will fail. Each transaction is sent within the span it is
+/// paired with, so that it can be traced back to the event that caused it.
+pub async fn concordium_tx_sender(
+    metrics: crate::metrics::Metrics,
+    mut client: v2::Client,
+    mut receiver: tokio::sync::mpsc::Receiver<(BlockItem<EncodedPayload>, tracing::Span)>,
+    // Sending is paused while either of the chains is behind.
+    mut lag_guard: crate::chain_lag::LagGuard,
+    health: Health,
+    // Flag to signal stopping the task gracefully.
+    mut stop: tokio::sync::watch::Receiver<()>,
+) -> anyhow::Result<()> {
+    while let Some((bi, span)) = tokio::select! {
         // Make sure to process all events that are in the queue before shutting down.
         // Thus prioritize getting things from the channel.
         // This only works in combination with the fact that we shut down senders
@@ -674,33 +1076,21 @@ pub async fn concordium_tx_sender(
             x = receiver.recv() => x,
             _ = stop.changed() => None,
     } {
-        let hash = bi.hash();
-        let retry = process_response(hash, client.send_block_item(&bi).await)?;
-        if retry {
-            // Retry at most 5 times, waiting at most 32 * 5 = 160s
-            let mut success = false;
-            for i in 0..6 {
-                // if the stop sender has been dropped we should terminate since that
-                // should be the last thing that happens in the program.
-                if stop.has_changed().unwrap_or(true) {
-                    break 'outer;
-                }
-                let delay = std::time::Duration::from_secs(5 << i);
-                metrics.warnings_total.inc();
-                log::warn!(
-                    "Waiting for {} seconds before resubmitting {hash}.",
-                    delay.as_secs()
-                );
-                tokio::time::sleep(delay).await;
-                let retry = process_response(hash, client.send_block_item(&bi).await)?;
-                if !retry {
-                    success = true;
-                    break;
-                }
-            }
-            anyhow::ensure!(success, "Unable to reconnect in 6 attempts.");
+        let stop_sending = send_transaction(
+            &metrics,
+            &mut client,
+            &bi,
+            &mut lag_guard,
+            &health,
+            &mut stop,
+        )
+        .instrument(span)
+        .await?;
+        if stop_sending {
+            break;
         }
+        health.progress(Task::ConcordiumSender);
     }
-    log::info!("Concordium transaction sender terminated.");
+    tracing::info!("Concordium transaction sender terminated.");
     Ok(())
 }
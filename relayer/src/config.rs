@@ -0,0 +1,110 @@
+//! Support for reading the configuration of the relayer from a TOML file, and
+//! for the subset of the configuration that can be changed while the relayer
+//! is running.
+//!
+//! The keys of the configuration file are the long names of the command line
+//! options, e.g.,
+//!
+//! ```toml
+//! max-gas-price = "20000000000"
+//! hold-limit = ["<1234,0>:1000000:", "<1235,0>::5000"]
+//! ```
+//!
+//! Options given on the command line or in the environment take precedence over
+//! the file.
+use anyhow::Context;
+use clap::parser::ValueSource;
+use concordium_rust_sdk::smart_contracts::common::Amount;
+use ethabi::ethereum_types::U256;
+use std::{ffi::OsString, path::PathBuf};
+
+/// Return the command line arguments of the process, extended with the options
+/// set in the configuration file given by the `config_arg` option of `command`,
+/// if any. Options from the file are only added if they are not set on the
+/// command line or in the environment.
+pub fn args_with_config_file(
+    command: clap::Command,
+    config_arg: &str,
+) -> anyhow::Result<Vec<OsString>> {
+    let mut args = std::env::args_os().collect::<Vec<_>>();
+    // Required arguments might only be given in the file, so ignore errors when
+    // looking for the file and the options that are already set.
+    let matches = command
+        .clone()
+        .ignore_errors(true)
+        .get_matches_from(args.clone());
+    let Some(path) = matches.get_one::<PathBuf>(config_arg) else {
+        return Ok(args);
+    };
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read the configuration file {}.", path.display()))?;
+    let table = contents
+        .parse::<toml::Table>()
+        .with_context(|| format!("Unable to parse the configuration file {}.", path.display()))?;
+    let mut file_args = Vec::new();
+    for (key, value) in table {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key.as_str()))
+        else {
+            anyhow::bail!("Unknown option {key} in the configuration file.");
+        };
+        anyhow::ensure!(
+            arg.get_id().as_str() != config_arg,
+            "The configuration file cannot refer to another configuration file."
+        );
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                // Flags do not take a value, they are only given if set.
+                toml::Value::Boolean(b) if !arg.get_action().takes_values() => {
+                    if b {
+                        file_args.push(OsString::from(format!("--{key}")));
+                    }
+                    continue;
+                }
+                toml::Value::String(s) => s,
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => anyhow::bail!("Unsupported value for option {key} in the configuration file."),
+            };
+            file_args.push(OsString::from(format!("--{key}={value}")));
+        }
+    }
+    // Insert the options directly after the program name so that they are not
+    // taken as arguments of a subcommand.
+    let rest = args.split_off(args.len().min(1));
+    args.extend(file_args);
+    args.extend(rest);
+    Ok(args)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The configuration that is reloaded when the relayer receives SIGHUP.
+pub struct Settings {
+    /// Maximum gas price for Merkle root updates.
+    pub max_gas_price:          U256,
+    /// Maximum gas for Merkle root updates.
+    pub max_gas:                U256,
+    /// Minimum time between Merkle root updates.
+    pub merkle_update_interval: std::time::Duration,
+    /// Interval at which the supply invariant is checked.
+    pub supply_check_interval:  std::time::Duration,
+    /// Minimum balance of the Concordium sender account.
+    pub min_concordium_balance: Amount,
+    /// Minimum balance of the Ethereum sender account, in wei.
+    pub min_ethereum_balance:   U256,
+}
+
+/// A handle used by tasks to get the current settings.
+pub type SettingsReceiver = tokio::sync::watch::Receiver<Settings>;
@@ -1,22 +1,28 @@
 use crate::{
+    circuit_breaker::{CircuitBreaker, TripCause},
     concordium_contracts::{self, BridgeEvent, BridgeManager, BridgeManagerClient, WithdrawEvent},
     ethereum,
+    health::{Health, Task},
+    screening::{Screener, ScreeningMatch},
+    withdrawal_holds::HoldPolicy,
 };
 use anyhow::Context;
 use concordium_rust_sdk::{
     cis2,
     common::{self, to_bytes},
+    id::types::AccountAddress,
     smart_contracts::common as contracts_common,
     types::{
         hashes::TransactionHash,
         queries::BlockInfo,
-        transactions::{BlockItem, EncodedPayload, PayloadLike},
-        AbsoluteBlockHeight, ContractAddress, Nonce,
+        transactions::{self, BlockItem, EncodedPayload, PayloadLike},
+        AbsoluteBlockHeight, Amount, ContractAddress, Nonce,
     },
     v2,
 };
 use ethabi::ethereum_types::{H160, H256, U256};
-use num_bigint::BigUint;
+use futures::StreamExt;
+use num_bigint::{BigInt, BigUint};
 use tokio::task::JoinHandle;
 use tokio_postgres::{NoTls, Statement, Transaction};
 
@@ -87,9 +93,66 @@ struct PreparedStatements {
     get_pending_withdrawals:      Statement,
     get_max_event_index:          Statement,
     set_expected_merkle_time:     Statement,
+    get_withdrawal_volume:        Statement,
+    insert_withdrawal_hold:       Statement,
+    insert_screening_match:       Statement,
+    set_pending_root:             Statement,
+    clear_pending_root:           Statement,
+    set_root:                     Statement,
+    notify_deposit:               Statement,
+    notify_withdrawal:            Statement,
+    /// Whether listeners on [`STATUS_CHANNEL`] are notified. See
+    /// [`Database::suppress_notifications`].
+    notify_listeners:             bool,
 }
 
 impl PreparedStatements {
+    /// Notify listeners on [`STATUS_CHANNEL`] that the deposit with the given
+    /// id reached the stage. The notification is delivered when the
+    /// transaction is committed.
+    pub async fn notify_deposit(
+        &self,
+        db_tx: &Transaction<'_>,
+        id: u64,
+        stage: TransferStage,
+        tx_hash: Option<&[u8]>,
+    ) -> anyhow::Result<()> {
+        if !self.notify_listeners {
+            return Ok(());
+        }
+        db_tx
+            .execute(&self.notify_deposit, &[
+                &(id as i64),
+                &stage.as_str(),
+                &tx_hash,
+            ])
+            .await?;
+        Ok(())
+    }
+
+    /// Notify listeners on [`STATUS_CHANNEL`] that the withdrawal with the
+    /// given event index reached the stage. The notification is delivered when
+    /// the transaction is committed.
+    pub async fn notify_withdrawal(
+        &self,
+        db_tx: &Transaction<'_>,
+        event_index: u64,
+        stage: TransferStage,
+        tx_hash: Option<&[u8]>,
+    ) -> anyhow::Result<()> {
+        if !self.notify_listeners {
+            return Ok(());
+        }
+        db_tx
+            .execute(&self.notify_withdrawal, &[
+                &(event_index as i64),
+                &stage.as_str(),
+                &tx_hash,
+            ])
+            .await?;
+        Ok(())
+    }
+
     /// Insert a Concordium transaction to the database.
     pub async fn insert_concordium_tx<'a, 'b, Payload: PayloadLike>(
         &'a self,
@@ -98,7 +161,7 @@ impl PreparedStatements {
         bi: &BlockItem<Payload>,
     ) -> anyhow::Result<i64> {
         let hash = bi.hash();
-        log::debug!(
+        tracing::debug!(
             "Inserting Concordium transaction with hash {hash} in response to {origin_tx_hash:#x}"
         );
         let timestamp = chrono::Utc::now().timestamp();
@@ -117,6 +180,10 @@ impl PreparedStatements {
 
     /// Insert the event. If the event is a Withdraw event
     /// return whether it has already been processed or not.
+    /// The latency of completed deposits is added to `deposit_latencies`.
+    /// In read-only mode the transactions emitting the events are not expected
+    /// to be in the database.
+    #[allow(clippy::too_many_arguments)]
     pub async fn insert_concordium_event<'a, 'b>(
         &'a self,
         metrics: &crate::metrics::Metrics,
@@ -124,8 +191,12 @@ impl PreparedStatements {
         tx_hash: &TransactionHash,
         event: &BridgeEvent,
         merkle_hash: Option<[u8; 32]>,
+        block_time: chrono::DateTime<chrono::Utc>,
+        read_only: bool,
+        unmatched_deposits: &mut Vec<TripCause>,
+        deposit_latencies: &mut Vec<chrono::Duration>,
     ) -> anyhow::Result<bool> {
-        log::debug!("Inserting Concordium event for transaction {tx_hash}.");
+        tracing::debug!("Inserting Concordium event for transaction {tx_hash}.");
         let (event_type, origin_event_index, data) = match event {
             BridgeEvent::TokenMap(tm) => {
                 let rows = db_tx
@@ -134,9 +205,9 @@ impl PreparedStatements {
                         &TransactionStatus::Finalized,
                     ])
                     .await?;
-                if rows.len() != 1 {
+                if rows.len() != 1 && !read_only {
                     metrics.warnings_total.inc();
-                    log::warn!(
+                    tracing::warn!(
                         "A TokenMap event was emitted by a transaction not submitted by the \
                          relayer."
                     );
@@ -149,17 +220,39 @@ impl PreparedStatements {
             }
             BridgeEvent::Deposit(de) => {
                 metrics.num_completed_deposits.inc();
-                log::debug!("Marking a deposit with event index {} as completed.", de.id);
+                tracing::debug!(
+                    ccd_tx_hash = %tx_hash,
+                    deposit_id = de.id,
+                    "Marking a deposit with event index {} as completed.",
+                    de.id
+                );
                 let rows = db_tx
                     .query(
                         "UPDATE ethereum_deposit_events SET tx_hash = $2 WHERE origin_event_index \
-                         = $1 RETURNING id",
+                         = $1 RETURNING id, block_time",
                         &[&(de.id as i64), &tx_hash.as_ref()],
                     )
                     .await?;
-                if rows.len() != 1 {
+                if let [row] = &rows[..] {
+                    if let Some(deposit_time) =
+                        row.try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("block_time")?
+                    {
+                        deposit_latencies.push(block_time - deposit_time);
+                    }
+                    self.notify_deposit(
+                        db_tx,
+                        de.id,
+                        TransferStage::ConcordiumFinalized,
+                        Some(tx_hash.as_ref()),
+                    )
+                    .await?;
+                } else {
                     metrics.warnings_total.inc();
-                    log::warn!("Deposited an event that was not emitted on Ethereum.");
+                    tracing::warn!("Deposited an event that was not emitted on Ethereum.");
+                    unmatched_deposits.push(TripCause::UnmatchedDeposit {
+                        tx_hash:            *tx_hash,
+                        origin_event_index: de.id,
+                    });
                 }
                 let rows = db_tx
                     .query(&self.mark_concordium_tx, &[
@@ -167,9 +260,9 @@ impl PreparedStatements {
                         &TransactionStatus::Finalized,
                     ])
                     .await?;
-                if rows.len() != 1 {
+                if rows.len() != 1 && !read_only {
                     metrics.warnings_total.inc();
-                    log::warn!(
+                    tracing::warn!(
                         "A deposit event was emitted by a transaction not submitted by the \
                          relayer."
                     );
@@ -181,7 +274,9 @@ impl PreparedStatements {
                 )
             }
             BridgeEvent::Withdraw(we) => {
-                log::debug!(
+                tracing::debug!(
+                    ccd_tx_hash = %tx_hash,
+                    event_index = we.event_index,
                     "Inserting new withdrawal event with event index {}.",
                     we.event_index,
                 );
@@ -197,8 +292,17 @@ impl PreparedStatements {
                         &Some(&we.amount.to_string()),
                         &contracts_common::to_bytes(we),
                         &merkle_hash.as_ref().map(|x| &x[..]),
+                        &block_time,
+                        &withdrawal_account(we).map(|a| &a.0[..]),
                     ])
                     .await?;
+                self.notify_withdrawal(
+                    db_tx,
+                    we.event_index,
+                    TransferStage::ConcordiumFinalized,
+                    Some(tx_hash.as_ref()),
+                )
+                .await?;
                 return Ok(res.get::<_, bool>(0));
             }
             BridgeEvent::GrantRole(gr) => (
@@ -224,10 +328,207 @@ impl PreparedStatements {
                 &None::<String>,
                 &data,
                 &merkle_hash.as_ref().map(|x| &x[..]),
+                &block_time,
+                &None::<Vec<u8>>,
             ])
             .await?;
         Ok(res.get::<_, bool>(0))
     }
+
+    /// Record the addresses involved in an operation that matched a screening
+    /// list.
+    pub async fn insert_screening_matches<'a, 'b>(
+        &'a self,
+        db_tx: &Transaction<'b>,
+        operation: ScreenedOperation,
+        event_index: u64,
+        matches: &[ScreeningMatch],
+    ) -> anyhow::Result<()> {
+        for m in matches {
+            db_tx
+                .query_one(&self.insert_screening_match, &[
+                    &operation,
+                    &(event_index as i64),
+                    &m.network,
+                    &m.address,
+                ])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the withdrawal involves a screened address or exceeds the
+    /// configured limits, and if so record a hold for it. Return whether the
+    /// withdrawal is held. The window of the limits ends at `block_time`, the
+    /// time of the block containing the withdrawal.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn hold_withdrawal<'a, 'b>(
+        &'a self,
+        db_tx: &Transaction<'b>,
+        policy: &HoldPolicy,
+        screener: &Screener,
+        we: &WithdrawEvent,
+        block_time: chrono::DateTime<chrono::Utc>,
+        new_holds: &mut Vec<(u64, WithdrawalHoldReason)>,
+        screened: &mut Vec<(u64, Vec<ScreeningMatch>)>,
+    ) -> anyhow::Result<bool> {
+        let matches = screener.screen_withdrawal(we);
+        if !matches.is_empty() {
+            self.insert_screening_matches(
+                db_tx,
+                ScreenedOperation::Withdrawal,
+                we.event_index,
+                &matches,
+            )
+            .await?;
+            // Screened withdrawals are only released by the operator.
+            db_tx
+                .query_one(&self.insert_withdrawal_hold, &[
+                    &(we.event_index as i64),
+                    &WithdrawalHoldReason::Screening,
+                    &None::<chrono::DateTime<chrono::Utc>>,
+                ])
+                .await?;
+            new_holds.push((we.event_index, WithdrawalHoldReason::Screening));
+            screened.push((we.event_index, matches));
+            return Ok(true);
+        }
+        let Some(limit) = policy.limit(we.contract) else {
+            return Ok(false);
+        };
+        let window_start = block_time - policy.window;
+        let volume = db_tx
+            .query_one(&self.get_withdrawal_volume, &[
+                &(we.contract.index as i64),
+                &(we.contract.subindex as i64),
+                &window_start,
+                &(we.event_index as i64),
+            ])
+            .await?
+            .try_get::<_, String>(0)?
+            .parse()
+            .context("Withdrawn volume is not an integer.")?;
+        let Some(reason) = limit.check(&we.amount.0, &volume) else {
+            return Ok(false);
+        };
+        db_tx
+            .query_one(&self.insert_withdrawal_hold, &[
+                &(we.event_index as i64),
+                &reason,
+                &policy.release_time(),
+            ])
+            .await?;
+        new_holds.push((we.event_index, reason));
+        Ok(true)
+    }
+}
+
+/// Connect to the database, using TLS if the SSL mode of the configuration
+/// asks for it. The returned handle drives the connection. This does not
+/// create the schema, see [`Database::new`].
+pub async fn connect(
+    config: &tokio_postgres::Config,
+) -> anyhow::Result<(
+    tokio_postgres::Client,
+    JoinHandle<Result<(), tokio_postgres::Error>>,
+)> {
+    if uses_tls(config) {
+        let (client, connection) = config.connect(make_tls_connect()?).await?;
+        Ok((client, tokio::spawn(connection)))
+    } else {
+        let (client, connection) = config.connect(NoTls).await?;
+        Ok((client, tokio::spawn(connection)))
+    }
+}
+
+/// Whether the SSL mode of the configuration asks for TLS.
+fn uses_tls(config: &tokio_postgres::Config) -> bool {
+    matches!(
+        config.get_ssl_mode(),
+        tokio_postgres::config::SslMode::Prefer | tokio_postgres::config::SslMode::Require
+    )
+}
+
+/// Construct a TLS connector that trusts the native root certificates.
+fn make_tls_connect() -> anyhow::Result<tokio_postgres_rustls::MakeRustlsConnect> {
+    let mut root_certs = rustls::RootCertStore::empty();
+    for cert in rustls_native_certs::load_native_certs().context("Unable to load certificates")? {
+        root_certs.add(&rustls::Certificate(cert.0))?;
+    }
+    let tls_config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_certs)
+        .with_no_client_auth();
+    Ok(tokio_postgres_rustls::MakeRustlsConnect::new(tls_config))
+}
+
+/// How long to wait before reconnecting when the connection used for listening
+/// to admin requests is lost.
+const ADMIN_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Listen to the requests made through the admin API of the API server, and
+/// wake the Merkle root sender through `merkle_update` when an immediate update
+/// is requested. The connection is re-established if it is lost.
+pub async fn listen_admin_requests(
+    config: tokio_postgres::Config,
+    merkle_update: std::sync::Arc<tokio::sync::Notify>,
+) {
+    loop {
+        if let Err(e) = listen_admin_connection(&config, &merkle_update).await {
+            tracing::warn!("Error listening to admin requests: {e:#}.");
+        }
+        tokio::time::sleep(ADMIN_RECONNECT_DELAY).await;
+    }
+}
+
+async fn listen_admin_connection(
+    config: &tokio_postgres::Config,
+    merkle_update: &std::sync::Arc<tokio::sync::Notify>,
+) -> anyhow::Result<()> {
+    let mut config = config.clone();
+    config.application_name(ADMIN_LISTENER_APPLICATION_NAME);
+    if uses_tls(&config) {
+        let (client, connection) = config.connect(make_tls_connect()?).await?;
+        forward_admin_requests(client, connection, merkle_update).await
+    } else {
+        let (client, connection) = config.connect(NoTls).await?;
+        forward_admin_requests(client, connection, merkle_update).await
+    }
+}
+
+/// Listen on [`ADMIN_CHANNEL`] until the connection is closed.
+async fn forward_admin_requests<S, T>(
+    client: tokio_postgres::Client,
+    mut connection: tokio_postgres::Connection<S, T>,
+    merkle_update: &std::sync::Arc<tokio::sync::Notify>,
+) -> anyhow::Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static, {
+    let merkle_update = merkle_update.clone();
+    // The connection must be polled for the LISTEN command to complete, so the
+    // messages are handled in a separate task.
+    let messages = tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if let tokio_postgres::AsyncMessage::Notification(notification) = message? {
+                match notification.payload() {
+                    MERKLE_UPDATE_REQUEST => {
+                        tracing::info!("An immediate Merkle root update was requested.");
+                        merkle_update.notify_one();
+                    }
+                    other => tracing::warn!("Unknown admin request {other}."),
+                }
+            }
+        }
+        Ok::<(), tokio_postgres::Error>(())
+    });
+    client
+        .batch_execute(&format!("LISTEN {ADMIN_CHANNEL}"))
+        .await?;
+    tracing::info!("Listening to admin requests.");
+    messages.await??;
+    anyhow::bail!("The database connection was closed.")
 }
 
 pub struct Database {
@@ -243,28 +544,40 @@ impl Database {
         match self.connection_handle.await {
             Ok(v) => {
                 if let Err(e) = v {
-                    log::error!("Database connection task terminated with an error {e:#}.");
+                    tracing::error!("Database connection task terminated with an error {e:#}.");
                 }
             }
             Err(e) => {
                 if !e.is_cancelled() {
-                    log::error!("Error {e:#} shutting down database connection task.");
+                    tracing::error!("Error {e:#} shutting down database connection task.");
                 }
             }
         }
     }
 }
 
+/// A finalized Concordium block together with the bridge manager events in
+/// each of its transactions.
+pub type ConcordiumBlockEvents = (BlockInfo, Vec<(TransactionHash, Vec<BridgeEvent>)>);
+
+/// The maximum number of Concordium blocks the database worker writes in one
+/// transaction.
+const MAX_CONCORDIUM_BATCH: usize = 100;
+
+/// How long the database worker waits for more Concordium blocks to write in
+/// the same transaction.
+const CONCORDIUM_BATCH_WAIT: std::time::Duration = std::time::Duration::from_millis(100);
+
 #[derive(Debug)]
 /// Operations supported by the database client.
 /// All database access is done by a single worker which communicates with other
 /// tasks in the relayer via channels.
 pub enum DatabaseOperation {
     ConcordiumEvents {
-        /// Events are from this block.
-        block:              BlockInfo,
-        /// Events for the given transactions.
-        transaction_events: Vec<(TransactionHash, Vec<BridgeEvent>)>,
+        /// Consecutive blocks with their events. The database worker merges
+        /// consecutive operations of this kind, so that the blocks are
+        /// written in one transaction.
+        blocks: Vec<ConcordiumBlockEvents>,
     },
     EthereumEvents {
         /// Insert these Ethereum events.
@@ -276,6 +589,12 @@ pub enum DatabaseOperation {
         /// With the given status.
         state:   TransactionStatus,
     },
+    /// Record the fees paid for transactions sent by the relayer account, and
+    /// count them in the metrics. Only transactions the relayer submitted
+    /// itself, and whose fee is not yet recorded, are counted.
+    RecordConcordiumFees {
+        fees: Vec<(TransactionHash, Amount)>,
+    },
     GetPendingConcordiumTransactions {
         /// Look up the pending Concordium transactions and write the values in
         /// the given channel.
@@ -313,6 +632,92 @@ pub enum DatabaseOperation {
     SetNextMerkleUpdateTime {
         next_time: chrono::DateTime<chrono::Utc>,
     },
+    /// Get the amounts needed for checking the supply invariant.
+    GetSupplySnapshot {
+        /// The channel where the snapshot is written. [`None`] is written if
+        /// the relayer has not yet processed any blocks on either chain.
+        response: tokio::sync::oneshot::Sender<Option<SupplySnapshot>>,
+    },
+    /// Record discrepancies found when checking the supply invariant.
+    InsertSupplyDiscrepancies {
+        discrepancies: Vec<SupplyDiscrepancy>,
+    },
+    /// Record a trip of the circuit breaker.
+    InsertCircuitBreakerTrip {
+        trigger:       CircuitBreakerTrigger,
+        /// Description of the cause.
+        cause:         String,
+        /// Hash of the transaction that paused the bridge manager, if any.
+        pause_tx_hash: Option<TransactionHash>,
+    },
+    /// Check whether the given Merkle root has been set, or is being set, by
+    /// the relayer.
+    IsKnownMerkleRoot {
+        root:     [u8; 32],
+        response: tokio::sync::oneshot::Sender<bool>,
+    },
+    /// Record a Merkle root that was set by another relayer, in read-only
+    /// mode.
+    RecordMerkleRoot {
+        root: [u8; 32],
+        /// The event indices that are part of the Merkle root.
+        ids:  Vec<u64>,
+    },
+    /// Release held withdrawals that are past their release time or have been
+    /// approved by the operator, and add them to the Merkle tree.
+    ReleaseWithdrawalHolds,
+    /// Send the held deposits that have been approved by the operator to
+    /// Concordium.
+    ReleaseDepositHolds,
+    /// Update the metrics for the age of the oldest pending item in each
+    /// queue.
+    UpdatePendingAges,
+}
+
+/// The part of the supply invariant that is known from the database. All the
+/// values are read in a single database transaction so that they are
+/// consistent with the checkpoints.
+#[derive(Debug)]
+pub struct SupplySnapshot {
+    /// The last Ethereum block that has been processed.
+    pub ethereum_height:   u64,
+    /// The last Concordium block that has been processed.
+    pub concordium_height: AbsoluteBlockHeight,
+    /// The mapped tokens.
+    pub tokens:            Vec<TokenSupplySnapshot>,
+}
+
+/// Amounts of a single mapped token that are in transit between the chains.
+#[derive(Debug)]
+pub struct TokenSupplySnapshot {
+    /// Address of the token on Ethereum.
+    pub root:                H160,
+    /// Address of the token on Concordium.
+    pub child:               ContractAddress,
+    /// Name of the token on Ethereum.
+    pub name:                String,
+    /// Number of decimals of the token.
+    pub decimals:            u8,
+    /// Sum of withdrawals done on Concordium but not yet completed on
+    /// Ethereum.
+    pub pending_withdrawals: BigUint,
+    /// Sum of deposits done on Ethereum but not yet completed on Concordium.
+    pub pending_deposits:    BigUint,
+}
+
+/// A violation of the supply invariant for a single token.
+#[derive(Debug, Clone)]
+pub struct SupplyDiscrepancy {
+    pub root:                H160,
+    pub child:               ContractAddress,
+    pub ethereum_height:     u64,
+    pub concordium_height:   AbsoluteBlockHeight,
+    pub vault_balance:       BigUint,
+    pub concordium_supply:   BigUint,
+    pub pending_withdrawals: BigUint,
+    pub pending_deposits:    BigUint,
+    /// The vault balance minus the amount accounted for on Concordium.
+    pub delta:               BigInt,
 }
 
 /// A pending Ethereum transaction stored in the Database.
@@ -325,6 +730,58 @@ pub struct PendingEthereumTransactions {
     pub idxs: std::sync::Arc<[u64]>,
 }
 
+#[derive(Debug, Clone, Copy, tokio_postgres::types::ToSql, tokio_postgres::types::FromSql)]
+#[postgres(name = "circuit_breaker_trigger")]
+pub enum CircuitBreakerTrigger {
+    #[postgres(name = "supply_invariant")]
+    SupplyInvariant,
+    #[postgres(name = "unknown_merkle_root")]
+    UnknownMerkleRoot,
+    #[postgres(name = "unmatched_deposit")]
+    UnmatchedDeposit,
+    #[postgres(name = "withdrawal_volume")]
+    WithdrawalVolume,
+}
+
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    tokio_postgres::types::ToSql,
+    tokio_postgres::types::FromSql,
+    utoipa::ToSchema,
+)]
+#[postgres(name = "withdrawal_hold_reason")]
+#[derive(serde::Serialize, serde::Deserialize)]
+/// The reason why a withdrawal is held back from the Merkle tree.
+pub enum WithdrawalHoldReason {
+    /// The withdrawal exceeds the limit on a single withdrawal of the token.
+    #[postgres(name = "single_limit")]
+    #[serde(rename = "single_limit")]
+    #[schema(rename = "single_limit")]
+    SingleLimit,
+    /// The withdrawal would exceed the limit on the amount of the token
+    /// withdrawn within the window.
+    #[postgres(name = "window_limit")]
+    #[serde(rename = "window_limit")]
+    #[schema(rename = "window_limit")]
+    WindowLimit,
+    /// The sender or the receiver of the withdrawal is on a screening list.
+    #[postgres(name = "screening")]
+    #[serde(rename = "screening")]
+    #[schema(rename = "screening")]
+    Screening,
+}
+
+#[derive(Debug, Copy, Clone, tokio_postgres::types::ToSql, tokio_postgres::types::FromSql)]
+#[postgres(name = "screened_operation")]
+pub enum ScreenedOperation {
+    #[postgres(name = "deposit")]
+    Deposit,
+    #[postgres(name = "withdrawal")]
+    Withdrawal,
+}
+
 #[derive(Debug, tokio_postgres::types::ToSql, tokio_postgres::types::FromSql)]
 #[postgres(name = "concordium_event_type")]
 pub enum ConcordiumEventType {
@@ -340,35 +797,329 @@ pub enum ConcordiumEventType {
     RevokeRole,
 }
 
+/// The channel on which the relayer notifies listeners of the progress of
+/// deposits and withdrawals.
+pub const STATUS_CHANNEL: &str = "bridge_status";
+
+/// The channel on which the admin API of the API server makes requests to the
+/// relayer.
+pub const ADMIN_CHANNEL: &str = "bridge_admin";
+
+/// The payload of a notification on [`ADMIN_CHANNEL`] that asks for a new
+/// Merkle root to be set without waiting for the update interval.
+pub const MERKLE_UPDATE_REQUEST: &str = "merkle_update";
+
+/// The application name of the connection on which the relayer listens on
+/// [`ADMIN_CHANNEL`], so that the API server can tell whether it is listening.
+pub const ADMIN_LISTENER_APPLICATION_NAME: &str = "ccdeth_relayer_admin_listener";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// The kind of transfer a [`StatusNotification`] is about.
+pub enum TransferKind {
+    #[serde(rename = "deposit")]
+    Deposit,
+    #[serde(rename = "withdraw")]
+    Withdraw,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// The stages of a transfer that are notified on [`STATUS_CHANNEL`].
+pub enum TransferStage {
+    /// The deposit was seen on Ethereum.
+    #[serde(rename = "ethereum_seen")]
+    EthereumSeen,
+    /// The transaction completing the deposit was submitted to Concordium.
+    #[serde(rename = "concordium_submitted")]
+    ConcordiumSubmitted,
+    /// The deposit was completed, or the withdrawal was made, in a finalized
+    /// block on Concordium.
+    #[serde(rename = "concordium_finalized")]
+    ConcordiumFinalized,
+    /// The withdrawal was included in a Merkle root set on Ethereum.
+    #[serde(rename = "merkle_root")]
+    MerkleRoot,
+    /// The withdrawal was claimed on Ethereum.
+    #[serde(rename = "claimed")]
+    Claimed,
+}
+
+impl TransferStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferStage::EthereumSeen => "ethereum_seen",
+            TransferStage::ConcordiumSubmitted => "concordium_submitted",
+            TransferStage::ConcordiumFinalized => "concordium_finalized",
+            TransferStage::MerkleRoot => "merkle_root",
+            TransferStage::Claimed => "claimed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// The payload of a notification on [`STATUS_CHANNEL`]. Hashes and addresses
+/// are hex encoded.
+pub struct StatusNotification {
+    pub kind:           TransferKind,
+    pub stage:          TransferStage,
+    /// Hash of the transaction that initiated the transfer, on Ethereum for
+    /// deposits and on Concordium for withdrawals.
+    pub origin_tx_hash: String,
+    /// The id of the deposit, or the event index of the withdrawal.
+    pub event_index:    u64,
+    /// The depositor, or the receiver of the withdrawal, on Ethereum.
+    pub wallet:         String,
+    /// The receiver of the deposit, or the account that initiated the
+    /// withdrawal, on Concordium. Not known for deposits inserted before it
+    /// was recorded, and for withdrawals initiated by contracts.
+    pub account:        Option<String>,
+    /// Hash of the transaction that made the transfer reach the stage, if any.
+    pub tx_hash:        Option<String>,
+    /// The Merkle root that includes the withdrawal, if any.
+    pub merkle_root:    Option<String>,
+}
+
+/// The account that initiated the withdrawal, or [`None`] if it was a
+/// contract.
+fn withdrawal_account(we: &WithdrawEvent) -> Option<&contracts_common::AccountAddress> {
+    match &we.ccd_address {
+        contracts_common::Address::Account(account) => Some(account),
+        contracts_common::Address::Contract(_) => None,
+    }
+}
+
+/// Name of the backfill of `ccd_address` in the `backfills` table.
+const WITHDRAWAL_ACCOUNTS_BACKFILL: &str = "withdrawal_accounts";
+
+/// Name of the backfill of `deposit_receiver` in the `backfills` table.
+const DEPOSIT_RECEIVERS_BACKFILL: &str = "deposit_receivers";
+
+/// Name of the backfill of the `root` of Ethereum transactions in the
+/// `backfills` table.
+const ETHEREUM_TX_ROOTS_BACKFILL: &str = "ethereum_transaction_roots";
+
+/// Whether the backfill with the given name has been completed.
+async fn backfill_completed(
+    client: &impl tokio_postgres::GenericClient,
+    name: &str,
+) -> anyhow::Result<bool> {
+    let row = client
+        .query_opt("SELECT 1 FROM backfills WHERE name = $1", &[&name])
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Set the `ccd_address` of withdraw events that were inserted before the
+/// column was added, from the serialized event. Withdrawals initiated by a
+/// contract have no account, so the backfill is recorded in `backfills` and
+/// only done once.
+async fn backfill_withdrawal_accounts(client: &mut tokio_postgres::Client) -> anyhow::Result<()> {
+    let db_tx = client.transaction().await?;
+    if backfill_completed(&db_tx, WITHDRAWAL_ACCOUNTS_BACKFILL).await? {
+        return Ok(());
+    }
+    let rows = db_tx
+        .query(
+            "SELECT id, event_data FROM concordium_events WHERE event_type = 'withdraw' AND \
+             ccd_address IS NULL",
+            &[],
+        )
+        .await?;
+    let mut num_updated = 0;
+    for row in rows {
+        let id = row.try_get::<_, i64>("id")?;
+        let data = row.try_get::<_, Vec<u8>>("event_data")?;
+        let we: WithdrawEvent = contracts_common::from_bytes(&data)
+            .map_err(|_| anyhow::anyhow!("Unable to parse the withdraw event with id {id}."))?;
+        if let Some(account) = withdrawal_account(&we) {
+            db_tx
+                .execute(
+                    "UPDATE concordium_events SET ccd_address = $2 WHERE id = $1",
+                    &[&id, &&account.0[..]],
+                )
+                .await?;
+            num_updated += 1;
+        }
+    }
+    db_tx
+        .execute("INSERT INTO backfills (name) VALUES ($1)", &[
+            &WITHDRAWAL_ACCOUNTS_BACKFILL,
+        ])
+        .await?;
+    db_tx.commit().await?;
+    if num_updated > 0 {
+        tracing::info!("Recorded the account of {num_updated} existing withdraw events.");
+    }
+    Ok(())
+}
+
+/// Set the `root` of Ethereum transactions that were inserted before the
+/// column was added. The transactions are signed `setMerkleRoot` calls, so
+/// the root follows the selector of the function in the transaction.
+async fn backfill_ethereum_tx_roots(client: &mut tokio_postgres::Client) -> anyhow::Result<()> {
+    use ethers::contract::EthCall;
+    let db_tx = client.transaction().await?;
+    if backfill_completed(&db_tx, ETHEREUM_TX_ROOTS_BACKFILL).await? {
+        return Ok(());
+    }
+    let selector = crate::root_chain_manager::SetMerkleRootCall::selector();
+    let num_updated = db_tx
+        .execute(
+            "UPDATE ethereum_transactions SET root = substring(tx FROM position($1 IN tx) + 4 FOR \
+             32) WHERE root IS NULL AND position($1 IN tx) > 0",
+            &[&&selector[..]],
+        )
+        .await?;
+    db_tx
+        .execute("INSERT INTO backfills (name) VALUES ($1)", &[
+            &ETHEREUM_TX_ROOTS_BACKFILL,
+        ])
+        .await?;
+    db_tx.commit().await?;
+    if num_updated > 0 {
+        tracing::info!("Recorded the Merkle root of {num_updated} existing Ethereum transactions.");
+    }
+    Ok(())
+}
+
+/// The state update sent to the bridge manager in the transaction, if any.
+fn state_update(tx: &BlockItem<EncodedPayload>) -> Option<concordium_contracts::StateUpdate> {
+    let BlockItem::AccountTransaction(at) = tx else {
+        return None;
+    };
+    let Ok(transactions::Payload::Update { payload }) = at.payload.decode() else {
+        return None;
+    };
+    contracts_common::from_bytes(payload.message.as_ref()).ok()
+}
+
+/// Set the `deposit_receiver` of deposits that were inserted before the column
+/// was added, from the deposit operation in the Concordium transaction that
+/// completes the deposit, or in the hold of the deposit. The remaining
+/// deposits, which the relayer has not sent, are backfilled from Ethereum, see
+/// [`missing_deposit_receivers`].
+async fn backfill_deposit_receivers(client: &tokio_postgres::Client) -> anyhow::Result<()> {
+    if backfill_completed(client, DEPOSIT_RECEIVERS_BACKFILL).await? {
+        return Ok(());
+    }
+    let rows = client
+        .query(
+            "SELECT de.id, de.origin_event_index, ct.tx, NULL::BYTEA AS deposit FROM \
+             ethereum_deposit_events de JOIN concordium_transactions ct ON ct.origin_tx_hash = \
+             de.origin_tx_hash WHERE de.deposit_receiver IS NULL UNION ALL SELECT de.id, \
+             de.origin_event_index, NULL::BYTEA AS tx, dh.deposit FROM ethereum_deposit_events de \
+             JOIN deposit_holds dh ON dh.origin_event_index = de.origin_event_index WHERE \
+             de.deposit_receiver IS NULL",
+            &[],
+        )
+        .await?;
+    let mut num_updated = 0;
+    for row in rows {
+        let id = row.try_get::<_, i64>("id")?;
+        let origin_event_index = row.try_get::<_, i64>("origin_event_index")? as u64;
+        let deposit = if let Some(tx) = row.try_get::<_, Option<Vec<u8>>>("tx")? {
+            let tx: BlockItem<EncodedPayload> = common::from_bytes(&mut &tx[..])?;
+            match state_update(&tx) {
+                Some(concordium_contracts::StateUpdate::Deposit(deposit)) => Some(deposit),
+                _ => None,
+            }
+        } else {
+            let data = row.try_get::<_, Vec<u8>>("deposit")?;
+            Some(contracts_common::from_bytes(&data).map_err(|_| {
+                anyhow::anyhow!("Unable to parse the held deposit with id {origin_event_index}.")
+            })?)
+        };
+        // Transactions sent for other deposits of the same Ethereum transaction
+        // are skipped.
+        let Some(deposit) = deposit.filter(|d| d.id == origin_event_index) else {
+            continue;
+        };
+        let contracts_common::Address::Account(receiver) = deposit.user else {
+            continue;
+        };
+        num_updated += client
+            .execute(
+                "UPDATE ethereum_deposit_events SET deposit_receiver = $2 WHERE id = $1 AND \
+                 deposit_receiver IS NULL",
+                &[&id, &&receiver.0[..]],
+            )
+            .await?;
+    }
+    if num_updated > 0 {
+        tracing::info!("Recorded the receiver of {num_updated} existing deposits.");
+    }
+    Ok(())
+}
+
+/// Get the deposits whose receiver is not recorded, as the id, the hash of the
+/// Ethereum transaction, and the id of the deposit on Ethereum, or [`None`] if
+/// the receivers have already been backfilled. See [`set_deposit_receivers`].
+pub async fn missing_deposit_receivers(
+    client: &tokio_postgres::Client,
+) -> anyhow::Result<Option<Vec<(i64, H256, u64)>>> {
+    if backfill_completed(client, DEPOSIT_RECEIVERS_BACKFILL).await? {
+        return Ok(None);
+    }
+    let rows = client
+        .query(
+            "SELECT id, origin_tx_hash, origin_event_index FROM ethereum_deposit_events WHERE \
+             deposit_receiver IS NULL ORDER BY id ASC",
+            &[],
+        )
+        .await?;
+    let mut missing = Vec::with_capacity(rows.len());
+    for row in rows {
+        missing.push((
+            row.try_get::<_, i64>("id")?,
+            H256::from_slice(&row.try_get::<_, Vec<u8>>("origin_tx_hash")?),
+            row.try_get::<_, i64>("origin_event_index")? as u64,
+        ));
+    }
+    Ok(Some(missing))
+}
+
+/// Record the receivers of deposits that were missing. If `completed` is set
+/// the backfill of the receivers is also recorded as completed, so that it is
+/// not attempted again.
+pub async fn set_deposit_receivers(
+    client: &mut tokio_postgres::Client,
+    receivers: &[(i64, AccountAddress)],
+    completed: bool,
+) -> anyhow::Result<()> {
+    let db_tx = client.transaction().await?;
+    for (id, receiver) in receivers {
+        db_tx
+            .execute(
+                "UPDATE ethereum_deposit_events SET deposit_receiver = $2 WHERE id = $1",
+                &[id, &&receiver.0[..]],
+            )
+            .await?;
+    }
+    if completed {
+        db_tx
+            .execute("INSERT INTO backfills (name) VALUES ($1)", &[
+                &DEPOSIT_RECEIVERS_BACKFILL,
+            ])
+            .await?;
+    }
+    db_tx.commit().await?;
+    if !receivers.is_empty() {
+        tracing::info!(
+            "Recorded the receiver of {} existing deposits.",
+            receivers.len()
+        );
+    }
+    Ok(())
+}
+
 impl Database {
     pub async fn new(
         config: &tokio_postgres::Config,
     ) -> anyhow::Result<(Option<u64>, Option<AbsoluteBlockHeight>, Self)> {
-        let (client, connection_handle) = {
-            match config.get_ssl_mode() {
-                tokio_postgres::config::SslMode::Prefer
-                | tokio_postgres::config::SslMode::Require => {
-                    let mut root_certs = rustls::RootCertStore::empty();
-                    for cert in rustls_native_certs::load_native_certs()
-                        .context("Unable to load certificates")?
-                    {
-                        root_certs.add(&rustls::Certificate(cert.0))?;
-                    }
-                    let tls_config = rustls::ClientConfig::builder()
-                        .with_safe_defaults()
-                        .with_root_certificates(root_certs)
-                        .with_no_client_auth();
-                    let tls = tokio_postgres_rustls::MakeRustlsConnect::new(tls_config);
-                    let (client, connection) = config.connect(tls).await?;
-                    (client, tokio::spawn(connection))
-                }
-                _ => {
-                    let (client, connection) = config.connect(NoTls).await?;
-                    (client, tokio::spawn(connection))
-                }
-            }
-        };
+        let (mut client, connection_handle) = connect(config).await?;
         client.batch_execute(SCHEMA).await?;
+        backfill_withdrawal_accounts(&mut client).await?;
+        backfill_deposit_receivers(&client).await?;
+        backfill_ethereum_tx_roots(&mut client).await?;
         let insert_concordium_tx = client
             .prepare(
                 "INSERT INTO concordium_transactions (tx_hash, tx, origin_tx_hash, timestamp, \
@@ -394,16 +1145,16 @@ WHERE status = 'pending' ORDER BY id ASC;",
             .await?;
         let insert_ethereum_tx = client
             .prepare(
-                "INSERT INTO ethereum_transactions (tx_hash, tx, timestamp, status)
-VALUES ($1, $2, $3, $4) RETURNING id",
+                "INSERT INTO ethereum_transactions (tx_hash, tx, timestamp, status, root)
+VALUES ($1, $2, $3, $4, $5) RETURNING id",
             )
             .await?;
         let insert_concordium_event = client
             .prepare(
                 "INSERT INTO concordium_events (tx_hash, event_index, origin_event_index, \
                  event_type, child_index, child_subindex, receiver, amount, event_data, \
-                 event_merkle_hash, processed)
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                 event_merkle_hash, block_time, ccd_address, processed)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
         (CASE WHEN $4 = ('withdraw' :: concordium_event_type)
               THEN (SELECT tx_hash FROM ethereum_withdraw_events
                     WHERE ethereum_withdraw_events.origin_event_index = $2
@@ -415,14 +1166,19 @@ RETURNING (CASE WHEN processed IS NULL THEN FALSE ELSE TRUE END)",
 
         let mark_withdrawal_as_completed = client
             .prepare(
-                "UPDATE concordium_events SET processed = $1 WHERE event_index = $2 RETURNING id;",
+                "UPDATE concordium_events SET processed = $1 WHERE event_index = $2 RETURNING id, \
+                 block_time;",
             )
             .await?;
 
         let get_pending_withdrawals = client
             .prepare(
                 "SELECT tx_hash, event_index, event_data FROM concordium_events
-WHERE (processed IS NULL) AND event_type = 'withdraw' ORDER BY id ASC;",
+WHERE (processed IS NULL) AND event_type = 'withdraw'
+AND NOT EXISTS (SELECT 1 FROM withdrawal_holds
+                WHERE withdrawal_holds.event_index = concordium_events.event_index
+                AND withdrawal_holds.released_time IS NULL)
+ORDER BY id ASC;",
             )
             .await?;
         let get_max_event_index = client
@@ -435,6 +1191,67 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
 ",
             )
             .await?;
+        let get_withdrawal_volume = client
+            .prepare(
+                "SELECT COALESCE(SUM(amount::NUMERIC), 0)::TEXT FROM concordium_events
+WHERE event_type = 'withdraw' AND child_index = $1 AND child_subindex = $2
+AND COALESCE(block_time, insert_time) > $3 AND event_index <> $4
+AND NOT EXISTS (SELECT 1 FROM withdrawal_holds
+                WHERE withdrawal_holds.event_index = concordium_events.event_index
+                AND withdrawal_holds.released_time IS NULL);",
+            )
+            .await?;
+        let insert_withdrawal_hold = client
+            .prepare(
+                "INSERT INTO withdrawal_holds (event_index, reason, release_time) VALUES ($1, $2, \
+                 $3) RETURNING id",
+            )
+            .await?;
+        let insert_screening_match = client
+            .prepare(
+                "INSERT INTO screening_matches (operation, event_index, network, address) VALUES \
+                 ($1, $2, $3, $4) RETURNING id",
+            )
+            .await?;
+        let set_pending_root = client
+            .prepare(
+                "UPDATE concordium_events SET pending_root = $1 WHERE event_index = $2 RETURNING \
+                 id",
+            )
+            .await?;
+        let clear_pending_root = client
+            .prepare(
+                "UPDATE concordium_events SET pending_root = NULL WHERE event_index = $1 \
+                 RETURNING id",
+            )
+            .await?;
+        let set_root = client
+            .prepare(
+                "UPDATE concordium_events SET pending_root = NULL, root = $1, first_root = CASE \
+                 WHEN root IS NULL THEN $1 ELSE first_root END, first_root_time = CASE WHEN root \
+                 IS NULL THEN NOW() ELSE first_root_time END WHERE event_index = $2 RETURNING id, \
+                 block_time",
+            )
+            .await?;
+        let notify_deposit = client
+            .prepare(&format!(
+                "SELECT pg_notify('{STATUS_CHANNEL}', json_build_object('kind', 'deposit', \
+                 'stage', $2::TEXT, 'origin_tx_hash', encode(origin_tx_hash, 'hex'), \
+                 'event_index', origin_event_index, 'wallet', encode(depositor, 'hex'), \
+                 'account', encode(deposit_receiver, 'hex'), 'tx_hash', encode($3, 'hex'), \
+                 'merkle_root', NULL)::TEXT) FROM ethereum_deposit_events WHERE \
+                 origin_event_index = $1"
+            ))
+            .await?;
+        let notify_withdrawal = client
+            .prepare(&format!(
+                "SELECT pg_notify('{STATUS_CHANNEL}', json_build_object('kind', 'withdraw', \
+                 'stage', $2::TEXT, 'origin_tx_hash', encode(tx_hash, 'hex'), 'event_index', \
+                 event_index, 'wallet', encode(receiver, 'hex'), 'account', encode(ccd_address, \
+                 'hex'), 'tx_hash', encode($3, 'hex'), 'merkle_root', encode(root, 'hex'))::TEXT) \
+                 FROM concordium_events WHERE event_index = $1"
+            ))
+            .await?;
         let ethereum_checkpoint = client
             .query_opt(
                 "SELECT last_processed_height FROM checkpoints WHERE network = 'ethereum'",
@@ -466,6 +1283,15 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
                 mark_withdrawal_as_completed,
                 get_max_event_index,
                 set_expected_merkle_time,
+                get_withdrawal_volume,
+                insert_withdrawal_hold,
+                insert_screening_match,
+                set_pending_root,
+                clear_pending_root,
+                set_root,
+                notify_deposit,
+                notify_withdrawal,
+                notify_listeners: true,
             },
         };
         Ok((
@@ -483,7 +1309,7 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
         ids: &[u64],
     ) -> anyhow::Result<u64> {
         let timestamp = chrono::Utc::now().timestamp();
-        log::debug!("Inserting Ethereum transaction {:#x}.", tx_hash);
+        tracing::debug!("Inserting Ethereum transaction {:#x}.", tx_hash);
         let statements = &self.prepared_statements;
         let db_tx = self.client.transaction().await?;
         let row = db_tx
@@ -492,23 +1318,21 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
                 &tx.as_ref(),
                 &timestamp,
                 &EthTransactionStatus::Pending,
+                &&root[..],
             ])
             .await
             .context("Unable to insert transaction.")?;
         for &id in ids {
-            // TODO: Make prepared statement for this.
             db_tx
-                .query_opt(
-                    "UPDATE concordium_events SET pending_root = $1 WHERE event_index = $2 \
-                     RETURNING id",
-                    &[&&root[..], &(id as i64)],
-                )
+                .query_opt(&statements.set_pending_root, &[&&root[..], &(id as i64)])
                 .await?;
         }
         db_tx.commit().await?;
         Ok(row.get::<_, i64>("id") as u64)
     }
 
+    /// Mark the Merkle root as set, and return the time from each approved
+    /// withdrawal until the root was set.
     pub async fn mark_merkle_root_set(
         &mut self,
         root: [u8; 32],
@@ -516,50 +1340,401 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
         success: bool,
         tx_hash: H256,
         failed_hashes: &[H256],
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Vec<chrono::Duration>> {
+        let statements = &self.prepared_statements;
         let db_tx = self.client.transaction().await?;
-        if success {
+        let approval_latencies = if success {
+            set_merkle_root(statements, &db_tx, root, ids).await?
+        } else {
             for &id in ids {
-                // TODO: Make prepared statement for this.
                 db_tx
-                    .query_opt(
-                        "UPDATE concordium_events SET pending_root = NULL, root = $1 WHERE \
-                         event_index = $2 RETURNING id",
-                        &[&&root[..], &(id as i64)],
-                    )
+                    .query_opt(&statements.clear_pending_root, &[&(id as i64)])
+                    .await?;
+            }
+            Vec::new()
+        };
+        db_tx
+            .query_one(
+                "UPDATE ethereum_transactions SET status = 'confirmed' WHERE tx_hash = $1 \
+                 RETURNING id;",
+                &[&tx_hash.as_bytes()],
+            )
+            .await?;
+        for failed_tx in failed_hashes {
+            db_tx
+                .query_one(
+                    "UPDATE ethereum_transactions SET status = 'missing' WHERE tx_hash = $1 \
+                     RETURNING id;",
+                    &[&failed_tx.as_bytes()],
+                )
+                .await?;
+        }
+        db_tx.commit().await?;
+        Ok(approval_latencies)
+    }
+
+    /// Record a Merkle root that was set on Ethereum by another relayer, in
+    /// read-only mode. Return the approval latencies of the withdrawals.
+    pub async fn record_merkle_root(
+        &mut self,
+        root: [u8; 32],
+        ids: &[u64],
+    ) -> anyhow::Result<Vec<chrono::Duration>> {
+        let statements = &self.prepared_statements;
+        let db_tx = self.client.transaction().await?;
+        let approval_latencies = set_merkle_root(statements, &db_tx, root, ids).await?;
+        db_tx.commit().await?;
+        Ok(approval_latencies)
+    }
+
+    /// Read the checkpoints and the amounts in transit for all mapped tokens.
+    pub async fn supply_snapshot(&mut self) -> anyhow::Result<Option<SupplySnapshot>> {
+        let db_tx = self
+            .client
+            .build_transaction()
+            .isolation_level(tokio_postgres::IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .await?;
+        let checkpoints = db_tx.query("SELECT * FROM checkpoints", &[]).await?;
+        let mut ethereum_height = None;
+        let mut concordium_height = None;
+        for row in checkpoints {
+            let height = row.try_get::<_, i64>("last_processed_height")? as u64;
+            match row.try_get::<_, Network>("network")? {
+                Network::Ethereum => ethereum_height = Some(height),
+                Network::Concordium => concordium_height = Some(height),
+            }
+        }
+        let (ethereum_height, concordium_height) = match (ethereum_height, concordium_height) {
+            (Some(e), Some(c)) => (e, c),
+            _ => return Ok(None),
+        };
+        let rows = db_tx
+            .query(
+                "SELECT tm.root, tm.child_index, tm.child_subindex, tm.eth_name, tm.decimals, \
+                 COALESCE((SELECT SUM(ce.amount::NUMERIC) FROM concordium_events ce WHERE \
+                 ce.event_type = 'withdraw' AND ce.processed IS NULL AND ce.child_index = \
+                 tm.child_index AND ce.child_subindex = tm.child_subindex), 0)::TEXT AS \
+                 pending_withdrawals, COALESCE((SELECT SUM(de.amount::NUMERIC) FROM \
+                 ethereum_deposit_events de WHERE de.tx_hash IS NULL AND de.root_token = \
+                 tm.root), 0)::TEXT AS pending_deposits FROM token_maps tm ORDER BY tm.id ASC",
+                &[],
+            )
+            .await?;
+        db_tx.commit().await?;
+        let mut tokens = Vec::with_capacity(rows.len());
+        for row in rows {
+            let root: Vec<u8> = row.try_get("root")?;
+            let root = H160(root.try_into().map_err(|_| {
+                anyhow::anyhow!("Database invariant violation. Root token not 20 bytes.")
+            })?);
+            let child = ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            );
+            let pending_withdrawals = row
+                .try_get::<_, String>("pending_withdrawals")?
+                .parse()
+                .context("Pending withdrawals are not an integer.")?;
+            let pending_deposits = row
+                .try_get::<_, String>("pending_deposits")?
+                .parse()
+                .context("Pending deposits are not an integer.")?;
+            tokens.push(TokenSupplySnapshot {
+                root,
+                child,
+                name: row.try_get("eth_name")?,
+                decimals: row.try_get::<_, i16>("decimals")? as u8,
+                pending_withdrawals,
+                pending_deposits,
+            });
+        }
+        Ok(Some(SupplySnapshot {
+            ethereum_height,
+            concordium_height: concordium_height.into(),
+            tokens,
+        }))
+    }
+
+    pub async fn insert_supply_discrepancies(
+        &mut self,
+        discrepancies: &[SupplyDiscrepancy],
+    ) -> anyhow::Result<()> {
+        let db_tx = self.client.transaction().await?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO supply_discrepancies (root_token, child_index, child_subindex, \
+                 ethereum_height, concordium_height, vault_balance, concordium_supply, \
+                 pending_withdrawals, pending_deposits, delta) VALUES ($1, $2, $3, $4, $5, $6, \
+                 $7, $8, $9, $10)",
+            )
+            .await?;
+        for d in discrepancies {
+            db_tx
+                .execute(&statement, &[
+                    &d.root.as_bytes(),
+                    &(d.child.index as i64),
+                    &(d.child.subindex as i64),
+                    &(d.ethereum_height as i64),
+                    &(d.concordium_height.height as i64),
+                    &d.vault_balance.to_string(),
+                    &d.concordium_supply.to_string(),
+                    &d.pending_withdrawals.to_string(),
+                    &d.pending_deposits.to_string(),
+                    &d.delta.to_string(),
+                ])
+                .await?;
+        }
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    /// Return the number of trips of the circuit breaker that have not been
+    /// resolved.
+    pub async fn unresolved_circuit_breaker_trips(&self) -> anyhow::Result<u64> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT COUNT(*) FROM circuit_breaker_trips WHERE resolved_time IS NULL",
+                &[],
+            )
+            .await?;
+        Ok(row.try_get::<_, i64>(0)? as u64)
+    }
+
+    /// Mark all trips of the circuit breaker as resolved. Returns the number
+    /// of trips that were resolved.
+    pub async fn resolve_circuit_breaker_trips(&self) -> anyhow::Result<u64> {
+        let n = self
+            .client
+            .execute(
+                "UPDATE circuit_breaker_trips SET resolved_time = NOW() WHERE resolved_time IS \
+                 NULL",
+                &[],
+            )
+            .await?;
+        Ok(n)
+    }
+
+    pub async fn insert_circuit_breaker_trip(
+        &self,
+        trigger: CircuitBreakerTrigger,
+        cause: &str,
+        pause_tx_hash: Option<TransactionHash>,
+    ) -> anyhow::Result<()> {
+        self.client
+            .execute(
+                "INSERT INTO circuit_breaker_trips (trigger, cause, pause_tx_hash) VALUES ($1, \
+                 $2, $3)",
+                &[
+                    &trigger,
+                    &cause,
+                    &pause_tx_hash.as_ref().map(|h| h.as_ref()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Stop notifying listeners on [`STATUS_CHANNEL`] of the stages that
+    /// transfers reach, e.g., when replaying history that subscribers are not
+    /// interested in.
+    pub fn suppress_notifications(&mut self) { self.prepared_statements.notify_listeners = false; }
+
+    /// Check whether no events have been recorded in the database, from
+    /// either chain.
+    pub async fn is_empty(&self) -> anyhow::Result<bool> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT NOT (EXISTS (SELECT 1 FROM checkpoints) OR EXISTS (SELECT 1 FROM \
+                 concordium_events) OR EXISTS (SELECT 1 FROM ethereum_deposit_events) OR EXISTS \
+                 (SELECT 1 FROM token_maps) OR EXISTS (SELECT 1 FROM merkle_roots))",
+                &[],
+            )
+            .await?;
+        Ok(row.try_get::<_, bool>(0)?)
+    }
+
+    /// Check whether the root is either a root set by the relayer, or the
+    /// root of a pending transaction.
+    pub async fn is_known_merkle_root(&self, root: [u8; 32]) -> anyhow::Result<bool> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT EXISTS (SELECT 1 FROM merkle_roots WHERE root = $1) OR EXISTS (SELECT 1 \
+                 FROM concordium_events WHERE pending_root = $1)",
+                &[&&root[..]],
+            )
+            .await?;
+        Ok(row.try_get::<_, bool>(0)?)
+    }
+
+    /// Get the number of withdrawals that are currently held.
+    pub async fn num_held_withdrawals(&self) -> anyhow::Result<u64> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT COUNT(*) FROM withdrawal_holds WHERE released_time IS NULL",
+                &[],
+            )
+            .await?;
+        Ok(row.try_get::<_, i64>(0)? as u64)
+    }
+
+    /// Release the held withdrawals that are past their release time or have
+    /// been approved by the operator. Return the event indices and Merkle leaf
+    /// hashes of the released withdrawals.
+    pub async fn release_withdrawal_holds(&mut self) -> anyhow::Result<Vec<(u64, [u8; 32])>> {
+        let db_tx = self.client.transaction().await?;
+        let rows = db_tx
+            .query(
+                "UPDATE withdrawal_holds SET released_time = NOW() FROM concordium_events WHERE \
+                 concordium_events.event_index = withdrawal_holds.event_index AND \
+                 withdrawal_holds.released_time IS NULL AND (withdrawal_holds.approved_time IS \
+                 NOT NULL OR withdrawal_holds.release_time <= NOW()) RETURNING \
+                 concordium_events.tx_hash, concordium_events.event_data",
+                &[],
+            )
+            .await?;
+        let mut released = Vec::with_capacity(rows.len());
+        for row in rows {
+            let tx_hash: Vec<u8> = row.try_get("tx_hash")?;
+            let tx_hash = tx_hash[..].try_into()?;
+            let data: Vec<u8> = row.try_get("event_data")?;
+            let we: WithdrawEvent = contracts_common::from_bytes(&data[..])?;
+            released.push((
+                we.event_index,
+                crate::merkle::make_event_leaf_hash(tx_hash, &we)?,
+            ));
+        }
+        db_tx.commit().await?;
+        Ok(released)
+    }
+
+    /// Get the addresses of the mapped tokens on Concordium.
+    pub async fn mapped_tokens(&self) -> anyhow::Result<Vec<ContractAddress>> {
+        let rows = self
+            .client
+            .query("SELECT child_index, child_subindex FROM token_maps", &[])
+            .await?;
+        let mut tokens = Vec::with_capacity(rows.len());
+        for row in rows {
+            tokens.push(ContractAddress::new(
+                row.try_get::<_, i64>("child_index")? as u64,
+                row.try_get::<_, i64>("child_subindex")? as u64,
+            ));
+        }
+        Ok(tokens)
+    }
+
+    /// Get the number of deposits that are currently held.
+    pub async fn num_held_deposits(&self) -> anyhow::Result<u64> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT COUNT(*) FROM deposit_holds WHERE released_time IS NULL",
+                &[],
+            )
+            .await?;
+        Ok(row.try_get::<_, i64>(0)? as u64)
+    }
+
+    /// Get the time at which the oldest pending item in each queue was
+    /// discovered. Held deposits and withdrawals are not counted as pending.
+    pub async fn oldest_pending(
+        &self,
+    ) -> anyhow::Result<[(&'static str, Option<chrono::DateTime<chrono::Utc>>); 4]> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT
+(SELECT MIN(insert_time) FROM ethereum_deposit_events
+ WHERE tx_hash IS NULL
+ AND NOT EXISTS (SELECT 1 FROM deposit_holds
+                 WHERE deposit_holds.origin_event_index = \
+                 ethereum_deposit_events.origin_event_index
+                 AND deposit_holds.released_time IS NULL)) AS deposits,
+(SELECT to_timestamp(MIN(timestamp)) FROM concordium_transactions
+ WHERE status = 'pending') AS concordium_transactions,
+(SELECT MIN(insert_time) FROM concordium_events
+ WHERE event_type = 'withdraw' AND root IS NULL AND processed IS NULL
+ AND NOT EXISTS (SELECT 1 FROM withdrawal_holds
+                 WHERE withdrawal_holds.event_index = concordium_events.event_index
+                 AND withdrawal_holds.released_time IS NULL)) AS merkle_inclusion,
+(SELECT MIN(insert_time) FROM concordium_events
+ WHERE event_type = 'withdraw' AND root IS NOT NULL AND processed IS NULL) AS withdrawal_claims",
+                &[],
+            )
+            .await?;
+        let get = |queue: &'static str| -> anyhow::Result<_> {
+            Ok((
+                queue,
+                row.try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(queue)?,
+            ))
+        };
+        Ok([
+            get("deposits")?,
+            get("concordium_transactions")?,
+            get("merkle_inclusion")?,
+            get("withdrawal_claims")?,
+        ])
+    }
+
+    /// Get the held deposits that have been approved by the operator, but not
+    /// yet released.
+    pub async fn approved_deposit_holds(
+        &self,
+    ) -> anyhow::Result<Vec<(H256, concordium_contracts::DepositOperation)>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT origin_tx_hash, deposit FROM deposit_holds WHERE released_time IS NULL \
+                 AND approved_time IS NOT NULL ORDER BY id ASC",
+                &[],
+            )
+            .await?;
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let origin_tx_hash: Vec<u8> = row.try_get("origin_tx_hash")?;
+            let data: Vec<u8> = row.try_get("deposit")?;
+            result.push((
+                H256::from_slice(&origin_tx_hash),
+                contracts_common::from_bytes(&data)?,
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Mark the held deposits as released, and store the transactions that
+    /// complete them. The transaction is [`None`] if the deposit has already
+    /// been completed on Concordium.
+    pub async fn release_deposit_holds<P: PayloadLike>(
+        &mut self,
+        released: &[(H256, u64, Option<BlockItem<P>>)],
+    ) -> anyhow::Result<()> {
+        let statements = &self.prepared_statements;
+        let db_tx = self.client.transaction().await?;
+        for (origin_tx_hash, origin_event_index, tx) in released {
+            if let Some(tx) = tx {
+                statements
+                    .insert_concordium_tx(&db_tx, origin_tx_hash, tx)
                     .await?;
-            }
-            db_tx
-                .query_one(
-                    "INSERT INTO merkle_roots (root) VALUES ($1) RETURNING id;",
-                    &[&&root[..]],
-                )
-                .await?;
-        } else {
-            for &id in ids {
-                // TODO: Make prepared statement for this.
-                db_tx
-                    .query_opt(
-                        "UPDATE concordium_events SET pending_root = NULL WHERE event_index = $1 \
-                         RETURNING id",
-                        &[&(id as i64)],
+                statements
+                    .notify_deposit(
+                        &db_tx,
+                        *origin_event_index,
+                        TransferStage::ConcordiumSubmitted,
+                        Some(tx.hash().as_ref()),
                     )
                     .await?;
             }
-        }
-        db_tx
-            .query_one(
-                "UPDATE ethereum_transactions SET status = 'confirmed' WHERE tx_hash = $1 \
-                 RETURNING id;",
-                &[&tx_hash.as_bytes()],
-            )
-            .await?;
-        for failed_tx in failed_hashes {
             db_tx
-                .query_one(
-                    "UPDATE ethereum_transactions SET status = 'missing' WHERE tx_hash = $1 \
-                     RETURNING id;",
-                    &[&failed_tx.as_bytes()],
+                .query_opt(
+                    "UPDATE deposit_holds SET released_time = NOW() WHERE origin_event_index = $1 \
+                     RETURNING id",
+                    &[&(*origin_event_index as i64)],
                 )
                 .await?;
         }
@@ -675,18 +1850,66 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
             // to only have one event for each event index, so using find is safe.
             let Some(crate::concordium_contracts::BridgeEvent::Withdraw(chain_we)) = chain_events
                 .into_iter()
-                .find(|e| e.event_index() == Some(event_index)) else {
-                    anyhow::bail!("Mismatching event. The database was tampered. Aborting.")
-                };
-            anyhow::ensure!(
-                chain_we == we,
-                "Mismatching withdraw event. The database was tampered with. Aborting."
-            );
+                .find(|e| e.event_index() == Some(event_index))
+            else {
+                tracing::error!(
+                    alert = "withdrawal_event_mismatch",
+                    critical = true,
+                    event_index,
+                    "Withdraw event not found on the chain."
+                );
+                anyhow::bail!("Mismatching event. The database was tampered. Aborting.")
+            };
+            if chain_we != we {
+                tracing::error!(
+                    alert = "withdrawal_event_mismatch",
+                    critical = true,
+                    event_index,
+                    "Withdraw event in the database does not match the chain."
+                );
+                anyhow::bail!(
+                    "Mismatching withdraw event. The database was tampered with. Aborting."
+                );
+            }
             result.push((tx_hash, we))
         }
         Ok((max_sent_event_index, result))
     }
 
+    /// Record the fees of the transactions that the relayer submitted and
+    /// whose fee is not yet recorded. Return the fees that were recorded,
+    /// together with the root token of the deposit or token map made by the
+    /// transaction.
+    pub async fn record_concordium_fees(
+        &mut self,
+        fees: &[(TransactionHash, Amount)],
+    ) -> anyhow::Result<Vec<(Option<H160>, Amount)>> {
+        let db_tx = self.client.transaction().await?;
+        let mut recorded = Vec::new();
+        for (tx_hash, fee) in fees {
+            let row = db_tx
+                .query_opt(
+                    "UPDATE concordium_transactions SET fee = $2 WHERE tx_hash = $1 AND fee IS \
+                     NULL RETURNING tx",
+                    &[&tx_hash.as_ref(), &(fee.micro_ccd as i64)],
+                )
+                .await?;
+            let Some(row) = row else {
+                continue;
+            };
+            let tx: BlockItem<EncodedPayload> =
+                common::from_bytes(&mut &row.try_get::<_, Vec<u8>>("tx")?[..])?;
+            let root = match state_update(&tx) {
+                Some(concordium_contracts::StateUpdate::Deposit(deposit)) => Some(deposit.root),
+                Some(concordium_contracts::StateUpdate::TokenMap(map)) => Some(map.root),
+                None => None,
+            };
+            recorded.push((root.map(H160::from), *fee));
+        }
+        db_tx.commit().await?;
+        Ok(recorded)
+    }
+
     pub async fn mark_concordium_tx(
         &self,
         tx_hash: TransactionHash,
@@ -702,33 +1925,73 @@ ON CONFLICT (tag) DO UPDATE SET expected_time = $1;
         Ok(rows.is_some())
     }
 
+    /// Insert the events from Ethereum and return the latencies of the
+    /// withdrawals that were completed.
     #[allow(clippy::too_many_arguments)]
     pub async fn insert_transactions<P: PayloadLike>(
         &mut self,
         metrics: &crate::metrics::Metrics,
         last_block_number: u64,
         txs: &[(H256, BlockItem<P>)],
-        // List of event indexes to mark as "done"
-        wes: &[(H256, u64, U256, TransactionHash, u64, H160, u64)],
-        deposits: &[(H256, u64, U256, H160, H160)],
+        // List of event indexes to mark as "done", with the time of the block they were done in.
+        wes: &[(
+            H256,
+            u64,
+            U256,
+            TransactionHash,
+            u64,
+            H160,
+            u64,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )],
+        // Deposits, with the receiver on Concordium, the time of the block they
+        // were made in, and the hash of the transaction that completes them, if
+        // one was made.
+        deposits: &[(
+            H256,
+            u64,
+            U256,
+            H160,
+            AccountAddress,
+            H160,
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<TransactionHash>,
+        )],
+        // Deposits that matched a screening list, with the matching addresses.
+        held_deposits: &[(
+            H256,
+            concordium_contracts::DepositOperation,
+            Vec<ScreeningMatch>,
+        )],
         // New token maps.
         maps: &[(H160, ContractAddress, String, u8)],
         // Removed token maps.
         unmaps: &[(H160, ContractAddress)],
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Vec<chrono::Duration>> {
         let statements = &self.prepared_statements;
         let db_tx = self.client.transaction().await?;
+        let mut claim_latencies = Vec::new();
         for (origin_tx_hash, tx) in txs {
             statements
                 .insert_concordium_tx(&db_tx, origin_tx_hash, tx)
                 .await?;
         }
-        for (origin_tx_hash, origin_event_index, amount, depositor, root_token) in deposits {
+        for (
+            origin_tx_hash,
+            origin_event_index,
+            amount,
+            depositor,
+            deposit_receiver,
+            root_token,
+            block_time,
+            ccd_tx_hash,
+        ) in deposits
+        {
             db_tx
                 .query(
                     "INSERT INTO ethereum_deposit_events (origin_tx_hash, origin_event_index, \
-                     amount, depositor, root_token, tx_hash)
-VALUES ($1, $2, $3, $4, $5, (SELECT tx_hash FROM concordium_events
+                     amount, depositor, root_token, block_time, deposit_receiver, tx_hash)
+VALUES ($1, $2, $3, $4, $5, $6, $7, (SELECT tx_hash FROM concordium_events
                     WHERE concordium_events.origin_event_index = $2
                     LIMIT 1));",
                     &[
@@ -737,20 +2000,80 @@ VALUES ($1, $2, $3, $4, $5, (SELECT tx_hash FROM concordium_events
                         &(amount.to_string()),
                         &depositor.as_bytes(),
                         &root_token.as_bytes(),
+                        block_time,
+                        &&deposit_receiver.0[..],
+                    ],
+                )
+                .await?;
+            statements
+                .notify_deposit(
+                    &db_tx,
+                    *origin_event_index,
+                    TransferStage::EthereumSeen,
+                    None,
+                )
+                .await?;
+            if let Some(ccd_tx_hash) = ccd_tx_hash {
+                statements
+                    .notify_deposit(
+                        &db_tx,
+                        *origin_event_index,
+                        TransferStage::ConcordiumSubmitted,
+                        Some(ccd_tx_hash.as_ref()),
+                    )
+                    .await?;
+            }
+        }
+        for (origin_tx_hash, deposit, matches) in held_deposits {
+            db_tx
+                .query(
+                    "INSERT INTO deposit_holds (origin_tx_hash, origin_event_index, deposit) \
+                     VALUES ($1, $2, $3);",
+                    &[
+                        &origin_tx_hash.as_bytes(),
+                        &(deposit.id as i64),
+                        &contracts_common::to_bytes(deposit),
                     ],
                 )
                 .await?;
+            statements
+                .insert_screening_matches(&db_tx, ScreenedOperation::Deposit, deposit.id, matches)
+                .await?;
         }
-        for (tx_hash, id, amount, origin_tx_hash, origin_event_id, receiver, event_index) in wes {
+        for (
+            tx_hash,
+            id,
+            amount,
+            origin_tx_hash,
+            origin_event_id,
+            receiver,
+            event_index,
+            claim_time,
+        ) in wes
+        {
             let rv = db_tx
                 .query_opt(&statements.mark_withdrawal_as_completed, &[
                     &tx_hash.as_bytes(),
                     &(*event_index as i64),
                 ])
                 .await?;
-            if rv.is_none() {
+            if let Some(row) = rv {
+                let withdraw_time =
+                    row.try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("block_time")?;
+                if let (Some(withdraw_time), Some(claim_time)) = (withdraw_time, claim_time) {
+                    claim_latencies.push(*claim_time - withdraw_time);
+                }
+                statements
+                    .notify_withdrawal(
+                        &db_tx,
+                        *event_index,
+                        TransferStage::Claimed,
+                        Some(tx_hash.as_bytes()),
+                    )
+                    .await?;
+            } else {
                 metrics.errors_total.inc();
-                log::error!(
+                tracing::error!(
                     "Event index {} not in the database. This is a database invariant violation.",
                     event_index
                 );
@@ -808,47 +2131,152 @@ VALUES ($1, $2, $3, $4, $5, (SELECT tx_hash FROM concordium_events
             .await
             .context("Unable to insert processed block.")?;
         db_tx.commit().await?;
-        Ok(())
+        Ok(claim_latencies)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn insert_concordium_events(
         &mut self,
         metrics: &crate::metrics::Metrics,
-        block: &BlockInfo,
-        events: &[(TransactionHash, Vec<BridgeEvent>)],
+        breaker: &CircuitBreaker,
+        holds: &HoldPolicy,
+        screener: &Screener,
+        blocks: &[ConcordiumBlockEvents],
+        read_only: bool,
     ) -> anyhow::Result<Vec<(u64, [u8; 32])>> {
+        let Some((last_block, _)) = blocks.last() else {
+            return Ok(Vec::new());
+        };
         let statements = &self.prepared_statements;
         let db_tx = self.client.transaction().await?;
         let mut withdraws = Vec::new();
-        for (tx_hash, events) in events {
-            for event in events {
-                let mh = if let BridgeEvent::Withdraw(we) = &event {
-                    Some((
-                        we.event_index,
-                        crate::merkle::make_event_leaf_hash(*tx_hash, we)?,
-                    ))
-                } else {
-                    None
-                };
-                let processed = statements
-                    .insert_concordium_event(metrics, &db_tx, tx_hash, event, mh.map(|x| x.1))
-                    .await?;
-                if !processed {
-                    if let Some(p) = mh {
-                        withdraws.push(p);
+        // The amounts withdrawn in each block, with the time of the block.
+        let mut withdrawn_amounts = Vec::with_capacity(blocks.len());
+        let mut unmatched_deposits = Vec::new();
+        let mut new_holds = Vec::new();
+        let mut screened = Vec::new();
+        let mut deposit_latencies = Vec::new();
+        for (block, events) in blocks {
+            let mut block_withdrawn_amounts = Vec::new();
+            for (tx_hash, events) in events {
+                for event in events {
+                    let mh = if let BridgeEvent::Withdraw(we) = &event {
+                        Some((
+                            we.event_index,
+                            crate::merkle::make_event_leaf_hash(*tx_hash, we)?,
+                        ))
+                    } else {
+                        None
                     };
+                    let processed = statements
+                        .insert_concordium_event(
+                            metrics,
+                            &db_tx,
+                            tx_hash,
+                            event,
+                            mh.map(|x| x.1),
+                            block.block_slot_time,
+                            read_only,
+                            &mut unmatched_deposits,
+                            &mut deposit_latencies,
+                        )
+                        .await?;
+                    if !processed {
+                        let held = if let BridgeEvent::Withdraw(we) = &event {
+                            block_withdrawn_amounts.push((we.contract, we.amount.0.clone()));
+                            statements
+                                .hold_withdrawal(
+                                    &db_tx,
+                                    holds,
+                                    screener,
+                                    we,
+                                    block.block_slot_time,
+                                    &mut new_holds,
+                                    &mut screened,
+                                )
+                                .await?
+                        } else {
+                            false
+                        };
+                        if let Some(p) = mh.filter(|_| !held) {
+                            withdraws.push(p);
+                        };
+                    }
                 }
             }
+            withdrawn_amounts.push((block.block_slot_time, block_withdrawn_amounts));
+        }
+        // The withdrawn volume is labelled by the root token, like the deposited
+        // volume.
+        let mut root_tokens = std::collections::BTreeMap::new();
+        for (token, _) in withdrawn_amounts.iter().flat_map(|(_, amounts)| amounts) {
+            if let std::collections::btree_map::Entry::Vacant(entry) = root_tokens.entry(*token) {
+                let row = db_tx
+                    .query_opt(
+                        "SELECT root FROM token_maps WHERE child_index = $1 AND child_subindex = \
+                         $2",
+                        &[&(token.index as i64), &(token.subindex as i64)],
+                    )
+                    .await?;
+                let root = match row {
+                    Some(row) => Some(H160::from_slice(&row.try_get::<_, Vec<u8>>("root")?)),
+                    None => None,
+                };
+                entry.insert(root);
+            }
         }
+        // The checkpoint is written in the same transaction as the events, so
+        // after a crash the blocks are processed again from the last one written.
         db_tx
             .query_opt(
                 "INSERT INTO checkpoints VALUES ('concordium', $1) ON CONFLICT (network) DO \
                  UPDATE SET last_processed_height = $1;",
-                &[&(block.block_height.height as i64)],
+                &[&(last_block.block_height.height as i64)],
             )
             .await
             .context("Unable to set checkpoint for Concordium events.")?;
         db_tx.commit().await?;
+        for (event_index, reason) in new_holds {
+            metrics.held_withdrawals.inc();
+            tracing::warn!(
+                "Withdrawal with event index {event_index} is held since {}.",
+                match reason {
+                    WithdrawalHoldReason::SingleLimit =>
+                        "it exceeds the limit on a single withdrawal",
+                    WithdrawalHoldReason::WindowLimit =>
+                        "it exceeds the limit on withdrawals within the window",
+                    WithdrawalHoldReason::Screening => "it matches a screening list",
+                }
+            );
+        }
+        for (event_index, matches) in screened {
+            metrics.screening_matches.inc();
+            for m in matches {
+                tracing::warn!(
+                    alert = "screening_match",
+                    "Withdrawal with event index {event_index} involves screened {m}."
+                );
+            }
+        }
+        for latency in deposit_latencies {
+            crate::metrics::observe_latency(&metrics.deposit_latency_seconds, latency);
+        }
+        for (token, amount) in withdrawn_amounts.iter().flat_map(|(_, amounts)| amounts) {
+            // Only mapped tokens can be withdrawn.
+            let Some(Some(root)) = root_tokens.get(token) else {
+                continue;
+            };
+            metrics
+                .withdrawn_volume
+                .with_label_values(&[&format!("{root:#x}")])
+                .inc_by(crate::metrics::amount_to_f64(&amount.to_u64_digits()));
+        }
+        for cause in unmatched_deposits {
+            breaker.report(cause);
+        }
+        for (block_time, amounts) in withdrawn_amounts {
+            breaker.report_withdrawals(block_time, amounts);
+        }
         Ok(withdraws)
     }
 
@@ -868,11 +2296,12 @@ VALUES ($1, $2, $3, $4, $5, (SELECT tx_hash FROM concordium_events
                     match status {
                         Ok(_) => (),
                         Err(e) if e.is_not_found() => {
-                            log::debug!("Submitting missing transaction {}.", tx_hash);
+                            tracing::debug!("Submitting missing transaction {}.", tx_hash);
                             if let Err(e) = client.send_block_item(&tx).await {
                                 if e.is_invalid_argument() {
                                     // Something is wrong with this transaction
-                                    log::error!(
+                                    tracing::error!(
+                                        alert = "concordium_transaction_failed",
                                         "Unable to resubmit transaction {e:#?}. Marking it as \
                                          failed."
                                     );
@@ -903,11 +2332,50 @@ fn convert_to_token_amount(a: U256) -> cis2::TokenAmount {
     cis2::TokenAmount(BigUint::from_bytes_le(&buf))
 }
 
+/// Mark the withdrawals with the given event indices as part of the root, and
+/// record the root as the current one. Return the approval latencies of the
+/// withdrawals.
+async fn set_merkle_root(
+    statements: &PreparedStatements,
+    db_tx: &Transaction<'_>,
+    root: [u8; 32],
+    ids: &[u64],
+) -> anyhow::Result<Vec<chrono::Duration>> {
+    let now = chrono::Utc::now();
+    let mut approval_latencies = Vec::new();
+    for &id in ids {
+        let row = db_tx
+            .query_opt(&statements.set_root, &[&&root[..], &(id as i64)])
+            .await?;
+        if let Some(row) = row {
+            if let Some(block_time) =
+                row.try_get::<_, Option<chrono::DateTime<chrono::Utc>>>("block_time")?
+            {
+                approval_latencies.push(now - block_time);
+            }
+            statements
+                .notify_withdrawal(db_tx, id, TransferStage::MerkleRoot, None)
+                .await?;
+        }
+    }
+    db_tx
+        .query_one(
+            "INSERT INTO merkle_roots (root) VALUES ($1) RETURNING id;",
+            &[&&root[..]],
+        )
+        .await?;
+    Ok(approval_latencies)
+}
+
 #[derive(Debug)]
 pub enum MerkleUpdate {
     NewWithdraws {
         withdraws: Vec<(u64, [u8; 32])>,
     },
+    /// Withdrawals that were held and have now been released.
+    ReleasedWithdraws {
+        withdraws: Vec<(u64, [u8; 32])>,
+    },
     WithdrawalCompleted {
         receiver:             H160,
         original_event_index: u64,
@@ -928,7 +2396,7 @@ async fn try_reconnect(
             Err(e) if i < MAX_CONNECT_ATTEMPTS => {
                 let delay = std::time::Duration::from_millis(500 * (1 << i));
                 metrics.warnings_total.inc();
-                log::warn!(
+                tracing::warn!(
                     "Could not connect to the database due to {:#}. Reconnecting in {}ms.",
                     e,
                     delay.as_millis()
@@ -938,7 +2406,7 @@ async fn try_reconnect(
             }
             Err(e) => {
                 metrics.errors_total.inc();
-                log::error!(
+                tracing::error!(
                     "Could not connect to the database in {} attempts. Last attempt failed with \
                      reason {:#}.",
                     MAX_CONNECT_ATTEMPTS,
@@ -957,17 +2425,30 @@ pub async fn handle_database(
     config: tokio_postgres::Config,
     mut db: Database,
     mut blocks: tokio::sync::mpsc::Receiver<DatabaseOperation>,
-    mut bridge_manager: BridgeManager,
-    ccd_transaction_sender: tokio::sync::mpsc::Sender<BlockItem<EncodedPayload>>,
+    mut bridge_manager: Option<BridgeManager>,
+    ccd_transaction_sender: tokio::sync::mpsc::Sender<(BlockItem<EncodedPayload>, tracing::Span)>,
     merkle_setter_sender: tokio::sync::mpsc::Sender<MerkleUpdate>,
+    breaker: CircuitBreaker,
+    holds: HoldPolicy,
+    screener: Screener,
+    health: Health,
     mut stop_flag: tokio::sync::watch::Receiver<()>,
 ) -> anyhow::Result<()> {
+    metrics
+        .held_withdrawals
+        .set(db.num_held_withdrawals().await? as i64);
+    metrics
+        .held_deposits
+        .set(db.num_held_deposits().await? as i64);
     let mut retry = None;
+    // An operation received while collecting Concordium blocks, to be
+    // processed after them.
+    let mut deferred = None;
 
     // Loop until told to stop. If the stop sender has been dropped
     // treat that as if we need to stop as well.
     loop {
-        let next_item = if let Some(v) = retry.take() {
+        let next_item = if let Some(v) = retry.take().or_else(|| deferred.take()) {
             Some(v)
         } else {
             tokio::select! {
@@ -980,24 +2461,44 @@ pub async fn handle_database(
                 _ = stop_flag.changed() => None,
             }
         };
-        let Some(action) = next_item else {break};
+        let Some(mut action) = next_item else { break };
+        // Consecutive Concordium blocks are written in one transaction, which
+        // speeds up catching up.
+        if let DatabaseOperation::ConcordiumEvents { blocks: batch } = &mut action {
+            let deadline = tokio::time::Instant::now() + CONCORDIUM_BATCH_WAIT;
+            while deferred.is_none() && batch.len() < MAX_CONCORDIUM_BATCH {
+                match tokio::time::timeout_at(deadline, blocks.recv()).await {
+                    Ok(Some(DatabaseOperation::ConcordiumEvents { blocks: more })) => {
+                        batch.extend(more)
+                    }
+                    Ok(Some(other)) => deferred = Some(other),
+                    // The channel is closed, or no more blocks arrived in time.
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        }
         match insert_into_db(
             &metrics,
             &mut db,
             action,
             &merkle_setter_sender,
             &ccd_transaction_sender,
-            &mut bridge_manager,
+            bridge_manager.as_mut(),
+            &breaker,
+            &holds,
+            &screener,
         )
         .await
         {
             Ok(()) => {
-                log::trace!("Processed database operation.");
+                tracing::trace!("Processed database operation.");
+                health.progress(Task::DatabaseWorker);
             }
             Err(InsertError::Retry(action)) => {
+                health.retrying(Task::DatabaseWorker);
                 let delay = std::time::Duration::from_millis(5000);
                 metrics.warnings_total.inc();
-                log::warn!(
+                tracing::warn!(
                     "Could not insert into the database. Reconnecting in {}ms.",
                     delay.as_millis()
                 );
@@ -1016,7 +2517,7 @@ pub async fn handle_database(
                     Ok(v) => {
                         if let Err(e) = v {
                             metrics.warnings_total.inc();
-                            log::warn!(
+                            tracing::warn!(
                                 "Could not correctly stop the old database connection due to: {}.",
                                 e
                             );
@@ -1025,20 +2526,20 @@ pub async fn handle_database(
                     Err(e) => {
                         if e.is_panic() {
                             metrics.warnings_total.inc();
-                            log::warn!(
+                            tracing::warn!(
                                 "Could not correctly stop the old database connection. The \
                                  connection thread panicked: {e:#}."
                             );
                         } else if !e.is_cancelled() {
                             metrics.warnings_total.inc();
-                            log::warn!("Could not correctly stop the old database connection.");
+                            tracing::warn!("Could not correctly stop the old database connection.");
                         }
                     }
                 }
                 retry = Some(action);
             }
             Err(other) => {
-                log::debug!(
+                tracing::debug!(
                     "One of the internal channels was closed ({:#}). Closing the database worker.",
                     other
                 );
@@ -1052,6 +2553,108 @@ pub async fn handle_database(
     Ok(())
 }
 
+/// How often the summaries used by the statistics endpoint of the API server
+/// are refreshed.
+const STATS_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Periodically refresh the summaries used by the statistics endpoint of the
+/// API server. The refresh uses a connection of its own, so that it never holds
+/// up the database worker. A failed refresh is logged and skipped, and the
+/// connection is re-established at the next interval if it was lost.
+pub async fn refresh_stats(metrics: crate::metrics::Metrics, config: tokio_postgres::Config) {
+    let mut interval = tokio::time::interval(STATS_REFRESH_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut connection: Option<(tokio_postgres::Client, JoinHandle<_>)> = None;
+    loop {
+        interval.tick().await;
+        if connection
+            .as_ref()
+            .map_or(true, |(_, handle)| handle.is_finished())
+        {
+            match connect(&config).await {
+                Ok(c) => connection = Some(c),
+                Err(e) => {
+                    metrics.warnings_total.inc();
+                    tracing::warn!(
+                        "Unable to connect to the database to refresh the statistics: {e:#}."
+                    );
+                    continue;
+                }
+            }
+        }
+        let Some((client, _)) = &connection else {
+            continue;
+        };
+        match refresh_stat_views(client).await {
+            Ok(()) => tracing::debug!("Refreshed the statistics."),
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to refresh the statistics: {e}.");
+            }
+        }
+    }
+}
+
+/// Refresh the materialized views with the summaries used by the statistics
+/// endpoint of the API server. They are refreshed concurrently, so that the API
+/// server can read them in the meantime.
+async fn refresh_stat_views(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    for view in ["token_stats", "hourly_volumes"] {
+        client
+            .batch_execute(&format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {view}"))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Apply events from one of the chains to the database directly, instead of
+/// through the database worker. This is used when rebuilding the database
+/// from the history of the chains. Concordium transactions made in response
+/// to the events are only stored in the database, where they are found as
+/// pending transactions when the relayer starts. The updates for the Merkle
+/// tree are returned.
+pub async fn replay_events(
+    metrics: &crate::metrics::Metrics,
+    db: &mut Database,
+    action: DatabaseOperation,
+    bridge_manager: Option<&mut BridgeManager>,
+    breaker: &CircuitBreaker,
+    holds: &HoldPolicy,
+    screener: &Screener,
+) -> anyhow::Result<Vec<MerkleUpdate>> {
+    // Each event results in at most one transaction and one update of the
+    // Merkle tree, so the channels have room for all of them.
+    let capacity = match &action {
+        DatabaseOperation::ConcordiumEvents { .. } => 1,
+        DatabaseOperation::EthereumEvents { events } => events.events.len() + 1,
+        _ => anyhow::bail!("Only events from the chains can be replayed."),
+    };
+    let (ccd_transaction_sender, _ccd_transaction_receiver) = tokio::sync::mpsc::channel(capacity);
+    let (merkle_setter_sender, mut merkle_setter_receiver) = tokio::sync::mpsc::channel(capacity);
+    match insert_into_db(
+        metrics,
+        db,
+        action,
+        &merkle_setter_sender,
+        &ccd_transaction_sender,
+        bridge_manager,
+        breaker,
+        holds,
+        screener,
+    )
+    .await
+    {
+        Ok(()) => (),
+        Err(InsertError::Other(e)) => return Err(e),
+        Err(InsertError::Retry(_)) => anyhow::bail!("Unable to write the events to the database."),
+    }
+    let mut updates = Vec::new();
+    while let Ok(update) = merkle_setter_receiver.try_recv() {
+        updates.push(update);
+    }
+    Ok(updates)
+}
+
 #[derive(Debug, thiserror::Error)]
 enum InsertError {
     #[error("Other error {0:#}")]
@@ -1061,21 +2664,30 @@ enum InsertError {
 }
 
 /// The main worker that does all database operations.
+#[allow(clippy::too_many_arguments)]
 async fn insert_into_db(
     metrics: &crate::metrics::Metrics,
     db: &mut Database,
     action: DatabaseOperation,
     merkle_setter_sender: &tokio::sync::mpsc::Sender<MerkleUpdate>,
-    ccd_transaction_sender: &tokio::sync::mpsc::Sender<BlockItem<EncodedPayload>>,
-    bridge_manager: &mut BridgeManager,
+    ccd_transaction_sender: &tokio::sync::mpsc::Sender<(BlockItem<EncodedPayload>, tracing::Span)>,
+    // The bridge manager used to send transactions, or [`None`] in read-only mode.
+    mut bridge_manager: Option<&mut BridgeManager>,
+    breaker: &CircuitBreaker,
+    holds: &HoldPolicy,
+    screener: &Screener,
 ) -> Result<(), InsertError> {
     match action {
-        DatabaseOperation::ConcordiumEvents {
-            block,
-            transaction_events,
-        } => {
+        DatabaseOperation::ConcordiumEvents { blocks } => {
             match db
-                .insert_concordium_events(metrics, &block, &transaction_events)
+                .insert_concordium_events(
+                    metrics,
+                    breaker,
+                    holds,
+                    screener,
+                    &blocks,
+                    bridge_manager.is_none(),
+                )
                 .await
             {
                 Ok(withdraws) => {
@@ -1086,7 +2698,7 @@ async fn insert_into_db(
                             .is_err()
                     {
                         metrics.warnings_total.inc();
-                        log::warn!(
+                        tracing::warn!(
                             "Unable to send new withdraw events to the Merkle updated since the \
                              channel is closed."
                         )
@@ -1094,10 +2706,9 @@ async fn insert_into_db(
                 }
                 Err(e) => {
                     metrics.warnings_total.inc();
-                    log::warn!("Database error when trying to insert Concordium events: {e}.");
+                    tracing::warn!("Database error when trying to insert Concordium events: {e}.");
                     return Err(InsertError::Retry(DatabaseOperation::ConcordiumEvents {
-                        block,
-                        transaction_events,
+                        blocks,
                     }));
                 }
             }
@@ -1105,10 +2716,14 @@ async fn insert_into_db(
         DatabaseOperation::EthereumEvents { events } => {
             let mut wes = Vec::new();
             let mut txs = Vec::with_capacity(events.events.len());
+            // The span of each transaction in `txs`.
+            let mut tx_spans = Vec::with_capacity(events.events.len());
             let mut maps = Vec::new();
             let mut unmaps = Vec::new();
             let mut deposits = Vec::new();
+            let mut held_deposits = Vec::new();
             for event in &events.events {
+                let block_time = events.block_times.get(&event.block_number).copied();
                 match event.event {
                     ethereum::EthEvent::TokenLocked {
                         id,
@@ -1119,22 +2734,46 @@ async fn insert_into_db(
                         amount,
                     } => {
                         metrics.num_deposits.inc();
+                        let span = tracing::info_span!(
+                            "deposit",
+                            eth_tx_hash = %format_args!("{:#x}", event.tx_hash),
+                            deposit_id = id.low_u64(),
+                            ccd_tx_hash = tracing::field::Empty,
+                        );
                         // Send transaction to Concordium.
-                        let deposit = concordium_contracts::DepositOperation {
-                            id:       id.low_u64(),
-                            user:     deposit_receiver.into(),
-                            root:     root_token.into(),
-                            amount:   convert_to_token_amount(amount),
-                            // TODO: Hardcoded token ID. Works with contracts as they are
-                            // now, but is not ideal. But until those contracts are changed not
-                            // much to do here.
-                            token_id: cis2::TokenId::new_unchecked(vec![0u8; 8]),
-                        };
-                        let update = concordium_contracts::StateUpdate::Deposit(deposit);
-                        if let Some(tx) = bridge_manager.make_state_update_tx(&update).await? {
-                            txs.push((event.tx_hash, tx));
+                        let deposit = concordium_contracts::DepositOperation::new(
+                            id.low_u64(),
+                            deposit_receiver,
+                            root_token.into(),
+                            convert_to_token_amount(amount),
+                        );
+                        let matches = screener.screen_deposit(depositor, &deposit_receiver);
+                        let mut ccd_tx_hash = None;
+                        if matches.is_empty() {
+                            if let Some(bridge_manager) = bridge_manager.as_deref_mut() {
+                                let update = concordium_contracts::StateUpdate::Deposit(deposit);
+                                if let Some(tx) =
+                                    bridge_manager.make_state_update_tx(&update).await?
+                                {
+                                    span.record("ccd_tx_hash", tracing::field::display(tx.hash()));
+                                    ccd_tx_hash = Some(tx.hash());
+                                    txs.push((event.tx_hash, tx));
+                                    tx_spans.push(span);
+                                }
+                            }
+                        } else {
+                            held_deposits.push((event.tx_hash, deposit, matches));
                         }
-                        deposits.push((event.tx_hash, id.low_u64(), amount, depositor, root_token));
+                        deposits.push((
+                            event.tx_hash,
+                            id.low_u64(),
+                            amount,
+                            depositor,
+                            deposit_receiver,
+                            root_token,
+                            block_time,
+                            ccd_tx_hash,
+                        ));
                     }
                     ethereum::EthEvent::TokenMapped {
                         id,
@@ -1151,7 +2790,19 @@ async fn insert_into_db(
                             child: child_token,
                         };
                         let update = concordium_contracts::StateUpdate::TokenMap(map);
-                        if let Some(tx) = bridge_manager.make_state_update_tx(&update).await? {
+                        let tx = match bridge_manager.as_deref_mut() {
+                            Some(bridge_manager) => {
+                                bridge_manager.make_state_update_tx(&update).await?
+                            }
+                            None => None,
+                        };
+                        if let Some(tx) = tx {
+                            tx_spans.push(tracing::info_span!(
+                                "token_map",
+                                eth_tx_hash = %format_args!("{:#x}", event.tx_hash),
+                                map_id = id.low_u64(),
+                                ccd_tx_hash = %tx.hash(),
+                            ));
                             txs.push((event.tx_hash, tx));
                         }
                         maps.push((root_token, child_token, name.clone(), decimals));
@@ -1164,7 +2815,7 @@ async fn insert_into_db(
                     } => {
                         // Do nothing at present. Manual intervention needed.
                         metrics.errors_total.inc();
-                        log::error!("Token {id} ({root_token} -> {child_token}) unmapped.");
+                        tracing::error!("Token {id} ({root_token} -> {child_token}) unmapped.");
                         unmaps.push((root_token, child_token));
                     }
                     ethereum::EthEvent::Withdraw {
@@ -1184,6 +2835,7 @@ async fn insert_into_db(
                             origin_event_index,
                             receiver,
                             origin_event_index,
+                            block_time,
                         ));
                     }
                 }
@@ -1196,13 +2848,38 @@ async fn insert_into_db(
                     &txs,
                     &wes,
                     &deposits,
+                    &held_deposits,
                     &maps,
                     &unmaps,
                 )
                 .await
             {
-                Ok(()) => {
-                    for (_, _, _, _, _, receiver, we) in wes {
+                Ok(claim_latencies) => {
+                    for latency in claim_latencies {
+                        crate::metrics::observe_latency(
+                            &metrics.withdrawal_claim_latency_seconds,
+                            latency,
+                        );
+                    }
+                    for (_, _, amount, _, root_token, _) in &deposits {
+                        metrics
+                            .deposited_volume
+                            .with_label_values(&[&format!("{root_token:#x}")])
+                            .inc_by(crate::metrics::amount_to_f64(&amount.0));
+                    }
+                    for (_, deposit, matches) in &held_deposits {
+                        metrics.held_deposits.inc();
+                        metrics.screening_matches.inc();
+                        for m in matches {
+                            tracing::warn!(
+                                alert = "screening_match",
+                                "Deposit with id {} involves screened {m}.",
+                                deposit.id
+                            );
+                        }
+                        tracing::warn!("Deposit with id {} is held.", deposit.id);
+                    }
+                    for (_, _, _, _, _, receiver, we, _) in wes {
                         if merkle_setter_sender
                             .send(MerkleUpdate::WithdrawalCompleted {
                                 original_event_index: we,
@@ -1213,7 +2890,7 @@ async fn insert_into_db(
                         {
                             {
                                 metrics.warnings_total.inc();
-                                log::warn!(
+                                tracing::warn!(
                                     "Unable to send completed withdrawal to the Merkle updater. \
                                      The channel is closed."
                                 )
@@ -1223,7 +2900,7 @@ async fn insert_into_db(
                 }
                 Err(e) => {
                     metrics.warnings_total.inc();
-                    log::warn!("Database error when trying to insert transactions: {e}.");
+                    tracing::warn!("Database error when trying to insert transactions: {e}.");
                     return Err(InsertError::Retry(DatabaseOperation::EthereumEvents {
                         events,
                     }));
@@ -1232,26 +2909,50 @@ async fn insert_into_db(
 
             // We have now written all the transactions to the database. Now send them to
             // the Concordium node.
-            for (_, tx) in txs {
+            for ((_, tx), span) in txs.into_iter().zip(tx_spans) {
                 let hash = tx.hash();
-                if ccd_transaction_sender.send(tx).await.is_err() {
+                if ccd_transaction_sender
+                    .send((tx, span.clone()))
+                    .await
+                    .is_err()
+                {
                     {
                         metrics.warnings_total.inc();
-                        log::warn!(
+                        tracing::warn!(
                             "Unable to send transctions stored in the database to the node since \
                              the channel is closed."
                         )
                     }
                 } else {
-                    log::info!("Enqueued transaction {}.", hash);
+                    span.in_scope(|| tracing::info!("Enqueued transaction {}.", hash));
+                }
+            }
+        }
+        DatabaseOperation::RecordConcordiumFees { fees } => {
+            match db.record_concordium_fees(&fees).await {
+                Ok(recorded) => {
+                    for (root, fee) in recorded {
+                        let token = root.map_or_else(String::new, |root| format!("{root:#x}"));
+                        metrics
+                            .concordium_fees_spent
+                            .with_label_values(&[&token])
+                            .inc_by(fee.micro_ccd);
+                    }
+                }
+                Err(e) => {
+                    metrics.warnings_total.inc();
+                    tracing::warn!("Database error when trying to record transaction fees: {e}.");
+                    return Err(InsertError::Retry(
+                        DatabaseOperation::RecordConcordiumFees { fees },
+                    ));
                 }
             }
         }
         DatabaseOperation::MarkConcordiumTransaction { tx_hash, state } => {
-            log::debug!("Marking {} as {:?}.", tx_hash, state);
+            tracing::debug!("Marking {} as {:?}.", tx_hash, state);
             if let Err(e) = db.mark_concordium_tx(tx_hash, state).await {
                 metrics.warnings_total.inc();
-                log::warn!("Database error: {e}");
+                tracing::warn!("Database error: {e}");
                 return Err(InsertError::Retry(
                     DatabaseOperation::MarkConcordiumTransaction { tx_hash, state },
                 ));
@@ -1262,7 +2963,7 @@ async fn insert_into_db(
                 Ok(txs) => {
                     if response.send(txs).is_err() {
                         metrics.errors_total.inc();
-                        log::error!(
+                        tracing::error!(
                             "Unable to send response to the sender of \
                              GetPendingConcordiumTransactions, indicating they have stopped."
                         );
@@ -1270,7 +2971,7 @@ async fn insert_into_db(
                 }
                 Err(e) => {
                     metrics.warnings_total.inc();
-                    log::warn!(
+                    tracing::warn!(
                         "Database error when trying to get pending Concordium transactions: {e}."
                     );
                     return Err(InsertError::Retry(
@@ -1293,7 +2994,9 @@ async fn insert_into_db(
             {
                 if response.send(tx).is_err() {
                     metrics.errors_total.inc();
-                    log::error!("Unable to send response StoreEthereumTransaction. Continuing.");
+                    tracing::error!(
+                        "Unable to send response StoreEthereumTransaction. Continuing."
+                    );
                 }
             } else {
                 return Err(InsertError::Retry(
@@ -1315,14 +3018,19 @@ async fn insert_into_db(
             tx_hash,
             failed_hashes,
         } => {
-            if db
+            if let Ok(approval_latencies) = db
                 .mark_merkle_root_set(root, &ids, success, tx_hash, &failed_hashes)
                 .await
-                .is_ok()
             {
+                for latency in approval_latencies {
+                    crate::metrics::observe_latency(
+                        &metrics.withdrawal_approval_latency_seconds,
+                        latency,
+                    );
+                }
                 if response.send(()).is_err() {
                     metrics.errors_total.inc();
-                    log::error!("Unable to send response MarkSetMerkleCompleted. Continuing.")
+                    tracing::error!("Unable to send response MarkSetMerkleCompleted. Continuing.")
                 }
             } else {
                 return Err(InsertError::Retry(
@@ -1351,6 +3059,192 @@ async fn insert_into_db(
                 ));
             }
         }
+        DatabaseOperation::GetSupplySnapshot { response } => match db.supply_snapshot().await {
+            Ok(snapshot) => {
+                if response.send(snapshot).is_err() {
+                    metrics.warnings_total.inc();
+                    tracing::warn!("Unable to send response to GetSupplySnapshot. Continuing.");
+                }
+            }
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to get the supply snapshot: {e}.");
+                return Err(InsertError::Retry(DatabaseOperation::GetSupplySnapshot {
+                    response,
+                }));
+            }
+        },
+        DatabaseOperation::InsertCircuitBreakerTrip {
+            trigger,
+            cause,
+            pause_tx_hash,
+        } => {
+            if let Err(e) = db
+                .insert_circuit_breaker_trip(trigger, &cause, pause_tx_hash)
+                .await
+            {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to record a circuit breaker trip: {e}.");
+                return Err(InsertError::Retry(
+                    DatabaseOperation::InsertCircuitBreakerTrip {
+                        trigger,
+                        cause,
+                        pause_tx_hash,
+                    },
+                ));
+            }
+        }
+        DatabaseOperation::IsKnownMerkleRoot { root, response } => {
+            match db.is_known_merkle_root(root).await {
+                Ok(known) => {
+                    if response.send(known).is_err() {
+                        metrics.warnings_total.inc();
+                        tracing::warn!("Unable to send response to IsKnownMerkleRoot. Continuing.");
+                    }
+                }
+                Err(e) => {
+                    metrics.warnings_total.inc();
+                    tracing::warn!("Database error when trying to look up a Merkle root: {e}.");
+                    return Err(InsertError::Retry(DatabaseOperation::IsKnownMerkleRoot {
+                        root,
+                        response,
+                    }));
+                }
+            }
+        }
+        DatabaseOperation::RecordMerkleRoot { root, ids } => {
+            match db.record_merkle_root(root, &ids).await {
+                Ok(approval_latencies) => {
+                    for latency in approval_latencies {
+                        crate::metrics::observe_latency(
+                            &metrics.withdrawal_approval_latency_seconds,
+                            latency,
+                        );
+                    }
+                }
+                Err(e) => {
+                    metrics.warnings_total.inc();
+                    tracing::warn!("Database error when trying to record a Merkle root: {e}.");
+                    return Err(InsertError::Retry(DatabaseOperation::RecordMerkleRoot {
+                        root,
+                        ids,
+                    }));
+                }
+            }
+        }
+        DatabaseOperation::InsertSupplyDiscrepancies { discrepancies } => {
+            if let Err(e) = db.insert_supply_discrepancies(&discrepancies).await {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to insert supply discrepancies: {e}.");
+                return Err(InsertError::Retry(
+                    DatabaseOperation::InsertSupplyDiscrepancies { discrepancies },
+                ));
+            }
+        }
+        DatabaseOperation::ReleaseWithdrawalHolds => match db.release_withdrawal_holds().await {
+            Ok(withdraws) => {
+                metrics.held_withdrawals.sub(withdraws.len() as i64);
+                for (event_index, _) in &withdraws {
+                    tracing::info!("Releasing held withdrawal with event index {event_index}.");
+                }
+                if !withdraws.is_empty()
+                    && merkle_setter_sender
+                        .send(MerkleUpdate::ReleasedWithdraws { withdraws })
+                        .await
+                        .is_err()
+                {
+                    metrics.warnings_total.inc();
+                    tracing::warn!(
+                        "Unable to send released withdraw events to the Merkle updater since the \
+                         channel is closed."
+                    )
+                }
+            }
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to release held withdrawals: {e}.");
+                return Err(InsertError::Retry(
+                    DatabaseOperation::ReleaseWithdrawalHolds,
+                ));
+            }
+        },
+        DatabaseOperation::UpdatePendingAges => match db.oldest_pending().await {
+            Ok(oldest) => {
+                let now = chrono::Utc::now();
+                for (queue, time) in oldest {
+                    metrics
+                        .oldest_pending_seconds
+                        .with_label_values(&[queue])
+                        .set(time.map_or(0, |t| (now - t).num_seconds()));
+                }
+            }
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to get the oldest pending items: {e}.");
+                return Err(InsertError::Retry(DatabaseOperation::UpdatePendingAges));
+            }
+        },
+        DatabaseOperation::ReleaseDepositHolds => {
+            // Held deposits are sent by the relayer that holds the keys.
+            let Some(bridge_manager) = bridge_manager else {
+                return Ok(());
+            };
+            let approved = match db.approved_deposit_holds().await {
+                Ok(approved) => approved,
+                Err(e) => {
+                    metrics.warnings_total.inc();
+                    tracing::warn!("Database error when trying to get approved deposits: {e}.");
+                    return Err(InsertError::Retry(DatabaseOperation::ReleaseDepositHolds));
+                }
+            };
+            let mut released = Vec::with_capacity(approved.len());
+            for (origin_tx_hash, deposit) in approved {
+                let id = deposit.id;
+                let update = concordium_contracts::StateUpdate::Deposit(deposit);
+                let tx = bridge_manager.make_state_update_tx(&update).await?;
+                released.push((origin_tx_hash, id, tx));
+            }
+            if released.is_empty() {
+                return Ok(());
+            }
+            if let Err(e) = db.release_deposit_holds(&released).await {
+                metrics.warnings_total.inc();
+                tracing::warn!("Database error when trying to release held deposits: {e}.");
+                return Err(InsertError::Retry(DatabaseOperation::ReleaseDepositHolds));
+            }
+            // Deposits requeued through the admin API are released in the same way,
+            // but were never counted as held, so the number is queried again.
+            match db.num_held_deposits().await {
+                Ok(n) => metrics.held_deposits.set(n as i64),
+                Err(e) => tracing::warn!("Unable to get the number of held deposits: {e}."),
+            }
+            for (origin_tx_hash, id, tx) in released {
+                let span = tracing::info_span!(
+                    "deposit",
+                    eth_tx_hash = %format_args!("{origin_tx_hash:#x}"),
+                    deposit_id = id,
+                    ccd_tx_hash = tracing::field::Empty,
+                );
+                span.in_scope(|| tracing::info!("Releasing held deposit with id {id}."));
+                if let Some(tx) = tx {
+                    let hash = tx.hash();
+                    span.record("ccd_tx_hash", tracing::field::display(hash));
+                    if ccd_transaction_sender
+                        .send((tx, span.clone()))
+                        .await
+                        .is_err()
+                    {
+                        metrics.warnings_total.inc();
+                        tracing::warn!(
+                            "Unable to send transctions stored in the database to the node since \
+                             the channel is closed."
+                        )
+                    } else {
+                        span.in_scope(|| tracing::info!("Enqueued transaction {}.", hash));
+                    }
+                }
+            }
+        }
     }
     Ok(())
 }
@@ -1,4 +1,5 @@
 use anyhow::Context;
+use chrono::TimeZone;
 use concordium_rust_sdk as concordium;
 use ethabi::{
     ethereum_types::{Address, H256, U256},
@@ -9,12 +10,14 @@ use ethers::{
     prelude::{Filter, Middleware},
 };
 use sha2::Digest;
+use std::collections::BTreeMap;
 
 use crate::{
     db::DatabaseOperation,
+    health::{Health, Task},
     state_sender::{
-        LockedTokenFilter, StateSender, TokenMapAddedFilter, TokenMapRemovedFilter,
-        WithdrawEventFilter,
+        LockedTokenFilter, MerkleRootFilter, StateSender, TokenMapAddedFilter,
+        TokenMapRemovedFilter, WithdrawEventFilter,
     },
 };
 
@@ -35,6 +38,8 @@ pub struct EthBlockEvents {
     pub last_number: u64,
     /// Events.
     pub events:      Vec<EthBlockEvent>,
+    /// Timestamps of the blocks that contain the events.
+    pub block_times: BTreeMap<u64, chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug)]
@@ -165,7 +170,9 @@ impl TryFrom<WithdrawEventFilter> for EthEvent {
     }
 }
 
-async fn get_eth_block_events<M: Middleware + 'static>(
+/// Get the events emitted by the state sender in blocks
+/// `block_number..=upper_block`, retrying on transient failures.
+pub async fn get_eth_block_events<M: Middleware + 'static>(
     contract: &StateSender<M>,
     block_number: u64,
     upper_block: u64,
@@ -186,13 +193,15 @@ async fn get_eth_block_events<M: Middleware + 'static>(
             }
             Err(EthereumQueryError::Retryable(e)) => {
                 if retry_num > 6 {
-                    log::error!("Too many failures attempting to query Ethereum events. Aborting.");
+                    tracing::error!(
+                        "Too many failures attempting to query Ethereum events. Aborting."
+                    );
                     anyhow::bail!(
                         "Too many failures attempting to query Ethereum events. Aborting."
                     );
                 } else {
                     let delay = std::time::Duration::from_secs(5 << retry_num);
-                    log::warn!(
+                    tracing::warn!(
                         "Failed getting Ethereum block events due to {e}. Retrying in {} seconds.",
                         delay.as_secs()
                     );
@@ -204,6 +213,94 @@ async fn get_eth_block_events<M: Middleware + 'static>(
     }
 }
 
+/// Get the Merkle roots set in blocks `block_number..=upper_block`, together
+/// with the number of the block each was set in, in the order they were set.
+pub async fn get_merkle_roots<M: Middleware + 'static>(
+    contract: &StateSender<M>,
+    block_number: u64,
+    upper_block: u64,
+) -> anyhow::Result<Vec<(u64, [u8; 32])>>
+where
+    M::Error: 'static, {
+    use ethers::contract::EthEvent;
+    let filter = contract
+        .merkle_root_filter()
+        .filter
+        .from_block(block_number)
+        .to_block(upper_block);
+    let logs = contract
+        .client()
+        .get_logs(&filter)
+        .await
+        .context("Unable to get MerkleRoot logs.")?;
+    let mut roots = Vec::with_capacity(logs.len());
+    for log in logs {
+        let block = log
+            .block_number
+            .context("Log in a confirmed block must have a block number.")?
+            .as_u64();
+        let decoded = MerkleRootFilter::decode_log(&RawLog {
+            topics: log.topics,
+            data:   log.data.0.into(),
+        })?;
+        roots.push((block, decoded.root));
+    }
+    Ok(roots)
+}
+
+/// Get the receivers of the deposits made in the transaction with the given
+/// hash, by the id of the deposit.
+pub async fn get_deposit_receivers<M: Middleware + 'static>(
+    contract: &StateSender<M>,
+    tx_hash: H256,
+) -> anyhow::Result<BTreeMap<u64, concordium::id::types::AccountAddress>>
+where
+    M::Error: 'static, {
+    use ethers::contract::EthEvent;
+    let receipt = contract
+        .client()
+        .get_transaction_receipt(tx_hash)
+        .await
+        .context("Unable to get transaction receipt.")?
+        .with_context(|| format!("No receipt for deposit transaction {tx_hash:#x}."))?;
+    let mut receivers = BTreeMap::new();
+    for log in receipt.logs {
+        if log.address != contract.address()
+            || log.topics.first() != Some(&LockedTokenFilter::signature())
+        {
+            continue;
+        }
+        let decoded = LockedTokenFilter::decode_log(&RawLog {
+            topics: log.topics,
+            data:   log.data.0.into(),
+        })?;
+        receivers.insert(
+            decoded.id.low_u64(),
+            concordium::id::types::AccountAddress(decoded.deposit_receiver),
+        );
+    }
+    Ok(receivers)
+}
+
+/// Get the timestamp of the block with the given number.
+pub async fn get_block_time<M: Middleware + 'static>(
+    contract: &StateSender<M>,
+    block_number: u64,
+) -> anyhow::Result<chrono::DateTime<chrono::Utc>>
+where
+    M::Error: 'static, {
+    let block = contract
+        .client()
+        .get_block(block_number)
+        .await
+        .context("Unable to get block.")?
+        .context("The block is confirmed, so it should exist.")?;
+    chrono::Utc
+        .timestamp_opt(block.timestamp.low_u64() as i64, 0)
+        .single()
+        .context("Block timestamp out of range.")
+}
+
 #[derive(Debug, thiserror::Error)]
 enum EthereumQueryError {
     /// An inconsistency in data from the provider. The service should shut
@@ -223,7 +320,7 @@ async fn get_eth_block_events_worker<M: Middleware + 'static>(
 ) -> Result<EthBlockEvents, EthereumQueryError>
 where
     M::Error: 'static, {
-    log::debug!("Getting block events for blocks at heights {block_number}..={upper_block}.");
+    tracing::debug!("Getting block events for blocks at heights {block_number}..={upper_block}.");
     let client = contract.client();
     let mut events = Vec::new();
     let block_filter = |filter: Filter| filter.from_block(block_number).to_block(upper_block);
@@ -236,7 +333,7 @@ where
             .context("Unable to get LockedToken logs.")?;
         for log in logs {
             if log.removed.unwrap_or(true) {
-                log::error!("An event in a confirmed block was removed.");
+                tracing::error!("An event in a confirmed block was removed.");
                 return Err(EthereumQueryError::Inconsistency);
             }
             let decoded = LockedTokenFilter::decode_log(&RawLog {
@@ -254,7 +351,9 @@ where
                     .as_u64(),
                 event:        decoded.try_into()?,
             };
-            log::debug!(
+            tracing::debug!(
+                eth_tx_hash = %format_args!("{:#x}", event.tx_hash),
+                deposit_id = %event.event.id(),
                 "Discovered new `Locked` event emitted by {:#x} in block number {}. Token = {:#x}.",
                 log.address,
                 event.block_number,
@@ -272,7 +371,7 @@ where
             .context("Unable to MapAdded logs.")?;
         for log in logs {
             if log.removed.unwrap_or(true) {
-                log::error!("An event in a confirmed block was removed.");
+                tracing::error!("An event in a confirmed block was removed.");
                 return Err(EthereumQueryError::Inconsistency);
             }
             let decoded = TokenMapAddedFilter::decode_log(&RawLog {
@@ -280,10 +379,10 @@ where
                 data:   log.data.0.into(),
             })?;
             let (name, decimals) = if decoded.token_type == sha3::Keccak256::digest("Ether")[..] {
-                log::debug!("New mapping for ETH.");
+                tracing::debug!("New mapping for ETH.");
                 ("ETH".into(), 18)
             } else {
-                log::debug!("New mapping for ERC20 token at {:#x}.", decoded.root_token);
+                tracing::debug!("New mapping for ERC20 token at {:#x}.", decoded.root_token);
                 let contract = crate::erc20::Erc20::new(decoded.root_token, client.clone());
                 let name = contract
                     .symbol()
@@ -307,7 +406,7 @@ where
                     .as_u64(),
                 event:        (decoded, name, decimals).into(),
             };
-            log::debug!(
+            tracing::debug!(
                 "Discovered new `TokenMapAdded` event emitted by {:#x} in block {}.",
                 log.address,
                 event.block_number
@@ -324,7 +423,7 @@ where
             .context("Unable to get MapRemoved logs.")?;
         for log in logs {
             if log.removed.unwrap_or(true) {
-                log::error!("An event in a confirmed block was removed.");
+                tracing::error!("An event in a confirmed block was removed.");
                 return Err(EthereumQueryError::Inconsistency);
             }
             let decoded = TokenMapRemovedFilter::decode_log(&RawLog {
@@ -341,7 +440,7 @@ where
                     .as_u64(),
                 event:        decoded.into(),
             };
-            log::debug!(
+            tracing::debug!(
                 "Discovered new `TokenMapRemoved` event emitted by {:#x} in block {}.",
                 log.address,
                 event.block_number
@@ -358,7 +457,7 @@ where
             .context("Unable to get Withdraw logs.")?;
         for log in logs {
             if log.removed.unwrap_or(true) {
-                log::error!("An event in a confirmed block was removed.");
+                tracing::error!("An event in a confirmed block was removed.");
                 return Err(EthereumQueryError::Inconsistency);
             }
             let decoded = WithdrawEventFilter::decode_log(&RawLog {
@@ -375,7 +474,7 @@ where
                     .as_u64(),
                 event:        decoded.try_into()?,
             };
-            log::debug!(
+            tracing::debug!(
                 "Discovered new `WithdrawEvent` event emitted by {:#x} in block {}.",
                 log.address,
                 event.block_number
@@ -385,9 +484,27 @@ where
     }
     // Sort events by increasing ids so we have a consistent view in the database.
     events.sort_by(|x, y| x.event.id().cmp(&y.event.id()));
+    let mut block_times = BTreeMap::new();
+    for event in &events {
+        if let std::collections::btree_map::Entry::Vacant(entry) =
+            block_times.entry(event.block_number)
+        {
+            let block = client
+                .get_block(event.block_number)
+                .await
+                .context("Unable to get block.")?
+                .context("The block is confirmed, so it should exist.")?;
+            let time = chrono::Utc
+                .timestamp_opt(block.timestamp.low_u64() as i64, 0)
+                .single()
+                .context("Block timestamp out of range.")?;
+            entry.insert(time);
+        }
+    }
     Ok(EthBlockEvents {
         events,
         last_number: upper_block,
+        block_times,
     })
 }
 
@@ -401,6 +518,7 @@ pub async fn watch_eth_blocks<M: Middleware + 'static>(
     mut block_number: u64,
     mut upper_block: u64,
     num_confirmations: u64,
+    health: Health,
 ) -> anyhow::Result<()>
 where
     M::Error: 'static, {
@@ -415,12 +533,13 @@ where
                 Err(e) => {
                     if retry_num <= 6 {
                         metrics.warnings_total.inc();
-                        log::warn!("Failed querying block number. Will retry.");
+                        health.retrying(Task::EthereumWatcher);
+                        tracing::warn!("Failed querying block number. Will retry.");
                         tokio::time::sleep(std::time::Duration::from_secs(1 << retry_num)).await;
                         retry_num += 1;
                     } else {
                         metrics.errors_total.inc();
-                        log::error!("Too many retries trying to get block number.");
+                        tracing::error!("Too many retries trying to get block number.");
                         return Err(e.into());
                     }
                 }
@@ -434,6 +553,7 @@ where
                     events: block_events,
                 })
                 .await?;
+            health.progress(Task::EthereumWatcher);
             block_number = upper_block + 1;
             upper_block = block_number;
         } else {
@@ -0,0 +1,156 @@
+//! Liveness and readiness reporting for the long-running tasks of the relayer.
+//! Each task records when it last made progress, and whether it is currently
+//! retrying after a failure. A task that has not made progress for longer than
+//! its configured threshold is considered stalled, unless it is waiting for
+//! work on an empty queue. The relayer is ready if no task is stalled or
+//! retrying.
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+/// The tasks whose progress is tracked.
+pub enum Task {
+    EthereumWatcher,
+    ConcordiumListener,
+    DatabaseWorker,
+    MerkleSender,
+    MerkleRootTracker,
+    ConcordiumSender,
+    ConcordiumBalance,
+    EthereumBalance,
+}
+
+/// Current depth and capacity of a task's input queue.
+type QueueDepth = Box<dyn Fn() -> (usize, usize) + Send + Sync>;
+
+struct TaskState {
+    registered:    chrono::DateTime<chrono::Utc>,
+    last_progress: Option<chrono::DateTime<chrono::Utc>>,
+    retrying:      bool,
+    max_stall:     chrono::Duration,
+    queue:         Option<QueueDepth>,
+}
+
+#[derive(Clone, Default)]
+/// A shared handle used by tasks to report progress, and by the HTTP server to
+/// report on the tasks.
+pub struct Health {
+    tasks: Arc<Mutex<BTreeMap<Task, TaskState>>>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct QueueReport {
+    pub depth:    usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TaskReport {
+    pub task:                   Task,
+    /// Unix timestamp, in seconds, of the last progress.
+    pub last_progress:          Option<i64>,
+    /// Seconds since the last progress, or since the task was started if it
+    /// has not made any progress yet.
+    pub seconds_since_progress: i64,
+    pub max_stall_seconds:      i64,
+    pub retrying:               bool,
+    pub stalled:                bool,
+    pub queue:                  Option<QueueReport>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct HealthReport {
+    /// Whether none of the tasks are stalled or retrying.
+    pub ready: bool,
+    pub tasks: Vec<TaskReport>,
+}
+
+impl Health {
+    pub fn new() -> Self { Self::default() }
+
+    fn update(&self, task: Task, f: impl FnOnce(&mut TaskState)) {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = tasks.get_mut(&task) {
+            f(state)
+        }
+    }
+
+    /// Start tracking the task. The task is stalled if it makes no progress
+    /// for longer than `max_stall`.
+    pub fn register(&self, task: Task, max_stall: std::time::Duration) {
+        let max_stall =
+            chrono::Duration::from_std(max_stall).unwrap_or(chrono::Duration::max_value());
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.insert(task, TaskState {
+            registered: chrono::Utc::now(),
+            last_progress: None,
+            retrying: false,
+            max_stall,
+            queue: None,
+        });
+    }
+
+    /// Record the channel from which the task receives its work. The depth of
+    /// the queue is reported, and the task is only considered stalled while
+    /// the queue is not empty.
+    pub fn register_queue<T: Send + 'static>(
+        &self,
+        task: Task,
+        sender: &tokio::sync::mpsc::Sender<T>,
+    ) {
+        // Use a weak sender so that the channel is closed as usual when the
+        // other senders are dropped.
+        let sender = sender.downgrade();
+        self.update(task, |state| {
+            state.queue = Some(Box::new(move || {
+                sender.upgrade().map_or((0, 0), |s| {
+                    (s.max_capacity() - s.capacity(), s.max_capacity())
+                })
+            }))
+        })
+    }
+
+    /// Record that the task has made progress.
+    pub fn progress(&self, task: Task) {
+        self.update(task, |state| {
+            state.last_progress = Some(chrono::Utc::now());
+            state.retrying = false;
+        })
+    }
+
+    /// Record that the task is waiting to retry after a failure.
+    pub fn retrying(&self, task: Task) { self.update(task, |state| state.retrying = true) }
+
+    /// Report on the state of all the registered tasks.
+    pub fn report(&self) -> HealthReport {
+        let now = chrono::Utc::now();
+        let tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let tasks = tasks
+            .iter()
+            .map(|(task, state)| {
+                let since = now - state.last_progress.unwrap_or(state.registered);
+                let queue = state.queue.as_ref().map(|q| {
+                    let (depth, capacity) = q();
+                    QueueReport { depth, capacity }
+                });
+                let waiting = queue.as_ref().map_or(false, |q| q.depth == 0);
+                TaskReport {
+                    task: *task,
+                    last_progress: state.last_progress.map(|t| t.timestamp()),
+                    seconds_since_progress: since.num_seconds(),
+                    max_stall_seconds: state.max_stall.num_seconds(),
+                    retrying: state.retrying,
+                    stalled: since > state.max_stall && !waiting,
+                    queue,
+                }
+            })
+            .collect::<Vec<_>>();
+        HealthReport {
+            ready: tasks.iter().all(|t| !t.stalled && !t.retrying),
+            tasks,
+        }
+    }
+}
@@ -1,9 +1,19 @@
+pub mod alerts;
 pub mod aws_secret_manager;
+pub mod chain_lag;
+pub mod circuit_breaker;
 pub mod concordium_contracts;
+pub mod config;
 pub mod db;
 pub mod ethereum;
+pub mod health;
 pub mod merkle;
 pub mod metrics;
+pub mod rebuild;
+pub mod reconciliation;
+pub mod screening;
+pub mod telemetry;
+pub mod withdrawal_holds;
 
 // These modules are auto-generated, so we don't bother with clippy.
 #[allow(clippy::all)]
@@ -5,43 +5,32 @@ use concordium_rust_sdk::{
 };
 use ethabi::{
     ethereum_types::{H160, H256, U256},
-    RawLog, Token,
+    ParamType, RawLog, Token,
 };
 use ethers::{
     prelude::{types::transaction::eip2718::TypedTransaction, Middleware, Signer},
     utils::rlp::Rlp,
 };
-use rs_merkle::{Hasher, MerkleProof, MerkleTree};
-use std::{collections::BTreeMap, sync::Arc};
+use rs_merkle::{Hasher, MerkleTree};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use crate::{
+    chain_lag::LagGuard,
+    circuit_breaker::BreakerState,
     concordium_contracts::WithdrawEvent,
+    config::SettingsReceiver,
     db::{self, DatabaseOperation, MerkleUpdate, PendingEthereumTransactions},
+    health::{Health, Task},
     root_chain_manager::BridgeManager,
     state_sender,
 };
 
-pub fn make_proof<A: PartialEq + Eq, E>(
-    leaves: impl IntoIterator<Item = Result<(A, [u8; 32]), E>>,
-    elem: A,
-) -> Result<Option<MerkleProof<Keccak256Algorithm>>, E> {
-    let mut tree = MerkleTree::<Keccak256Algorithm>::new();
-    let mut index = Vec::new();
-    for (i, ok_or) in leaves.into_iter().enumerate() {
-        let (v, leaf) = ok_or?;
-        tree.insert(leaf);
-        if v == elem {
-            index.push(i)
-        }
-    }
-    if !index.is_empty() {
-        tree.commit();
-        Ok(Some(tree.proof(&index)))
-    } else {
-        Ok(None)
-    }
-}
-
 pub struct MerkleData {
     /// The child token address that is being withdrawn.
     pub child_token:          ContractAddress,
@@ -58,7 +47,25 @@ pub struct MerkleData {
 }
 
 impl MerkleData {
-    pub fn encode(&self) -> Vec<u8> {
+    /// The data of the withdraw event emitted in the given transaction.
+    pub fn new(transaction_hash: TransactionHash, we: &WithdrawEvent) -> anyhow::Result<Self> {
+        Ok(Self {
+            child_token: we.contract,
+            amount: convert_from_token_amount(&we.amount),
+            user_wallet: we.eth_address.into(),
+            transaction_hash,
+            transaction_event_id: we.event_index,
+            token_id: u64::from_le_bytes(
+                Vec::from(we.token_id.clone())
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Invalid token id."))?,
+            ),
+        })
+    }
+
+    /// The fields of the `WithdrawParams` struct of the RootChainManager
+    /// contract.
+    fn tokens(&self) -> Vec<Token> {
         let ccd_index = Token::Uint(U256::from(self.child_token.index));
         let ccd_sub_index = Token::Uint(U256::from(self.child_token.subindex));
         let amount = Token::Uint(self.amount);
@@ -67,7 +74,7 @@ impl MerkleData {
         let transaction_event_id = Token::Uint(U256::from(self.transaction_event_id));
         let token_id = Token::Uint(U256::from(self.token_id));
 
-        ethabi::encode(&vec![
+        vec![
             ccd_index,
             ccd_sub_index,
             amount,
@@ -75,7 +82,40 @@ impl MerkleData {
             transaction_hash,
             transaction_event_id,
             token_id,
-        ])
+        ]
+    }
+
+    pub fn encode(&self) -> Vec<u8> { ethabi::encode(&self.tokens()) }
+
+    /// The calldata of a call of `withdraw` on the RootChainManager contract
+    /// that completes this withdrawal. The proof hashes must be in the order
+    /// from the leaf to the root, as produced by [`rs_merkle::MerkleProof`].
+    ///
+    /// This is encoded by hand since the generated bindings in
+    /// [`root_chain_manager`](crate::root_chain_manager) are for an earlier
+    /// version of the contract.
+    pub fn withdraw_calldata(&self, proof: &[[u8; 32]]) -> Vec<u8> {
+        let params = ParamType::Tuple(vec![
+            ParamType::Uint(64),
+            ParamType::Uint(64),
+            ParamType::Uint(256),
+            ParamType::Address,
+            ParamType::FixedBytes(32),
+            ParamType::Uint(64),
+            ParamType::Uint(64),
+        ]);
+        let proof_type = ParamType::Array(Box::new(ParamType::FixedBytes(32)));
+        let mut calldata = ethabi::short_signature("withdraw", &[params, proof_type]).to_vec();
+        calldata.extend(ethabi::encode(&[
+            Token::Tuple(self.tokens()),
+            Token::Array(
+                proof
+                    .iter()
+                    .map(|hash| Token::FixedBytes(hash.to_vec()))
+                    .collect(),
+            ),
+        ]));
+        calldata
     }
 }
 
@@ -123,18 +163,7 @@ pub fn build_merkle_tree(
 ) -> anyhow::Result<MerkleTree<Keccak256Algorithm>> {
     let mut tree = MerkleTree::new();
     for (transaction_hash, we) in events {
-        let data = MerkleData {
-            child_token:          we.contract,
-            amount:               convert_from_token_amount(&we.amount),
-            user_wallet:          we.eth_address.into(),
-            transaction_hash:     *transaction_hash,
-            transaction_event_id: we.event_index,
-            token_id:             u64::from_le_bytes(
-                Vec::from(we.token_id.clone())
-                    .try_into()
-                    .map_err(|_| anyhow::anyhow!("Invalid token id."))?,
-            ),
-        };
+        let data = MerkleData::new(*transaction_hash, we)?;
         let hash = Keccak256Algorithm::hash(&data.encode());
         tree.insert(hash);
     }
@@ -162,28 +191,31 @@ pub struct MerkleSetterClient<M, S> {
     pub warn_duration:          std::time::Duration,
     /// List of event indices and the hashes
     pub current_leaves:         Arc<std::sync::Mutex<BTreeMap<u64, [u8; 32]>>>,
+    /// The current reloadable settings, used to update `max_gas_price`,
+    /// `max_gas`, and `update_interval`.
+    pub settings:               SettingsReceiver,
     /// The high water mark. The last event that was set in the merkle root.
     /// This is used to skip sending updates when there are no new
     /// withdrawals to be approved.
     pub max_marked_event_index: Option<u64>,
+    /// Incremented every time held withdrawals are released into
+    /// `current_leaves`. Released withdrawals can have event indices below the
+    /// high water mark, so this is used in addition to it to detect that a new
+    /// root must be set.
+    pub released_leaves:        Arc<AtomicU64>,
+    /// The value of `released_leaves` when the last Merkle root was computed
+    /// that was successfully set.
+    pub marked_released:        u64,
+    /// Notified when the operator asks for a new root to be set without
+    /// waiting for the update interval.
+    pub update_requests:        Arc<tokio::sync::Notify>,
 }
 
 pub fn make_event_leaf_hash(
     transaction_hash: TransactionHash,
     we: &WithdrawEvent,
 ) -> anyhow::Result<[u8; 32]> {
-    let data = MerkleData {
-        child_token: we.contract,
-        amount: convert_from_token_amount(&we.amount),
-        user_wallet: we.eth_address.into(),
-        transaction_hash,
-        transaction_event_id: we.event_index,
-        token_id: u64::from_le_bytes(
-            Vec::from(we.token_id.clone())
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("Invalid token id."))?,
-        ),
-    };
+    let data = MerkleData::new(transaction_hash, we)?;
     Ok(Keccak256Algorithm::hash(&data.encode()))
 }
 
@@ -192,11 +224,9 @@ impl<M, S: Signer> MerkleSetterClient<M, S> {
     pub fn new(
         root_manager: BridgeManager<M>,
         signer: S,
-        max_gas_price: U256,
-        max_gas: U256,
+        mut settings: SettingsReceiver,
         next_nonce: U256,
         pending_merkle_set: &Option<db::PendingEthereumTransactions>,
-        update_interval: std::time::Duration,
         pending_withdrawals: Vec<(TransactionHash, WithdrawEvent)>,
         max_marked_event_index: Option<u64>,
         escalate_interval: std::time::Duration,
@@ -209,7 +239,7 @@ impl<M, S: Signer> MerkleSetterClient<M, S> {
             let (tx, _) = ethers::types::transaction::eip2718::TypedTransaction::decode_signed(
                 &Rlp::new(data),
             )?;
-            log::debug!(
+            tracing::debug!(
                 "There is a pending Ethereum transaction with hash {:#x}. Using it's nonce as the \
                  next nonce.",
                 tx_hash,
@@ -222,15 +252,20 @@ impl<M, S: Signer> MerkleSetterClient<M, S> {
         } else {
             next_nonce
         };
+        let current = settings.borrow_and_update().clone();
         let msc = Self {
             root_manager,
             signer,
-            max_gas_price,
-            max_gas,
+            max_gas_price: current.max_gas_price,
+            max_gas: current.max_gas,
             next_nonce,
-            update_interval,
+            update_interval: current.merkle_update_interval,
+            settings,
             current_leaves: Arc::new(std::sync::Mutex::new(BTreeMap::new())),
             max_marked_event_index,
+            released_leaves: Arc::new(AtomicU64::new(0)),
+            marked_released: 0,
+            update_requests: Arc::default(),
             escalate_interval,
             warn_duration,
         };
@@ -243,6 +278,18 @@ impl<M, S: Signer> MerkleSetterClient<M, S> {
     }
 }
 
+impl<M, S> MerkleSetterClient<M, S> {
+    /// Apply the current settings. Return whether the update interval changed.
+    fn apply_settings(&mut self) -> bool {
+        let settings = self.settings.borrow_and_update().clone();
+        self.max_gas_price = settings.max_gas_price;
+        self.max_gas = settings.max_gas;
+        let interval_changed = self.update_interval != settings.merkle_update_interval;
+        self.update_interval = settings.merkle_update_interval;
+        interval_changed
+    }
+}
+
 fn add_withdraw_event(
     leaves: &Arc<std::sync::Mutex<BTreeMap<u64, [u8; 32]>>>,
     event_index: u64,
@@ -267,11 +314,13 @@ fn remove_withdraw_event(
 }
 pub enum SetMerkleRootResult {
     SetTransaction {
-        tx_hash: H256,
-        raw_tx:  ethers::prelude::Bytes,
-        tx:      TypedTransaction,
-        root:    [u8; 32],
-        ids:     Arc<[u64]>,
+        tx_hash:  H256,
+        raw_tx:   ethers::prelude::Bytes,
+        tx:       TypedTransaction,
+        root:     [u8; 32],
+        ids:      Arc<[u64]>,
+        /// The value of `released_leaves` when the tree was computed.
+        released: u64,
     },
     GasTooHigh {
         max_gas_price:     U256,
@@ -297,23 +346,29 @@ where
 {
     async fn set_merkle_root(&self) -> Result<SetMerkleRootResult, SetMerkleRootError<M, S>> {
         let mut tree = MerkleTree::<Keccak256Algorithm>::new();
-        let ids = {
+        let (ids, released) = {
             let leaves = self.current_leaves.lock().map_err(|_| {
                 SetMerkleRootError::LockError(anyhow::anyhow!(
                     "Unable to set merkle root, unable to acquire lock."
                 ))
             })?;
-            if leaves.last_key_value().map(|x| *x.0) <= self.max_marked_event_index {
+            // Released withdrawals are added to the leaves before the counter is
+            // incremented, so reading it under the lock never misses a release.
+            let released = self.released_leaves.load(Ordering::Acquire);
+            if leaves.last_key_value().map(|x| *x.0) <= self.max_marked_event_index
+                && released == self.marked_released
+            {
                 // Nothing to do.
                 return Ok(SetMerkleRootResult::NoPendingWithdrawals);
             }
-            leaves
+            let ids = leaves
                 .iter()
                 .map(|(&id, hash)| {
                     tree.insert(*hash);
                     id
                 })
-                .collect::<Arc<[_]>>()
+                .collect::<Arc<[_]>>();
+            (ids, released)
         }; // drop lock.
         tree.commit();
         if let Some(new_root) = tree.root() {
@@ -322,7 +377,7 @@ where
                 .get_gas_price()
                 .await
                 .map_err(SetMerkleRootError::Network)?;
-            log::debug!("Current gas price is {}.", current_gas_price);
+            tracing::debug!("Current gas price is {}.", current_gas_price);
             if current_gas_price <= self.max_gas_price {
                 let call = self.root_manager.set_merkle_root(new_root);
                 let mut tx = call.tx;
@@ -343,6 +398,7 @@ where
                     tx,
                     root: new_root,
                     ids,
+                    released,
                 })
             } else {
                 Ok(SetMerkleRootResult::GasTooHigh {
@@ -365,6 +421,7 @@ where
 ///
 /// This worker instead monitors the provided `receiver` channel for new Merkle
 /// tree updates to update its in-memory state.
+#[allow(clippy::too_many_arguments)]
 pub async fn send_merkle_root_updates<M: Middleware + 'static, S: Signer + 'static>(
     metrics: crate::metrics::Metrics,
     client: MerkleSetterClient<M, S>,
@@ -372,6 +429,9 @@ pub async fn send_merkle_root_updates<M: Middleware + 'static, S: Signer + 'stat
     mut receiver: tokio::sync::mpsc::Receiver<MerkleUpdate>,
     db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
     num_confirmations: u64,
+    lag_guard: LagGuard,
+    breaker: BreakerState,
+    health: Health,
     mut stop: tokio::sync::watch::Receiver<()>,
 ) -> anyhow::Result<()>
 where
@@ -387,7 +447,7 @@ where
                 .await
                 .context("Unable to get transaction status.")?;
             if status.is_none() {
-                log::info!(
+                tracing::info!(
                     "Transaction with hash {tx_hash:#x} is in the database, but not known to the \
                      Ethereum chain. Submitting it."
                 );
@@ -402,15 +462,20 @@ where
             root,
             ids: idxs,
             pending_txs: txs,
+            released: 0,
         });
     }
     let leaves = client.current_leaves.clone();
+    let released_leaves = client.released_leaves.clone();
     let sender_handle = tokio::spawn(ethereum_tx_sender(
         metrics.clone(),
         client,
         db_sender,
         pending,
         num_confirmations,
+        lag_guard,
+        breaker,
+        health,
         stop.clone(),
     ));
     metrics
@@ -430,16 +495,31 @@ where
             MerkleUpdate::NewWithdraws { withdraws } => {
                 metrics.num_withdrawals.inc_by(withdraws.len() as u64);
                 for (event_index, merkle_hash) in withdraws {
-                    log::debug!("New withdraw event with index {event_index}.");
+                    tracing::debug!("New withdraw event with index {event_index}.");
+                    let (r, new_size) = add_withdraw_event(&leaves, event_index, merkle_hash)?;
+                    metrics.merkle_tree_size.set(new_size as i64);
+                    if r.is_some() {
+                        metrics.warnings_total.inc();
+                        tracing::warn!(
+                            "Duplicate event index {event_index} added to the withdraw events."
+                        );
+                    }
+                }
+            }
+            MerkleUpdate::ReleasedWithdraws { withdraws } => {
+                metrics.num_withdrawals.inc_by(withdraws.len() as u64);
+                for (event_index, merkle_hash) in withdraws {
+                    tracing::debug!("Released withdraw event with index {event_index}.");
                     let (r, new_size) = add_withdraw_event(&leaves, event_index, merkle_hash)?;
                     metrics.merkle_tree_size.set(new_size as i64);
                     if r.is_some() {
                         metrics.warnings_total.inc();
-                        log::warn!(
+                        tracing::warn!(
                             "Duplicate event index {event_index} added to the withdraw events."
                         );
                     }
                 }
+                released_leaves.fetch_add(1, Ordering::Release);
             }
             MerkleUpdate::WithdrawalCompleted {
                 receiver: _,
@@ -450,7 +530,7 @@ where
                 metrics.merkle_tree_size.set(new_size as i64);
                 if r.is_none() {
                     metrics.errors_total.inc();
-                    log::error!(
+                    tracing::error!(
                         "An event {original_event_index} marked as withdrawn, but was not known."
                     );
                 }
@@ -461,6 +541,234 @@ where
     Ok(())
 }
 
+/// How often the Merkle root on Ethereum is checked in read-only mode.
+pub const ROOT_TRACKING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The withdrawals that can be part of the next Merkle root set by another
+/// relayer.
+#[derive(Default)]
+pub(crate) struct RootTracker {
+    /// The withdrawals that are not yet completed.
+    leaves:    BTreeMap<u64, [u8; 32]>,
+    /// Withdrawals completed since the last root was recorded, in the order in
+    /// which they were completed. The other relayer removes them from its tree
+    /// in the same order, so the next root contains the ones it had not yet
+    /// seen completed when it computed the root, which is a suffix of these.
+    completed: Vec<(u64, [u8; 32])>,
+}
+
+impl RootTracker {
+    pub(crate) fn update(&mut self, metrics: &crate::metrics::Metrics, update: MerkleUpdate) {
+        match update {
+            MerkleUpdate::NewWithdraws { withdraws }
+            | MerkleUpdate::ReleasedWithdraws { withdraws } => {
+                metrics.num_withdrawals.inc_by(withdraws.len() as u64);
+                for (event_index, merkle_hash) in withdraws {
+                    tracing::debug!("New withdraw event with index {event_index}.");
+                    if self.leaves.insert(event_index, merkle_hash).is_some() {
+                        metrics.warnings_total.inc();
+                        tracing::warn!(
+                            "Duplicate event index {event_index} added to the withdraw events."
+                        );
+                    }
+                }
+            }
+            MerkleUpdate::WithdrawalCompleted {
+                receiver: _,
+                original_event_index,
+            } => {
+                metrics.num_completed_withdrawals.inc();
+                if let Some(hash) = self.leaves.remove(&original_event_index) {
+                    self.completed.push((original_event_index, hash));
+                } else {
+                    metrics.errors_total.inc();
+                    tracing::error!(
+                        "An event {original_event_index} marked as withdrawn, but was not known."
+                    );
+                }
+            }
+        }
+        metrics.merkle_tree_size.set(self.leaves.len() as i64);
+    }
+
+    /// Find the withdrawals that make up the given root. The relayer setting
+    /// the roots builds the tree from all the withdrawals it has processed and
+    /// not seen completed, ordered by event index. It processes withdrawals in
+    /// the order of their event indices, so the ones it has not yet processed
+    /// are the last ones. The candidates are thus the known withdrawals without
+    /// some of the completed ones, and without some of the last ones.
+    ///
+    /// Withdrawals held by the relayer setting the roots are not part of its
+    /// tree, and are not known here, so a root that leaves them out does not
+    /// match.
+    pub(crate) fn find_root(&self, root: [u8; 32]) -> Option<Vec<u64>> {
+        for skip in 0..=self.completed.len() {
+            let mut candidate = self.leaves.clone();
+            candidate.extend(self.completed[skip..].iter().copied());
+            let (ids, hashes): (Vec<_>, Vec<_>) = candidate.into_iter().unzip();
+            if let Some(len) = matching_prefix(&hashes, root) {
+                return Some(ids[..len].to_vec());
+            }
+        }
+        None
+    }
+
+    /// Forget the completed withdrawals once a root has been recorded. They
+    /// cannot be part of later roots.
+    pub(crate) fn root_recorded(&mut self) { self.completed.clear(); }
+}
+
+/// Find the longest prefix of `leaves` whose Merkle tree has the given root.
+/// The roots of all the prefixes are computed in a single pass, by keeping the
+/// roots of the perfect subtrees that cover the leaves so far. The tree is
+/// built the same way as [`MerkleTree::from_leaves`] builds it, where a node
+/// without a sibling is moved up unchanged, so its root combines these from
+/// the smallest subtree to the largest.
+fn matching_prefix(leaves: &[[u8; 32]], root: [u8; 32]) -> Option<usize> {
+    // The roots of the perfect subtrees together with their heights, from
+    // left to right.
+    let mut peaks: Vec<(u32, [u8; 32])> = Vec::new();
+    let mut found = None;
+    for (i, leaf) in leaves.iter().enumerate() {
+        let mut peak = (0, *leaf);
+        while let Some(&(height, left)) = peaks.last() {
+            if height != peak.0 {
+                break;
+            }
+            peaks.pop();
+            peak = (
+                height + 1,
+                Keccak256Algorithm::concat_and_hash(&left, Some(&peak.1)),
+            );
+        }
+        peaks.push(peak);
+        let prefix_root = peaks
+            .iter()
+            .rev()
+            .map(|(_, hash)| *hash)
+            .reduce(|right, left| Keccak256Algorithm::concat_and_hash(&left, Some(&right)));
+        if prefix_root == Some(root) {
+            found = Some(i + 1);
+        }
+    }
+    found
+}
+
+/// Track the Merkle roots set on Ethereum by another relayer, for use in
+/// read-only mode. The task maintains the withdrawals that are not yet
+/// completed from the updates in `receiver`, the same as
+/// [`send_merkle_root_updates`], and periodically checks the Merkle root on
+/// Ethereum. When a new root is found the withdrawals that are part of it are
+/// recorded in the database so that the API server can produce proofs for them.
+#[allow(clippy::too_many_arguments)]
+pub async fn track_merkle_roots<M: Middleware + 'static>(
+    metrics: crate::metrics::Metrics,
+    root_manager: BridgeManager<M>,
+    pending_withdrawals: Vec<(TransactionHash, WithdrawEvent)>,
+    mut receiver: tokio::sync::mpsc::Receiver<MerkleUpdate>,
+    db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
+    num_confirmations: u64,
+    health: Health,
+    mut stop: tokio::sync::watch::Receiver<()>,
+) -> anyhow::Result<()>
+where
+    M::Error: 'static, {
+    let mut tracker = RootTracker::default();
+    for (tx_hash, we) in pending_withdrawals {
+        tracker
+            .leaves
+            .insert(we.event_index, make_event_leaf_hash(tx_hash, &we)?);
+    }
+    metrics.merkle_tree_size.set(tracker.leaves.len() as i64);
+    let mut interval = tokio::time::interval(ROOT_TRACKING_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_root = None;
+    let mut last_unmatched = None;
+    loop {
+        let update = tokio::select! {
+            biased;
+
+            v = receiver.recv() => v,
+            _ = stop.changed() => None,
+            _ = interval.tick() => {
+                let root = async {
+                    let height = root_manager
+                        .client()
+                        .get_block_number()
+                        .await?
+                        .as_u64()
+                        .saturating_sub(num_confirmations);
+                    anyhow::Ok(root_manager.get_merkle_root().block(height).call().await?)
+                }
+                .await;
+                let root = match root {
+                    Ok(root) => root,
+                    Err(e) => {
+                        metrics.warnings_total.inc();
+                        health.retrying(Task::MerkleRootTracker);
+                        tracing::warn!("Unable to query the Merkle root on Ethereum: {e:#}");
+                        continue;
+                    }
+                };
+                health.progress(Task::MerkleRootTracker);
+                // No root was set yet, or it is already recorded.
+                if root == [0u8; 32] || last_root == Some(root) {
+                    continue;
+                }
+                let (response, known) = tokio::sync::oneshot::channel();
+                if db_sender
+                    .send(DatabaseOperation::IsKnownMerkleRoot { root, response })
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+                let Ok(known) = known.await else {
+                    break;
+                };
+                if !known {
+                    let Some(ids) = tracker.find_root(root) else {
+                        // The withdrawals might not have been processed yet, so
+                        // try again next time, but only warn once.
+                        if last_unmatched != Some(root) {
+                            metrics.warnings_total.inc();
+                            tracing::warn!(
+                                alert = "unmatched_merkle_root",
+                                "The Merkle root {} on Ethereum does not match the known \
+                                 withdrawals.",
+                                TransactionHash::from(root)
+                            );
+                            last_unmatched = Some(root);
+                        }
+                        continue;
+                    };
+                    tracing::info!(
+                        "Recording Merkle root {} with {} withdrawals.",
+                        TransactionHash::from(root),
+                        ids.len()
+                    );
+                    if db_sender
+                        .send(DatabaseOperation::RecordMerkleRoot { root, ids })
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                last_root = Some(root);
+                tracker.root_recorded();
+                continue;
+            }
+        };
+        let Some(update) = update else {
+            break;
+        };
+        tracker.update(&metrics, update);
+    }
+    tracing::info!("Stopping tracking Merkle roots.");
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 enum EthereumSenderError<M: Middleware> {
     #[error("A network error occurred: {0}")]
@@ -494,14 +802,21 @@ struct EthereumPendingTransactions {
     /// The list of transactions hashes and transactions.
     /// This list is never empty and is ordered by increasing gas price.
     pending_txs: Vec<(H256, ethers::prelude::Bytes)>,
+    /// The value of `released_leaves` of the client when the root was
+    /// computed.
+    released:    u64,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn ethereum_tx_sender<M: Middleware, S: Signer>(
     metrics: crate::metrics::Metrics,
     mut client: MerkleSetterClient<M, S>,
     db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
     mut pending: Option<EthereumPendingTransactions>,
     num_confirmations: u64,
+    mut lag_guard: LagGuard,
+    mut breaker: BreakerState,
+    health: Health,
     mut stop: tokio::sync::watch::Receiver<()>,
 ) -> anyhow::Result<()>
 where
@@ -513,6 +828,9 @@ where
         &db_sender,
         &mut pending,
         num_confirmations,
+        &mut lag_guard,
+        &mut breaker,
+        &health,
         &mut stop,
     )
     .await
@@ -520,7 +838,8 @@ where
         match e {
             EthereumSenderError::Retryable(e) => {
                 metrics.warnings_total.inc();
-                log::warn!(
+                health.retrying(Task::MerkleSender);
+                tracing::warn!(
                     "An error occurred when trying to send transactions to Ethereum or query the \
                      sent transaciton. Will attempt again in 10s: {e}"
                 );
@@ -530,7 +849,7 @@ where
             }
             EthereumSenderError::InternalABI(e) => {
                 metrics.errors_total.inc();
-                log::error!(
+                tracing::error!(
                     "Unable to parse responses from Ethereum. This indicates a configuration \
                      error: {e:#}"
                 );
@@ -538,7 +857,7 @@ where
             }
             EthereumSenderError::Internal(e) => {
                 metrics.errors_total.inc();
-                log::error!(
+                tracing::error!(
                     "An unrecoverable error occurred when sending transactions to Ethereum: {e:#}."
                 );
                 return Err(e);
@@ -548,6 +867,16 @@ where
     Ok(())
 }
 
+/// An interval that first ticks after `update_interval`.
+fn new_send_interval(update_interval: std::time::Duration) -> tokio::time::Interval {
+    let mut send_interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + update_interval,
+        update_interval,
+    );
+    send_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    send_interval
+}
+
 /// The task that sends updates to the Ethereum chain.
 /// - Check if there are any non-approved withdrawals, and if so makes a merkle
 ///   proof and submits it to the Ethereum chain. Before transaction submission
@@ -561,22 +890,23 @@ where
 /// one of the errors if some part of the job was interrupted.
 /// The client and `pending` are always left in a consistent state, so that a
 /// retry can be made.
+#[allow(clippy::too_many_arguments)]
 async fn ethereum_tx_sender_worker<M: Middleware, S: Signer>(
     metrics: &crate::metrics::Metrics,
     client: &mut MerkleSetterClient<M, S>,
     db_sender: &tokio::sync::mpsc::Sender<DatabaseOperation>,
     pending: &mut Option<EthereumPendingTransactions>,
     num_confirmations: u64,
+    lag_guard: &mut LagGuard,
+    breaker: &mut BreakerState,
+    health: &Health,
     stop: &mut tokio::sync::watch::Receiver<()>,
 ) -> Result<(), EthereumSenderError<M>>
 where
     M::Error: 'static,
     S::Error: 'static, {
-    let mut send_interval = tokio::time::interval_at(
-        tokio::time::Instant::now() + client.update_interval,
-        client.update_interval,
-    );
-    send_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut send_interval = new_send_interval(client.update_interval);
+    let update_requests = client.update_requests.clone();
     if db_sender
         .send(db::DatabaseOperation::SetNextMerkleUpdateTime {
             next_time: chrono::Utc::now()
@@ -587,11 +917,14 @@ where
         .is_err()
     {
         {
-            log::debug!("The database has been shut down. Stopping the transaction sender.");
+            tracing::debug!("The database has been shut down. Stopping the transaction sender.");
             return Ok(());
         }
     }
     'outer: loop {
+        if client.apply_settings() {
+            send_interval = new_send_interval(client.update_interval);
+        }
         // Handle followup for any pending transaction first.
         let pending_result =
             wait_pending_ethereum_tx(metrics, client, db_sender, pending, num_confirmations, stop)
@@ -602,10 +935,25 @@ where
                 break 'outer;
             }
             WaitPendingResult::Ok => {
-                // wait for next scheduled send if nothing is pending.
-                tokio::select! {
-                    _ = stop.changed() => true,
-                    _ = send_interval.tick() => false,
+                // wait for next scheduled send if nothing is pending. If the
+                // settings are reloaded in the meantime the new update
+                // interval applies from now on. The operator can request an
+                // update before the next scheduled one, after which the
+                // schedule starts again.
+                loop {
+                    tokio::select! {
+                        _ = stop.changed() => break true,
+                        _ = send_interval.tick() => break false,
+                        _ = update_requests.notified() => {
+                            send_interval.reset();
+                            break false;
+                        }
+                        Ok(()) = client.settings.changed() => {
+                            if client.apply_settings() {
+                                send_interval = new_send_interval(client.update_interval);
+                            }
+                        }
+                    }
                 }
             }
             WaitPendingResult::Escalate => {
@@ -617,10 +965,12 @@ where
             break 'outer;
         }
         // Now check if we have to send a new one
-        let stop_loop = send_ethereum_tx(metrics, client, db_sender, pending).await?;
+        let stop_loop =
+            send_ethereum_tx(metrics, client, db_sender, pending, lag_guard, breaker).await?;
         if stop_loop {
             break 'outer;
         }
+        health.progress(Task::MerkleSender);
         // Record in the database for next time we are going to attempt an update.
         if db_sender
             .send(db::DatabaseOperation::SetNextMerkleUpdateTime {
@@ -632,7 +982,9 @@ where
             .is_err()
         {
             {
-                log::debug!("The database has been shut down. Stopping the transaction sender.");
+                tracing::debug!(
+                    "The database has been shut down. Stopping the transaction sender."
+                );
                 return Ok(());
             }
         }
@@ -670,6 +1022,7 @@ where
         root,
         ids,
         pending_txs,
+        released,
     }) = pending
     {
         let stop = tokio::select! {
@@ -685,7 +1038,8 @@ where
                 return Ok(WaitPendingResult::Escalate);
             } else if elapsed > client.warn_duration {
                 metrics.warnings_total.inc();
-                log::warn!(
+                tracing::warn!(
+                    alert = "merkle_root_update_pending",
                     "More than {}s elapsed waiting for {pending_hash:#x} to be confirmed.",
                     elapsed.as_secs()
                 );
@@ -697,7 +1051,7 @@ where
                 .await
                 .map_err(EthereumSenderError::Retryable)?;
             let Some(receipt) = result else {
-                log::debug!("Ethereum transaction {pending_hash:#x} has no receipt.");
+                tracing::debug!("Ethereum transaction {pending_hash:#x} has no receipt.");
                 continue;
             };
             let Some(bn) = receipt.block_number else {
@@ -713,6 +1067,14 @@ where
                 .await
                 .map_err(EthereumSenderError::Retryable)?;
             if bn.saturating_add(num_confirmations.into()) <= current_block {
+                if let (Some(gas_used), Some(gas_price)) =
+                    (receipt.gas_used, receipt.effective_gas_price)
+                {
+                    // Convert from Wei to microEther.
+                    metrics
+                        .merkle_root_gas_cost
+                        .inc_by(crate::metrics::amount_to_f64(&(gas_used * gas_price).0) / 1e12);
+                }
                 let mut found = false;
                 for log in receipt.logs {
                     use ethers::contract::EthEvent;
@@ -730,10 +1092,10 @@ where
                         found = true;
                     }
                 }
-                log::info!("Withdrawal transaction confirmed in block number {bn}.");
+                tracing::info!("Withdrawal transaction confirmed in block number {bn}.");
                 if !found {
                     metrics.errors_total.inc();
-                    log::error!(
+                    tracing::error!(
                         "A transaction with hash {pending_hash:#x} did not set a Merkle root. \
                          This means it failed."
                     )
@@ -758,7 +1120,7 @@ where
                     .await
                     .is_err()
                 {
-                    log::debug!(
+                    tracing::debug!(
                         "The database has been shut down. Stopping the transaction sender."
                     );
                     return Ok(WaitPendingResult::Stop);
@@ -769,17 +1131,20 @@ where
                 // Wait until the database operation completes.
                 if receiver.await.is_err() {
                     metrics.warnings_total.inc();
-                    log::warn!("The database has been shut down. Stopping the transaction sender.");
+                    tracing::warn!(
+                        "The database has been shut down. Stopping the transaction sender."
+                    );
                     return Ok(WaitPendingResult::Stop);
                 }
                 if found {
-                    log::info!(
+                    tracing::info!(
                         "New merkle root set to {} and marked in the database.",
                         TransactionHash::from(*root)
                     );
                     // Assuming that the order is preserved by the channel.
                     // Mark the high watermark of processed ids.
                     client.max_marked_event_index = last_id;
+                    client.marked_released = *released;
                 }
                 // Transaction is confirmed, update the nonce for the next iteration
                 // of sending.
@@ -787,7 +1152,7 @@ where
                 // we have completed this nonce/root setting. Terminate normally.
                 break 'outer;
             } else {
-                log::debug!(
+                tracing::debug!(
                     "Ethereum transaction {pending_hash:#x} is in block {bn}, but not yet \
                      confirmed."
                 );
@@ -805,6 +1170,8 @@ async fn send_ethereum_tx<M: Middleware, S: Signer>(
     client: &mut MerkleSetterClient<M, S>,
     db_sender: &tokio::sync::mpsc::Sender<DatabaseOperation>,
     pending: &mut Option<EthereumPendingTransactions>,
+    lag_guard: &mut LagGuard,
+    breaker: &mut BreakerState,
 ) -> Result<bool, EthereumSenderError<M>>
 where
     M::Error: 'static,
@@ -815,6 +1182,7 @@ where
         root,
         ids,
         pending_txs,
+        released: _,
     }) = pending
     {
         let Some((_, tx)) = pending_txs.last() else
@@ -852,7 +1220,7 @@ where
         );
         if new_gas_price > client.max_gas_price {
             metrics.warnings_total.inc();
-            log::warn!(
+            tracing::warn!(
                 "Escalating would lead to transaction price that is too high {new_gas_price} > \
                  {}. Waiting for next iteration.",
                 client.max_gas_price,
@@ -867,10 +1235,16 @@ where
                 .map_err(|e| EthereumSenderError::Internal(e.into()))?;
             let tx_hash = tx.hash(&signature);
             let raw_tx = tx.rlp_signed(&signature);
-            log::debug!("Sending escalation SetMerkleRoot transaction with hash {tx_hash:#x}.");
+            tracing::debug!("Sending escalation SetMerkleRoot transaction with hash {tx_hash:#x}.");
             pending_txs.push((tx_hash, raw_tx.clone()));
             (tx_hash, raw_tx, ids.clone(), *root)
         }
+    } else if lag_guard.check(metrics, "setting new Merkle roots") {
+        // Escalating an already signed transaction above is fine, but a new root
+        // must not be computed from a stale view of the chains.
+        return Ok(false);
+    } else if breaker.check(metrics, "setting new Merkle roots") {
+        return Ok(false);
     } else {
         match client.set_merkle_root().await? {
             SetMerkleRootResult::SetTransaction {
@@ -879,8 +1253,9 @@ where
                 tx: _,
                 root,
                 ids,
+                released,
             } => {
-                log::debug!(
+                tracing::debug!(
                     "New merkle root to be set to {} using transaction {tx_hash:#x}.",
                     TransactionHash::from(root)
                 );
@@ -888,6 +1263,7 @@ where
                     root,
                     ids: ids.clone(),
                     pending_txs: vec![(tx_hash, raw_tx.clone())],
+                    released,
                 });
                 (tx_hash, raw_tx, ids, root)
             }
@@ -896,14 +1272,14 @@ where
                 current_gas_price,
             } => {
                 metrics.warnings_total.inc();
-                log::warn!(
+                tracing::warn!(
                     "Ethereum transaction price is too high {current_gas_price} > \
                      {max_gas_price}. Waiting for next iteration."
                 );
                 return Ok(false);
             }
             SetMerkleRootResult::NoPendingWithdrawals => {
-                log::debug!("No pending withdrawals. Doing nothing.");
+                tracing::debug!("No pending withdrawals. Doing nothing.");
                 return Ok(false);
             }
         }
@@ -921,19 +1297,19 @@ where
         .await
         .is_err()
     {
-        log::info!("The database has been shut down. Stopping the transaction sender.");
+        tracing::info!("The database has been shut down. Stopping the transaction sender.");
         return Ok(true);
     }
     let raw_tx = match receiver.await {
         Ok(x) => x,
         Err(_) => {
             metrics.warnings_total.inc();
-            log::warn!("The database has been shut down. Stopping the transaction sender.");
+            tracing::warn!("The database has been shut down. Stopping the transaction sender.");
             return Ok(true);
         }
     };
     let ethereum_client = client.root_manager.client();
-    log::debug!(
+    tracing::debug!(
         "Sending SetMerkleRoot transaction with hash {tx_hash:#x} to set Merkle root to {}.",
         TransactionHash::from(root)
     );
@@ -944,3 +1320,41 @@ where
     metrics.sent_ethereum_transactions.inc();
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_prefix_agrees_with_merkle_tree() {
+        let leaves = (0u8..20)
+            .map(|i| Keccak256Algorithm::hash(&[i]))
+            .collect::<Vec<_>>();
+        for len in 1..=leaves.len() {
+            let root = MerkleTree::<Keccak256Algorithm>::from_leaves(&leaves[..len])
+                .root()
+                .expect("The tree is not empty.");
+            assert_eq!(
+                matching_prefix(&leaves, root),
+                Some(len),
+                "prefix of length {len}"
+            );
+        }
+        assert_eq!(matching_prefix(&leaves, [0u8; 32]), None);
+    }
+
+    #[test]
+    fn find_root_skips_completed_withdrawals() {
+        let hash = |i: u64| Keccak256Algorithm::hash(&i.to_be_bytes());
+        let tracker = RootTracker {
+            leaves:    [1, 3, 4].into_iter().map(|i| (i, hash(i))).collect(),
+            completed: vec![(0, hash(0)), (2, hash(2))],
+        };
+        // The root was computed after withdrawal 0 was completed, but before 2
+        // was, and before withdrawal 4 was processed.
+        let root = MerkleTree::<Keccak256Algorithm>::from_leaves(&[hash(1), hash(2), hash(3)])
+            .root()
+            .expect("The tree is not empty.");
+        assert_eq!(tracker.find_root(root), Some(vec![1, 2, 3]));
+    }
+}
@@ -1,6 +1,8 @@
+use crate::health::{Health, HealthReport};
 use prometheus::{
     core::{AtomicU64, GenericGauge},
-    IntCounter, IntGauge, Registry, TextEncoder,
+    exponential_buckets, Counter, CounterVec, GaugeVec, Histogram, HistogramOpts, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
 };
 
 #[derive(Clone)]
@@ -19,6 +21,23 @@ pub struct Metrics {
     pub(crate) time_last_merkle_root: IntGauge,
     pub concordium_balance: GenericGauge<AtomicU64>,
     pub ethereum_balance: GenericGauge<AtomicU64>,
+    pub(crate) concordium_lag_seconds: IntGauge,
+    pub(crate) ethereum_lag_seconds: IntGauge,
+    pub(crate) supply_delta: GaugeVec,
+    pub(crate) circuit_breaker_tripped: IntGauge,
+    pub(crate) circuit_breaker_trips: IntCounter,
+    pub(crate) held_withdrawals: IntGauge,
+    pub(crate) held_deposits: IntGauge,
+    pub(crate) screening_matches: IntCounter,
+    pub(crate) screening_list_entries: IntGaugeVec,
+    pub(crate) deposit_latency_seconds: Histogram,
+    pub(crate) withdrawal_approval_latency_seconds: Histogram,
+    pub(crate) withdrawal_claim_latency_seconds: Histogram,
+    pub(crate) deposited_volume: CounterVec,
+    pub(crate) withdrawn_volume: CounterVec,
+    pub(crate) merkle_root_gas_cost: Counter,
+    pub(crate) concordium_fees_spent: IntCounterVec,
+    pub(crate) oldest_pending_seconds: IntGaugeVec,
 }
 
 impl Metrics {
@@ -103,6 +122,142 @@ impl Metrics {
         )?;
         registry.register(Box::new(ethereum_balance.clone()))?;
 
+        let concordium_lag_seconds = IntGauge::new(
+            "concordium_lag_seconds",
+            "Age, in seconds, of the last finalized block of the Concordium node.",
+        )?;
+        registry.register(Box::new(concordium_lag_seconds.clone()))?;
+
+        let ethereum_lag_seconds = IntGauge::new(
+            "ethereum_lag_seconds",
+            "Age, in seconds, of the latest block of the Ethereum API.",
+        )?;
+        registry.register(Box::new(ethereum_lag_seconds.clone()))?;
+
+        let supply_delta = GaugeVec::new(
+            Opts::new(
+                "supply_delta",
+                "Balance of the Ethereum vault minus the amount accounted for on Concordium, in \
+                 units of the token.",
+            ),
+            &["token", "root_token"],
+        )?;
+        registry.register(Box::new(supply_delta.clone()))?;
+
+        let circuit_breaker_tripped = IntGauge::new(
+            "circuit_breaker_tripped",
+            "Whether the circuit breaker is tripped (1) or not (0).",
+        )?;
+        registry.register(Box::new(circuit_breaker_tripped.clone()))?;
+
+        let circuit_breaker_trips = IntCounter::new(
+            "circuit_breaker_trips",
+            "Number of trips of the circuit breaker since start.",
+        )?;
+        registry.register(Box::new(circuit_breaker_trips.clone()))?;
+
+        let held_withdrawals = IntGauge::new(
+            "held_withdrawals",
+            "Number of withdrawals currently held back from the Merkle tree.",
+        )?;
+        registry.register(Box::new(held_withdrawals.clone()))?;
+
+        let held_deposits = IntGauge::new(
+            "held_deposits",
+            "Number of deposits currently held because they matched a screening list.",
+        )?;
+        registry.register(Box::new(held_deposits.clone()))?;
+
+        let screening_matches = IntCounter::new(
+            "screening_matches",
+            "Number of deposits and withdrawals that matched a screening list since start.",
+        )?;
+        registry.register(Box::new(screening_matches.clone()))?;
+
+        let screening_list_entries = IntGaugeVec::new(
+            Opts::new(
+                "screening_list_entries",
+                "Number of addresses on the screening list of each network.",
+            ),
+            &["network"],
+        )?;
+        registry.register(Box::new(screening_list_entries.clone()))?;
+
+        let deposit_latency_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "deposit_latency_seconds",
+                "Time, in seconds, from the Ethereum block of a deposit to the Concordium block \
+                 that completed it.",
+            )
+            .buckets(exponential_buckets(15.0, 2.0, 12)?),
+        )?;
+        registry.register(Box::new(deposit_latency_seconds.clone()))?;
+
+        let withdrawal_approval_latency_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "withdrawal_approval_latency_seconds",
+                "Time, in seconds, from the Concordium block of a withdrawal to the confirmation \
+                 of the Merkle root that includes it.",
+            )
+            .buckets(exponential_buckets(60.0, 2.0, 12)?),
+        )?;
+        registry.register(Box::new(withdrawal_approval_latency_seconds.clone()))?;
+
+        let withdrawal_claim_latency_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "withdrawal_claim_latency_seconds",
+                "Time, in seconds, from the Concordium block of a withdrawal to the Ethereum \
+                 block in which it was claimed.",
+            )
+            .buckets(exponential_buckets(60.0, 2.0, 16)?),
+        )?;
+        registry.register(Box::new(withdrawal_claim_latency_seconds.clone()))?;
+
+        let deposited_volume = CounterVec::new(
+            Opts::new(
+                "deposited_volume",
+                "Amount, in the smallest unit of the token, deposited on Ethereum since start.",
+            ),
+            &["token"],
+        )?;
+        registry.register(Box::new(deposited_volume.clone()))?;
+
+        let withdrawn_volume = CounterVec::new(
+            Opts::new(
+                "withdrawn_volume",
+                "Amount, in the smallest unit of the token, withdrawn on Concordium since start.",
+            ),
+            &["token"],
+        )?;
+        registry.register(Box::new(withdrawn_volume.clone()))?;
+
+        // A Merkle root approves the withdrawals of all tokens, so its cost is not
+        // labelled by token.
+        let merkle_root_gas_cost = Counter::new(
+            "merkle_root_gas_cost",
+            "Amount, in microEther, spent on gas for setting Merkle roots since start.",
+        )?;
+        registry.register(Box::new(merkle_root_gas_cost.clone()))?;
+
+        let concordium_fees_spent = IntCounterVec::new(
+            Opts::new(
+                "concordium_fees_spent",
+                "Amount, in microCCD, spent on fees for transactions submitted by the relayer \
+                 since start.",
+            ),
+            &["token"],
+        )?;
+        registry.register(Box::new(concordium_fees_spent.clone()))?;
+
+        let oldest_pending_seconds = IntGaugeVec::new(
+            Opts::new(
+                "oldest_pending_seconds",
+                "Age, in seconds, of the oldest pending item in each queue.",
+            ),
+            &["queue"],
+        )?;
+        registry.register(Box::new(oldest_pending_seconds.clone()))?;
+
         Ok((registry, Self {
             merkle_tree_size,
             warnings_total,
@@ -118,10 +273,66 @@ impl Metrics {
             num_completed_deposits,
             concordium_balance,
             ethereum_balance,
+            concordium_lag_seconds,
+            ethereum_lag_seconds,
+            supply_delta,
+            circuit_breaker_tripped,
+            circuit_breaker_trips,
+            held_withdrawals,
+            held_deposits,
+            screening_matches,
+            screening_list_entries,
+            deposit_latency_seconds,
+            withdrawal_approval_latency_seconds,
+            withdrawal_claim_latency_seconds,
+            deposited_volume,
+            withdrawn_volume,
+            merkle_root_gas_cost,
+            concordium_fees_spent,
+            oldest_pending_seconds,
         }))
     }
 }
 
+/// How often the age of the oldest pending items is updated.
+const PENDING_AGES_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Periodically ask the database worker to update the
+/// `oldest_pending_seconds` metric.
+pub async fn report_pending_ages(
+    db_sender: tokio::sync::mpsc::Sender<crate::db::DatabaseOperation>,
+) {
+    let mut interval = tokio::time::interval(PENDING_AGES_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if db_sender
+            .send(crate::db::DatabaseOperation::UpdatePendingAges)
+            .await
+            .is_err()
+        {
+            tracing::debug!("The database has been shut down. Stopping reporting pending ages.");
+            return;
+        }
+    }
+}
+
+/// Record a latency in the histogram. Negative latencies, which can occur
+/// because the clocks of the two chains are not synchronized, are recorded as
+/// 0.
+pub(crate) fn observe_latency(histogram: &Histogram, latency: chrono::Duration) {
+    histogram.observe(latency.num_milliseconds().max(0) as f64 / 1000.0);
+}
+
+/// Convert an amount given as little-endian 64-bit digits to a float for use
+/// in metrics. Large amounts lose precision.
+pub(crate) fn amount_to_f64(digits: &[u64]) -> f64 {
+    digits
+        .iter()
+        .rev()
+        .fold(0.0, |acc, &d| acc * 18_446_744_073_709_551_616.0 + d as f64)
+}
+
 async fn text_metrics(
     axum::extract::State(registry): axum::extract::State<Registry>,
 ) -> Result<String, axum::response::ErrorResponse> {
@@ -132,16 +343,43 @@ async fn text_metrics(
         .map_err(|_| "Unable to encode metrics.")?)
 }
 
+/// Report on all the tasks. This always succeeds as long as the server is
+/// running.
+async fn health(
+    axum::extract::State(health): axum::extract::State<Health>,
+) -> axum::Json<HealthReport> {
+    axum::Json(health.report())
+}
+
+/// Report on all the tasks. Fails with status 503 if any of the tasks are
+/// stalled or retrying.
+async fn ready(
+    axum::extract::State(health): axum::extract::State<Health>,
+) -> (axum::http::StatusCode, axum::Json<HealthReport>) {
+    let report = health.report();
+    let status = if report.ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, axum::Json(report))
+}
+
 /// Start the server. The task only terminates if the server terminates, i.e.,
 /// if it crashes.
 pub async fn start_prometheus_server(
     addr: std::net::SocketAddr,
     registry: Registry,
+    health_state: Health,
 ) -> anyhow::Result<()> {
-    // build our application with a single route
+    let health_routes = axum::Router::new()
+        .route("/health", axum::routing::get(health))
+        .route("/ready", axum::routing::get(ready))
+        .with_state(health_state);
     let app = axum::Router::new()
         .route("/metrics", axum::routing::get(text_metrics))
         .with_state(registry)
+        .merge(health_routes)
         .layer(tower_http::timeout::TimeoutLayer::new(
             std::time::Duration::from_millis(1000),
         ))
@@ -0,0 +1,304 @@
+//! Rebuilding the database from the history of both chains, for recovery when
+//! the database is lost. The events emitted by the state sender on Ethereum
+//! and by the bridge manager on Concordium are replayed in the order in which
+//! they happened, so that deposits are seen on Ethereum before they are
+//! completed on Concordium, and withdrawals are seen on Concordium before they
+//! are completed on Ethereum. The Merkle roots set on Ethereum are matched
+//! against the withdrawals known at the time they were set, the same way as in
+//! read-only mode.
+use crate::{
+    circuit_breaker::{self, Triggers},
+    concordium_contracts::{query_block, BridgeManager, BridgeManagerClient},
+    db::{self, ConcordiumBlockEvents, Database, DatabaseOperation},
+    ethereum::{self, EthBlockEvent, EthBlockEvents},
+    merkle::RootTracker,
+    screening::Screener,
+    state_sender::StateSender,
+    withdrawal_holds::HoldPolicy,
+};
+use concordium_rust_sdk::{
+    types::{hashes::TransactionHash, AbsoluteBlockHeight, ContractAddress},
+    v2::BlockIdentifier,
+};
+use ethers::prelude::Middleware;
+use futures::StreamExt;
+use std::collections::{BTreeMap, VecDeque};
+
+/// The finalized Concordium blocks in a range of heights, queried in chunks.
+struct ConcordiumBlocks {
+    client:       BridgeManagerClient,
+    /// The next height to query.
+    next:         AbsoluteBlockHeight,
+    /// The last height to query.
+    last:         AbsoluteBlockHeight,
+    max_parallel: usize,
+    buffer:       VecDeque<ConcordiumBlockEvents>,
+}
+
+impl ConcordiumBlocks {
+    /// Get the next block, if there is one and it was made no later than
+    /// `until`. If `until` is [`None`] the remaining blocks are returned.
+    async fn next_until(
+        &mut self,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> anyhow::Result<Option<ConcordiumBlockEvents>> {
+        if self.buffer.is_empty() {
+            self.query_chunk().await?;
+        }
+        match self.buffer.front() {
+            Some((block, _)) if until.map_or(true, |t| block.block_slot_time <= t) => {
+                Ok(self.buffer.pop_front())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn query_chunk(&mut self) -> anyhow::Result<()> {
+        let mut futures = futures::stream::FuturesOrdered::new();
+        while self.next <= self.last && futures.len() < self.max_parallel {
+            futures.push_back(query_block(
+                self.client.client.clone(),
+                BlockIdentifier::AbsoluteHeight(self.next),
+            ));
+            self.next = self.next.next();
+        }
+        while let Some(result) = futures.next().await {
+            let (block, summaries) = result?;
+            let mut transaction_events = Vec::new();
+            for summary in summaries {
+                let events = self.client.extract_events(&summary)?;
+                if !events.is_empty() {
+                    transaction_events.push((summary.hash, events));
+                }
+            }
+            self.buffer.push_back((block, transaction_events));
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild an empty database from the history of both chains, up to the last
+/// finalized block on Concordium and the last block on Ethereum with
+/// `num_confirmations` confirmations.
+///
+/// If a bridge manager is given, transactions are made for deposits and token
+/// maps that are not yet completed on Concordium. They are stored as pending
+/// in the database and submitted when the relayer starts.
+#[allow(clippy::too_many_arguments)]
+pub async fn rebuild_database<M: Middleware + 'static>(
+    metrics: &crate::metrics::Metrics,
+    db: &mut Database,
+    mut bridge_manager: Option<BridgeManager>,
+    client: BridgeManagerClient,
+    bridge_manager_address: ContractAddress,
+    state_sender: &StateSender<M>,
+    state_sender_creation_block_number: u64,
+    num_confirmations: u64,
+    max_parallel: u32,
+) -> anyhow::Result<()>
+where
+    M::Error: 'static, {
+    anyhow::ensure!(
+        db.is_empty().await?,
+        "The database already contains events. Only an empty database can be rebuilt."
+    );
+    // Subscribers of the API server are not interested in a notification for
+    // every transfer in the history.
+    db.suppress_notifications();
+    // No anomalies are reported and nothing is held by limits or screening
+    // while replaying, since the history has already been acted on.
+    let (breaker_sender, _breaker_receiver) = tokio::sync::mpsc::channel(1);
+    let (breaker, _, _) = circuit_breaker::circuit_breaker(
+        metrics.clone(),
+        Triggers {
+            supply_invariant:    false,
+            unknown_merkle_root: false,
+            unmatched_deposit:   false,
+            withdrawal_limits:   Vec::new(),
+            withdrawal_window:   chrono::Duration::zero(),
+        },
+        None,
+        breaker_sender,
+        false,
+    );
+    let holds = HoldPolicy {
+        limits:        Vec::new(),
+        window:        chrono::Duration::zero(),
+        release_delay: None,
+    };
+    let screener = Screener::empty();
+
+    let ethereum_height = state_sender
+        .client()
+        .get_block_number()
+        .await?
+        .as_u64()
+        .saturating_sub(num_confirmations);
+    let mut ethereum_blocks = BTreeMap::<u64, (Vec<EthBlockEvent>, Vec<[u8; 32]>)>::new();
+    let mut block_times = BTreeMap::new();
+    if ethereum_height >= state_sender_creation_block_number {
+        tracing::info!(
+            "Querying state sender events in blocks \
+             {state_sender_creation_block_number}..={ethereum_height}."
+        );
+        let events = ethereum::get_eth_block_events(
+            state_sender,
+            state_sender_creation_block_number,
+            ethereum_height,
+        )
+        .await?;
+        for event in events.events {
+            ethereum_blocks
+                .entry(event.block_number)
+                .or_default()
+                .0
+                .push(event);
+        }
+        block_times = events.block_times;
+        for (block_number, root) in ethereum::get_merkle_roots(
+            state_sender,
+            state_sender_creation_block_number,
+            ethereum_height,
+        )
+        .await?
+        {
+            ethereum_blocks
+                .entry(block_number)
+                .or_default()
+                .1
+                .push(root);
+            if let std::collections::btree_map::Entry::Vacant(entry) =
+                block_times.entry(block_number)
+            {
+                entry.insert(ethereum::get_block_time(state_sender, block_number).await?);
+            }
+        }
+    }
+
+    let mut concordium_blocks = {
+        let mut node = client.client.clone();
+        let (first, _, _) = node
+            .find_instance_creation(.., bridge_manager_address)
+            .await?;
+        let last = node
+            .get_block_info(BlockIdentifier::LastFinal)
+            .await?
+            .response
+            .block_height;
+        tracing::info!("Querying bridge manager events in blocks {first}..={last} on Concordium.");
+        ConcordiumBlocks {
+            client,
+            next: first,
+            last,
+            max_parallel: max_parallel.max(1) as usize,
+            buffer: VecDeque::new(),
+        }
+    };
+
+    let mut tracker = RootTracker::default();
+    let mut num_roots = 0;
+    let mut num_unmatched_roots = 0;
+    for (block_number, (events, roots)) in ethereum_blocks {
+        let block_time = block_times[&block_number];
+        // Concordium events are replayed first if they happened no later than the
+        // events in this block.
+        while let Some(block) = concordium_blocks.next_until(Some(block_time)).await? {
+            // No transactions are made for Concordium events, so the bridge manager is
+            // not needed. Without it, deposits and token maps made by transactions that
+            // are not in the database are not reported.
+            let updates = db::replay_events(
+                metrics,
+                db,
+                DatabaseOperation::ConcordiumEvents {
+                    blocks: vec![block],
+                },
+                None,
+                &breaker,
+                &holds,
+                &screener,
+            )
+            .await?;
+            for update in updates {
+                tracker.update(metrics, update);
+            }
+        }
+        tracing::debug!("Replaying Ethereum block {block_number}.");
+        let updates = db::replay_events(
+            metrics,
+            db,
+            DatabaseOperation::EthereumEvents {
+                events: EthBlockEvents {
+                    last_number: block_number,
+                    events,
+                    block_times: BTreeMap::from([(block_number, block_time)]),
+                },
+            },
+            bridge_manager.as_mut(),
+            &breaker,
+            &holds,
+            &screener,
+        )
+        .await?;
+        for update in updates {
+            tracker.update(metrics, update);
+        }
+        for root in roots {
+            num_roots += 1;
+            let ids = tracker.find_root(root);
+            if ids.is_some() {
+                tracker.root_recorded();
+            } else {
+                num_unmatched_roots += 1;
+                metrics.warnings_total.inc();
+                tracing::warn!(
+                    "The Merkle root {} set in block {block_number} does not match the known \
+                     withdrawals.",
+                    TransactionHash::from(root)
+                );
+            }
+            db.record_merkle_root(root, ids.as_deref().unwrap_or_default())
+                .await?;
+        }
+    }
+    while let Some(block) = concordium_blocks.next_until(None).await? {
+        let updates = db::replay_events(
+            metrics,
+            db,
+            DatabaseOperation::ConcordiumEvents {
+                blocks: vec![block],
+            },
+            None,
+            &breaker,
+            &holds,
+            &screener,
+        )
+        .await?;
+        for update in updates {
+            tracker.update(metrics, update);
+        }
+    }
+    // Record the last processed Ethereum block even if it has no events.
+    db::replay_events(
+        metrics,
+        db,
+        DatabaseOperation::EthereumEvents {
+            events: EthBlockEvents {
+                last_number: ethereum_height,
+                events:      Vec::new(),
+                block_times: BTreeMap::new(),
+            },
+        },
+        bridge_manager.as_mut(),
+        &breaker,
+        &holds,
+        &screener,
+    )
+    .await?;
+    tracing::info!(
+        "Rebuilt the database up to block {ethereum_height} on Ethereum and block {} on \
+         Concordium. Recorded {num_roots} Merkle roots, of which {num_unmatched_roots} did not \
+         match the known withdrawals.",
+        concordium_blocks.last
+    );
+    Ok(())
+}
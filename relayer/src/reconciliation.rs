@@ -0,0 +1,368 @@
+//! A monitor of the supply invariant of the bridge. For each mapped token the
+//! amount locked in the Ethereum vault must equal the amount accounted for on
+//! Concordium, that is
+//!
+//! vault balance = supply on Concordium + pending withdrawals + pending
+//! deposits
+//!
+//! where pending withdrawals are withdrawals done on Concordium but not yet
+//! completed on Ethereum, and pending deposits are deposits done on Ethereum
+//! but not yet completed on Concordium. Both chains are queried at the heights
+//! the relayer has last processed, so that the pending amounts, which are read
+//! from the database, are consistent with the chain state.
+use crate::{
+    circuit_breaker::{CircuitBreaker, TripCause},
+    config::SettingsReceiver,
+    db::{DatabaseOperation, SupplyDiscrepancy, SupplySnapshot},
+    erc20::Erc20,
+    root_chain_manager::BridgeManager,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    cis2::{self, TokenId},
+    common::types::Amount,
+    smart_contracts::common::{self as contracts_common, Deserial, Serial},
+    types::{
+        smart_contracts::{
+            ContractContext, InvokeContractResult, OwnedParameter, OwnedReceiveName,
+        },
+        AbsoluteBlockHeight, Address, ContractAddress,
+    },
+    v2::{self, BlockIdentifier},
+};
+use ethabi::ethereum_types::{H160, U256};
+use ethers::prelude::Middleware;
+use num_bigint::{BigInt, BigUint};
+use std::collections::BTreeMap;
+
+/// Maximum number of addresses to query in a single `balanceOf` invocation.
+const BALANCE_QUERY_CHUNK: usize = 100;
+
+/// Energy allowed for invoking view functions of token contracts.
+const ALLOWED_VIEW_NRG: u64 = 1_000_000;
+
+#[derive(contracts_common::Serialize)]
+/// Return value of the `viewTokenOwners` entrypoint of the `cis2-bridgeable`
+/// contract.
+struct ViewTokenOwners {
+    token_owners: Vec<Address>,
+}
+
+/// Parameter of the CIS-2 `balanceOf` entrypoint. CIS-2 uses a 2 byte length
+/// prefix for the list of queries.
+struct BalanceOfQueries<'a> {
+    token_id:  &'a TokenId,
+    addresses: &'a [Address],
+}
+
+impl<'a> Serial for BalanceOfQueries<'a> {
+    fn serial<W: contracts_common::Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        (self.addresses.len() as u16).serial(out)?;
+        for address in self.addresses {
+            self.token_id.serial(out)?;
+            address.serial(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Return value of the CIS-2 `balanceOf` entrypoint.
+struct BalanceOfResponse(Vec<cis2::TokenAmount>);
+
+impl Deserial for BalanceOfResponse {
+    fn deserial<R: contracts_common::Read>(source: &mut R) -> contracts_common::ParseResult<Self> {
+        let len = u16::deserial(source)?;
+        let mut amounts = Vec::with_capacity(len.into());
+        for _ in 0..len {
+            amounts.push(cis2::TokenAmount::deserial(source)?);
+        }
+        Ok(Self(amounts))
+    }
+}
+
+/// Invoke a view entrypoint of a `cis2-bridgeable` contract and return the
+/// return value.
+async fn invoke_token_view(
+    client: &mut v2::Client,
+    contract: ContractAddress,
+    entrypoint: &str,
+    parameter: Vec<u8>,
+    height: AbsoluteBlockHeight,
+) -> anyhow::Result<Vec<u8>> {
+    let ctx = ContractContext {
+        invoker: None,
+        contract,
+        amount: Amount::from_micro_ccd(0),
+        method: OwnedReceiveName::new_unchecked(format!("cis2-bridgeable.{entrypoint}")),
+        parameter: OwnedParameter::new_unchecked(parameter),
+        energy: ALLOWED_VIEW_NRG.into(),
+    };
+    let result = client
+        .invoke_instance(BlockIdentifier::AbsoluteHeight(height), &ctx)
+        .await?;
+    match result.response {
+        InvokeContractResult::Success { return_value, .. } => {
+            Ok(return_value.context("Unexpected response.")?.value)
+        }
+        InvokeContractResult::Failure { reason, .. } => {
+            anyhow::bail!("Invocation of {entrypoint} failed: {reason:?}")
+        }
+    }
+}
+
+/// Compute the total supply of the bridged token in the given contract by
+/// summing the balances of all the token owners.
+async fn concordium_supply(
+    client: &mut v2::Client,
+    contract: ContractAddress,
+    height: AbsoluteBlockHeight,
+) -> anyhow::Result<BigUint> {
+    // TODO: Hardcoded token ID. Works with contracts as they are now.
+    let token_id = TokenId::new_unchecked(vec![0u8; 8]);
+    let owners = invoke_token_view(client, contract, "viewTokenOwners", Vec::new(), height).await?;
+    let owners: ViewTokenOwners = contracts_common::from_bytes(&owners)
+        .context("Unable to parse the response of viewTokenOwners.")?;
+    let mut supply = BigUint::from(0u8);
+    for addresses in owners.token_owners.chunks(BALANCE_QUERY_CHUNK) {
+        let parameter = contracts_common::to_bytes(&BalanceOfQueries {
+            token_id: &token_id,
+            addresses,
+        });
+        let balances = invoke_token_view(client, contract, "balanceOf", parameter, height).await?;
+        let BalanceOfResponse(amounts) = contracts_common::from_bytes(&balances)
+            .context("Unable to parse the response of balanceOf.")?;
+        anyhow::ensure!(
+            amounts.len() == addresses.len(),
+            "balanceOf returned an unexpected number of balances."
+        );
+        for amount in amounts {
+            supply += amount.0;
+        }
+    }
+    Ok(supply)
+}
+
+/// Get the balance of the vault that holds the given root token.
+async fn vault_balance<M: Middleware + 'static>(
+    root_manager: &BridgeManager<M>,
+    ether_address: H160,
+    root: H160,
+    height: u64,
+) -> anyhow::Result<U256>
+where
+    M::Error: 'static, {
+    let token_type = root_manager
+        .token_to_type(root)
+        .block(height)
+        .call()
+        .await?;
+    let vault = root_manager
+        .type_to_vault(token_type)
+        .block(height)
+        .call()
+        .await?;
+    anyhow::ensure!(!vault.is_zero(), "No vault registered for token {root:#x}.");
+    if root == ether_address {
+        Ok(root_manager
+            .client()
+            .get_balance(vault, Some(height.into()))
+            .await?)
+    } else {
+        Ok(Erc20::new(root, root_manager.client())
+            .balance_of(vault)
+            .block(height)
+            .call()
+            .await?)
+    }
+}
+
+fn u256_to_biguint(x: U256) -> BigUint {
+    let mut buf = [0u8; 32];
+    x.to_little_endian(&mut buf);
+    BigUint::from_bytes_le(&buf)
+}
+
+/// Convert the delta to a floating point number in the units of the token for
+/// reporting as a metric.
+fn delta_as_units(delta: &BigInt, decimals: u8) -> f64 {
+    delta.to_string().parse::<f64>().unwrap_or(f64::NAN) / 10f64.powi(decimals.into())
+}
+
+/// Check the supply invariant of all the tokens in the snapshot and return
+/// the discrepancies.
+async fn check_supply<M: Middleware + 'static>(
+    metrics: &crate::metrics::Metrics,
+    client: &mut v2::Client,
+    root_manager: &BridgeManager<M>,
+    snapshot: SupplySnapshot,
+) -> anyhow::Result<Vec<SupplyDiscrepancy>>
+where
+    M::Error: 'static, {
+    let ether_address = root_manager
+        .ether_address()
+        .block(snapshot.ethereum_height)
+        .call()
+        .await?;
+    let mut discrepancies = Vec::new();
+    for token in snapshot.tokens {
+        let vault_balance = u256_to_biguint(
+            vault_balance(
+                root_manager,
+                ether_address,
+                token.root,
+                snapshot.ethereum_height,
+            )
+            .await
+            .with_context(|| format!("Unable to get vault balance of {}.", token.name))?,
+        );
+        let concordium_supply = concordium_supply(client, token.child, snapshot.concordium_height)
+            .await
+            .with_context(|| format!("Unable to get the supply of {}.", token.name))?;
+        let expected = &concordium_supply + &token.pending_withdrawals + &token.pending_deposits;
+        let delta = BigInt::from(vault_balance.clone()) - BigInt::from(expected);
+        metrics
+            .supply_delta
+            .with_label_values(&[&token.name, &format!("{:#x}", token.root)])
+            .set(delta_as_units(&delta, token.decimals));
+        tracing::debug!(
+            "Supply of {} ({:#x}): vault balance {vault_balance}, Concordium supply \
+             {concordium_supply}, pending withdrawals {}, pending deposits {}.",
+            token.name,
+            token.root,
+            token.pending_withdrawals,
+            token.pending_deposits
+        );
+        if delta != BigInt::from(0u8) {
+            discrepancies.push(SupplyDiscrepancy {
+                root: token.root,
+                child: token.child,
+                ethereum_height: snapshot.ethereum_height,
+                concordium_height: snapshot.concordium_height,
+                vault_balance,
+                concordium_supply,
+                pending_withdrawals: token.pending_withdrawals,
+                pending_deposits: token.pending_deposits,
+                delta,
+            });
+        }
+    }
+    Ok(discrepancies)
+}
+
+/// Periodically check the supply invariant for all mapped tokens, export the
+/// difference between the vault balance and the amount accounted for on
+/// Concordium as a metric, and record discrepancies in the database. A
+/// discrepancy is recorded when it is first observed or when the difference
+/// changes. The check interval is taken from the settings, and a new interval
+/// applies from when the settings are reloaded.
+pub async fn monitor_supply<M: Middleware + 'static>(
+    metrics: crate::metrics::Metrics,
+    mut client: v2::Client,
+    root_manager: BridgeManager<M>,
+    db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>,
+    breaker: CircuitBreaker,
+    mut settings: SettingsReceiver,
+) -> anyhow::Result<()>
+where
+    M::Error: 'static, {
+    let mut interval = tokio::time::interval(settings.borrow_and_update().supply_check_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The last observed delta for each token.
+    let mut last_deltas = BTreeMap::<H160, BigInt>::new();
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            Ok(()) = settings.changed() => {
+                let check_interval = settings.borrow().supply_check_interval;
+                if check_interval != interval.period() {
+                    interval = tokio::time::interval_at(
+                        tokio::time::Instant::now() + check_interval,
+                        check_interval,
+                    );
+                    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                }
+                continue;
+            }
+        }
+        let (response, receiver) = tokio::sync::oneshot::channel();
+        if db_sender
+            .send(DatabaseOperation::GetSupplySnapshot { response })
+            .await
+            .is_err()
+        {
+            tracing::info!("The database channel is closed. Stopping the supply monitor.");
+            return Ok(());
+        }
+        let Ok(snapshot) = receiver.await else {
+            tracing::info!("The database worker stopped. Stopping the supply monitor.");
+            return Ok(());
+        };
+        let Some(snapshot) = snapshot else {
+            tracing::debug!("No blocks processed yet. Skipping the supply check.");
+            continue;
+        };
+        let (ethereum_height, concordium_height) =
+            (snapshot.ethereum_height, snapshot.concordium_height);
+        let discrepancies = match check_supply(&metrics, &mut client, &root_manager, snapshot).await
+        {
+            Ok(discrepancies) => discrepancies,
+            Err(e) => {
+                metrics.warnings_total.inc();
+                tracing::warn!("Unable to check the supply invariant: {e:#}");
+                continue;
+            }
+        };
+        tracing::debug!(
+            "Checked the supply invariant at Ethereum height {ethereum_height} and Concordium \
+             height {concordium_height}."
+        );
+        let mut new_discrepancies = Vec::new();
+        let mut current_deltas = BTreeMap::new();
+        for discrepancy in discrepancies {
+            current_deltas.insert(discrepancy.root, discrepancy.delta.clone());
+            if last_deltas.get(&discrepancy.root) == Some(&discrepancy.delta) {
+                continue;
+            }
+            if discrepancy.delta < BigInt::from(0u8) {
+                metrics.errors_total.inc();
+                tracing::error!(
+                    alert = "supply_deficit",
+                    critical = true,
+                    "The vault for token {:#x} holds {} less than is accounted for on Concordium.",
+                    discrepancy.root,
+                    -&discrepancy.delta
+                );
+                breaker.report(TripCause::SupplyInvariant {
+                    root_token: discrepancy.root,
+                    delta:      discrepancy.delta.clone(),
+                });
+            } else {
+                metrics.warnings_total.inc();
+                tracing::warn!(
+                    alert = "supply_surplus",
+                    "The vault for token {:#x} holds {} more than is accounted for on Concordium.",
+                    discrepancy.root,
+                    discrepancy.delta
+                );
+            }
+            new_discrepancies.push(discrepancy);
+        }
+        for root in last_deltas.keys() {
+            if !current_deltas.contains_key(root) {
+                tracing::info!("The supply of token {root:#x} is consistent again.");
+            }
+        }
+        last_deltas = current_deltas;
+        if !new_discrepancies.is_empty()
+            && db_sender
+                .send(DatabaseOperation::InsertSupplyDiscrepancies {
+                    discrepancies: new_discrepancies,
+                })
+                .await
+                .is_err()
+        {
+            tracing::info!("The database channel is closed. Stopping the supply monitor.");
+            return Ok(());
+        }
+    }
+}
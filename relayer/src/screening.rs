@@ -0,0 +1,226 @@
+//! Screening of addresses against lists of sanctioned addresses. The lists are
+//! loaded from local files, and reloaded when the files change. Deposits and
+//! withdrawals that involve a listed address are held instead of relayed.
+use crate::{concordium_contracts::WithdrawEvent, db::Network};
+use anyhow::Context;
+use concordium_rust_sdk::{id::types::AccountAddress, types::Address};
+use ethabi::ethereum_types::H160;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// How often the list files are checked for changes.
+const RELOAD_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// All aliases of a Concordium account share the first 29 bytes of the
+/// address.
+const ACCOUNT_ALIAS_PREFIX_LEN: usize = 29;
+
+fn alias_prefix(address: &[u8; 32]) -> [u8; ACCOUNT_ALIAS_PREFIX_LEN] {
+    let mut prefix = [0u8; ACCOUNT_ALIAS_PREFIX_LEN];
+    prefix.copy_from_slice(&address[..ACCOUNT_ALIAS_PREFIX_LEN]);
+    prefix
+}
+
+#[derive(Debug, Default)]
+/// The addresses that are screened.
+struct ScreeningLists {
+    ethereum:   HashSet<H160>,
+    /// Concordium accounts, identified by the part of the address that is
+    /// common to all aliases of the account.
+    concordium: HashSet<[u8; ACCOUNT_ALIAS_PREFIX_LEN]>,
+}
+
+#[derive(Debug, Clone)]
+/// An address that is on one of the screening lists.
+pub struct ScreeningMatch {
+    pub network: Network,
+    pub address: Vec<u8>,
+}
+
+impl std::fmt::Display for ScreeningMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.network {
+            Network::Ethereum => write!(f, "Ethereum address 0x{}", hex::encode(&self.address)),
+            Network::Concordium => match <[u8; 32]>::try_from(self.address.as_slice()) {
+                Ok(address) => write!(f, "Concordium account {}", AccountAddress(address)),
+                Err(_) => write!(f, "Concordium account {}", hex::encode(&self.address)),
+            },
+        }
+    }
+}
+
+/// Read a list of addresses, one per line. Empty lines and everything after
+/// `#` on a line are ignored.
+fn read_list<A>(path: &Path, parse: impl Fn(&str) -> anyhow::Result<A>) -> anyhow::Result<Vec<A>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read screening list {}.", path.display()))?;
+    let mut out = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if !line.is_empty() {
+            out.push(parse(line).with_context(|| {
+                format!("Invalid address on line {} of {}.", i + 1, path.display())
+            })?);
+        }
+    }
+    Ok(out)
+}
+
+impl ScreeningLists {
+    fn load(ethereum: Option<&Path>, concordium: Option<&Path>) -> anyhow::Result<Self> {
+        let mut lists = Self::default();
+        if let Some(path) = ethereum {
+            lists.ethereum = read_list(path, |s| Ok(s.parse::<H160>()?))?
+                .into_iter()
+                .collect();
+        }
+        if let Some(path) = concordium {
+            lists.concordium = read_list(path, |s| Ok(s.parse::<AccountAddress>()?))?
+                .into_iter()
+                .map(|a| alias_prefix(&a.0))
+                .collect();
+        }
+        Ok(lists)
+    }
+
+    fn ethereum_match(&self, address: H160) -> Option<ScreeningMatch> {
+        self.ethereum.contains(&address).then(|| ScreeningMatch {
+            network: Network::Ethereum,
+            address: address.as_bytes().to_vec(),
+        })
+    }
+
+    fn concordium_match(&self, address: &[u8; 32]) -> Option<ScreeningMatch> {
+        self.concordium
+            .contains(&alias_prefix(address))
+            .then(|| ScreeningMatch {
+                network: Network::Concordium,
+                address: address.to_vec(),
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A handle used to screen deposits and withdrawals against the current
+/// lists.
+pub struct Screener {
+    lists: tokio::sync::watch::Receiver<Arc<ScreeningLists>>,
+}
+
+impl Screener {
+    /// A screener with empty lists, which matches nothing.
+    pub fn empty() -> Self {
+        let (_, lists) = tokio::sync::watch::channel(Arc::default());
+        Self { lists }
+    }
+
+    /// Return the addresses involved in the deposit that are on a screening
+    /// list.
+    pub fn screen_deposit(
+        &self,
+        depositor: H160,
+        deposit_receiver: &AccountAddress,
+    ) -> Vec<ScreeningMatch> {
+        let lists = self.lists.borrow();
+        lists
+            .ethereum_match(depositor)
+            .into_iter()
+            .chain(lists.concordium_match(&deposit_receiver.0))
+            .collect()
+    }
+
+    /// Return the addresses involved in the withdrawal that are on a screening
+    /// list.
+    pub fn screen_withdrawal(&self, we: &WithdrawEvent) -> Vec<ScreeningMatch> {
+        let lists = self.lists.borrow();
+        let ccd_match = match &we.ccd_address {
+            Address::Account(account) => lists.concordium_match(&account.0),
+            Address::Contract(_) => None,
+        };
+        lists
+            .ethereum_match(we.eth_address.into())
+            .into_iter()
+            .chain(ccd_match)
+            .collect()
+    }
+}
+
+fn set_list_sizes(metrics: &crate::metrics::Metrics, lists: &ScreeningLists) {
+    metrics
+        .screening_list_entries
+        .with_label_values(&["ethereum"])
+        .set(lists.ethereum.len() as i64);
+    metrics
+        .screening_list_entries
+        .with_label_values(&["concordium"])
+        .set(lists.concordium.len() as i64);
+}
+
+fn modified(path: Option<&Path>) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path?).and_then(|m| m.modified()).ok()
+}
+
+/// Check that the screening lists in the given files can be loaded.
+pub fn check_lists(
+    ethereum_list: Option<&Path>,
+    concordium_list: Option<&Path>,
+) -> anyhow::Result<()> {
+    ScreeningLists::load(ethereum_list, concordium_list).map(|_| ())
+}
+
+/// Load the screening lists from the given files. Return a handle for
+/// screening, and a task that reloads the lists when the files change.
+pub fn screener(
+    metrics: crate::metrics::Metrics,
+    ethereum_list: Option<PathBuf>,
+    concordium_list: Option<PathBuf>,
+) -> anyhow::Result<(Screener, impl std::future::Future<Output = ()>)> {
+    let lists = ScreeningLists::load(ethereum_list.as_deref(), concordium_list.as_deref())?;
+    set_list_sizes(&metrics, &lists);
+    let (sender, receiver) = tokio::sync::watch::channel(Arc::new(lists));
+    let reload = async move {
+        let mut last_modified = (
+            modified(ethereum_list.as_deref()),
+            modified(concordium_list.as_deref()),
+        );
+        let mut interval = tokio::time::interval(RELOAD_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let current = (
+                modified(ethereum_list.as_deref()),
+                modified(concordium_list.as_deref()),
+            );
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+            match ScreeningLists::load(ethereum_list.as_deref(), concordium_list.as_deref()) {
+                Ok(lists) => {
+                    tracing::info!(
+                        "Reloaded screening lists with {} Ethereum and {} Concordium addresses.",
+                        lists.ethereum.len(),
+                        lists.concordium.len()
+                    );
+                    set_list_sizes(&metrics, &lists);
+                    if sender.send(Arc::new(lists)).is_err() {
+                        tracing::debug!(
+                            "No more users of the screening lists. Stopping reloading."
+                        );
+                        return;
+                    }
+                }
+                Err(e) => {
+                    metrics.errors_total.inc();
+                    tracing::error!(
+                        "Unable to reload screening lists, keeping the old ones: {e:#}"
+                    );
+                }
+            }
+        }
+    };
+    Ok((Screener { lists: receiver }, reload))
+}
@@ -0,0 +1,99 @@
+//! Setup of logging and tracing shared by the relayer and the API server.
+//! Logs are written to stderr as text or JSON, and spans are optionally
+//! exported to an OpenTelemetry collector over OTLP.
+use crate::alerts::AlertLayer;
+use opentelemetry::{
+    propagation::Extractor,
+    sdk::{propagation::TraceContextPropagator, trace, Resource},
+    KeyValue,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    prelude::*,
+    reload, Registry,
+};
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+/// Format of the log output.
+pub enum LogFormat {
+    /// Human readable lines.
+    Text,
+    /// One JSON object per line, including the fields of the current spans.
+    Json,
+}
+
+/// A handle for changing the filter of the log output and exported spans.
+pub type FilterHandle = reload::Handle<Targets, Registry>;
+
+/// Install the global subscriber. If `otlp_endpoint` is given spans are
+/// exported to it, identified by the given service name. If `alert_layer` is
+/// given warnings and errors are also turned into alerts, independently of the
+/// filter. Return a handle for changing the filter.
+pub fn init(
+    service_name: &'static str,
+    filter: Targets,
+    format: LogFormat,
+    otlp_endpoint: Option<&str>,
+    alert_layer: Option<AlertLayer>,
+) -> anyhow::Result<FilterHandle> {
+    let (filter, filter_handle) = reload::Layer::new(filter);
+    let fmt_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+    let otlp_layer = match otlp_endpoint {
+        Some(endpoint) => {
+            opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+            let tracer =
+                opentelemetry_otlp::new_pipeline()
+                    .tracing()
+                    .with_exporter(
+                        opentelemetry_otlp::new_exporter()
+                            .tonic()
+                            .with_endpoint(endpoint),
+                    )
+                    .with_trace_config(trace::config().with_resource(Resource::new([
+                        KeyValue::new("service.name", service_name),
+                    ])))
+                    .install_batch(opentelemetry::runtime::Tokio)?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+    // The filter only applies to the log output and the exported spans. Alerts
+    // are raised for all warnings and errors, whatever the log level.
+    tracing_subscriber::registry()
+        .with(fmt_layer.and_then(otlp_layer).with_filter(filter))
+        .with(alert_layer.map(|layer| layer.with_filter(LevelFilter::WARN)))
+        .init();
+    Ok(filter_handle)
+}
+
+/// Flush the spans that have not yet been exported.
+pub fn shutdown() { opentelemetry::global::shutdown_tracer_provider() }
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> { self.0.get(key).and_then(|v| v.to_str().ok()) }
+
+    fn keys(&self) -> Vec<&str> { self.0.keys().map(|k| k.as_str()).collect() }
+}
+
+/// Make the trace context given in the `traceparent` header of a request, if
+/// any, the parent of the span. This allows a request to be followed from the
+/// caller into the server.
+pub fn set_remote_parent(span: &tracing::Span, headers: &http::HeaderMap) {
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(context);
+}
@@ -0,0 +1,114 @@
+//! Limits on withdrawals. Withdrawals that exceed a configured limit are not
+//! added to the Merkle tree, but put in a hold queue in the database. Held
+//! withdrawals are released after a configurable delay, or earlier when the
+//! operator approves them.
+use crate::db::{DatabaseOperation, WithdrawalHoldReason};
+use concordium_rust_sdk::types::ContractAddress;
+use num_bigint::BigUint;
+
+/// How often the database is checked for holds that can be released.
+const RELEASE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+/// Limits on withdrawals of a single token.
+pub struct HoldLimit {
+    pub token:        ContractAddress,
+    /// The maximum total amount that is withdrawn within the window without
+    /// being held.
+    pub window_limit: Option<u128>,
+    /// The maximum amount of a single withdrawal that is not held.
+    pub single_limit: Option<u128>,
+}
+
+impl std::str::FromStr for HoldLimit {
+    type Err = anyhow::Error;
+
+    /// Parse limits in the format
+    /// `<index,subindex>:window_amount:single_amount`. Either of the
+    /// amounts may be left empty, meaning no limit.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, window_limit, single_limit) = match s.split(':').collect::<Vec<_>>()[..] {
+            [token, window_limit, single_limit] => (token, window_limit, single_limit),
+            _ => anyhow::bail!("Expected format <index,subindex>:window_amount:single_amount"),
+        };
+        let parse_limit = |x: &str| -> anyhow::Result<Option<u128>> {
+            if x.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(x.parse()?))
+            }
+        };
+        Ok(Self {
+            token:        token.parse()?,
+            window_limit: parse_limit(window_limit)?,
+            single_limit: parse_limit(single_limit)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Configuration of when withdrawals are held, and when they are released.
+pub struct HoldPolicy {
+    pub limits:        Vec<HoldLimit>,
+    /// The window over which the withdrawn amount is computed.
+    pub window:        chrono::Duration,
+    /// How long a withdrawal is held before it is released automatically. If
+    /// [`None`] held withdrawals are only released by the operator.
+    pub release_delay: Option<chrono::Duration>,
+}
+
+impl HoldPolicy {
+    /// Get the limits for the given token, if any are configured.
+    pub fn limit(&self, token: ContractAddress) -> Option<&HoldLimit> {
+        self.limits.iter().find(|l| l.token == token)
+    }
+
+    /// The time at which a hold created now is automatically released.
+    pub fn release_time(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.release_delay.map(|d| chrono::Utc::now() + d)
+    }
+}
+
+impl HoldLimit {
+    /// Check whether a withdrawal of `amount` must be held. The
+    /// `window_volume` is the amount of the token already withdrawn within
+    /// the window, not counting withdrawals that are currently held.
+    pub fn check(&self, amount: &BigUint, window_volume: &BigUint) -> Option<WithdrawalHoldReason> {
+        if self
+            .single_limit
+            .map_or(false, |l| *amount > BigUint::from(l))
+        {
+            Some(WithdrawalHoldReason::SingleLimit)
+        } else if self
+            .window_limit
+            .map_or(false, |l| window_volume + amount > BigUint::from(l))
+        {
+            Some(WithdrawalHoldReason::WindowLimit)
+        } else {
+            None
+        }
+    }
+}
+
+/// Periodically ask the database worker to release the held withdrawals that
+/// are due, either because the delay has passed or because they were approved
+/// by the operator. Deposits held by screening are released in the same way
+/// once approved.
+pub async fn release_holds(db_sender: tokio::sync::mpsc::Sender<DatabaseOperation>) {
+    let mut interval = tokio::time::interval(RELEASE_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        for op in [
+            DatabaseOperation::ReleaseWithdrawalHolds,
+            DatabaseOperation::ReleaseDepositHolds,
+        ] {
+            if db_sender.send(op).await.is_err() {
+                tracing::debug!(
+                    "The database has been shut down. Stopping releasing held operations."
+                );
+                return;
+            }
+        }
+    }
+}